@@ -9,7 +9,7 @@ use bevy_oxr::xr_input::interactions::{
 use bevy_oxr::xr_input::prototype_locomotion::{proto_locomotion, PrototypeLocomotionConfig};
 use bevy_oxr::xr_input::trackers::{
     AimPose, OpenXRController, OpenXRLeftController, OpenXRRightController, OpenXRTracker,
-    OpenXRTrackingRoot,
+    XrTrackingRoot,
 };
 use bevy_oxr::xr_input::Vec3Conv;
 use bevy_oxr::DefaultXrPlugins;
@@ -158,16 +158,15 @@ fn spawn_controllers_example(mut commands: Commands) {
 
 fn pull_to_ground(
     time: Res<Time>,
-    mut tracking_root_query: Query<&mut Transform, (With<OpenXRTrackingRoot>, Without<Globe>)>,
-    globe: Query<(&Transform, &Globe), Without<OpenXRTrackingRoot>>,
-    views: ResMut<XrViews>,
+    mut tracking_root_query: Query<&mut Transform, (With<XrTrackingRoot>, Without<Globe>)>,
+    globe: Query<(&Transform, &Globe), Without<XrTrackingRoot>>,
+    views: Res<XrViews>,
 ) {
     let mut root = tracking_root_query.single_mut();
     let (globe_pos, globe) = globe.single();
 
     // Get player position (position of playground + position within playground)
-    let v = views.lock().unwrap();
-    let Some(view) = v.get(0) else { return };
+    let Some(view) = views.get(0) else { return };
     let mut hmd_translation = view.pose.position.to_vec3();
     hmd_translation.y = 0.0;
     let local = root.translation;
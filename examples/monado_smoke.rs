@@ -0,0 +1,12 @@
+//! runs a session against monado's simulated devices and checks that it starts, renders frames,
+//! syncs input and ends again. used by ci, see `bevy_oxr::test_support`
+
+use bevy_oxr::test_support::XrTestHarness;
+
+fn main() {
+    let mut harness = XrTestHarness::new();
+    harness.assert_session_starts(600);
+    harness.assert_frame_loop(120);
+    harness.assert_session_ends(600);
+    println!("monado smoke test passed");
+}
@@ -8,9 +8,10 @@ use bevy_oxr::resources::{XrFrameState, XrSession};
 use bevy_oxr::xr_input::actions::XrActionSets;
 use bevy_oxr::xr_input::hands::common::{HandInputDebugRenderer, OpenXrHandInput};
 use bevy_oxr::xr_input::interactions::{
-    draw_interaction_gizmos, draw_socket_gizmos, interactions, socket_interactions,
-    update_interactable_states, InteractionEvent, Touched, XRDirectInteractor, XRInteractable,
-    XRInteractableState, XRInteractorState, XRRayInteractor, XRSocketInteractor,
+    draw_interaction_gizmos, draw_poke_gizmos, draw_socket_gizmos, interactions, poke_interactions,
+    socket_interactions, update_interactable_states, InteractionEvent, PokeEvent, Touched,
+    XRDirectInteractor, XRInteractable, XRInteractableState, XRInteractorState, XRPokeInteractor,
+    XRPokeState, XRPokeable, XRRayInteractor, XRSocketInteractor,
 };
 use bevy_oxr::xr_input::oculus_touch::OculusController;
 use bevy_oxr::xr_input::prototype_locomotion::{proto_locomotion, PrototypeLocomotionConfig};
@@ -49,6 +50,8 @@ fn main() {
         .add_systems(Update, update_interactable_states)
         .add_systems(Update, update_grabbables.after(update_interactable_states))
         .add_event::<InteractionEvent>()
+        .add_systems(Update, (poke_interactions, draw_poke_gizmos).chain())
+        .add_event::<PokeEvent>()
         .run();
 }
 
@@ -114,6 +117,15 @@ fn setup(
         Grabbable,
         Touched(false),
     ));
+    //pokeable button
+    commands.spawn((
+        SpatialBundle {
+            transform: Transform::from_xyz(0.5, 1.0, -0.5),
+            ..default()
+        },
+        XRPokeable::default(),
+        XRPokeState::default(),
+    ));
 }
 
 fn spawn_controllers_example(mut commands: Commands) {
@@ -134,6 +146,7 @@ fn spawn_controllers_example(mut commands: Commands) {
         OpenXRTracker,
         SpatialBundle::default(),
         XRDirectInteractor,
+        XRPokeInteractor::default(),
         XRInteractorState::default(),
     ));
 }
@@ -0,0 +1,146 @@
+use std::ffi::CString;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::call_trace::trace;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrFrameSet;
+use crate::raw_events::XrRawEvent;
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::xr_only;
+
+pub(crate) const BOUNDARY_VISIBILITY_EXTENSION: &str = "XR_META_boundary_visibility";
+
+// XrBoundaryVisibilityMETA
+const BOUNDARY_VISIBILITY_NOT_SUPPRESSED: i32 = 1;
+const BOUNDARY_VISIBILITY_SUPPRESSED: i32 = 2;
+// XR_BOUNDARY_VISIBILITY_SUPPRESSION_NOT_ALLOWED_META, a success code
+const SUPPRESSION_NOT_ALLOWED: i32 = 1000528000;
+const TYPE_EVENT_BOUNDARY_VISIBILITY_CHANGED: i32 = 1000528001;
+
+#[repr(C)]
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+struct BoundaryVisibilityChangedEvent {
+    ty: xr::sys::StructureType,
+    next: *const std::ffi::c_void,
+    boundary_visibility: i32,
+}
+
+type RequestBoundaryVisibility =
+    unsafe extern "system" fn(session: xr::sys::Session, visibility: i32) -> xr::sys::Result;
+
+/// lets passthrough apps hide the quest boundary with XR_META_boundary_visibility. the runtime
+/// only allows it while passthrough is shown, [`XrBoundaryVisibilityChanged`] reports what it
+/// decided. the openxr crate doesn't know the extension yet, so the function is loaded by hand
+pub struct XrBoundaryVisibilityPlugin;
+
+impl Plugin for XrBoundaryVisibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrBoundaryVisibility>();
+        app.add_event::<XrBoundaryVisibilityChanged>();
+        app.add_systems(
+            PreUpdate,
+            (request_boundary_visibility, read_boundary_visibility_events)
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+    }
+}
+
+/// whether the app wants the boundary hidden
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrBoundaryVisibility {
+    pub suppressed: bool,
+}
+
+impl XrBoundaryVisibility {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        instance
+            .exts()
+            .other
+            .iter()
+            .any(|ext| ext == BOUNDARY_VISIBILITY_EXTENSION)
+    }
+}
+
+/// the runtime answered a request or changed the visibility itself, `suppressed` is false
+/// when it didn't allow hiding the boundary
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrBoundaryVisibilityChanged {
+    pub suppressed: bool,
+}
+
+pub fn request_boundary_visibility(
+    setting: Res<XrBoundaryVisibility>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    error_log: Res<XrErrorLog>,
+    mut events: EventWriter<XrBoundaryVisibilityChanged>,
+    mut function: Local<Option<Option<RequestBoundaryVisibility>>>,
+    mut requested: Local<Option<bool>>,
+) {
+    if *requested == Some(setting.suppressed) || (requested.is_none() && !setting.suppressed) {
+        return;
+    }
+    *requested = Some(setting.suppressed);
+    let function = *function.get_or_insert_with(|| load_request_boundary_visibility(&instance));
+    let Some(function) = function else {
+        warn!(
+            "hiding the boundary needs {}",
+            BOUNDARY_VISIBILITY_EXTENSION
+        );
+        return;
+    };
+    let visibility = match setting.suppressed {
+        true => BOUNDARY_VISIBILITY_SUPPRESSED,
+        false => BOUNDARY_VISIBILITY_NOT_SUPPRESSED,
+    };
+    let result = trace(
+        "xrRequestBoundaryVisibilityMETA",
+        || visibility.to_string(),
+        || unsafe { function(session.as_raw(), visibility) },
+    );
+    match result.into_raw() {
+        SUPPRESSION_NOT_ALLOWED => {
+            info!("the runtime didn't allow hiding the boundary");
+            events.send(XrBoundaryVisibilityChanged { suppressed: false });
+        }
+        raw if raw >= 0 => events.send(XrBoundaryVisibilityChanged {
+            suppressed: setting.suppressed,
+        }),
+        _ => error_log.report_result(XrErrorSource::Other, result),
+    }
+}
+
+pub fn read_boundary_visibility_events(
+    mut raw_events: EventReader<XrRawEvent>,
+    mut events: EventWriter<XrBoundaryVisibilityChanged>,
+) {
+    for raw in raw_events.read() {
+        if raw.ty.into_raw() != TYPE_EVENT_BOUNDARY_VISIBILITY_CHANGED {
+            continue;
+        }
+        let event = unsafe { raw.read::<BoundaryVisibilityChangedEvent>() };
+        events.send(XrBoundaryVisibilityChanged {
+            suppressed: event.boundary_visibility == BOUNDARY_VISIBILITY_SUPPRESSED,
+        });
+    }
+}
+
+fn load_request_boundary_visibility(instance: &XrInstance) -> Option<RequestBoundaryVisibility> {
+    if !XrBoundaryVisibility::is_supported(instance) {
+        return None;
+    }
+    let name = CString::new("xrRequestBoundaryVisibilityMETA").unwrap();
+    let function = unsafe {
+        instance
+            .entry()
+            .get_instance_proc_addr(instance.as_raw(), name.as_ptr())
+            .ok()?
+    };
+    Some(unsafe {
+        std::mem::transmute::<xr::sys::pfn::VoidFunction, RequestBoundaryVisibility>(function)
+    })
+}
@@ -0,0 +1,150 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use bevy::log::{info, warn};
+use bevy::utils::Instant;
+
+/// setting this environment variable to a file path turns on the call trace at startup
+pub const CALL_TRACE_ENV_VAR: &str = "BEVY_OXR_TRACE";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TRACE: Mutex<Option<CallTrace>> = Mutex::new(None);
+
+struct CallTrace {
+    writer: BufWriter<File>,
+    start: Instant,
+    last_flush: Instant,
+}
+
+//flushing every call costs a syscall per openxr call, a second of calls may be lost on a crash
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// writes every openxr call the crate makes (function, parameters, result, duration) to `path`,
+/// so runtime specific failures can be debugged from a file users attach to bug reports.
+/// call this before adding the openxr plugin to also trace instance and session creation
+pub fn enable_call_trace(path: impl AsRef<Path>) -> std::io::Result<()> {
+    let file = File::create(path.as_ref())?;
+    let mut writer = BufWriter::new(file);
+    writeln!(
+        writer,
+        "# time_us thread function(params) -> result duration_us"
+    )?;
+    *TRACE.lock().unwrap() = Some(CallTrace {
+        writer,
+        start: Instant::now(),
+        last_flush: Instant::now(),
+    });
+    ENABLED.store(true, Ordering::Relaxed);
+    info!("tracing openxr calls to {}", path.as_ref().display());
+    Ok(())
+}
+
+pub fn disable_call_trace() {
+    ENABLED.store(false, Ordering::Relaxed);
+    if let Some(mut trace) = TRACE.lock().unwrap().take() {
+        let _ = trace.writer.flush();
+    }
+}
+
+pub fn call_trace_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn enable_call_trace_from_env() {
+    if call_trace_enabled() {
+        return;
+    }
+    if let Ok(path) = std::env::var(CALL_TRACE_ENV_VAR) {
+        if let Err(err) = enable_call_trace(&path) {
+            warn!("failed to create openxr call trace {}: {}", path, err);
+        }
+    }
+}
+
+/// what a traced call returned, a `Result` of the openxr wrappers or the raw code of an extension
+/// function
+pub(crate) trait TraceOutcome {
+    fn outcome(&self) -> String;
+    fn failed(&self) -> bool;
+}
+
+impl<T, E: Debug> TraceOutcome for Result<T, E> {
+    fn outcome(&self) -> String {
+        match self {
+            Ok(_) => "Ok".to_string(),
+            Err(err) => format!("Err({:?})", err),
+        }
+    }
+
+    fn failed(&self) -> bool {
+        self.is_err()
+    }
+}
+
+impl TraceOutcome for openxr::sys::Result {
+    fn outcome(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    fn failed(&self) -> bool {
+        self.into_raw() < 0
+    }
+}
+
+/// turns the raw code an extension function returned into the `Result` the openxr wrappers use,
+/// success codes like `SESSION_LOSS_PENDING` are `Ok`
+pub(crate) fn check(result: openxr::sys::Result) -> openxr::Result<()> {
+    match result.into_raw() >= 0 {
+        true => Ok(()),
+        false => Err(result),
+    }
+}
+
+/// runs `call` and records it in the trace, `params` is only evaluated while tracing
+pub(crate) fn trace<R: TraceOutcome>(
+    function: &str,
+    params: impl FnOnce() -> String,
+    call: impl FnOnce() -> R,
+) -> R {
+    if !call_trace_enabled() {
+        return call();
+    }
+    let params = params();
+    let started = Instant::now();
+    let result = call();
+    let duration = started.elapsed();
+    let outcome = result.outcome();
+    if let Some(trace) = TRACE.lock().unwrap().as_mut() {
+        let time = started.saturating_duration_since(trace.start);
+        let thread = std::thread::current();
+        let _ = writeln!(
+            trace.writer,
+            "{} {} {}({}) -> {} {}",
+            time.as_micros(),
+            thread.name().unwrap_or("unnamed"),
+            function,
+            params,
+            outcome,
+            duration.as_micros()
+        );
+        //a failed call is often followed by a panic, it has to be in the file by then
+        if result.failed() || trace.last_flush.elapsed() >= FLUSH_INTERVAL {
+            let _ = trace.writer.flush();
+            trace.last_flush = Instant::now();
+        }
+    }
+    result
+}
+
+/// writes the buffered calls to the file, they are otherwise flushed about once a second and
+/// after every failed call
+pub fn flush_call_trace() {
+    if let Some(trace) = TRACE.lock().unwrap().as_mut() {
+        let _ = trace.writer.flush();
+        trace.last_flush = Instant::now();
+    }
+}
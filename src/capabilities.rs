@@ -0,0 +1,306 @@
+use std::ffi::c_void;
+use std::ptr;
+
+use bevy::prelude::*;
+use bevy::render::Extract;
+use openxr as xr;
+use openxr::sys;
+
+use crate::resources::{XrEnvironmentBlendMode, XrViewSizes};
+
+/// what the runtime and the system it runs on support, filled in once at startup so apps can
+/// branch on features without probing openxr themselves
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrCapabilities {
+    pub runtime_name: String,
+    pub runtime_version: String,
+    pub system_name: String,
+    pub vendor_id: u32,
+    pub max_swapchain_size: UVec2,
+    pub max_layer_count: u32,
+    pub orientation_tracking: bool,
+    pub position_tracking: bool,
+    pub hand_tracking: bool,
+    pub eye_tracking: bool,
+    pub passthrough: bool,
+    pub foveation: bool,
+    /// the environment blend modes the system supports, the preferred one first
+    pub blend_modes: Vec<xr::EnvironmentBlendMode>,
+    /// empty if the runtime doesn't support XR_FB_display_refresh_rate
+    pub refresh_rates: Vec<f32>,
+    /// every extension the runtime offers, not just the ones that got enabled
+    pub available_extensions: xr::ExtensionSet,
+    /// also inserted as its own resource
+    pub display: XrDisplayProperties,
+}
+
+impl XrCapabilities {
+    pub fn new(
+        available_extensions: xr::ExtensionSet,
+        instance: &xr::Instance,
+        session: &xr::Session<xr::AnyGraphics>,
+        system: xr::SystemId,
+        view_configuration: xr::ViewConfigurationType,
+    ) -> xr::Result<Self> {
+        let instance_props = instance.properties()?;
+        let system_props = instance.system_properties(system)?;
+
+        //the property structs of extensions that aren't enabled can't be chained in
+        let enabled = instance.exts();
+        let hand_tracking = enabled.ext_hand_tracking.is_some()
+            && instance.supports_hand_tracking(system).unwrap_or(false);
+
+        let eye_tracking = enabled.ext_eye_gaze_interaction.is_some() && {
+            let mut props = sys::SystemEyeGazeInteractionPropertiesEXT {
+                ty: sys::SystemEyeGazeInteractionPropertiesEXT::TYPE,
+                next: ptr::null_mut(),
+                supports_eye_gaze_interaction: sys::FALSE,
+            };
+            get_system_properties(instance, system, &mut props as *mut _ as _)
+                && props.supports_eye_gaze_interaction != sys::FALSE
+        };
+
+        let passthrough = enabled.fb_passthrough.is_some() && {
+            let mut props = sys::SystemPassthroughPropertiesFB {
+                ty: sys::SystemPassthroughPropertiesFB::TYPE,
+                next: ptr::null_mut(),
+                supports_passthrough: sys::FALSE,
+            };
+            get_system_properties(instance, system, &mut props as *mut _ as _)
+                && props.supports_passthrough != sys::FALSE
+        };
+
+        let blend_modes = instance.enumerate_environment_blend_modes(system, view_configuration)?;
+
+        let display = XrDisplayProperties::new(instance, system, view_configuration)?;
+
+        let refresh_rates = match enabled.fb_display_refresh_rate.is_some() {
+            true => session
+                .enumerate_display_refresh_rates()
+                .unwrap_or_default(),
+            false => vec![],
+        };
+
+        Ok(Self {
+            runtime_name: instance_props.runtime_name,
+            runtime_version: instance_props.runtime_version.to_string(),
+            system_name: system_props.system_name,
+            vendor_id: system_props.vendor_id,
+            max_swapchain_size: UVec2::new(
+                system_props.graphics_properties.max_swapchain_image_width,
+                system_props.graphics_properties.max_swapchain_image_height,
+            ),
+            max_layer_count: system_props.graphics_properties.max_layer_count,
+            orientation_tracking: system_props.tracking_properties.orientation_tracking,
+            position_tracking: system_props.tracking_properties.position_tracking,
+            hand_tracking,
+            eye_tracking,
+            passthrough,
+            foveation: enabled.fb_foveation.is_some(),
+            blend_modes,
+            refresh_rates,
+            available_extensions,
+            display,
+        })
+    }
+
+    /// the highest refresh rate the display can run at
+    pub fn max_refresh_rate(&self) -> Option<f32> {
+        self.refresh_rates.iter().copied().reduce(f32::max)
+    }
+}
+
+/// the limits of the display the runtime renders to, for checking the swapchain sizes and layer
+/// counts an app wants before using them
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrDisplayProperties {
+    pub max_swapchain_size: UVec2,
+    /// including the projection layer
+    pub max_layer_count: u32,
+    /// one per eye, left first
+    pub views: Vec<XrViewProperties>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct XrViewProperties {
+    /// the same as the [`XrViewSizes`] resource for the left eye
+    pub sizes: XrViewSizes,
+    /// the field of view the runtime recommends and the widest the projection can get, only with
+    /// XR_EPIC_view_configuration_fov
+    pub recommended_fov: Option<xr::Fovf>,
+    pub max_fov: Option<xr::Fovf>,
+}
+
+impl XrDisplayProperties {
+    pub fn new(
+        instance: &xr::Instance,
+        system: xr::SystemId,
+        view_configuration: xr::ViewConfigurationType,
+    ) -> xr::Result<Self> {
+        let system_props = instance.system_properties(system)?;
+        let fovs = match instance.exts().epic_view_configuration_fov.is_some() {
+            true => view_fovs(instance, system, view_configuration),
+            false => None,
+        };
+        let views = instance
+            .enumerate_view_configuration_views(system, view_configuration)?
+            .iter()
+            .enumerate()
+            .map(|(index, view)| {
+                let fov = fovs.as_ref().and_then(|fovs| fovs.get(index));
+                XrViewProperties {
+                    sizes: XrViewSizes::from_view(view),
+                    recommended_fov: fov.map(|fov| fov.recommended_fov),
+                    max_fov: fov.map(|fov| fov.max_mutable_fov),
+                }
+            })
+            .collect();
+        Ok(Self {
+            max_swapchain_size: UVec2::new(
+                system_props.graphics_properties.max_swapchain_image_width,
+                system_props.graphics_properties.max_swapchain_image_height,
+            ),
+            max_layer_count: system_props.graphics_properties.max_layer_count,
+            views,
+        })
+    }
+
+    /// whether a swapchain of this size can be created
+    pub fn fits_swapchain(&self, size: UVec2) -> bool {
+        size.cmple(self.max_swapchain_size).all()
+    }
+
+    /// how many layers can be submitted besides the projection
+    pub fn max_extra_layers(&self) -> u32 {
+        self.max_layer_count.saturating_sub(1)
+    }
+}
+
+/// reverts changes to [`XrEnvironmentBlendMode`] the system can't display
+pub fn validate_environment_blend_mode(
+    mut blend_mode: ResMut<XrEnvironmentBlendMode>,
+    capabilities: Res<XrCapabilities>,
+    mut last_valid: Local<Option<xr::EnvironmentBlendMode>>,
+) {
+    if !blend_mode.is_changed() && last_valid.is_some() {
+        return;
+    }
+    if capabilities.blend_modes.contains(&**blend_mode) {
+        *last_valid = Some(**blend_mode);
+        return;
+    }
+    let fallback = last_valid
+        .or_else(|| capabilities.blend_modes.first().copied())
+        .unwrap_or(xr::EnvironmentBlendMode::OPAQUE);
+    warn!(
+        "environment blend mode {:?} isn't supported, using {:?}",
+        **blend_mode, fallback
+    );
+    blend_mode.set(fallback);
+    *last_valid = Some(fallback);
+}
+
+pub fn extract_environment_blend_mode(
+    mut commands: Commands,
+    blend_mode: Extract<Res<XrEnvironmentBlendMode>>,
+) {
+    if blend_mode.is_changed() {
+        commands.insert_resource(blend_mode.clone());
+    }
+}
+
+// the fov structs have to be chained into the view configuration views, which openxr doesn't do
+fn view_fovs(
+    instance: &xr::Instance,
+    system: xr::SystemId,
+    view_configuration: xr::ViewConfigurationType,
+) -> Option<Vec<sys::ViewConfigurationViewFovEPIC>> {
+    let enumerate = instance.fp().enumerate_view_configuration_views;
+    let mut count = 0;
+    let result = unsafe {
+        enumerate(
+            instance.as_raw(),
+            system,
+            view_configuration,
+            0,
+            &mut count,
+            ptr::null_mut(),
+        )
+    };
+    if result != sys::Result::SUCCESS {
+        warn!("failed to get the view fovs: {}", result);
+        return None;
+    }
+    let empty_fov = xr::Fovf {
+        angle_left: 0.0,
+        angle_right: 0.0,
+        angle_up: 0.0,
+        angle_down: 0.0,
+    };
+    let mut fovs = vec![
+        sys::ViewConfigurationViewFovEPIC {
+            ty: sys::ViewConfigurationViewFovEPIC::TYPE,
+            next: ptr::null_mut(),
+            recommended_fov: empty_fov,
+            max_mutable_fov: empty_fov,
+        };
+        count as usize
+    ];
+    let mut views: Vec<sys::ViewConfigurationView> = fovs
+        .iter_mut()
+        .map(|fov| sys::ViewConfigurationView {
+            ty: sys::ViewConfigurationView::TYPE,
+            next: fov as *mut _ as _,
+            recommended_image_rect_width: 0,
+            max_image_rect_width: 0,
+            recommended_image_rect_height: 0,
+            max_image_rect_height: 0,
+            recommended_swapchain_sample_count: 0,
+            max_swapchain_sample_count: 0,
+        })
+        .collect();
+    let result = unsafe {
+        enumerate(
+            instance.as_raw(),
+            system,
+            view_configuration,
+            count,
+            &mut count,
+            views.as_mut_ptr(),
+        )
+    };
+    if result != sys::Result::SUCCESS {
+        warn!("failed to get the view fovs: {}", result);
+        return None;
+    }
+    fovs.truncate(count as usize);
+    Some(fovs)
+}
+
+// openxr only wraps the system properties structs it knows about, the others have to be chained in
+// by hand
+fn get_system_properties(instance: &xr::Instance, system: xr::SystemId, next: *mut c_void) -> bool {
+    let mut props = sys::SystemProperties {
+        ty: sys::SystemProperties::TYPE,
+        next,
+        system_id: system,
+        vendor_id: 0,
+        system_name: [0; sys::MAX_SYSTEM_NAME_SIZE],
+        graphics_properties: sys::SystemGraphicsProperties {
+            max_swapchain_image_height: 0,
+            max_swapchain_image_width: 0,
+            max_layer_count: 0,
+        },
+        tracking_properties: sys::SystemTrackingProperties {
+            orientation_tracking: sys::FALSE,
+            position_tracking: sys::FALSE,
+        },
+    };
+    let result =
+        unsafe { (instance.fp().get_system_properties)(instance.as_raw(), system, &mut props) };
+    if result != sys::Result::SUCCESS {
+        warn!("failed to get system properties: {}", result);
+        return false;
+    }
+    true
+}
@@ -0,0 +1,574 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+use openxr::sys;
+
+use crate::call_trace::{check, trace};
+use crate::convert::to_posef;
+use crate::frame_loop::XrFrameSet;
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::xr_only;
+use crate::xr_input::spaces::XrSpaces;
+use crate::xr_input::trackers::XrTrackingRoot;
+use crate::xr_tasks::{XrAsyncRequests, XrTask, XrTaskAppExt, XrTaskOutput};
+
+/// local multiplayer in one room. the host puts a spatial anchor on the floor, saves it to the
+/// cloud and shares it with the other players, who load it by its uuid. once the anchor is
+/// localized the tracking root is moved so the anchor is the world origin, the same spot in the
+/// room for everyone. needs the XR_FB_spatial_entity extensions, the uuid has to be sent to the
+/// other players by the app
+pub struct XrColocationPlugin;
+
+impl Plugin for XrColocationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrColocation>();
+        app.add_event::<XrColocationRequest>();
+        app.add_event::<XrColocationEvent>();
+        app.add_xr_task::<XrLoadedAnchor>();
+        app.add_systems(
+            PreUpdate,
+            (
+                handle_colocation_requests,
+                finish_colocation_tasks,
+                despawn_cancelled_colocation_tasks,
+                align_to_shared_anchor,
+            )
+                .chain()
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+    }
+}
+
+#[derive(Event, Clone, Debug, PartialEq, Eq)]
+pub enum XrColocationRequest {
+    /// creates the shared anchor below the head, facing where the head looks. `users` are the
+    /// platform user ids of the other players
+    Host { users: Vec<u64> },
+    /// loads the anchor the host shared
+    Join { uuid: u128 },
+    /// forgets the anchor and undoes the alignment
+    Leave,
+}
+
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub enum XrColocationEvent {
+    /// the anchor is shared, send `uuid` to the other players
+    Hosted {
+        uuid: u128,
+    },
+    /// the shared anchor was loaded, the alignment follows once it's localized
+    Found {
+        uuid: u128,
+    },
+    /// the tracking root was aligned to the anchor. `drift` is how far the anchor moved since
+    /// the last alignment in meters, 0 for the first one. `tracked` is false while the runtime
+    /// only infers the anchor pose
+    Aligned {
+        drift: f32,
+        tracked: bool,
+    },
+    /// the anchor isn't located anymore, the last alignment stays
+    Lost,
+    Failed {
+        error: sys::Result,
+    },
+}
+
+#[derive(Resource)]
+pub struct XrColocation {
+    /// the alignment is only redone when the anchor moved further than this, in meters, so
+    /// tracking noise doesn't shake the world
+    pub realign_distance: f32,
+    /// like `realign_distance` for turning, in radians
+    pub realign_angle: f32,
+    anchor: Option<SharedAnchor>,
+}
+
+impl Default for XrColocation {
+    fn default() -> Self {
+        Self {
+            realign_distance: 0.02,
+            realign_angle: 0.02,
+            anchor: None,
+        }
+    }
+}
+
+struct SharedAnchor {
+    uuid: u128,
+    space: xr::Space,
+    //the anchor pose in the stage the current alignment is based on
+    aligned: Option<Transform>,
+    lost: bool,
+}
+
+impl XrColocation {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        let exts = instance.exts();
+        exts.fb_spatial_entity.is_some()
+            && exts.fb_spatial_entity_storage.is_some()
+            && exts.fb_spatial_entity_query.is_some()
+            && exts.fb_spatial_entity_sharing.is_some()
+            && exts.fb_spatial_entity_user.is_some()
+    }
+
+    /// the uuid of the shared anchor once it's hosted or found
+    pub fn uuid(&self) -> Option<u128> {
+        self.anchor.as_ref().map(|anchor| anchor.uuid)
+    }
+
+    pub fn is_aligned(&self) -> bool {
+        self.anchor
+            .as_ref()
+            .is_some_and(|anchor| anchor.aligned.is_some())
+    }
+}
+
+/// the anchor a colocation task created or loaded, the space is destroyed with it
+pub struct XrLoadedAnchor {
+    space: xr::Space,
+    uuid: u128,
+    hosted: bool,
+}
+
+/// marks the entity of a running colocation task
+#[derive(Component)]
+pub struct XrColocationTask;
+
+/// marks a colocation task a newer request replaced. it keeps running, the runtime may still
+/// hand it a space, and is despawned once it's done, which destroys that space
+#[derive(Component)]
+pub struct XrColocationCancelled;
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_colocation_requests(
+    mut commands: Commands,
+    mut requests: EventReader<XrColocationRequest>,
+    mut colocation: ResMut<XrColocation>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    spaces: XrSpaces,
+    async_requests: Res<XrAsyncRequests>,
+    tasks: Query<Entity, With<XrColocationTask>>,
+    mut events: EventWriter<XrColocationEvent>,
+) {
+    for request in requests.read() {
+        //a new request replaces whatever was going on
+        for entity in &tasks {
+            commands
+                .entity(entity)
+                .remove::<XrColocationTask>()
+                .insert(XrColocationCancelled);
+        }
+        colocation.anchor = None;
+        if *request == XrColocationRequest::Leave {
+            continue;
+        }
+        if !XrColocation::is_supported(&instance) {
+            events.send(XrColocationEvent::Failed {
+                error: sys::Result::ERROR_EXTENSION_NOT_PRESENT,
+            });
+            continue;
+        }
+        let instance = (**instance).clone();
+        let owner = (**session).clone();
+        let session = session.as_raw();
+        let async_requests = async_requests.clone();
+        let task = match request {
+            XrColocationRequest::Host { users } => {
+                let Some(head) = spaces.locate(spaces.view(), spaces.stage()) else {
+                    events.send(XrColocationEvent::Failed {
+                        error: sys::Result::ERROR_POSE_INVALID,
+                    });
+                    continue;
+                };
+                let mut pose = gravity_aligned(head);
+                pose.translation.y = 0.0;
+                let request = match create_anchor(&instance, session, &spaces, pose) {
+                    Ok(request) => request,
+                    Err(error) => {
+                        events.send(XrColocationEvent::Failed { error });
+                        continue;
+                    }
+                };
+                let users = users.clone();
+                XrTask::spawn(async move {
+                    let space = async_requests.wait_for_space(request).await?;
+                    //destroyed when dropped, so no error leaks it
+                    let space = unsafe { xr::Space::reference_from_raw(owner, space) };
+                    share_anchor(&instance, session, &async_requests, &space, &users).await?;
+                    let uuid = anchor_uuid(&instance, space.as_raw())?;
+                    Ok(XrLoadedAnchor {
+                        space,
+                        uuid,
+                        hosted: true,
+                    })
+                })
+            }
+            XrColocationRequest::Join { uuid } => {
+                let uuid = *uuid;
+                let request = match query_anchor(&instance, session, uuid) {
+                    Ok(request) => request,
+                    Err(error) => {
+                        events.send(XrColocationEvent::Failed { error });
+                        continue;
+                    }
+                };
+                XrTask::spawn(async move {
+                    async_requests.wait(request).await?;
+                    let space = query_result(&instance, session, request)?;
+                    Ok(XrLoadedAnchor {
+                        space: unsafe { xr::Space::reference_from_raw(owner, space) },
+                        uuid,
+                        hosted: false,
+                    })
+                })
+            }
+            XrColocationRequest::Leave => unreachable!(),
+        };
+        commands.spawn((XrColocationTask, task));
+    }
+}
+
+pub fn finish_colocation_tasks(
+    mut commands: Commands,
+    mut colocation: ResMut<XrColocation>,
+    mut tasks: Query<(Entity, &mut XrTaskOutput<XrLoadedAnchor>), With<XrColocationTask>>,
+    mut events: EventWriter<XrColocationEvent>,
+) {
+    for (entity, mut output) in &mut tasks {
+        commands.entity(entity).despawn();
+        let result = std::mem::replace(&mut output.0, Err(sys::Result::ERROR_RUNTIME_FAILURE));
+        let anchor = match result {
+            Ok(anchor) => anchor,
+            Err(error) => {
+                warn!("colocation failed: {}", error);
+                events.send(XrColocationEvent::Failed { error });
+                continue;
+            }
+        };
+        events.send(match anchor.hosted {
+            true => XrColocationEvent::Hosted { uuid: anchor.uuid },
+            false => XrColocationEvent::Found { uuid: anchor.uuid },
+        });
+        colocation.anchor = Some(SharedAnchor {
+            uuid: anchor.uuid,
+            space: anchor.space,
+            aligned: None,
+            lost: false,
+        });
+    }
+}
+
+pub fn despawn_cancelled_colocation_tasks(
+    mut commands: Commands,
+    tasks: Query<
+        Entity,
+        (
+            With<XrColocationCancelled>,
+            With<XrTaskOutput<XrLoadedAnchor>>,
+        ),
+    >,
+) {
+    for entity in &tasks {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn align_to_shared_anchor(
+    mut colocation: ResMut<XrColocation>,
+    spaces: XrSpaces,
+    mut root: Query<&mut Transform, With<XrTrackingRoot>>,
+    mut events: EventWriter<XrColocationEvent>,
+    //the alignment that is part of the root transform right now
+    mut applied: Local<Option<Transform>>,
+) {
+    let Ok(mut root) = root.get_single_mut() else {
+        return;
+    };
+    let (realign_distance, realign_angle) = (colocation.realign_distance, colocation.realign_angle);
+    let target = match colocation.anchor.as_mut() {
+        Some(anchor) => {
+            let location = spaces.locate_with_velocity(&anchor.space, spaces.stage());
+            let Some(location) = location else {
+                if !anchor.lost && anchor.aligned.is_some() {
+                    anchor.lost = true;
+                    events.send(XrColocationEvent::Lost);
+                }
+                return;
+            };
+            anchor.lost = false;
+            let pose = gravity_aligned(location.transform);
+            let drift = anchor.aligned.map(|aligned| {
+                (
+                    aligned.translation.distance(pose.translation),
+                    aligned.rotation.angle_between(pose.rotation),
+                )
+            });
+            if drift.is_some_and(|(distance, angle)| {
+                distance < realign_distance && angle < realign_angle
+            }) {
+                return;
+            }
+            anchor.aligned = Some(pose);
+            events.send(XrColocationEvent::Aligned {
+                drift: drift.map(|(distance, _)| distance).unwrap_or_default(),
+                tracked: location.tracked,
+            });
+            //the anchor becomes the origin of the tracking space
+            Transform::from_matrix(pose.compute_matrix().inverse())
+        }
+        None if applied.is_none() => return,
+        None => Transform::IDENTITY,
+    };
+    //swap the old alignment for the new one, keeping what the app did to the root
+    let current = applied.unwrap_or_default();
+    let app_root = root.compute_matrix() * current.compute_matrix().inverse();
+    *root = Transform::from_matrix(app_root).mul_transform(target);
+    *applied = (target != Transform::IDENTITY).then_some(target);
+}
+
+//only the yaw, so a tilted head or anchor doesn't tilt the floor
+fn gravity_aligned(transform: Transform) -> Transform {
+    let forward = transform.forward();
+    let yaw = Vec3::new(forward.x, 0.0, forward.z)
+        .try_normalize()
+        .map(|forward| (-forward.x).atan2(-forward.z))
+        .unwrap_or_default();
+    Transform::from_translation(transform.translation).with_rotation(Quat::from_rotation_y(yaw))
+}
+
+fn create_anchor(
+    instance: &xr::Instance,
+    session: sys::Session,
+    spaces: &XrSpaces,
+    pose: Transform,
+) -> xr::Result<sys::AsyncRequestIdFB> {
+    let ext = instance.exts().fb_spatial_entity.as_ref();
+    let ext = ext.ok_or(sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    let info = sys::SpatialAnchorCreateInfoFB {
+        ty: sys::SpatialAnchorCreateInfoFB::TYPE,
+        next: ptr::null(),
+        space: spaces.stage().as_raw(),
+        pose_in_space: to_posef(pose.translation, pose.rotation),
+        time: spaces.display_time(),
+    };
+    let mut request = sys::AsyncRequestIdFB::from_raw(0);
+    check(trace(
+        "xrCreateSpatialAnchorFB",
+        || format!("{:?}", pose),
+        || unsafe { (ext.create_spatial_anchor)(session, &info, &mut request) },
+    ))?;
+    Ok(request)
+}
+
+/// makes the anchor storable and sharable, saves it to the cloud and shares it with `users`
+async fn share_anchor(
+    instance: &xr::Instance,
+    session: sys::Session,
+    async_requests: &XrAsyncRequests,
+    space: &xr::Space,
+    users: &[u64],
+) -> xr::Result<()> {
+    let space = space.as_raw();
+    for component in [
+        sys::SpaceComponentTypeFB::STORABLE,
+        sys::SpaceComponentTypeFB::SHARABLE,
+    ] {
+        if let Some(request) = enable_component(instance, space, component)? {
+            async_requests.wait(request).await?;
+        }
+    }
+    async_requests
+        .wait(save_to_cloud(instance, session, space)?)
+        .await?;
+    async_requests
+        .wait(share_with(instance, session, space, users)?)
+        .await?;
+    Ok(())
+}
+
+// `None` if the component is enabled already
+fn enable_component(
+    instance: &xr::Instance,
+    space: sys::Space,
+    component: sys::SpaceComponentTypeFB,
+) -> xr::Result<Option<sys::AsyncRequestIdFB>> {
+    let ext = instance.exts().fb_spatial_entity.as_ref();
+    let ext = ext.ok_or(sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    let info = sys::SpaceComponentStatusSetInfoFB {
+        ty: sys::SpaceComponentStatusSetInfoFB::TYPE,
+        next: ptr::null(),
+        component_type: component,
+        enabled: sys::TRUE,
+        timeout: xr::Duration::from_nanos(0),
+    };
+    let mut request = sys::AsyncRequestIdFB::from_raw(0);
+    let result = trace(
+        "xrSetSpaceComponentStatusFB",
+        || format!("{:?}", component),
+        || unsafe { (ext.set_space_component_status)(space, &info, &mut request) },
+    );
+    if result == sys::Result::ERROR_SPACE_COMPONENT_STATUS_ALREADY_SET_FB {
+        return Ok(None);
+    }
+    check(result)?;
+    Ok(Some(request))
+}
+
+fn save_to_cloud(
+    instance: &xr::Instance,
+    session: sys::Session,
+    space: sys::Space,
+) -> xr::Result<sys::AsyncRequestIdFB> {
+    let ext = instance.exts().fb_spatial_entity_storage.as_ref();
+    let ext = ext.ok_or(sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    let info = sys::SpaceSaveInfoFB {
+        ty: sys::SpaceSaveInfoFB::TYPE,
+        next: ptr::null(),
+        space,
+        location: sys::SpaceStorageLocationFB::CLOUD,
+        persistence_mode: sys::SpacePersistenceModeFB::INDEFINITE,
+    };
+    let mut request = sys::AsyncRequestIdFB::from_raw(0);
+    check(trace("xrSaveSpaceFB", String::new, || unsafe {
+        (ext.save_space)(session, &info, &mut request)
+    }))?;
+    Ok(request)
+}
+
+fn share_with(
+    instance: &xr::Instance,
+    session: sys::Session,
+    mut space: sys::Space,
+    user_ids: &[u64],
+) -> xr::Result<sys::AsyncRequestIdFB> {
+    let exts = instance.exts();
+    let (Some(user_ext), Some(sharing_ext)) = (
+        exts.fb_spatial_entity_user.as_ref(),
+        exts.fb_spatial_entity_sharing.as_ref(),
+    ) else {
+        return Err(sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let mut users = Vec::with_capacity(user_ids.len());
+    let mut result = Ok(());
+    for &user_id in user_ids {
+        let info = sys::SpaceUserCreateInfoFB {
+            ty: sys::SpaceUserCreateInfoFB::TYPE,
+            next: ptr::null(),
+            user_id,
+        };
+        let mut user = sys::SpaceUserFB::NULL;
+        result = check(trace(
+            "xrCreateSpaceUserFB",
+            || user_id.to_string(),
+            || unsafe { (user_ext.create_space_user)(session, &info, &mut user) },
+        ));
+        if result.is_err() {
+            break;
+        }
+        users.push(user);
+    }
+    let mut request = sys::AsyncRequestIdFB::from_raw(0);
+    if result.is_ok() {
+        let info = sys::SpaceShareInfoFB {
+            ty: sys::SpaceShareInfoFB::TYPE,
+            next: ptr::null(),
+            space_count: 1,
+            spaces: &mut space,
+            user_count: users.len() as u32,
+            users: users.as_mut_ptr(),
+        };
+        result = check(trace(
+            "xrShareSpacesFB",
+            || format!("{:?}", user_ids),
+            || unsafe { (sharing_ext.share_spaces)(session, &info, &mut request) },
+        ));
+    }
+    //the runtime copies the users it shares with
+    for user in users {
+        unsafe { (user_ext.destroy_space_user)(user) };
+    }
+    result.map(|_| request)
+}
+
+fn anchor_uuid(instance: &xr::Instance, space: sys::Space) -> xr::Result<u128> {
+    let ext = instance.exts().fb_spatial_entity.as_ref();
+    let ext = ext.ok_or(sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    let mut uuid = sys::UuidEXT { data: [0; 16] };
+    check(trace("xrGetSpaceUuidFB", String::new, || unsafe {
+        (ext.get_space_uuid)(space, &mut uuid)
+    }))?;
+    Ok(u128::from_be_bytes(uuid.data))
+}
+
+fn query_anchor(
+    instance: &xr::Instance,
+    session: sys::Session,
+    uuid: u128,
+) -> xr::Result<sys::AsyncRequestIdFB> {
+    let ext = instance.exts().fb_spatial_entity_query.as_ref();
+    let ext = ext.ok_or(sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    let location_filter = sys::SpaceStorageLocationFilterInfoFB {
+        ty: sys::SpaceStorageLocationFilterInfoFB::TYPE,
+        next: ptr::null(),
+        location: sys::SpaceStorageLocationFB::CLOUD,
+    };
+    let mut uuids = [sys::UuidEXT {
+        data: uuid.to_be_bytes(),
+    }];
+    let filter = sys::SpaceUuidFilterInfoFB {
+        ty: sys::SpaceUuidFilterInfoFB::TYPE,
+        next: &location_filter as *const _ as *const _,
+        uuid_count: 1,
+        uuids: uuids.as_mut_ptr(),
+    };
+    let info = sys::SpaceQueryInfoFB {
+        ty: sys::SpaceQueryInfoFB::TYPE,
+        next: ptr::null(),
+        query_action: sys::SpaceQueryActionFB::LOAD,
+        max_result_count: 1,
+        timeout: xr::Duration::from_nanos(0),
+        filter: &filter as *const _ as *const _,
+        exclude_filter: ptr::null(),
+    };
+    let mut request = sys::AsyncRequestIdFB::from_raw(0);
+    check(trace(
+        "xrQuerySpacesFB",
+        || format!("{:032x}", uuid),
+        || unsafe { (ext.query_spaces)(session, &info as *const _ as *const _, &mut request) },
+    ))?;
+    Ok(request)
+}
+
+fn query_result(
+    instance: &xr::Instance,
+    session: sys::Session,
+    request: sys::AsyncRequestIdFB,
+) -> xr::Result<sys::Space> {
+    let ext = instance.exts().fb_spatial_entity_query.as_ref();
+    let ext = ext.ok_or(sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    let mut results = [sys::SpaceQueryResultFB {
+        space: sys::Space::NULL,
+        uuid: sys::UuidEXT { data: [0; 16] },
+    }];
+    let mut output = sys::SpaceQueryResultsFB {
+        ty: sys::SpaceQueryResultsFB::TYPE,
+        next: ptr::null_mut(),
+        result_capacity_input: results.len() as u32,
+        result_count_output: 0,
+        results: results.as_mut_ptr(),
+    };
+    check(trace(
+        "xrRetrieveSpaceQueryResultsFB",
+        String::new,
+        || unsafe { (ext.retrieve_space_query_results)(session, request, &mut output) },
+    ))?;
+    if output.result_count_output == 0 {
+        warn!("the shared anchor wasn't found, it may not be shared with this user");
+        return Err(sys::Result::ERROR_RUNTIME_FAILURE);
+    }
+    Ok(results[0].space)
+}
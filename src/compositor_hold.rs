@@ -0,0 +1,162 @@
+use bevy::asset::RecursiveDependencyLoadState;
+use bevy::prelude::*;
+use bevy::render::{Extract, RenderApp};
+
+use crate::frame_loop::XrFrameSet;
+use crate::layers::{XrLayerOrder, XrQuadLayer};
+use crate::render_suspend::update_xr_render_suspended;
+use crate::xr_init::xr_only;
+use crate::xr_input::trackers::{OpenXRHMD, XrTrackingRoot};
+
+/// a loading screen the compositor keeps stable. while holding, the scene isn't rendered and only
+/// the quad layers are submitted, with [`XrCompositorHold::image`] as a world locked quad in front
+/// of the user. when the app hitches the compositor reprojects the last submitted layers, a quad
+/// survives that without judder where a stale projection wouldn't. needs `XrLayersPlugin`
+pub struct XrCompositorHoldPlugin;
+
+impl Plugin for XrCompositorHoldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrCompositorHold>();
+        app.init_resource::<XrHoldWhileLoading>();
+        app.add_systems(
+            PreUpdate,
+            (update_hold_while_loading, update_hold_layer)
+                .chain()
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame)
+                .before(update_xr_render_suspended),
+        );
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<XrCompositorHolding>();
+        render_app.add_systems(ExtractSchedule, extract_compositor_hold.run_if(xr_only()));
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct XrCompositorHold {
+    /// holds until this is turned off again, besides the loads of [`XrHoldWhileLoading`]
+    pub active: bool,
+    /// e.g. a logo, it needs `TextureUsages::COPY_SRC` like every quad layer. without an image
+    /// the user sees black
+    pub image: Option<Handle<Image>>,
+    /// in meters
+    pub size: Vec2,
+    /// how far in front of the head the quad is placed when the hold starts, in meters
+    pub distance: f32,
+    loading: bool,
+}
+
+impl Default for XrCompositorHold {
+    fn default() -> Self {
+        Self {
+            active: false,
+            image: None,
+            size: Vec2::new(1.6, 0.9),
+            distance: 2.0,
+            loading: false,
+        }
+    }
+}
+
+impl XrCompositorHold {
+    pub fn is_holding(&self) -> bool {
+        self.active || self.loading
+    }
+}
+
+/// holds while any of these assets or their dependencies is still loading, the handles are
+/// dropped once they're done
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrHoldWhileLoading(pub Vec<UntypedHandle>);
+
+/// the hold state in the render world
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrCompositorHolding(pub bool);
+
+/// the quad of [`XrCompositorHold::image`]
+#[derive(Component)]
+pub struct XrHoldLayer;
+
+pub fn update_hold_while_loading(
+    asset_server: Res<AssetServer>,
+    mut loads: ResMut<XrHoldWhileLoading>,
+    mut hold: ResMut<XrCompositorHold>,
+) {
+    loads.0.retain(|handle| {
+        matches!(
+            asset_server.get_recursive_dependency_load_state(handle.id()),
+            Some(RecursiveDependencyLoadState::NotLoaded | RecursiveDependencyLoadState::Loading)
+        )
+    });
+    let loading = !loads.0.is_empty();
+    if hold.loading != loading {
+        hold.loading = loading;
+    }
+}
+
+pub fn update_hold_layer(
+    mut commands: Commands,
+    hold: Res<XrCompositorHold>,
+    root: Query<Entity, With<XrTrackingRoot>>,
+    hmd: Query<&Transform, (With<OpenXRHMD>, Without<XrHoldLayer>)>,
+    mut layer: Query<(&mut XrQuadLayer, &mut Transform, &mut Visibility), With<XrHoldLayer>>,
+    mut was_holding: Local<bool>,
+) {
+    let holding = hold.is_holding();
+    let started = holding && !*was_holding;
+    *was_holding = holding;
+    let (Some(image), true) = (hold.image.clone(), holding) else {
+        for (.., mut visibility) in &mut layer {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+    //the hmd is a child of the root, like the layer
+    let placement = || {
+        let head = hmd.get_single().ok()?;
+        let forward = head.forward();
+        let forward = Vec3::new(forward.x, 0.0, forward.z)
+            .try_normalize()
+            .unwrap_or(Vec3::NEG_Z);
+        let position = head.translation + forward * hold.distance;
+        Some(Transform::from_translation(position).looking_to(forward, Vec3::Y))
+    };
+    match layer.get_single_mut() {
+        Ok((mut quad, mut transform, mut visibility)) => {
+            quad.image = image;
+            quad.size = hold.size;
+            if let Some(placement) = placement().filter(|_| started) {
+                *transform = placement;
+            }
+            *visibility = Visibility::Inherited;
+        }
+        Err(_) => {
+            let Ok(root) = root.get_single() else {
+                return;
+            };
+            let layer = commands
+                .spawn((
+                    SpatialBundle::from_transform(placement().unwrap_or_default()),
+                    XrQuadLayer {
+                        image,
+                        size: hold.size,
+                    },
+                    //in front of everything else
+                    XrLayerOrder(i32::MAX),
+                    XrHoldLayer,
+                    Name::new("Compositor Hold"),
+                ))
+                .id();
+            commands.entity(root).add_child(layer);
+        }
+    }
+}
+
+pub fn extract_compositor_hold(
+    mut holding: ResMut<XrCompositorHolding>,
+    hold: Extract<Res<XrCompositorHold>>,
+) {
+    holding.0 = hold.is_holding();
+}
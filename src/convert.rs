@@ -0,0 +1,334 @@
+//! conversions between openxr and bevy types. openxr and bevy use the same coordinate system
+//! (right handed, +y up, -z forward, meters), so poses convert component by component. the
+//! handedness helpers are for data from left handed sources, like some engines and tools
+
+use bevy::prelude::*;
+use openxr::{Fovf, Posef, Quaternionf, Vector2f, Vector3f};
+
+pub trait Vec2Conv {
+    fn to_vec2(&self) -> Vec2;
+}
+
+impl Vec2Conv for Vector2f {
+    fn to_vec2(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+}
+
+pub trait Vec3Conv {
+    fn to_vec3(&self) -> Vec3;
+}
+
+impl Vec3Conv for Vector3f {
+    fn to_vec3(&self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+}
+
+pub trait QuatConv {
+    fn to_quat(&self) -> Quat;
+}
+
+impl QuatConv for Quaternionf {
+    fn to_quat(&self) -> Quat {
+        Quat::from_xyzw(self.x, self.y, self.z, self.w)
+    }
+}
+
+pub trait PosefConv {
+    /// a transform with the pose and a scale of one
+    fn to_transform(&self) -> Transform;
+}
+
+impl PosefConv for Posef {
+    fn to_transform(&self) -> Transform {
+        Transform::from_translation(self.position.to_vec3())
+            .with_rotation(self.orientation.to_quat())
+    }
+}
+
+pub trait XrVec2Conv {
+    fn to_xr_vec2(&self) -> Vector2f;
+}
+
+impl XrVec2Conv for Vec2 {
+    fn to_xr_vec2(&self) -> Vector2f {
+        Vector2f {
+            x: self.x,
+            y: self.y,
+        }
+    }
+}
+
+pub trait XrVec3Conv {
+    fn to_xr_vec3(&self) -> Vector3f;
+}
+
+impl XrVec3Conv for Vec3 {
+    fn to_xr_vec3(&self) -> Vector3f {
+        Vector3f {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+        }
+    }
+}
+
+pub trait XrQuatConv {
+    fn to_xr_quat(&self) -> Quaternionf;
+}
+
+impl XrQuatConv for Quat {
+    fn to_xr_quat(&self) -> Quaternionf {
+        Quaternionf {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+            w: self.w,
+        }
+    }
+}
+
+pub trait TransformConv {
+    /// the translation and rotation as a pose, openxr poses have no scale
+    fn to_posef(&self) -> Posef;
+}
+
+impl TransformConv for Transform {
+    fn to_posef(&self) -> Posef {
+        to_posef(self.translation, self.rotation)
+    }
+}
+
+impl TransformConv for GlobalTransform {
+    fn to_posef(&self) -> Posef {
+        let (_, rotation, translation) = self.to_scale_rotation_translation();
+        to_posef(translation, rotation)
+    }
+}
+
+pub fn to_posef(position: Vec3, orientation: Quat) -> Posef {
+    Posef {
+        orientation: orientation.to_xr_quat(),
+        position: position.to_xr_vec3(),
+    }
+}
+
+/// mirrors a position along z, converts between left handed (+z forward) and right handed
+/// coordinates. works in both directions
+pub fn flip_handedness_position(position: Vec3) -> Vec3 {
+    Vec3::new(position.x, position.y, -position.z)
+}
+
+/// the rotation matching [`flip_handedness_position`], works in both directions
+pub fn flip_handedness_rotation(rotation: Quat) -> Quat {
+    Quat::from_xyzw(-rotation.x, -rotation.y, rotation.z, rotation.w)
+}
+
+pub fn flip_handedness_transform(transform: Transform) -> Transform {
+    Transform {
+        translation: flip_handedness_position(transform.translation),
+        rotation: flip_handedness_rotation(transform.rotation),
+        scale: transform.scale,
+    }
+}
+
+// =============================================================================
+// math code adapted from
+// https://github.com/KhronosGroup/OpenXR-SDK-Source/blob/master/src/common/xr_linear.h
+// Copyright (c) 2017 The Khronos Group Inc.
+// Copyright (c) 2016 Oculus VR, LLC.
+// SPDX-License-Identifier: Apache-2.0
+// =============================================================================
+/// the projection matrix of an asymmetric openxr fov. the depth is reversed like in bevy's own
+/// perspective projection, with `far` set to `None` the far plane is at infinity
+pub fn fov_to_projection(fov: Fovf, near: f32, far: Option<f32>) -> Mat4 {
+    //  symmetric perspective for debugging
+    // let x_fov = (self.fov.angle_left.abs() + self.fov.angle_right.abs());
+    // let y_fov = (self.fov.angle_up.abs() + self.fov.angle_down.abs());
+    // return Mat4::perspective_infinite_reverse_rh(y_fov, x_fov / y_fov, self.near);
+
+    let is_vulkan_api = false; // FIXME wgpu probably abstracts this
+    let near_z = near;
+    let far_z = far.unwrap_or(-1.);
+
+    let tan_angle_left = fov.angle_left.tan();
+    let tan_angle_right = fov.angle_right.tan();
+
+    let tan_angle_down = fov.angle_down.tan();
+    let tan_angle_up = fov.angle_up.tan();
+
+    let tan_angle_width = tan_angle_right - tan_angle_left;
+
+    // Set to tanAngleDown - tanAngleUp for a clip space with positive Y
+    // down (Vulkan). Set to tanAngleUp - tanAngleDown for a clip space with
+    // positive Y up (OpenGL / D3D / Metal).
+    // const float tanAngleHeight =
+    //     graphicsApi == GRAPHICS_VULKAN ? (tanAngleDown - tanAngleUp) : (tanAngleUp - tanAngleDown);
+    let tan_angle_height = if is_vulkan_api {
+        tan_angle_down - tan_angle_up
+    } else {
+        tan_angle_up - tan_angle_down
+    };
+
+    // Set to nearZ for a [-1,1] Z clip space (OpenGL / OpenGL ES).
+    // Set to zero for a [0,1] Z clip space (Vulkan / D3D / Metal).
+    // const float offsetZ =
+    //     (graphicsApi == GRAPHICS_OPENGL || graphicsApi == GRAPHICS_OPENGL_ES) ? nearZ : 0;
+    // FIXME handle enum of graphics apis
+    let offset_z = 0.;
+
+    let mut cols: [f32; 16] = [0.0; 16];
+
+    //  bevy uses the _reverse_ infinite projection
+    //  https://dev.theomader.com/depth-precision/
+    let z_reversal = Mat4::from_cols_array_2d(&[
+        [1f32, 0., 0., 0.],
+        [0., 1., 0., 0.],
+        [0., 0., -1., 0.],
+        [0., 0., 1., 1.],
+    ]);
+
+    if far_z <= near_z {
+        // place the far plane at infinity
+        cols[0] = 2. / tan_angle_width;
+        cols[4] = 0.;
+        cols[8] = (tan_angle_right + tan_angle_left) / tan_angle_width;
+        cols[12] = 0.;
+
+        cols[1] = 0.;
+        cols[5] = 2. / tan_angle_height;
+        cols[9] = (tan_angle_up + tan_angle_down) / tan_angle_height;
+        cols[13] = 0.;
+
+        cols[2] = 0.;
+        cols[6] = 0.;
+        cols[10] = -1.;
+        cols[14] = -(near_z + offset_z);
+
+        cols[3] = 0.;
+        cols[7] = 0.;
+        cols[11] = -1.;
+        cols[15] = 0.;
+    } else {
+        // normal projection
+        cols[0] = 2. / tan_angle_width;
+        cols[4] = 0.;
+        cols[8] = (tan_angle_right + tan_angle_left) / tan_angle_width;
+        cols[12] = 0.;
+
+        cols[1] = 0.;
+        cols[5] = 2. / tan_angle_height;
+        cols[9] = (tan_angle_up + tan_angle_down) / tan_angle_height;
+        cols[13] = 0.;
+
+        cols[2] = 0.;
+        cols[6] = 0.;
+        cols[10] = -(far_z + offset_z) / (far_z - near_z);
+        cols[14] = -(far_z * (near_z + offset_z)) / (far_z - near_z);
+
+        cols[3] = 0.;
+        cols[7] = 0.;
+        cols[11] = -1.;
+        cols[15] = 0.;
+    }
+
+    z_reversal * Mat4::from_cols_array(&cols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn fov() -> Fovf {
+        Fovf {
+            angle_left: -0.9,
+            angle_right: 0.7,
+            angle_up: 0.8,
+            angle_down: -0.6,
+        }
+    }
+
+    fn clip_depth(projection: Mat4, distance: f32) -> f32 {
+        projection.project_point3(Vec3::new(0.0, 0.0, -distance)).z
+    }
+
+    #[test]
+    fn pose_round_trip() {
+        let rotation = Quat::from_euler(EulerRot::YXZ, 0.4, -0.2, 0.1);
+        let transform = Transform::from_xyz(0.3, 1.6, -2.0).with_rotation(rotation);
+        let back = transform.to_posef().to_transform();
+        assert!(back.translation.abs_diff_eq(transform.translation, EPSILON));
+        assert!(back.rotation.abs_diff_eq(transform.rotation, EPSILON));
+        assert_eq!(back.scale, Vec3::ONE);
+    }
+
+    #[test]
+    fn global_transform_pose_drops_scale() {
+        let transform = Transform::from_xyz(1.0, 2.0, 3.0)
+            .with_rotation(Quat::from_rotation_y(1.0))
+            .with_scale(Vec3::splat(2.0));
+        let pose = GlobalTransform::from(transform).to_posef().to_transform();
+        assert!(pose.translation.abs_diff_eq(transform.translation, EPSILON));
+        assert!(pose.rotation.abs_diff_eq(transform.rotation, EPSILON));
+    }
+
+    #[test]
+    fn handedness_round_trip() {
+        let rotation = Quat::from_euler(EulerRot::XYZ, 0.3, 0.6, -0.9);
+        let transform = Transform::from_xyz(0.5, -1.0, 2.0).with_rotation(rotation);
+        let back = flip_handedness_transform(flip_handedness_transform(transform));
+        assert!(back.translation.abs_diff_eq(transform.translation, EPSILON));
+        assert!(back.rotation.abs_diff_eq(transform.rotation, EPSILON));
+    }
+
+    #[test]
+    fn handedness_rotation_matches_position() {
+        let rotation = Quat::from_euler(EulerRot::XYZ, 0.3, 0.6, -0.9);
+        let point = Vec3::new(0.2, 0.4, -1.0);
+        let flipped = flip_handedness_rotation(rotation) * flip_handedness_position(point);
+        assert!(flipped.abs_diff_eq(flip_handedness_position(rotation * point), EPSILON));
+    }
+
+    #[test]
+    fn projection_fov_edges() {
+        let fov = fov();
+        let projection = fov_to_projection(fov, 0.1, None);
+        let left = projection.project_point3(Vec3::new(fov.angle_left.tan(), 0.0, -1.0));
+        let right = projection.project_point3(Vec3::new(fov.angle_right.tan(), 0.0, -1.0));
+        let up = projection.project_point3(Vec3::new(0.0, fov.angle_up.tan(), -1.0));
+        let down = projection.project_point3(Vec3::new(0.0, fov.angle_down.tan(), -1.0));
+        assert!((left.x + 1.0).abs() < EPSILON);
+        assert!((right.x - 1.0).abs() < EPSILON);
+        assert!((up.y - 1.0).abs() < EPSILON);
+        assert!((down.y + 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn infinite_projection_depth_is_reversed() {
+        let projection = fov_to_projection(fov(), 0.1, None);
+        assert!((clip_depth(projection, 0.1) - 1.0).abs() < EPSILON);
+        assert!((clip_depth(projection, 1.0) - 0.1).abs() < EPSILON);
+        assert!(clip_depth(projection, 1e6) > 0.0);
+        assert!(clip_depth(projection, 1e6) < EPSILON);
+    }
+
+    #[test]
+    fn finite_projection_depth_is_reversed() {
+        let projection = fov_to_projection(fov(), 0.1, Some(100.0));
+        assert!((clip_depth(projection, 0.1) - 1.0).abs() < EPSILON);
+        assert!(clip_depth(projection, 100.0).abs() < EPSILON);
+        assert!(clip_depth(projection, 1.0) > clip_depth(projection, 10.0));
+        //behind the far plane is clipped
+        assert!(clip_depth(projection, 200.0) < 0.0);
+    }
+
+    #[test]
+    fn far_before_near_is_infinite() {
+        let infinite = fov_to_projection(fov(), 0.1, None);
+        let inverted = fov_to_projection(fov(), 0.1, Some(0.05));
+        assert!(infinite.abs_diff_eq(inverted, EPSILON));
+    }
+}
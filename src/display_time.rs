@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::resources::XrFrameState;
+
+/// when the frame that is being simulated will be on the display, copied out of the frame state
+/// after `xr_begin_frame`. the head and controllers are located at this time, so moving objects
+/// that are only simulated up to the start of the frame lag behind them by [`Self::lead`]
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrPredictedDisplayTime {
+    pub time: xr::Time,
+    pub period: xr::Duration,
+    /// how far the display time is ahead of the frame's `Time`. `xrWaitFrame` returns about a
+    /// display period before the frame is shown, so this is estimated as one period
+    pub lead: Duration,
+}
+
+impl Default for XrPredictedDisplayTime {
+    fn default() -> Self {
+        Self {
+            time: xr::Time::from_nanos(0),
+            period: xr::Duration::from_nanos(0),
+            lead: Duration::ZERO,
+        }
+    }
+}
+
+impl XrPredictedDisplayTime {
+    /// the display period in seconds, 0 before the first frame
+    pub fn period_secs(&self) -> f32 {
+        self.period.as_nanos().max(0) as f32 / 1e9
+    }
+
+    /// `translation` moved on by `velocity` (per second) until the display time
+    pub fn extrapolate_translation(&self, translation: Vec3, velocity: Vec3) -> Vec3 {
+        translation + velocity * self.lead.as_secs_f32()
+    }
+
+    /// `transform` moved and turned on until the display time, `angular` is in radians per
+    /// second around its axis like `SpaceVelocity::angular_velocity`
+    pub fn extrapolate(&self, transform: &Transform, linear: Vec3, angular: Vec3) -> Transform {
+        let secs = self.lead.as_secs_f32();
+        Transform {
+            translation: transform.translation + linear * secs,
+            rotation: (Quat::from_scaled_axis(angular * secs) * transform.rotation).normalize(),
+            scale: transform.scale,
+        }
+    }
+}
+
+/// renders the entity where it will be at the display time instead of where it is at the start
+/// of the frame, for projectiles and other fast objects. only the `GlobalTransform` is moved
+/// after transform propagation, the `Transform` stays where gameplay put it and children of the
+/// entity are not moved along. the velocities are in world space
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrExtrapolate {
+    /// meters per second
+    pub linear: Vec3,
+    /// radians per second around the axis
+    pub angular: Vec3,
+}
+
+pub fn update_predicted_display_time(
+    frame_state: Res<XrFrameState>,
+    mut display_time: ResMut<XrPredictedDisplayTime>,
+) {
+    let state = frame_state.lock().unwrap();
+    let period = state.predicted_display_period;
+    *display_time = XrPredictedDisplayTime {
+        time: state.predicted_display_time,
+        period,
+        lead: Duration::from_nanos(period.as_nanos().max(0) as u64),
+    };
+}
+
+pub fn extrapolate_to_display_time(
+    display_time: Res<XrPredictedDisplayTime>,
+    mut query: Query<(&mut GlobalTransform, &XrExtrapolate)>,
+) {
+    if display_time.lead.is_zero() {
+        return;
+    }
+    for (mut global, extrapolate) in &mut query {
+        let transform = global.compute_transform();
+        let extrapolated =
+            display_time.extrapolate(&transform, extrapolate.linear, extrapolate.angular);
+        *global = GlobalTransform::from(extrapolated);
+    }
+}
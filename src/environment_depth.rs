@@ -0,0 +1,257 @@
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponentPlugin;
+use bevy::render::render_resource::{
+    CommandEncoderDescriptor, Extent3d, ImageCopyTexture, Origin3d, TextureAspect, TextureUsages,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::view::ViewDepthTexture;
+use bevy::render::{Extract, Render, RenderApp};
+use openxr as xr;
+
+use crate::call_trace::{check, trace};
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::{XrFrameSet, XrRenderFrameSet};
+use crate::graphics;
+use crate::layers::{SubmittedDepth, XrLayerSubmission};
+use crate::resources::{LayerSwapchain, XrInstance, XrSession, XrSwapchain};
+use crate::xr_init::xr_only;
+use crate::xr_input::xr_camera::{Eye, XRProjection, XrCameraType};
+
+/// occludes virtual content with real geometry through XR_VARJO_environment_depth_estimation.
+/// the compositor estimates the depth of the video passthrough and compares it against the depth
+/// of the projection, so while the estimation is on the depth of the eye cameras is copied into
+/// a depth swapchain and submitted with XR_KHR_composition_layer_depth. the eye cameras need
+/// msaa off for that, multisampled depth can't be copied into the swapchain
+pub struct XrEnvironmentDepthPlugin;
+
+impl Plugin for XrEnvironmentDepthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrEnvironmentDepthEstimation>();
+        if !app.is_plugin_added::<ExtractComponentPlugin<XrCameraType>>() {
+            app.add_plugins(ExtractComponentPlugin::<XrCameraType>::default());
+        }
+        app.add_systems(
+            PreUpdate,
+            apply_environment_depth_estimation
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+        app.add_systems(PostUpdate, allow_eye_depth_copies.run_if(xr_only()));
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<XrDepthSwapchain>();
+        render_app.init_resource::<XrLayerSubmission>();
+        render_app.add_systems(ExtractSchedule, extract_environment_depth.run_if(xr_only()));
+        render_app.add_systems(
+            Render,
+            copy_eye_depth
+                .run_if(xr_only())
+                .in_set(XrRenderFrameSet::BeforeSubmit),
+        );
+    }
+}
+
+/// whether the compositor estimates the depth of the video passthrough, can be changed every
+/// frame. only has an effect with a mixed reality blend mode
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrEnvironmentDepthEstimation {
+    pub enabled: bool,
+}
+
+impl XrEnvironmentDepthEstimation {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        instance.exts().varjo_environment_depth_estimation.is_some()
+    }
+}
+
+pub fn apply_environment_depth_estimation(
+    setting: Res<XrEnvironmentDepthEstimation>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    error_log: Res<XrErrorLog>,
+    mut applied: Local<Option<bool>>,
+) {
+    if *applied == Some(setting.enabled) || (applied.is_none() && !setting.enabled) {
+        return;
+    }
+    //failures aren't retried every frame, only when the setting changes again
+    *applied = Some(setting.enabled);
+    if let Err(err) = set_environment_depth_estimation(&instance, &session, setting.enabled) {
+        error_log.report_result(XrErrorSource::Other, err);
+    }
+}
+
+/// needs XR_VARJO_environment_depth_estimation
+pub fn set_environment_depth_estimation(
+    instance: &XrInstance,
+    session: &XrSession,
+    enabled: bool,
+) -> xr::Result<()> {
+    let Some(ext) = instance.exts().varjo_environment_depth_estimation.as_ref() else {
+        return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    check(trace(
+        "xrSetEnvironmentDepthEstimationVARJO",
+        || enabled.to_string(),
+        || unsafe { (ext.set_environment_depth_estimation)(session.as_raw(), enabled.into()) },
+    ))
+}
+
+/// the eye depth textures are copied out of, bevy creates them without COPY_SRC
+pub fn allow_eye_depth_copies(mut cameras: Query<(&XrCameraType, &mut Camera3d)>) {
+    for (camera_type, mut camera_3d) in cameras.iter_mut() {
+        let usages = TextureUsages::from(camera_3d.depth_texture_usages);
+        if matches!(camera_type, XrCameraType::Xr(_)) && !usages.contains(TextureUsages::COPY_SRC) {
+            camera_3d.depth_texture_usages = (usages | TextureUsages::COPY_SRC).into();
+        }
+    }
+}
+
+/// whether the depth gets submitted this frame, with the distances of depth 0 and 1
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExtractedEnvironmentDepth {
+    pub submit: bool,
+    pub near_z: f32,
+    pub far_z: f32,
+}
+
+pub fn extract_environment_depth(
+    mut commands: Commands,
+    setting: Extract<Res<XrEnvironmentDepthEstimation>>,
+    cameras: Extract<Query<(&XrCameraType, &XRProjection)>>,
+) {
+    let projection = cameras
+        .iter()
+        .find(|(camera_type, _)| **camera_type == XrCameraType::Xr(Eye::Left))
+        .map(|(_, projection)| projection);
+    let depth = match projection {
+        //bevy renders with reversed z, depth 0 is the far end, at infinity without `clip_far`
+        Some(projection) => ExtractedEnvironmentDepth {
+            submit: setting.enabled,
+            near_z: match projection.clip_far {
+                true => projection.far,
+                false => f32::INFINITY,
+            },
+            far_z: projection.near,
+        },
+        None => default(),
+    };
+    commands.insert_resource(depth);
+}
+
+/// the depth swapchain, only exists in the render world
+#[derive(Resource, Default)]
+pub struct XrDepthSwapchain {
+    swapchain: Option<LayerSwapchain>,
+    // warned about, not retried
+    failed: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn copy_eye_depth(
+    depth: Option<Res<ExtractedEnvironmentDepth>>,
+    mut depth_swapchain: ResMut<XrDepthSwapchain>,
+    mut submission: ResMut<XrLayerSubmission>,
+    instance: Res<XrInstance>,
+    swapchain: Res<XrSwapchain>,
+    views: Query<(&XrCameraType, &ViewDepthTexture)>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    error_log: Res<XrErrorLog>,
+) {
+    submission.depth = None;
+    let Some(depth) = depth.filter(|depth| depth.submit) else {
+        return;
+    };
+    if depth_swapchain.failed || instance.exts().khr_composition_layer_depth.is_none() {
+        return;
+    }
+    let mut eyes = [None, None];
+    for (camera_type, depth_texture) in views.iter() {
+        match camera_type {
+            XrCameraType::Xr(Eye::Left) => eyes[0] = Some(&depth_texture.texture),
+            XrCameraType::Xr(Eye::Right) => eyes[1] = Some(&depth_texture.texture),
+            _ => {}
+        }
+    }
+    let [Some(left), Some(right)] = eyes else {
+        return;
+    };
+    //the usage is added by `allow_eye_depth_copies`, the first frames may not have it yet
+    if !left.usage().contains(TextureUsages::COPY_SRC)
+        || !right.usage().contains(TextureUsages::COPY_SRC)
+    {
+        return;
+    }
+    if left.sample_count() != 1 {
+        error_log.report(
+            XrErrorSource::EndFrame,
+            "the depth of multisampled eye cameras can't be submitted",
+        );
+        depth_swapchain.failed = true;
+        return;
+    }
+    let size = UVec2::new(left.width(), left.height());
+    let format = left.format();
+    let recreate = match &depth_swapchain.swapchain {
+        Some(current) => current.size() != size || current.format() != format,
+        None => true,
+    };
+    if recreate {
+        match graphics::create_depth_swapchain(&swapchain, &render_device, size, format) {
+            Ok(created) => depth_swapchain.swapchain = Some(created),
+            Err(err) => {
+                error_log.report(
+                    XrErrorSource::EndFrame,
+                    format!("couldn't create the depth swapchain: {}", err),
+                );
+                depth_swapchain.failed = true;
+                return;
+            }
+        }
+    }
+    let Some(target) = &depth_swapchain.swapchain else {
+        return;
+    };
+    let texture = match target.acquire_image() {
+        Ok(texture) => texture,
+        Err(err) => {
+            error_log.report_result(XrErrorSource::EndFrame, err);
+            return;
+        }
+    };
+    let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("xr_depth_copy"),
+    });
+    for (layer, eye) in [left, right].into_iter().enumerate() {
+        encoder.copy_texture_to_texture(
+            eye.as_image_copy(),
+            ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer as u32,
+                },
+                aspect: TextureAspect::All,
+            },
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+    render_queue.submit([encoder.finish()]);
+    if let Err(err) = target.release_image() {
+        error_log.report_result(XrErrorSource::EndFrame, err);
+        return;
+    }
+    submission.depth = Some(SubmittedDepth {
+        swapchain: target.as_raw(),
+        near_z: depth.near_z,
+        far_z: depth.far_z,
+    });
+}
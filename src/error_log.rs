@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, Instant};
+use openxr as xr;
+
+/// how many entries the log keeps
+const MAX_ENTRIES: usize = 64;
+/// the same error isn't reported again within this time, it only increases the count
+const RATE_LIMIT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrErrorSource {
+    PollEvents,
+    WaitFrame,
+    BeginFrame,
+    LocateViews,
+    EndFrame,
+    SyncActions,
+    Tracking,
+    Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct XrErrorEntry {
+    pub source: XrErrorSource,
+    pub message: String,
+    /// the openxr result, if the error came from an openxr call
+    pub result: Option<xr::sys::Result>,
+    /// time since startup the error was first seen at
+    pub first_seen: Duration,
+    /// time since startup the error was last seen at
+    pub last_seen: Duration,
+    /// how often the error happened
+    pub count: u32,
+}
+
+/// sent once for every new error, repeats within the rate limit don't send another event
+#[derive(Event, Clone, Debug)]
+pub struct XrErrorEvent(pub XrErrorEntry);
+
+#[derive(Default)]
+struct XrErrorLogInner {
+    entries: VecDeque<XrErrorEntry>,
+    pending: Vec<XrErrorEntry>,
+    last_reported: HashMap<(XrErrorSource, String), Instant>,
+}
+
+/// non fatal openxr errors and compositor warnings, shared between the main and the render world,
+/// so apps can tell the user about them instead of them only ending up in the log
+#[derive(Resource, Clone)]
+pub struct XrErrorLog {
+    start: Instant,
+    inner: Arc<Mutex<XrErrorLogInner>>,
+}
+
+impl Default for XrErrorLog {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            inner: default(),
+        }
+    }
+}
+
+impl XrErrorLog {
+    pub fn report(&self, source: XrErrorSource, message: impl ToString) {
+        self.report_inner(source, message.to_string(), None);
+    }
+
+    pub fn report_result(&self, source: XrErrorSource, result: xr::sys::Result) {
+        self.report_inner(source, result.to_string(), Some(result));
+    }
+
+    fn report_inner(
+        &self,
+        source: XrErrorSource,
+        message: String,
+        result: Option<xr::sys::Result>,
+    ) {
+        let now = Instant::now();
+        let elapsed = now - self.start;
+        let mut inner = self.inner.lock().unwrap();
+        let key = (source, message);
+        if let Some(last) = inner.last_reported.get(&key).copied() {
+            if now - last < RATE_LIMIT {
+                if let Some(entry) = inner
+                    .entries
+                    .iter_mut()
+                    .rev()
+                    .find(|e| e.source == key.0 && e.message == key.1)
+                {
+                    entry.count += 1;
+                    entry.last_seen = elapsed;
+                }
+                return;
+            }
+        }
+        warn!("{:?}: {}", source, key.1);
+        let entry = XrErrorEntry {
+            source,
+            message: key.1.clone(),
+            result,
+            first_seen: elapsed,
+            last_seen: elapsed,
+            count: 1,
+        };
+        //only entries within the rate limit matter, messages with changing details would
+        //otherwise keep the map growing
+        inner
+            .last_reported
+            .retain(|_, last| now - *last < RATE_LIMIT);
+        inner.last_reported.insert(key, now);
+        inner.entries.push_back(entry.clone());
+        while inner.entries.len() > MAX_ENTRIES {
+            inner.entries.pop_front();
+        }
+        inner.pending.push(entry);
+    }
+
+    /// the most recent errors, oldest first
+    pub fn entries(&self) -> Vec<XrErrorEntry> {
+        self.inner.lock().unwrap().entries.iter().cloned().collect()
+    }
+
+    /// the newest error from `source`
+    pub fn latest(&self, source: XrErrorSource) -> Option<XrErrorEntry> {
+        self.inner
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .rev()
+            .find(|e| e.source == source)
+            .cloned()
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.last_reported.clear();
+    }
+
+    fn drain_pending(&self) -> Vec<XrErrorEntry> {
+        std::mem::take(&mut self.inner.lock().unwrap().pending)
+    }
+}
+
+pub fn send_xr_error_events(log: Res<XrErrorLog>, mut events: EventWriter<XrErrorEvent>) {
+    events.send_batch(log.drain_pending().into_iter().map(XrErrorEvent));
+}
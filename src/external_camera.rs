@@ -0,0 +1,165 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use bevy::prelude::*;
+
+use crate::convert::flip_handedness_transform;
+use crate::mixed_reality_capture::XrMrcCamera;
+
+/// how often the file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// positions the [`XrMrcCamera`] from an `externalcamera.cfg` calibration, the file that LIV
+/// and the mixed reality capture tools of steamvr and oculus write next to the executable. the
+/// file is read again whenever it changes, so the camera can be calibrated while the app runs.
+/// a calibration enables the camera, the way the file does in other engines
+pub struct XrExternalCameraPlugin {
+    pub file: PathBuf,
+}
+
+impl Default for XrExternalCameraPlugin {
+    fn default() -> Self {
+        Self {
+            file: PathBuf::from("externalcamera.cfg"),
+        }
+    }
+}
+
+impl Plugin for XrExternalCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrMrcCamera>();
+        app.insert_resource(XrExternalCameraFile {
+            path: self.file.clone(),
+            timer: Timer::new(POLL_INTERVAL, TimerMode::Repeating),
+            modified: None,
+        });
+        app.add_systems(Update, reload_external_camera);
+    }
+}
+
+/// the calibration file and when it was last read
+#[derive(Resource, Debug)]
+pub struct XrExternalCameraFile {
+    pub path: PathBuf,
+    timer: Timer,
+    modified: Option<SystemTime>,
+}
+
+/// the values of an `externalcamera.cfg`, in the left handed space it is written in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrExternalCamera {
+    /// in meters, relative to the origin of the tracking space
+    pub position: Vec3,
+    /// euler angles in degrees, applied in the z x y order
+    pub rotation: Vec3,
+    /// vertical, in degrees
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for XrExternalCamera {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            rotation: Vec3::ZERO,
+            fov: 60.0,
+            near: 0.01,
+            far: 1000.0,
+        }
+    }
+}
+
+impl XrExternalCamera {
+    /// one `key=value` pair per line, keys this doesn't know about are skipped
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut camera = Self::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim();
+            let field = match key {
+                "x" => &mut camera.position.x,
+                "y" => &mut camera.position.y,
+                "z" => &mut camera.position.z,
+                "rx" => &mut camera.rotation.x,
+                "ry" => &mut camera.rotation.y,
+                "rz" => &mut camera.rotation.z,
+                "fov" => &mut camera.fov,
+                "near" => &mut camera.near,
+                "far" => &mut camera.far,
+                _ => continue,
+            };
+            *field = value.trim().parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: invalid value {}", key, value.trim()),
+                )
+            })?;
+        }
+        Ok(camera)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// the pose relative to the tracking root, converted to bevy's right handed space
+    pub fn pose(&self) -> Transform {
+        let rotation = Quat::from_euler(
+            EulerRot::YXZ,
+            self.rotation.y.to_radians(),
+            self.rotation.x.to_radians(),
+            self.rotation.z.to_radians(),
+        );
+        flip_handedness_transform(
+            Transform::from_translation(self.position).with_rotation(rotation),
+        )
+    }
+
+    pub fn apply(&self, camera: &mut XrMrcCamera) {
+        camera.enabled = true;
+        camera.pose = self.pose();
+        camera.fov = self.fov.to_radians();
+        camera.near = self.near;
+        camera.far = self.far;
+    }
+}
+
+pub fn reload_external_camera(
+    time: Res<Time>,
+    mut file: ResMut<XrExternalCameraFile>,
+    mut camera: ResMut<XrMrcCamera>,
+) {
+    //the first run reads the file right away
+    let first = file.modified.is_none();
+    if !file.timer.tick(time.delta()).just_finished() && !first {
+        return;
+    }
+    let modified = match fs::metadata(&file.path).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified,
+        //keep the last calibration while the file is gone or being replaced
+        Err(_) => {
+            file.modified.get_or_insert(SystemTime::UNIX_EPOCH);
+            return;
+        }
+    };
+    if file.modified == Some(modified) {
+        return;
+    }
+    file.modified = Some(modified);
+    match XrExternalCamera::load(&file.path) {
+        Ok(external) => {
+            info!("loaded the external camera from {}", file.path.display());
+            external.apply(&mut camera);
+        }
+        Err(err) => warn!(
+            "couldn't load the external camera from {}: {}",
+            file.path.display(),
+            err
+        ),
+    }
+}
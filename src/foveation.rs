@@ -0,0 +1,162 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::call_trace::{check, trace};
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrFrameSet;
+use crate::resources::{XrInstance, XrSession, XrSwapchain};
+use crate::xr_init::xr_only;
+
+/// renders the edges of the eye images at a lower resolution through XR_FB_foveation, set with
+/// [`XrFoveation`]. the level is applied to the swapchain with XR_FB_swapchain_update_state, and
+/// again whenever the swapchain is recreated. it only saves gpu time where the runtime foveates
+/// the swapchain by itself, quest runtimes expect vulkan apps to render with the fragment density
+/// map of XR_FB_foveation_vulkan, which wgpu can't
+pub struct XrFoveationPlugin;
+
+impl Plugin for XrFoveationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrFoveation>();
+        app.add_systems(
+            PreUpdate,
+            apply_foveation
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+    }
+}
+
+/// how much of the edges of the eye images is rendered at a lower resolution, from low to high
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
+pub enum XrFoveationLevel {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl XrFoveationLevel {
+    pub fn higher(self) -> Option<Self> {
+        match self {
+            Self::None => Some(Self::Low),
+            Self::Low => Some(Self::Medium),
+            Self::Medium => Some(Self::High),
+            Self::High => None,
+        }
+    }
+
+    pub fn lower(self) -> Option<Self> {
+        match self {
+            Self::None => None,
+            Self::Low => Some(Self::None),
+            Self::Medium => Some(Self::Low),
+            Self::High => Some(Self::Medium),
+        }
+    }
+
+    fn into_raw(self) -> xr::sys::FoveationLevelFB {
+        match self {
+            Self::None => xr::sys::FoveationLevelFB::NONE,
+            Self::Low => xr::sys::FoveationLevelFB::LOW,
+            Self::Medium => xr::sys::FoveationLevelFB::MEDIUM,
+            Self::High => xr::sys::FoveationLevelFB::HIGH,
+        }
+    }
+}
+
+/// the foveation of the swapchain, can be changed every frame. `dynamic` lets the runtime go
+/// below `level` while the gpu has time to spare
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrFoveation {
+    pub level: XrFoveationLevel,
+    pub dynamic: bool,
+}
+
+impl XrFoveation {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        let exts = instance.exts();
+        exts.fb_foveation.is_some()
+            && exts.fb_foveation_configuration.is_some()
+            && exts.fb_swapchain_update_state.is_some()
+    }
+}
+
+pub fn apply_foveation(
+    setting: Res<XrFoveation>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    swapchain: Res<XrSwapchain>,
+    error_log: Res<XrErrorLog>,
+    mut applied: Local<Option<(XrFoveation, xr::sys::Swapchain)>>,
+) {
+    //a recreated swapchain has a new handle and no foveation
+    let target = (*setting, swapchain.as_raw());
+    if *applied == Some(target) || (applied.is_none() && setting.level == XrFoveationLevel::None) {
+        return;
+    }
+    //failures aren't retried every frame, only when the setting or the swapchain changes
+    *applied = Some(target);
+    if let Err(err) = set_swapchain_foveation(&instance, &session, target.1, *setting) {
+        error_log.report_result(XrErrorSource::Other, err);
+    }
+}
+
+/// needs XR_FB_foveation, XR_FB_foveation_configuration and XR_FB_swapchain_update_state
+pub fn set_swapchain_foveation(
+    instance: &XrInstance,
+    session: &XrSession,
+    swapchain: xr::sys::Swapchain,
+    foveation: XrFoveation,
+) -> xr::Result<()> {
+    let exts = instance.exts();
+    let (Some(foveation_ext), Some(update_ext), Some(_)) = (
+        exts.fb_foveation.as_ref(),
+        exts.fb_swapchain_update_state.as_ref(),
+        exts.fb_foveation_configuration.as_ref(),
+    ) else {
+        return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let mut level_info = xr::sys::FoveationLevelProfileCreateInfoFB {
+        ty: xr::sys::FoveationLevelProfileCreateInfoFB::TYPE,
+        next: ptr::null_mut(),
+        level: foveation.level.into_raw(),
+        vertical_offset: 0.0,
+        dynamic: match foveation.dynamic {
+            true => xr::sys::FoveationDynamicFB::LEVEL_ENABLED,
+            false => xr::sys::FoveationDynamicFB::DISABLED,
+        },
+    };
+    let info = xr::sys::FoveationProfileCreateInfoFB {
+        ty: xr::sys::FoveationProfileCreateInfoFB::TYPE,
+        next: &mut level_info as *mut _ as *mut _,
+    };
+    let mut profile = xr::sys::FoveationProfileFB::NULL;
+    check(trace(
+        "xrCreateFoveationProfileFB",
+        || format!("{:?}", foveation),
+        || unsafe {
+            (foveation_ext.create_foveation_profile)(session.as_raw(), &info, &mut profile)
+        },
+    ))?;
+    let state = xr::sys::SwapchainStateFoveationFB {
+        ty: xr::sys::SwapchainStateFoveationFB::TYPE,
+        next: ptr::null_mut(),
+        flags: xr::sys::SwapchainStateFoveationFlagsFB::EMPTY,
+        profile,
+    };
+    let updated = check(trace("xrUpdateSwapchainFB", String::new, || unsafe {
+        (update_ext.update_swapchain)(swapchain, &state as *const _ as *const _)
+    }));
+    //the swapchain keeps the foveation, the profile isn't needed after the update
+    check(trace(
+        "xrDestroyFoveationProfileFB",
+        String::new,
+        || unsafe { (foveation_ext.destroy_foveation_profile)(profile) },
+    ))?;
+    updated
+}
@@ -0,0 +1,69 @@
+//! the openxr frame loop as system sets. a frame is waited on and begun in the main world's
+//! `PreUpdate`, then rendered and submitted in the render world's `Render`:
+//!
+//! - [`XrFrameSet::BeginFrame`]: `xr_begin_frame` polls the events, takes the frame the
+//!   [`XrFramePacer`](crate::frame_pacing::XrFramePacer) thread waited for and begins it
+//! - [`XrFrameSet::AfterBeginFrame`]: the predicted display time, the session state and the
+//!   actions are up to date, most of the per frame systems of the crate run here
+//! - [`XrRenderFrameSet::AcquireImage`]: `post_frame` acquires the swapchain image and points
+//!   the camera targets at it, before anything renders
+//! - [`XrRenderFrameSet::AfterAcquire`]: the image can be rendered into
+//! - [`XrRenderFrameSet::BeforeSubmit`]: the scene is rendered, layers copy their images and
+//!   add themselves to the submission here
+//! - [`XrRenderFrameSet::Submit`]: `end_frame` releases the image and ends the frame
+//!
+//! plugins order their systems by these sets instead of the systems of the crate
+
+use bevy::prelude::*;
+use bevy::render::renderer::render_system;
+use bevy::render::{Extract, Render, RenderSet};
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum XrFrameSet {
+    BeginFrame,
+    AfterBeginFrame,
+}
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum XrRenderFrameSet {
+    AcquireImage,
+    AfterAcquire,
+    BeforeSubmit,
+    Submit,
+}
+
+/// whether `xr_begin_frame` began a frame this update. xrEndFrame and the swapchain images need
+/// a begun frame, so `post_frame` and `end_frame` only run with one, e.g. not while the session
+/// is idle or stopping
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrFrameBegun(pub bool);
+
+pub fn extract_frame_begun(mut commands: Commands, begun: Extract<Res<XrFrameBegun>>) {
+    commands.insert_resource(**begun);
+}
+
+pub(crate) fn configure_frame_sets(app: &mut App) {
+    app.configure_sets(
+        PreUpdate,
+        (XrFrameSet::BeginFrame, XrFrameSet::AfterBeginFrame).chain(),
+    );
+}
+
+pub(crate) fn configure_render_frame_sets(render_app: &mut App) {
+    render_app.configure_sets(
+        Render,
+        (
+            (
+                XrRenderFrameSet::AcquireImage,
+                XrRenderFrameSet::AfterAcquire,
+            )
+                .chain()
+                .after(RenderSet::ExtractCommands)
+                .before(render_system),
+            (XrRenderFrameSet::BeforeSubmit, XrRenderFrameSet::Submit)
+                .chain()
+                .in_set(RenderSet::Render)
+                .after(render_system),
+        ),
+    );
+}
@@ -0,0 +1,91 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+use bevy::prelude::*;
+use bevy::utils::Instant;
+use openxr as xr;
+
+use crate::call_trace::trace;
+use crate::resources::XrFrameWaiter;
+
+/// a frame the runtime released
+pub(crate) struct XrWaitedFrame {
+    pub started: Instant,
+    pub finished: Instant,
+    pub state: xr::Result<xr::FrameState>,
+}
+
+/// calls `xrWaitFrame` on its own thread. the wait for the next frame starts as soon as the
+/// current one is begun, so it runs alongside the main world's update instead of after it, and
+/// a long update doesn't push the begin of the next frame back. the update only parks at the
+/// start of a frame the runtime hasn't released yet, without holding a system in a blocking
+/// openxr call. the thread ends when the session's pacer is dropped
+#[derive(Resource)]
+pub struct XrFramePacer {
+    requests: Sender<()>,
+    frames: Mutex<Receiver<XrWaitedFrame>>,
+    waiting: bool,
+}
+
+impl XrFramePacer {
+    pub fn new(frame_waiter: XrFrameWaiter) -> Self {
+        let (requests, requested) = channel::<()>();
+        let (sender, frames) = channel();
+        thread::Builder::new()
+            .name("xr_wait_frame".to_string())
+            .spawn(move || {
+                //one wait per request, a second one before the frame is begun would only block
+                while requested.recv().is_ok() {
+                    let started = Instant::now();
+                    let state = trace("xrWaitFrame", String::new, || {
+                        frame_waiter.lock().unwrap().wait()
+                    });
+                    let frame = XrWaitedFrame {
+                        started,
+                        finished: Instant::now(),
+                        state,
+                    };
+                    if sender.send(frame).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("failed to spawn the frame pacing thread");
+        Self {
+            requests,
+            frames: Mutex::new(frames),
+            waiting: false,
+        }
+    }
+
+    /// whether the thread is in `xrWaitFrame` or has a frame that wasn't taken yet
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    /// starts waiting for the next frame, once the current one is begun
+    pub(crate) fn wait_next(&mut self) {
+        if !self.waiting {
+            self.waiting = self.requests.send(()).is_ok();
+        }
+    }
+
+    /// the next frame, parks until the runtime releases it. `None` if the thread is gone
+    pub(crate) fn wait(&mut self) -> Option<XrWaitedFrame> {
+        self.wait_next();
+        if !self.waiting {
+            return None;
+        }
+        self.waiting = false;
+        self.frames.get_mut().unwrap().recv().ok()
+    }
+
+    /// drops the frame that is waited for, before the session is ended
+    pub(crate) fn discard(&mut self) {
+        if self.waiting {
+            self.waiting = false;
+            let _ = self.frames.get_mut().unwrap().recv();
+        }
+    }
+}
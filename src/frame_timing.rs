@@ -0,0 +1,169 @@
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use bevy::log::{info, warn};
+use bevy::utils::Instant;
+use openxr as xr;
+
+/// setting this environment variable to a file path turns on the frame timing log at startup
+pub const FRAME_TIMING_ENV_VAR: &str = "BEVY_OXR_FRAME_TIMING";
+
+const HEADER: &str = "frame,time_ms,wait_ms,app_cpu_ms,render_cpu_ms,predicted_period_ms,missed_frames,should_render,runtime_wait_ms";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LOG: Mutex<Option<FrameTimingLog>> = Mutex::new(None);
+
+struct FrameTimingLog {
+    writer: BufWriter<std::fs::File>,
+    start: Instant,
+    frame: u64,
+    last_display_time: Option<i64>,
+    current: PendingFrame,
+}
+
+#[derive(Default)]
+struct PendingFrame {
+    wait: Option<(Instant, Instant)>,
+    runtime_wait: Duration,
+    render_start: Option<Instant>,
+    display_time: i64,
+    period: i64,
+    should_render: bool,
+}
+
+/// appends a row per frame with the time the main world was parked waiting for the frame, the
+/// time spent on the cpu in the app and render worlds, the predicted display period, how many
+/// frames were missed and the time the pacing thread spent in `xrWaitFrame` to `path`. the
+/// header is only written to new files, so runs from different devices and builds can be
+/// collected in one file
+pub fn enable_frame_timing_log(path: impl AsRef<Path>) -> std::io::Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path.as_ref())?;
+    let empty = file.metadata()?.len() == 0;
+    let mut writer = BufWriter::new(file);
+    if empty {
+        writeln!(writer, "{}", HEADER)?;
+    }
+    *LOG.lock().unwrap() = Some(FrameTimingLog {
+        writer,
+        start: Instant::now(),
+        frame: 0,
+        last_display_time: None,
+        current: PendingFrame::default(),
+    });
+    ENABLED.store(true, Ordering::Relaxed);
+    info!("logging frame timings to {}", path.as_ref().display());
+    Ok(())
+}
+
+pub fn disable_frame_timing_log() {
+    ENABLED.store(false, Ordering::Relaxed);
+    if let Some(mut log) = LOG.lock().unwrap().take() {
+        let _ = log.writer.flush();
+    }
+}
+
+pub fn frame_timing_log_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn enable_frame_timing_log_from_env() {
+    if frame_timing_log_enabled() {
+        return;
+    }
+    if let Ok(path) = std::env::var(FRAME_TIMING_ENV_VAR) {
+        if let Err(err) = enable_frame_timing_log(&path) {
+            warn!("failed to create frame timing log {}: {}", path, err);
+        }
+    }
+}
+
+/// called once the main world got the frame. `started` and `finished` are when the main world
+/// began and stopped waiting for the pacing thread, `runtime_wait` is how long the thread was in
+/// `xrWaitFrame`, which mostly overlaps the last update
+pub(crate) fn frame_waited(
+    started: Instant,
+    finished: Instant,
+    runtime_wait: Duration,
+    state: &xr::FrameState,
+) {
+    if !frame_timing_log_enabled() {
+        return;
+    }
+    if let Some(log) = LOG.lock().unwrap().as_mut() {
+        log.current = PendingFrame {
+            wait: Some((started, finished)),
+            runtime_wait,
+            render_start: None,
+            display_time: state.predicted_display_time.as_nanos(),
+            period: state.predicted_display_period.as_nanos(),
+            should_render: state.should_render,
+        };
+    }
+}
+
+/// called when the render world starts working on the frame
+pub(crate) fn render_started() {
+    if !frame_timing_log_enabled() {
+        return;
+    }
+    if let Some(log) = LOG.lock().unwrap().as_mut() {
+        log.current.render_start = Some(Instant::now());
+    }
+}
+
+/// called after `xrEndFrame`, writes the row of the frame
+pub(crate) fn frame_ended() {
+    if !frame_timing_log_enabled() {
+        return;
+    }
+    let mut guard = LOG.lock().unwrap();
+    let Some(log) = guard.as_mut() else {
+        return;
+    };
+    let ended = Instant::now();
+    let frame = std::mem::take(&mut log.current);
+    let Some((wait_start, wait_end)) = frame.wait else {
+        return;
+    };
+    let render_start = frame.render_start.unwrap_or(ended);
+    //the runtime skips display times for frames we didn't submit in time
+    let missed = match (log.last_display_time, frame.period > 0) {
+        (Some(last), true) => {
+            let elapsed = frame.display_time - last;
+            ((elapsed as f64 / frame.period as f64).round() as i64 - 1).max(0)
+        }
+        _ => 0,
+    };
+    log.last_display_time = Some(frame.display_time);
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let result = writeln!(
+        log.writer,
+        "{},{:.3},{:.3},{:.3},{:.3},{:.3},{},{},{:.3}",
+        log.frame,
+        ms(wait_start.saturating_duration_since(log.start)),
+        ms(wait_end.saturating_duration_since(wait_start)),
+        ms(render_start.saturating_duration_since(wait_end)),
+        ms(ended.saturating_duration_since(render_start)),
+        frame.period as f64 / 1_000_000.0,
+        missed,
+        frame.should_render as u8,
+        ms(frame.runtime_wait)
+    )
+    .and_then(|_| log.writer.flush());
+    log.frame += 1;
+    if let Err(err) = result {
+        warn!(
+            "failed to write the frame timing log, disabling it: {}",
+            err
+        );
+        ENABLED.store(false, Ordering::Relaxed);
+        *guard = None;
+    }
+}
@@ -1,38 +1,99 @@
 mod vulkan;
 
+use bevy::prelude::Resource;
 use bevy::render::renderer::{RenderAdapter, RenderAdapterInfo, RenderDevice, RenderQueue};
 use bevy::window::RawHandleWrapper;
 use wgpu::Instance;
 
-use crate::input::XrInput;
-use crate::resources::{
-    XrEnvironmentBlendMode, XrFormat, XrFrameState, XrFrameWaiter, XrInstance, XrResolution,
-    XrSession, XrSessionRunning, XrSwapchain, XrViews,
-};
+use crate::input::XrStageOrigin;
+use crate::resources::{LayerSwapchain, Swapchain, XrInstance, XrSupersampling, XrSwapchainUsage};
+use crate::xr_init::XrRenderData;
+use crate::OpenXrSettings;
 
 use openxr as xr;
 
-pub fn initialize_xr_graphics(
+/// the openxr instance and the graphics device made for it, everything needed to create a
+/// session later
+#[derive(Resource, Clone)]
+pub(crate) struct XrGraphicsContext {
+    pub instance: XrInstance,
+    /// every extension the runtime offers, the enabled ones are in `instance.exts()`
+    pub available_extensions: xr::ExtensionSet,
+    pub system: xr::SystemId,
+    pub view_configuration: xr::ViewConfigurationType,
+    pub blend_mode: xr::EnvironmentBlendMode,
+    pub format: wgpu::TextureFormat,
+    pub swapchain_usage: XrSwapchainUsage,
+    pub supersampling: XrSupersampling,
+    pub stage_origin: XrStageOrigin,
+    pub reference_space: xr::ReferenceSpaceType,
+    vk_instance: u64,
+    vk_physical_device: u64,
+    vk_device: u64,
+    queue_family_index: u32,
+}
+
+pub fn initialize_xr_device(
     window: Option<RawHandleWrapper>,
+    settings: &OpenXrSettings,
 ) -> anyhow::Result<(
     RenderDevice,
     RenderQueue,
     RenderAdapterInfo,
     RenderAdapter,
     Instance,
-    XrInstance,
-    XrSession,
-    XrEnvironmentBlendMode,
-    XrResolution,
-    XrFormat,
-    XrSessionRunning,
-    XrFrameWaiter,
-    XrSwapchain,
-    XrInput,
-    XrViews,
-    XrFrameState,
+    XrGraphicsContext,
 )> {
-    vulkan::initialize_xr_graphics(window)
+    vulkan::initialize_xr_device(window, settings)
+}
+
+pub fn create_xr_session(
+    context: &XrGraphicsContext,
+    render_device: &RenderDevice,
+) -> anyhow::Result<XrRenderData> {
+    vulkan::create_xr_session(context, render_device)
+}
+
+pub fn create_layer_swapchain(
+    swapchain: &Swapchain,
+    render_device: &RenderDevice,
+    size: bevy::math::UVec2,
+    format: wgpu::TextureFormat,
+) -> anyhow::Result<LayerSwapchain> {
+    match swapchain {
+        Swapchain::Vulkan(swapchain) => {
+            vulkan::create_layer_swapchain(&swapchain.session, render_device, size, format)
+        }
+    }
+}
+
+pub fn create_depth_swapchain(
+    swapchain: &Swapchain,
+    render_device: &RenderDevice,
+    size: bevy::math::UVec2,
+    format: wgpu::TextureFormat,
+) -> anyhow::Result<LayerSwapchain> {
+    match swapchain {
+        Swapchain::Vulkan(swapchain) => {
+            vulkan::create_depth_swapchain(&swapchain.session, render_device, size, format)
+        }
+    }
+}
+
+/// replaces the images of the eye swapchain, see [`crate::swapchain_recreation`]
+pub fn recreate_swapchain(
+    swapchain: &Swapchain,
+    render_device: &RenderDevice,
+    resolution: bevy::math::UVec2,
+    max: bevy::math::UVec2,
+    format: wgpu::TextureFormat,
+    storage: bool,
+) -> anyhow::Result<bevy::math::UVec2> {
+    match swapchain {
+        Swapchain::Vulkan(swapchain) => {
+            vulkan::recreate_swapchain(swapchain, render_device, resolution, max, format, storage)
+        }
+    }
 }
 
 pub fn xr_entry() -> anyhow::Result<xr::Entry> {
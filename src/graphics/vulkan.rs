@@ -1,46 +1,40 @@
 use std::ffi::{c_void, CString};
-use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
 use anyhow::Context;
 use ash::vk::{self, Handle};
-use bevy::math::uvec2;
 use bevy::prelude::*;
 use bevy::render::renderer::{RenderAdapter, RenderAdapterInfo, RenderDevice, RenderQueue};
 use bevy::window::RawHandleWrapper;
 use openxr as xr;
 use wgpu::Instance;
 
+use super::XrGraphicsContext;
+use crate::boundary_visibility::BOUNDARY_VISIBILITY_EXTENSION;
+use crate::call_trace::trace;
+use crate::idle_throttle::USER_PRESENCE_EXTENSION;
 use crate::input::XrInput;
 use crate::resources::{
-    Swapchain, SwapchainInner, XrEnvironmentBlendMode, XrFormat, XrFrameState, XrFrameWaiter,
-    XrInstance, XrResolution, XrSession, XrSessionRunning, XrSwapchain, XrViews,
+    LayerSwapchain, LayerSwapchainInner, Swapchain, SwapchainImageViews, SwapchainInner,
+    XrViewSizes,
 };
-use crate::VIEW_TYPE;
-
-pub fn initialize_xr_graphics(
+use crate::scene::SPATIAL_ENTITY_MESH_EXTENSION;
+use crate::xr_init::XrRenderData;
+use crate::xr_input::hands::multimodal::SIMULTANEOUS_HANDS_AND_CONTROLLERS_EXTENSION;
+use crate::xr_input::touch_pro::TOUCH_CONTROLLER_PLUS_EXTENSION;
+use crate::xr_input::virtual_keyboard::VIRTUAL_KEYBOARD_EXTENSION;
+use crate::OpenXrSettings;
+
+pub fn initialize_xr_device(
     window: Option<RawHandleWrapper>,
-    // Horrible hack to get the Handtacking extension Loaded, Replace with good system to load
-    // any extension at some point
+    settings: &OpenXrSettings,
 ) -> anyhow::Result<(
     RenderDevice,
     RenderQueue,
     RenderAdapterInfo,
     RenderAdapter,
     Instance,
-    XrInstance,
-    XrSession,
-    XrEnvironmentBlendMode,
-    XrResolution,
-    XrFormat,
-    XrSessionRunning,
-    XrFrameWaiter,
-    XrSwapchain,
-    XrInput,
-    XrViews,
-    XrFrameState,
-    // Horrible hack to get the Handtacking extension Loaded, Replace with good system to load
-    // any extension at some point
+    XrGraphicsContext,
 )> {
     use wgpu_hal::{api::Vulkan as V, Api};
 
@@ -53,30 +47,126 @@ pub fn initialize_xr_graphics(
     assert!(available_extensions.khr_vulkan_enable2);
     info!("available xr exts: {:#?}", available_extensions);
 
-    let mut enabled_extensions = xr::ExtensionSet::default();
+    //the ones of the settings are kept, the crate only adds to them
+    let mut enabled_extensions = settings.extensions.clone();
+    enabled_extensions.other.retain(|extension| {
+        let available = available_extensions.other.contains(extension);
+        if !available {
+            warn!(
+                "the runtime doesn't have the requested extension {}",
+                extension
+            );
+        }
+        available
+    });
+    //the named ones too, the instance can't be created with an extension the runtime lacks
+    macro_rules! keep_available {
+        ($($extension:ident),* $(,)?) => {
+            $(
+                if enabled_extensions.$extension && !available_extensions.$extension {
+                    warn!(
+                        "the runtime doesn't have the requested extension {}",
+                        stringify!($extension)
+                    );
+                    enabled_extensions.$extension = false;
+                }
+            )*
+        };
+    }
+    keep_available!(
+        khr_composition_layer_cylinder,
+        khr_composition_layer_equirect2,
+        khr_composition_layer_cube,
+        khr_convert_timespec_time,
+        ext_debug_utils,
+        ext_hand_joints_motion_range,
+        fb_color_space,
+        fb_foveation_vulkan,
+        fb_swapchain_update_state_vulkan,
+        fb_hand_tracking_aim,
+        fb_hand_tracking_capsules,
+        fb_hand_tracking_mesh,
+        msft_unbounded_reference_space,
+    );
     enabled_extensions.khr_vulkan_enable2 = true;
     #[cfg(target_os = "android")]
     {
         enabled_extensions.khr_android_create_instance = true;
     }
     enabled_extensions.ext_hand_tracking = available_extensions.ext_hand_tracking;
+    enabled_extensions.ext_eye_gaze_interaction = available_extensions.ext_eye_gaze_interaction;
+    enabled_extensions.fb_face_tracking = available_extensions.fb_face_tracking;
+    enabled_extensions.fb_display_refresh_rate = available_extensions.fb_display_refresh_rate;
+    enabled_extensions.khr_composition_layer_color_scale_bias =
+        available_extensions.khr_composition_layer_color_scale_bias;
+    enabled_extensions.khr_visibility_mask = available_extensions.khr_visibility_mask;
+    enabled_extensions.fb_render_model = available_extensions.fb_render_model;
+    enabled_extensions.ext_performance_settings = available_extensions.ext_performance_settings;
+    enabled_extensions.fb_spatial_entity = available_extensions.fb_spatial_entity;
+    enabled_extensions.fb_spatial_entity_storage = available_extensions.fb_spatial_entity_storage;
+    enabled_extensions.fb_spatial_entity_query = available_extensions.fb_spatial_entity_query;
+    enabled_extensions.fb_spatial_entity_sharing = available_extensions.fb_spatial_entity_sharing;
+    enabled_extensions.fb_spatial_entity_user = available_extensions.fb_spatial_entity_user;
+    enabled_extensions.fb_scene = available_extensions.fb_scene;
+    enabled_extensions.fb_passthrough = available_extensions.fb_passthrough;
+    enabled_extensions.fb_triangle_mesh = available_extensions.fb_triangle_mesh;
+    enabled_extensions.varjo_environment_depth_estimation =
+        available_extensions.varjo_environment_depth_estimation;
+    enabled_extensions.khr_composition_layer_depth =
+        available_extensions.khr_composition_layer_depth;
+    enabled_extensions.epic_view_configuration_fov =
+        available_extensions.epic_view_configuration_fov;
+    enabled_extensions.fb_touch_controller_pro = available_extensions.fb_touch_controller_pro;
+    enabled_extensions.fb_foveation = available_extensions.fb_foveation;
+    enabled_extensions.fb_foveation_configuration = available_extensions.fb_foveation_configuration;
+    enabled_extensions.fb_swapchain_update_state = available_extensions.fb_swapchain_update_state;
+    // extensions the openxr crate has no bindings for
+    for extension in [
+        BOUNDARY_VISIBILITY_EXTENSION,
+        VIRTUAL_KEYBOARD_EXTENSION,
+        USER_PRESENCE_EXTENSION,
+        SIMULTANEOUS_HANDS_AND_CONTROLLERS_EXTENSION,
+        TOUCH_CONTROLLER_PLUS_EXTENSION,
+        SPATIAL_ENTITY_MESH_EXTENSION,
+    ] {
+        let requested = enabled_extensions.other.iter().any(|ext| ext == extension);
+        if !requested
+            && available_extensions
+                .other
+                .iter()
+                .any(|ext| ext == extension)
+        {
+            enabled_extensions.other.push(extension.to_string());
+        }
+    }
     // enabled_extensions.ext_hand_joints_motion_range = available_extensions.ext_hand_joints_motion_range;
-    
 
     let available_layers = xr_entry.enumerate_layers()?;
     info!("available xr layers: {:#?}", available_layers);
 
-    let xr_instance = xr_entry.create_instance(
-        &xr::ApplicationInfo {
-            application_name: "Ambient",
-            ..Default::default()
+    let xr_instance = trace(
+        "xrCreateInstance",
+        || format!("{:?}", enabled_extensions),
+        || {
+            xr_entry.create_instance(
+                &xr::ApplicationInfo {
+                    application_name: &settings.app_name,
+                    application_version: settings.app_version,
+                    engine_name: &settings.engine_name,
+                    engine_version: settings.engine_version,
+                },
+                &enabled_extensions,
+                &[],
+            )
         },
-        &enabled_extensions,
-        &[],
     )?;
     info!("created instance");
     let instance_props = xr_instance.properties()?;
-    let xr_system_id = xr_instance.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)?;
+    let xr_system_id = trace(
+        "xrGetSystem",
+        || format!("{:?}", settings.form_factor),
+        || xr_instance.system(settings.form_factor),
+    )?;
     info!("created system");
     let system_props = xr_instance.system_properties(xr_system_id).unwrap();
     info!(
@@ -90,7 +180,17 @@ pub fn initialize_xr_graphics(
         }
     );
 
-    let blend_mode = xr_instance.enumerate_environment_blend_modes(xr_system_id, VIEW_TYPE)?[0];
+    let blend_modes = trace(
+        "xrEnumerateEnvironmentBlendModes",
+        || format!("{:?}", settings.view_configuration),
+        || xr_instance.enumerate_environment_blend_modes(xr_system_id, settings.view_configuration),
+    )?;
+    let blend_mode = settings
+        .blend_modes
+        .iter()
+        .find(|blend_mode| blend_modes.contains(blend_mode))
+        .copied()
+        .unwrap_or(blend_modes[0]);
 
     #[cfg(not(target_os = "android"))]
     let vk_target_version = vk::make_api_version(0, 1, 2, 0);
@@ -102,7 +202,9 @@ pub fn initialize_xr_graphics(
     #[cfg(target_os = "android")]
     let vk_target_version_xr = xr::Version::new(1, 1, 0);
 
-    let reqs = xr_instance.graphics_requirements::<xr::Vulkan>(xr_system_id)?;
+    let reqs = trace("xrGetVulkanGraphicsRequirements2KHR", String::new, || {
+        xr_instance.graphics_requirements::<xr::Vulkan>(xr_system_id)
+    })?;
     if vk_target_version_xr < reqs.min_api_version_supported
         || vk_target_version_xr.major() > reqs.max_api_version_supported.major()
     {
@@ -131,28 +233,34 @@ pub fn initialize_xr_graphics(
     let vk_instance = unsafe {
         let extensions_cchar: Vec<_> = extensions.iter().map(|s| s.as_ptr()).collect();
 
-        let app_name = CString::new("Ambient")?;
+        let app_name = CString::new(settings.app_name.as_str())?;
+        let engine_name = CString::new(settings.engine_name.as_str())?;
         let vk_app_info = vk::ApplicationInfo::builder()
             .application_name(&app_name)
-            .application_version(1)
-            .engine_name(&app_name)
-            .engine_version(1)
+            .application_version(settings.app_version)
+            .engine_name(&engine_name)
+            .engine_version(settings.engine_version)
             .api_version(vk_target_version);
 
-        let vk_instance = xr_instance
-            .create_vulkan_instance(
-                xr_system_id,
-                std::mem::transmute(vk_entry.static_fn().get_instance_proc_addr),
-                &vk::InstanceCreateInfo::builder()
-                    .application_info(&vk_app_info)
-                    .enabled_extension_names(&extensions_cchar) as *const _
-                    as *const _,
-            )
-            .context("XR error creating Vulkan instance")
-            .unwrap()
-            .map_err(vk::Result::from_raw)
-            .context("Vulkan error creating Vulkan instance")
-            .unwrap();
+        let vk_instance = trace(
+            "xrCreateVulkanInstanceKHR",
+            || format!("{:?}", extensions),
+            || {
+                xr_instance.create_vulkan_instance(
+                    xr_system_id,
+                    std::mem::transmute(vk_entry.static_fn().get_instance_proc_addr),
+                    &vk::InstanceCreateInfo::builder()
+                        .application_info(&vk_app_info)
+                        .enabled_extension_names(&extensions_cchar) as *const _
+                        as *const _,
+                )
+            },
+        )
+        .context("XR error creating Vulkan instance")
+        .unwrap()
+        .map_err(vk::Result::from_raw)
+        .context("Vulkan error creating Vulkan instance")
+        .unwrap();
 
         ash::Instance::load(
             vk_entry.static_fn(),
@@ -161,12 +269,11 @@ pub fn initialize_xr_graphics(
     };
     info!("created vulkan instance");
 
-    let vk_instance_ptr = vk_instance.handle().as_raw() as *const c_void;
-
     let vk_physical_device = vk::PhysicalDevice::from_raw(unsafe {
-        xr_instance.vulkan_graphics_device(xr_system_id, vk_instance.handle().as_raw() as _)? as _
+        trace("xrGetVulkanGraphicsDevice2KHR", String::new, || {
+            xr_instance.vulkan_graphics_device(xr_system_id, vk_instance.handle().as_raw() as _)
+        })? as _
     });
-    let vk_physical_device_ptr = vk_physical_device.as_raw() as *const c_void;
 
     let vk_device_properties =
         unsafe { vk_instance.get_physical_device_properties(vk_physical_device) };
@@ -189,20 +296,22 @@ pub fn initialize_xr_graphics(
         )?
     };
 
-    let wgpu_features = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
-        | wgpu::Features::MULTIVIEW
-        | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT
-        | wgpu::Features::MULTI_DRAW_INDIRECT;
-
     let wgpu_exposed_adapter = wgpu_vk_instance
         .expose_adapter(vk_physical_device)
         .context("failed to expose adapter")?;
 
+    //timestamp queries are optional, they're only used for the per eye pass durations
+    let wgpu_features = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+        | wgpu::Features::MULTIVIEW
+        | wgpu::Features::MULTI_DRAW_INDIRECT_COUNT
+        | wgpu::Features::MULTI_DRAW_INDIRECT
+        | (wgpu_exposed_adapter.features & wgpu::Features::TIMESTAMP_QUERY);
+
     let enabled_extensions = wgpu_exposed_adapter
         .adapter
         .required_device_extensions(wgpu_features);
 
-    let (wgpu_open_device, vk_device_ptr, queue_family_index) = {
+    let (wgpu_open_device, vk_device_handle, queue_family_index) = {
         let extensions_cchar: Vec<_> = device_extensions.iter().map(|s| s.as_ptr()).collect();
         let mut enabled_phd_features = wgpu_exposed_adapter
             .adapter
@@ -225,20 +334,25 @@ pub fn initialize_xr_graphics(
             .enabled_extension_names(&extensions_cchar)
             .build();
         let vk_device = unsafe {
-            let vk_device = xr_instance
-                .create_vulkan_device(
-                    xr_system_id,
-                    std::mem::transmute(vk_entry.static_fn().get_instance_proc_addr),
-                    vk_physical_device.as_raw() as _,
-                    &info as *const _ as *const _,
-                )
-                .context("XR error creating Vulkan device")?
-                .map_err(vk::Result::from_raw)
-                .context("Vulkan error creating Vulkan device")?;
+            let vk_device = trace(
+                "xrCreateVulkanDeviceKHR",
+                || format!("{:?}", device_extensions),
+                || {
+                    xr_instance.create_vulkan_device(
+                        xr_system_id,
+                        std::mem::transmute(vk_entry.static_fn().get_instance_proc_addr),
+                        vk_physical_device.as_raw() as _,
+                        &info as *const _ as *const _,
+                    )
+                },
+            )
+            .context("XR error creating Vulkan device")?
+            .map_err(vk::Result::from_raw)
+            .context("Vulkan error creating Vulkan device")?;
 
             ash::Device::load(vk_instance.fp_v1_0(), vk::Device::from_raw(vk_device as _))
         };
-        let vk_device_ptr = vk_device.handle().as_raw() as *const c_void;
+        let vk_device_handle = vk_device.handle().as_raw();
 
         let wgpu_open_device = unsafe {
             wgpu_exposed_adapter.adapter.device_from_raw(
@@ -253,7 +367,7 @@ pub fn initialize_xr_graphics(
 
         (
             wgpu_open_device,
-            vk_device_ptr,
+            vk_device_handle,
             family_info.queue_family_index,
         )
     };
@@ -280,21 +394,6 @@ pub fn initialize_xr_graphics(
         )
     }?;
 
-    let (session, frame_wait, frame_stream) = unsafe {
-        xr_instance.create_session::<xr::Vulkan>(
-            xr_system_id,
-            &xr::vulkan::SessionCreateInfo {
-                instance: vk_instance_ptr,
-                physical_device: vk_physical_device_ptr,
-                device: vk_device_ptr,
-                queue_family_index,
-                queue_index: 0,
-            },
-        )
-    }?;
-
-    let views = xr_instance.enumerate_view_configuration_views(xr_system_id, VIEW_TYPE)?;
-
     let surface = window.map(|wrapper| unsafe {
         // SAFETY: Plugins should be set up on the main thread.
         let handle = wrapper.get_handle();
@@ -307,29 +406,164 @@ pub fn initialize_xr_graphics(
         .map(|surface| surface.get_capabilities(&wgpu_adapter).formats[0])
         .unwrap_or(wgpu::TextureFormat::Rgba8UnormSrgb);
 
-    let resolution = uvec2(
-        views[0].recommended_image_rect_width,
-        views[0].recommended_image_rect_height,
-    );
+    Ok((
+        wgpu_device.into(),
+        RenderQueue(Arc::new(wgpu_queue)),
+        RenderAdapterInfo(wgpu_adapter.get_info()),
+        RenderAdapter(Arc::new(wgpu_adapter)),
+        wgpu_instance,
+        XrGraphicsContext {
+            instance: xr_instance.into(),
+            available_extensions,
+            system: xr_system_id,
+            view_configuration: settings.view_configuration,
+            blend_mode,
+            format: swapchain_format,
+            swapchain_usage: settings.swapchain_usage,
+            supersampling: settings.supersampling,
+            stage_origin: settings.stage_origin,
+            reference_space: settings.reference_space,
+            vk_instance: vk_instance.handle().as_raw(),
+            vk_physical_device: vk_physical_device.as_raw(),
+            vk_device: vk_device_handle,
+            queue_family_index,
+        },
+    ))
+}
 
-    let handle = session
-        .create_swapchain(&xr::SwapchainCreateInfo {
-            create_flags: xr::SwapchainCreateFlags::EMPTY,
-            usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT
-                | xr::SwapchainUsageFlags::SAMPLED,
-            format: wgpu_to_vulkan(swapchain_format).as_raw() as _,
-            // The Vulkan graphics pipeline we create is not set up for multisampling,
-            // so we hardcode this to 1. If we used a proper multisampling setup, we
-            // could set this to `views[0].recommended_swapchain_sample_count`.
-            sample_count: 1,
-            width: resolution.x,
-            height: resolution.y,
-            face_count: 1,
-            array_size: 2,
-            mip_count: 1,
+/// creates the session and swapchain on a device made by [`initialize_xr_device`]
+pub fn create_xr_session(
+    context: &XrGraphicsContext,
+    render_device: &RenderDevice,
+) -> anyhow::Result<XrRenderData> {
+    let xr_instance = &context.instance;
+    let xr_system_id = context.system;
+    let queue_family_index = context.queue_family_index;
+    let swapchain_format = context.format;
+
+    let (session, frame_wait, frame_stream) = trace(
+        "xrCreateSession",
+        || format!("{:?}, queue family {}", xr_system_id, queue_family_index),
+        || unsafe {
+            xr_instance.create_session::<xr::Vulkan>(
+                xr_system_id,
+                &xr::vulkan::SessionCreateInfo {
+                    instance: context.vk_instance as *const c_void,
+                    physical_device: context.vk_physical_device as *const c_void,
+                    device: context.vk_device as *const c_void,
+                    queue_family_index,
+                    queue_index: 0,
+                },
+            )
+        },
+    )?;
+
+    let views = trace(
+        "xrEnumerateViewConfigurationViews",
+        || format!("{:?}", context.view_configuration),
+        || xr_instance.enumerate_view_configuration_views(xr_system_id, context.view_configuration),
+    )?;
+    //one camera renders each eye
+    if views.len() != 2 {
+        anyhow::bail!(
+            "{:?} has {} views, only stereo is supported",
+            context.view_configuration,
+            views.len()
+        );
+    }
+    let view_sizes = XrViewSizes::from_view(&views[0]);
+    let resolution = view_sizes.supersampled(context.supersampling.0);
+
+    let (handle, buffers) = create_swapchain(
+        &session,
+        render_device,
+        resolution,
+        swapchain_format,
+        context.swapchain_usage.storage,
+    )?;
+    let views = buffers.iter().map(SwapchainImageViews::new).collect();
+
+    Ok(XrRenderData {
+        xr_instance: xr_instance.clone(),
+        xr_session: session.clone().into_any_graphics().into(),
+        xr_blend_mode: context.blend_mode.into(),
+        xr_resolution: resolution.into(),
+        xr_view_sizes: view_sizes,
+        xr_format: swapchain_format.into(),
+        xr_frame_waiter: Mutex::new(frame_wait).into(),
+        xr_swapchain: Swapchain::Vulkan(SwapchainInner {
+            session: session.clone(),
+            stream: Mutex::new(frame_stream),
+            handle: Mutex::new(handle),
+            buffers: Mutex::new(buffers),
+            views: Mutex::new(views),
+            image_index: Mutex::new(0),
         })
-        .unwrap();
-    let images = handle.enumerate_images().unwrap();
+        .into(),
+        xr_input: XrInput::with_reference_space(
+            (**xr_instance).clone(),
+            session.into_any_graphics(),
+            context.reference_space,
+            context.stage_origin.0,
+        )?,
+        xr_views: default(),
+        xr_frame_state: Mutex::new(xr::FrameState {
+            predicted_display_time: xr::Time::from_nanos(1),
+            predicted_display_period: xr::Duration::from_nanos(1),
+            should_render: true,
+        })
+        .into(),
+    })
+}
+
+/// the swapchain the eyes render into, both eyes are layers of one array image
+fn create_swapchain(
+    session: &xr::Session<xr::Vulkan>,
+    render_device: &RenderDevice,
+    resolution: UVec2,
+    swapchain_format: wgpu::TextureFormat,
+    storage: bool,
+) -> anyhow::Result<(xr::Swapchain<xr::Vulkan>, Vec<wgpu::Texture>)> {
+    use wgpu_hal::{api::Vulkan as V, Api};
+
+    //storage usage lets compute passes write into the images, see XrSwapchainUsage
+    let (xr_usage, hal_usage, wgpu_usage) = match storage {
+        true => (
+            xr::SwapchainUsageFlags::UNORDERED_ACCESS,
+            wgpu_hal::TextureUses::STORAGE_READ_WRITE,
+            wgpu::TextureUsages::STORAGE_BINDING,
+        ),
+        false => (
+            xr::SwapchainUsageFlags::EMPTY,
+            wgpu_hal::TextureUses::empty(),
+            wgpu::TextureUsages::empty(),
+        ),
+    };
+    let vk_format = vulkan_swapchain_format(swapchain_format)?;
+    let swapchain_info = xr::SwapchainCreateInfo {
+        create_flags: xr::SwapchainCreateFlags::EMPTY,
+        usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT
+            | xr::SwapchainUsageFlags::SAMPLED
+            | xr_usage,
+        format: vk_format,
+        // The Vulkan graphics pipeline we create is not set up for multisampling,
+        // so we hardcode this to 1. If we used a proper multisampling setup, we
+        // could set this to `views[0].recommended_swapchain_sample_count`.
+        sample_count: 1,
+        width: resolution.x,
+        height: resolution.y,
+        face_count: 1,
+        array_size: 2,
+        mip_count: 1,
+    };
+    let handle = trace(
+        "xrCreateSwapchain",
+        || format!("{}x{}, {:?}", resolution.x, resolution.y, swapchain_format),
+        || session.create_swapchain(&swapchain_info),
+    )?;
+    let images = trace("xrEnumerateSwapchainImages", String::new, || {
+        handle.enumerate_images()
+    })?;
 
     let buffers = images
         .into_iter()
@@ -350,7 +584,8 @@ pub fn initialize_xr_graphics(
                         dimension: wgpu::TextureDimension::D2,
                         format: swapchain_format,
                         usage: wgpu_hal::TextureUses::COLOR_TARGET
-                            | wgpu_hal::TextureUses::COPY_DST,
+                            | wgpu_hal::TextureUses::COPY_DST
+                            | hal_usage,
                         memory_flags: wgpu_hal::MemoryFlags::empty(),
                         view_formats: vec![],
                     },
@@ -358,7 +593,7 @@ pub fn initialize_xr_graphics(
                 )
             };
             let texture = unsafe {
-                wgpu_device.create_texture_from_hal::<V>(
+                render_device.wgpu_device().create_texture_from_hal::<V>(
                     wgpu_hal_texture,
                     &wgpu::TextureDescriptor {
                         label: Some("VR Swapchain"),
@@ -372,110 +607,234 @@ pub fn initialize_xr_graphics(
                         dimension: wgpu::TextureDimension::D2,
                         format: swapchain_format,
                         usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                            | wgpu::TextureUsages::COPY_DST,
+                            | wgpu::TextureUsages::COPY_DST
+                            | wgpu_usage,
                         view_formats: &[],
                     },
                 )
             };
             texture
         })
-        .collect();
+        .collect::<Vec<_>>();
+    Ok((handle, buffers))
+}
 
-    Ok((
-        wgpu_device.into(),
-        RenderQueue(Arc::new(wgpu_queue)),
-        RenderAdapterInfo(wgpu_adapter.get_info()),
-        RenderAdapter(Arc::new(wgpu_adapter)),
-        wgpu_instance,
-        xr_instance.clone().into(),
-        session.clone().into_any_graphics().into(),
-        blend_mode.into(),
-        resolution.into(),
-        swapchain_format.into(),
-        AtomicBool::new(false).into(),
-        Mutex::new(frame_wait).into(),
-        Swapchain::Vulkan(SwapchainInner {
-            stream: Mutex::new(frame_stream),
-            handle: Mutex::new(handle),
-            buffers,
-            image_index: Mutex::new(0),
-        })
-        .into(),
-        XrInput::new(xr_instance, session.into_any_graphics())?,
-        Mutex::default().into(),
-        Mutex::new(xr::FrameState {
-            predicted_display_time: xr::Time::from_nanos(1),
-            predicted_display_period: xr::Duration::from_nanos(1),
-            should_render: true,
+/// replaces the images of the eye swapchain, none of them may be acquired. returns the
+/// resolution it was created with, clamped to `max`
+pub fn recreate_swapchain(
+    swapchain: &SwapchainInner<xr::Vulkan>,
+    render_device: &RenderDevice,
+    resolution: UVec2,
+    max: UVec2,
+    format: wgpu::TextureFormat,
+    storage: bool,
+) -> anyhow::Result<UVec2> {
+    let resolution = resolution.clamp(UVec2::ONE, max.max(UVec2::ONE));
+    let formats = trace("xrEnumerateSwapchainFormats", String::new, || {
+        swapchain.session.enumerate_swapchain_formats()
+    })?;
+    if !formats.contains(&vulkan_swapchain_format(format)?) {
+        anyhow::bail!("the runtime doesn't support {:?} swapchains", format);
+    }
+    //the old images may still be used by submitted command buffers
+    render_device.wgpu_device().poll(wgpu::Maintain::Wait);
+    let (handle, buffers) = create_swapchain(
+        &swapchain.session,
+        render_device,
+        resolution,
+        format,
+        storage,
+    )?;
+    let views = buffers.iter().map(SwapchainImageViews::new).collect();
+    *swapchain.handle.lock().unwrap() = handle;
+    *swapchain.views.lock().unwrap() = views;
+    *swapchain.buffers.lock().unwrap() = buffers;
+    *swapchain.image_index.lock().unwrap() = 0;
+    Ok(resolution)
+}
+
+/// a swapchain for a layer whose images are copied in from bevy images
+pub fn create_layer_swapchain(
+    session: &xr::Session<xr::Vulkan>,
+    render_device: &RenderDevice,
+    size: UVec2,
+    format: wgpu::TextureFormat,
+) -> anyhow::Result<LayerSwapchain> {
+    create_copy_swapchain(
+        session,
+        render_device,
+        size,
+        format,
+        1,
+        xr::SwapchainUsageFlags::SAMPLED,
+    )
+}
+
+/// the depth swapchain of the projection, both eyes are layers of one array image like in the
+/// color swapchain. the depth of the eye cameras is copied into it
+pub fn create_depth_swapchain(
+    session: &xr::Session<xr::Vulkan>,
+    render_device: &RenderDevice,
+    size: UVec2,
+    format: wgpu::TextureFormat,
+) -> anyhow::Result<LayerSwapchain> {
+    create_copy_swapchain(
+        session,
+        render_device,
+        size,
+        format,
+        2,
+        xr::SwapchainUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+    )
+}
+
+fn create_copy_swapchain(
+    session: &xr::Session<xr::Vulkan>,
+    render_device: &RenderDevice,
+    size: UVec2,
+    format: wgpu::TextureFormat,
+    array_size: u32,
+    usage: xr::SwapchainUsageFlags,
+) -> anyhow::Result<LayerSwapchain> {
+    use wgpu_hal::{api::Vulkan as V, Api};
+
+    let vk_format = vulkan_swapchain_format(format)?;
+    let handle = trace(
+        "xrCreateSwapchain",
+        || format!("{}x{}x{}, {:?}", size.x, size.y, array_size, format),
+        || {
+            session.create_swapchain(&xr::SwapchainCreateInfo {
+                create_flags: xr::SwapchainCreateFlags::EMPTY,
+                usage_flags: xr::SwapchainUsageFlags::TRANSFER_DST | usage,
+                format: vk_format,
+                sample_count: 1,
+                width: size.x,
+                height: size.y,
+                face_count: 1,
+                array_size,
+                mip_count: 1,
+            })
+        },
+    )?;
+    let extent = wgpu::Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: array_size,
+    };
+    let images = trace("xrEnumerateSwapchainImages", String::new, || {
+        handle.enumerate_images()
+    })?;
+    let buffers = images
+        .into_iter()
+        .map(|image| unsafe {
+            let hal_texture = <V as Api>::Device::texture_from_raw(
+                vk::Image::from_raw(image),
+                &wgpu_hal::TextureDescriptor {
+                    label: Some("XR Layer Swapchain"),
+                    size: extent,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu_hal::TextureUses::COPY_DST,
+                    memory_flags: wgpu_hal::MemoryFlags::empty(),
+                    view_formats: vec![],
+                },
+                None,
+            );
+            render_device.wgpu_device().create_texture_from_hal::<V>(
+                hal_texture,
+                &wgpu::TextureDescriptor {
+                    label: Some("XR Layer Swapchain"),
+                    size: extent,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                },
+            )
         })
-        .into(),
-        // Horrible hack to get the Handtacking extension Loaded, Replace with good system to load
-        // any extension at some point
-    ))
+        .collect();
+    Ok(LayerSwapchain::Vulkan(LayerSwapchainInner {
+        handle: Mutex::new(handle),
+        buffers,
+        size,
+        format,
+    }))
 }
 
-fn wgpu_to_vulkan(format: wgpu::TextureFormat) -> vk::Format {
+// none for the formats vulkan has no match for
+fn wgpu_to_vulkan(format: wgpu::TextureFormat) -> Option<vk::Format> {
     use vk::Format;
     match format {
-        wgpu::TextureFormat::R8Unorm => Format::R8_UNORM,
-        wgpu::TextureFormat::R8Snorm => Format::R8_SNORM,
-        wgpu::TextureFormat::R8Uint => Format::R8_UINT,
-        wgpu::TextureFormat::R8Sint => Format::R8_SINT,
-        wgpu::TextureFormat::R16Uint => Format::R16_UINT,
-        wgpu::TextureFormat::R16Sint => Format::R16_SINT,
-        wgpu::TextureFormat::R16Unorm => Format::R16_UNORM,
-        wgpu::TextureFormat::R16Snorm => Format::R16_SNORM,
-        wgpu::TextureFormat::R16Float => Format::R16_SFLOAT,
-        wgpu::TextureFormat::Rg8Unorm => Format::R8G8_UNORM,
-        wgpu::TextureFormat::Rg8Snorm => Format::R8G8_SNORM,
-        wgpu::TextureFormat::Rg8Uint => Format::R8G8_UINT,
-        wgpu::TextureFormat::Rg8Sint => Format::R8G8_SINT,
-        wgpu::TextureFormat::R32Uint => Format::R32_UINT,
-        wgpu::TextureFormat::R32Sint => Format::R32_SINT,
-        wgpu::TextureFormat::R32Float => Format::R32_SFLOAT,
-        wgpu::TextureFormat::Rg16Uint => Format::R16G16_UINT,
-        wgpu::TextureFormat::Rg16Sint => Format::R16G16_SINT,
-        wgpu::TextureFormat::Rg16Unorm => Format::R16G16_UNORM,
-        wgpu::TextureFormat::Rg16Snorm => Format::R16G16_SNORM,
-        wgpu::TextureFormat::Rg16Float => Format::R16G16_SFLOAT,
-        wgpu::TextureFormat::Rgba8Unorm => Format::R8G8B8A8_UNORM,
-        wgpu::TextureFormat::Rgba8UnormSrgb => Format::R8G8B8A8_SRGB,
-        wgpu::TextureFormat::Rgba8Snorm => Format::R8G8B8A8_SNORM,
-        wgpu::TextureFormat::Rgba8Uint => Format::R8G8B8A8_UINT,
-        wgpu::TextureFormat::Rgba8Sint => Format::R8G8B8A8_SINT,
-        wgpu::TextureFormat::Bgra8Unorm => Format::B8G8R8A8_UNORM,
-        wgpu::TextureFormat::Bgra8UnormSrgb => Format::B8G8R8A8_SRGB,
-        wgpu::TextureFormat::Rgb9e5Ufloat => Format::E5B9G9R9_UFLOAT_PACK32, // this might be the wrong type??? i can't tell
-        wgpu::TextureFormat::Rgb10a2Unorm => Format::A2R10G10B10_UNORM_PACK32,
-        wgpu::TextureFormat::Rg11b10Float => panic!("this texture type invokes nothing but fear within my soul and i don't think vulkan has a proper type for this"),
-        wgpu::TextureFormat::Rg32Uint => Format::R32G32_UINT,
-        wgpu::TextureFormat::Rg32Sint => Format::R32G32_SINT,
-        wgpu::TextureFormat::Rg32Float => Format::R32G32_SFLOAT,
-        wgpu::TextureFormat::Rgba16Uint => Format::R16G16B16A16_UINT,
-        wgpu::TextureFormat::Rgba16Sint => Format::R16G16B16A16_SINT,
-        wgpu::TextureFormat::Rgba16Unorm => Format::R16G16B16A16_UNORM,
-        wgpu::TextureFormat::Rgba16Snorm => Format::R16G16B16A16_SNORM,
-        wgpu::TextureFormat::Rgba16Float => Format::R16G16B16A16_SFLOAT,
-        wgpu::TextureFormat::Rgba32Uint => Format::R32G32B32A32_UINT,
-        wgpu::TextureFormat::Rgba32Sint => Format::R32G32B32A32_SINT,
-        wgpu::TextureFormat::Rgba32Float => Format::R32G32B32A32_SFLOAT,
-        wgpu::TextureFormat::Stencil8 => Format::S8_UINT,
-        wgpu::TextureFormat::Depth16Unorm => Format::D16_UNORM,
-        wgpu::TextureFormat::Depth24Plus => Format::X8_D24_UNORM_PACK32,
-        wgpu::TextureFormat::Depth24PlusStencil8 => Format::D24_UNORM_S8_UINT,
-        wgpu::TextureFormat::Depth32Float => Format::D32_SFLOAT,
-        wgpu::TextureFormat::Depth32FloatStencil8 => Format::D32_SFLOAT_S8_UINT,
-        wgpu::TextureFormat::Etc2Rgb8Unorm => Format::ETC2_R8G8B8_UNORM_BLOCK,
-        wgpu::TextureFormat::Etc2Rgb8UnormSrgb => Format::ETC2_R8G8B8_SRGB_BLOCK,
-        wgpu::TextureFormat::Etc2Rgb8A1Unorm => Format::ETC2_R8G8B8A1_UNORM_BLOCK,
-        wgpu::TextureFormat::Etc2Rgb8A1UnormSrgb => Format::ETC2_R8G8B8A1_SRGB_BLOCK,
-        wgpu::TextureFormat::Etc2Rgba8Unorm => Format::ETC2_R8G8B8A8_UNORM_BLOCK,
-        wgpu::TextureFormat::Etc2Rgba8UnormSrgb => Format::ETC2_R8G8B8A8_SRGB_BLOCK,
-        wgpu::TextureFormat::EacR11Unorm => Format::EAC_R11_UNORM_BLOCK,
-        wgpu::TextureFormat::EacR11Snorm => Format::EAC_R11_SNORM_BLOCK,
-        wgpu::TextureFormat::EacRg11Unorm => Format::EAC_R11G11_UNORM_BLOCK,
-        wgpu::TextureFormat::EacRg11Snorm => Format::EAC_R11G11_SNORM_BLOCK,
-        wgpu::TextureFormat::Astc { .. } => panic!("please god kill me now"),
-        _ => panic!("fuck no")
+        wgpu::TextureFormat::R8Unorm => Some(Format::R8_UNORM),
+        wgpu::TextureFormat::R8Snorm => Some(Format::R8_SNORM),
+        wgpu::TextureFormat::R8Uint => Some(Format::R8_UINT),
+        wgpu::TextureFormat::R8Sint => Some(Format::R8_SINT),
+        wgpu::TextureFormat::R16Uint => Some(Format::R16_UINT),
+        wgpu::TextureFormat::R16Sint => Some(Format::R16_SINT),
+        wgpu::TextureFormat::R16Unorm => Some(Format::R16_UNORM),
+        wgpu::TextureFormat::R16Snorm => Some(Format::R16_SNORM),
+        wgpu::TextureFormat::R16Float => Some(Format::R16_SFLOAT),
+        wgpu::TextureFormat::Rg8Unorm => Some(Format::R8G8_UNORM),
+        wgpu::TextureFormat::Rg8Snorm => Some(Format::R8G8_SNORM),
+        wgpu::TextureFormat::Rg8Uint => Some(Format::R8G8_UINT),
+        wgpu::TextureFormat::Rg8Sint => Some(Format::R8G8_SINT),
+        wgpu::TextureFormat::R32Uint => Some(Format::R32_UINT),
+        wgpu::TextureFormat::R32Sint => Some(Format::R32_SINT),
+        wgpu::TextureFormat::R32Float => Some(Format::R32_SFLOAT),
+        wgpu::TextureFormat::Rg16Uint => Some(Format::R16G16_UINT),
+        wgpu::TextureFormat::Rg16Sint => Some(Format::R16G16_SINT),
+        wgpu::TextureFormat::Rg16Unorm => Some(Format::R16G16_UNORM),
+        wgpu::TextureFormat::Rg16Snorm => Some(Format::R16G16_SNORM),
+        wgpu::TextureFormat::Rg16Float => Some(Format::R16G16_SFLOAT),
+        wgpu::TextureFormat::Rgba8Unorm => Some(Format::R8G8B8A8_UNORM),
+        wgpu::TextureFormat::Rgba8UnormSrgb => Some(Format::R8G8B8A8_SRGB),
+        wgpu::TextureFormat::Rgba8Snorm => Some(Format::R8G8B8A8_SNORM),
+        wgpu::TextureFormat::Rgba8Uint => Some(Format::R8G8B8A8_UINT),
+        wgpu::TextureFormat::Rgba8Sint => Some(Format::R8G8B8A8_SINT),
+        wgpu::TextureFormat::Bgra8Unorm => Some(Format::B8G8R8A8_UNORM),
+        wgpu::TextureFormat::Bgra8UnormSrgb => Some(Format::B8G8R8A8_SRGB),
+        wgpu::TextureFormat::Rgb9e5Ufloat => Some(Format::E5B9G9R9_UFLOAT_PACK32), // this might be the wrong type??? i can't tell
+        wgpu::TextureFormat::Rgb10a2Unorm => Some(Format::A2R10G10B10_UNORM_PACK32),
+        wgpu::TextureFormat::Rg32Uint => Some(Format::R32G32_UINT),
+        wgpu::TextureFormat::Rg32Sint => Some(Format::R32G32_SINT),
+        wgpu::TextureFormat::Rg32Float => Some(Format::R32G32_SFLOAT),
+        wgpu::TextureFormat::Rgba16Uint => Some(Format::R16G16B16A16_UINT),
+        wgpu::TextureFormat::Rgba16Sint => Some(Format::R16G16B16A16_SINT),
+        wgpu::TextureFormat::Rgba16Unorm => Some(Format::R16G16B16A16_UNORM),
+        wgpu::TextureFormat::Rgba16Snorm => Some(Format::R16G16B16A16_SNORM),
+        wgpu::TextureFormat::Rgba16Float => Some(Format::R16G16B16A16_SFLOAT),
+        wgpu::TextureFormat::Rgba32Uint => Some(Format::R32G32B32A32_UINT),
+        wgpu::TextureFormat::Rgba32Sint => Some(Format::R32G32B32A32_SINT),
+        wgpu::TextureFormat::Rgba32Float => Some(Format::R32G32B32A32_SFLOAT),
+        wgpu::TextureFormat::Stencil8 => Some(Format::S8_UINT),
+        wgpu::TextureFormat::Depth16Unorm => Some(Format::D16_UNORM),
+        wgpu::TextureFormat::Depth24Plus => Some(Format::X8_D24_UNORM_PACK32),
+        wgpu::TextureFormat::Depth24PlusStencil8 => Some(Format::D24_UNORM_S8_UINT),
+        wgpu::TextureFormat::Depth32Float => Some(Format::D32_SFLOAT),
+        wgpu::TextureFormat::Depth32FloatStencil8 => Some(Format::D32_SFLOAT_S8_UINT),
+        wgpu::TextureFormat::Etc2Rgb8Unorm => Some(Format::ETC2_R8G8B8_UNORM_BLOCK),
+        wgpu::TextureFormat::Etc2Rgb8UnormSrgb => Some(Format::ETC2_R8G8B8_SRGB_BLOCK),
+        wgpu::TextureFormat::Etc2Rgb8A1Unorm => Some(Format::ETC2_R8G8B8A1_UNORM_BLOCK),
+        wgpu::TextureFormat::Etc2Rgb8A1UnormSrgb => Some(Format::ETC2_R8G8B8A1_SRGB_BLOCK),
+        wgpu::TextureFormat::Etc2Rgba8Unorm => Some(Format::ETC2_R8G8B8A8_UNORM_BLOCK),
+        wgpu::TextureFormat::Etc2Rgba8UnormSrgb => Some(Format::ETC2_R8G8B8A8_SRGB_BLOCK),
+        wgpu::TextureFormat::EacR11Unorm => Some(Format::EAC_R11_UNORM_BLOCK),
+        wgpu::TextureFormat::EacR11Snorm => Some(Format::EAC_R11_SNORM_BLOCK),
+        wgpu::TextureFormat::EacRg11Unorm => Some(Format::EAC_R11G11_UNORM_BLOCK),
+        wgpu::TextureFormat::EacRg11Snorm => Some(Format::EAC_R11G11_SNORM_BLOCK),
+        _ => None,
+    }
+}
+
+fn vulkan_swapchain_format(
+    format: wgpu::TextureFormat,
+) -> anyhow::Result<<xr::Vulkan as xr::Graphics>::Format> {
+    match wgpu_to_vulkan(format) {
+        Some(format) => Ok(format.as_raw() as _),
+        None => anyhow::bail!("{:?} can't be used for a vulkan swapchain", format),
     }
 }
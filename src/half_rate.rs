@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+
+use crate::frame_loop::XrFrameSet;
+use crate::xr_init::xr_only;
+use crate::xr_input::xr_camera::XrCameraType;
+
+/// renders only every other frame. the frames in between still go through wait/begin/end, but
+/// resubmit the last image with the poses it was rendered with, so the compositor reprojects
+/// it. halves the gpu work for apps that prefer quality over frame rate, at the cost of motion
+/// looking less smooth
+pub struct XrHalfRatePlugin;
+
+impl Plugin for XrHalfRatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrHalfRate>();
+        app.add_plugins(ExtractResourcePlugin::<XrHalfRate>::default());
+        app.add_systems(
+            PreUpdate,
+            update_xr_half_rate
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+    }
+}
+
+/// can be turned on and off at runtime
+#[derive(Resource, ExtractResource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrHalfRate {
+    pub enabled: bool,
+    skip: bool,
+}
+
+impl XrHalfRate {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            skip: false,
+        }
+    }
+
+    /// whether the current frame reuses the last image
+    pub fn skips_frame(&self) -> bool {
+        self.skip
+    }
+}
+
+pub fn update_xr_half_rate(
+    mut half_rate: ResMut<XrHalfRate>,
+    mut cameras: Query<(Entity, &XrCameraType, &mut Camera)>,
+    //the cameras the plugin turned off, so cameras the user turned off stay off
+    mut deactivated: Local<Vec<Entity>>,
+) {
+    let skip = half_rate.enabled && !half_rate.skip;
+    if half_rate.skip != skip {
+        half_rate.skip = skip;
+    }
+    match skip {
+        true => {
+            for (entity, camera_type, mut camera) in cameras.iter_mut() {
+                if matches!(camera_type, XrCameraType::Xr(_)) && camera.is_active {
+                    camera.is_active = false;
+                    deactivated.push(entity);
+                }
+            }
+        }
+        false => {
+            for entity in deactivated.drain(..) {
+                if let Ok((_, _, mut camera)) = cameras.get_mut(entity) {
+                    camera.is_active = true;
+                }
+            }
+        }
+    }
+}
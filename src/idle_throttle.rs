@@ -0,0 +1,210 @@
+use std::ffi::c_void;
+
+use bevy::prelude::*;
+use bevy::utils::Instant;
+use openxr::sys;
+
+use crate::call_trace::trace;
+use crate::capabilities::XrCapabilities;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrFrameSet;
+use crate::raw_events::XrRawEvent;
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::{xr_only, XrSessionState};
+use crate::xr_input::hands::hand_tracking::DisableHandTracking;
+
+pub const USER_PRESENCE_EXTENSION: &str = "XR_EXT_user_presence";
+
+const TYPE_EVENT_USER_PRESENCE_CHANGED: i32 = 1000470000;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+struct UserPresenceChangedEvent {
+    ty: sys::StructureType,
+    next: *const c_void,
+    session: sys::Session,
+    is_user_present: sys::Bool32,
+}
+
+/// saves battery while nobody wears the headset. the proximity sensor is read through
+/// XR_EXT_user_presence, runtimes without it count the user as absent while the session isn't
+/// visible. after [`XrIdlePolicy::delay`] without a user the policy is applied, it's undone as
+/// soon as the headset is put back on
+pub struct XrIdleThrottlePlugin;
+
+impl Plugin for XrIdleThrottlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrUserPresence>();
+        app.init_resource::<XrIdlePolicy>();
+        app.add_event::<XrIdleChanged>();
+        app.add_systems(
+            PreUpdate,
+            (read_user_presence_events, apply_idle_policy)
+                .chain()
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrUserPresence {
+    /// what the proximity sensor says, true without XR_EXT_user_presence
+    pub sensor: bool,
+    /// whether the idle policy is applied right now
+    pub idle: bool,
+}
+
+impl Default for XrUserPresence {
+    fn default() -> Self {
+        Self {
+            sensor: true,
+            idle: false,
+        }
+    }
+}
+
+impl XrUserPresence {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        instance
+            .exts()
+            .other
+            .iter()
+            .any(|ext| ext == USER_PRESENCE_EXTENSION)
+    }
+}
+
+/// what is turned down while the user is away, can be changed at runtime
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrIdlePolicy {
+    pub enabled: bool,
+    /// seconds without a user before the policy is applied, so quick adjustments of the
+    /// headset don't throttle
+    pub delay: f32,
+    /// switches to the lowest refresh rate, needs XR_FB_display_refresh_rate
+    pub lower_refresh_rate: bool,
+    /// pauses `Time<Virtual>`, so gameplay and everything driven by `Time` stops
+    pub pause_time: bool,
+    /// turns hand tracking off through [`DisableHandTracking`]
+    pub stop_hand_tracking: bool,
+}
+
+impl Default for XrIdlePolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            delay: 2.0,
+            lower_refresh_rate: true,
+            pause_time: true,
+            stop_hand_tracking: true,
+        }
+    }
+}
+
+/// sent when the idle policy is applied or undone
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrIdleChanged {
+    pub idle: bool,
+}
+
+pub fn read_user_presence_events(
+    mut raw_events: EventReader<XrRawEvent>,
+    mut presence: ResMut<XrUserPresence>,
+) {
+    for raw in raw_events.read() {
+        if raw.ty.into_raw() != TYPE_EVENT_USER_PRESENCE_CHANGED {
+            continue;
+        }
+        let event = unsafe { raw.read::<UserPresenceChangedEvent>() };
+        let sensor = event.is_user_present != sys::FALSE;
+        info!("user presence changed: {}", sensor);
+        presence.sensor = sensor;
+    }
+}
+
+// what the policy changed, to put it back on don
+#[derive(Default)]
+pub struct IdleRestore {
+    refresh_rate: Option<f32>,
+    paused_time: bool,
+    hand_tracking: Option<Option<DisableHandTracking>>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn apply_idle_policy(
+    mut commands: Commands,
+    mut presence: ResMut<XrUserPresence>,
+    policy: Res<XrIdlePolicy>,
+    session_state: Res<State<XrSessionState>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    capabilities: Option<Res<XrCapabilities>>,
+    disabled_hands: Option<Res<DisableHandTracking>>,
+    mut time: ResMut<Time<Virtual>>,
+    error_log: Res<XrErrorLog>,
+    mut events: EventWriter<XrIdleChanged>,
+    mut absent_since: Local<Option<Instant>>,
+    mut restore: Local<IdleRestore>,
+) {
+    let present = presence.sensor && session_state.is_visible();
+    let absent_since = match present {
+        true => {
+            *absent_since = None;
+            None
+        }
+        false => Some(*absent_since.get_or_insert_with(Instant::now)),
+    };
+    let idle = policy.enabled
+        && absent_since.is_some_and(|since| since.elapsed().as_secs_f32() >= policy.delay);
+    if idle == presence.idle {
+        return;
+    }
+    presence.idle = idle;
+    events.send(XrIdleChanged { idle });
+    let request_refresh_rate = |rate: f32| {
+        if let Err(err) = trace(
+            "xrRequestDisplayRefreshRateFB",
+            || rate.to_string(),
+            || session.request_display_refresh_rate(rate),
+        ) {
+            error_log.report_result(XrErrorSource::Other, err);
+        }
+    };
+    if idle {
+        info!("nobody is wearing the headset, throttling");
+        let lowest = capabilities
+            .as_ref()
+            .and_then(|capabilities| capabilities.refresh_rates.iter().copied().reduce(f32::min));
+        if let (true, true, Some(lowest)) = (
+            policy.lower_refresh_rate,
+            instance.exts().fb_display_refresh_rate.is_some(),
+            lowest,
+        ) {
+            restore.refresh_rate = session.get_display_refresh_rate().ok();
+            request_refresh_rate(lowest);
+        }
+        if policy.pause_time && !time.is_paused() {
+            time.pause();
+            restore.paused_time = true;
+        }
+        if policy.stop_hand_tracking {
+            restore.hand_tracking = Some(disabled_hands.as_deref().copied());
+            commands.insert_resource(DisableHandTracking::Both);
+        }
+    } else {
+        info!("the headset is worn again, restoring");
+        let restore = std::mem::take(&mut *restore);
+        if let Some(rate) = restore.refresh_rate {
+            request_refresh_rate(rate);
+        }
+        if restore.paused_time {
+            time.unpause();
+        }
+        match restore.hand_tracking {
+            Some(Some(disabled)) => commands.insert_resource(disabled),
+            Some(None) => commands.remove_resource::<DisableHandTracking>(),
+            None => {}
+        }
+    }
+}
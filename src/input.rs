@@ -1,68 +1,141 @@
 use std::sync::Arc;
 
 use bevy::prelude::*;
+use bevy::render::Extract;
 use openxr as xr;
 
+use crate::call_trace::trace;
+use crate::convert::to_posef;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::resources::XrSession;
+
+/// where the app's origin is in the reference space behind the stage, e.g. on a scanned desk.
+/// the stage space is created with the one in [`OpenXrSettings`](crate::OpenXrSettings), changing
+/// the resource later recreates the stage. the scale is ignored
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrStageOrigin(pub Transform);
+
+/// the reference spaces of the session. the action spaces of the hands are components of their
+/// [`XrInputSource`](crate::xr_input::input_sources::XrInputSource) entities
 #[derive(Clone, Resource)]
 pub struct XrInput {
-    //pub action_set: xr::ActionSet,
-    //pub hand_pose: xr::Action<xr::Posef>,
-    //pub right_space: Arc<xr::Space>,
-    //pub left_space: Arc<xr::Space>,
     pub stage: Arc<xr::Space>,
     pub head: Arc<xr::Space>,
+    pub local: Arc<xr::Space>,
+    /// the space behind `stage`, `STAGE` or `LOCAL` when the runtime doesn't have the requested
+    /// one. a local space has its origin at the head instead of the floor, see `XrFloorHeight`
+    pub stage_type: xr::ReferenceSpaceType,
+    /// the [`XrStageOrigin`] `stage` was created with
+    pub stage_origin: Transform,
 }
 
 impl XrInput {
-    pub fn new(_instance: xr::Instance, session: xr::Session<xr::AnyGraphics>) -> xr::Result<Self> {
-        // let action_set = instance.create_action_set("input", "input pose information", 0)?;
-        // let left_hand_subaction_path = instance.string_to_path("/user/hand/left").unwrap();
-        // let right_hand_subaction_path = instance.string_to_path("/user/hand/right").unwrap();
-        // let left_hand_grip_pose_path = instance
-        //     .string_to_path("/user/hand/left/input/grip/pose")
-        //     .unwrap();
-        // let right_hand_grip_pose_path = instance
-        //     .string_to_path("/user/hand/right/input/grip/pose")
-        //     .unwrap();
-        // let hand_pose = action_set.create_action::<xr::Posef>(
-        //     "hand_pose",
-        //     "Hand Pose",
-        //     &[left_hand_subaction_path, right_hand_subaction_path],
-        // )?;
-        // /* let left_action =
-        // action_set.create_action::<xr::Posef>("left_hand", "Left Hand Controller", &[])?;*/
-        // instance.suggest_interaction_profile_bindings(
-        //     instance.string_to_path("/interaction_profiles/khr/simple_controller")?,
-        //     &[
-        //         xr::Binding::new(&hand_pose, right_hand_grip_pose_path),
-        //         xr::Binding::new(&hand_pose, left_hand_grip_pose_path),
-        //     ],
-        // )?;
-        //
-        // let right_space = hand_pose.create_space(
-        //     session.clone(),
-        //     right_hand_subaction_path,
-        //     xr::Posef::IDENTITY,
-        // )?;
-        // let left_space = hand_pose.create_space(
-        //     session.clone(),
-        //     left_hand_subaction_path,
-        //     xr::Posef::IDENTITY,
-        // )?;
-        let stage =
-            session.create_reference_space(xr::ReferenceSpaceType::STAGE, xr::Posef::IDENTITY)?;
-        let head = session
-            .create_reference_space(xr::ReferenceSpaceType::VIEW, xr::Posef::IDENTITY)
-            .unwrap();
-        //session.attach_action_sets(&[&action_set])?;
-        //session.attach_action_sets(&[])?;
+    pub fn new(instance: xr::Instance, session: xr::Session<xr::AnyGraphics>) -> xr::Result<Self> {
+        Self::with_stage_origin(instance, session, Transform::IDENTITY)
+    }
+
+    pub fn with_stage_origin(
+        instance: xr::Instance,
+        session: xr::Session<xr::AnyGraphics>,
+        stage_origin: Transform,
+    ) -> xr::Result<Self> {
+        Self::with_reference_space(
+            instance,
+            session,
+            xr::ReferenceSpaceType::STAGE,
+            stage_origin,
+        )
+    }
+
+    pub fn with_reference_space(
+        _instance: xr::Instance,
+        session: xr::Session<xr::AnyGraphics>,
+        reference_space: xr::ReferenceSpaceType,
+        stage_origin: Transform,
+    ) -> xr::Result<Self> {
+        let available = trace("xrEnumerateReferenceSpaces", String::new, || {
+            session.enumerate_reference_spaces()
+        })?;
+        let stage_type = [reference_space, xr::ReferenceSpaceType::STAGE]
+            .into_iter()
+            .find(|space| available.contains(space))
+            .unwrap_or(xr::ReferenceSpaceType::LOCAL);
+        if stage_type != reference_space {
+            warn!(
+                "the runtime has no {:?} space, falling back to {:?}",
+                reference_space, stage_type
+            );
+        }
+        let stage = create_reference_space(
+            &session,
+            stage_type,
+            to_posef(stage_origin.translation, stage_origin.rotation),
+        )?;
+        let head =
+            create_reference_space(&session, xr::ReferenceSpaceType::VIEW, xr::Posef::IDENTITY)?;
+        let local =
+            create_reference_space(&session, xr::ReferenceSpaceType::LOCAL, xr::Posef::IDENTITY)?;
         Ok(Self {
-            //action_set,
-            //hand_pose,
-            // right_space: Arc::new(right_space),
-            // left_space: Arc::new(left_space),
             stage: Arc::new(stage),
             head: Arc::new(head),
+            local: Arc::new(local),
+            stage_type,
+            stage_origin,
         })
     }
+
+    /// recreates the stage space with its origin at `origin`
+    pub fn set_stage_origin(
+        &mut self,
+        session: &xr::Session<xr::AnyGraphics>,
+        origin: Transform,
+    ) -> xr::Result<()> {
+        let stage = create_reference_space(
+            session,
+            self.stage_type,
+            to_posef(origin.translation, origin.rotation),
+        )?;
+        self.stage = Arc::new(stage);
+        self.stage_origin = origin;
+        Ok(())
+    }
+}
+
+fn create_reference_space(
+    session: &xr::Session<xr::AnyGraphics>,
+    ty: xr::ReferenceSpaceType,
+    pose: xr::Posef,
+) -> xr::Result<xr::Space> {
+    trace(
+        "xrCreateReferenceSpace",
+        || format!("{:?}, {:?}", ty, pose),
+        || session.create_reference_space(ty, pose),
+    )
+}
+
+pub fn apply_stage_origin(
+    origin: Option<Res<XrStageOrigin>>,
+    session: Res<XrSession>,
+    error_log: Res<XrErrorLog>,
+    mut input: ResMut<XrInput>,
+) {
+    let Some(origin) = origin else {
+        return;
+    };
+    if origin.0 == input.stage_origin {
+        return;
+    }
+    info!("moving the stage origin to {:?}", origin.0);
+    if let Err(err) = input.set_stage_origin(&session, origin.0) {
+        error_log.report_result(XrErrorSource::Other, err);
+        //not retried every frame
+        input.stage_origin = origin.0;
+    }
+}
+
+/// the render world submits the frame in the same stage the views were located in
+pub fn extract_xr_input(mut commands: Commands, input: Extract<Res<XrInput>>) {
+    if input.is_changed() {
+        commands.insert_resource(input.clone());
+    }
 }
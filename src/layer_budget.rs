@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+
+use crate::capabilities::XrDisplayProperties;
+use crate::layers::{XrLayerOrder, XrQuadLayer};
+use crate::passthrough_cutouts::XrPassthroughCutouts;
+
+/// the quads with the lowest priority are flattened first, equal priorities go by
+/// [`XrLayerOrder`], the ones in the back first. quads without one are at 0
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct XrLayerPriority(pub i32);
+
+/// a quad layer that doesn't fit into the budget, it isn't submitted
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct XrLayerOverBudget;
+
+/// the mesh entity under a quad layer that draws it into the scene while it's over the budget.
+/// kept hidden while the quad fits, so flattening it again doesn't create new assets
+#[derive(Component, Clone, Copy, Debug)]
+pub struct XrFlattenedLayer {
+    pub mesh: Entity,
+}
+
+/// keeps the quad layers of `XrLayersPlugin` within the layer count of the runtime, which
+/// rejects a whole frame with more. the layers are counted every frame and
+/// [`XrLayerBudgetEvent`]s tell when the limit gets close. the quads that don't fit, lowest
+/// [`XrLayerPriority`] first, get [`XrLayerOverBudget`] and are flattened into the scene as an
+/// unlit mesh of their image, which looks softer than a layer but still shows. they go back to
+/// layers once there is room again
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct XrLayerBudget {
+    /// [`XrLayerBudgetEvent::Approaching`] is sent when only this many layers are left
+    pub warn_margin: u32,
+    /// draws the quads that don't fit into the scene, otherwise they're left out
+    pub flatten: bool,
+    used: u32,
+    max: u32,
+    flattened: u32,
+}
+
+impl Default for XrLayerBudget {
+    fn default() -> Self {
+        Self {
+            warn_margin: 1,
+            flatten: true,
+            used: 0,
+            max: 0,
+            flattened: 0,
+        }
+    }
+}
+
+impl XrLayerBudget {
+    /// the layers submitted this frame, including the projection
+    pub fn used(&self) -> u32 {
+        self.used
+    }
+
+    /// the layer count of the runtime, 0 if it's unknown
+    pub fn max(&self) -> u32 {
+        self.max
+    }
+
+    /// the quads that didn't fit
+    pub fn flattened(&self) -> u32 {
+        self.flattened
+    }
+
+    fn state(&self) -> XrLayerBudgetState {
+        match self.max {
+            0 => XrLayerBudgetState::Within,
+            _ if self.flattened > 0 => XrLayerBudgetState::Exceeded,
+            max if self.used + self.warn_margin >= max => XrLayerBudgetState::Approaching,
+            _ => XrLayerBudgetState::Within,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum XrLayerBudgetState {
+    Within,
+    Approaching,
+    Exceeded,
+}
+
+/// sent when the budget changes between being comfortable, close to the limit and over it
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrLayerBudgetEvent {
+    /// back under the warn margin
+    Within {
+        used: u32,
+        max: u32,
+    },
+    Approaching {
+        used: u32,
+        max: u32,
+    },
+    /// `flattened` quads didn't fit
+    Exceeded {
+        used: u32,
+        max: u32,
+        flattened: u32,
+    },
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_layer_budget(
+    mut commands: Commands,
+    mut budget: ResMut<XrLayerBudget>,
+    display: Option<Res<XrDisplayProperties>>,
+    passthrough: Option<Res<XrPassthroughCutouts>>,
+    quads: Query<(
+        Entity,
+        Ref<XrQuadLayer>,
+        Option<&XrLayerPriority>,
+        Option<&XrLayerOrder>,
+        Option<&InheritedVisibility>,
+        Has<XrLayerOverBudget>,
+        Option<&XrFlattenedLayer>,
+    )>,
+    mut flat_meshes: Query<(&Handle<Mesh>, &Handle<StandardMaterial>, &mut Visibility)>,
+    mut events: EventWriter<XrLayerBudgetEvent>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let max = display.map_or(0, |display| display.max_layer_count);
+    let passthrough = passthrough.is_some_and(|passthrough| passthrough.has_cutouts());
+    let mut visible: Vec<_> = quads
+        .iter()
+        .filter(|(.., visibility, _, _)| visibility.map(InheritedVisibility::get).unwrap_or(true))
+        .collect();
+    //the ones that keep their layer first
+    visible.sort_by_key(|(_, _, priority, order, ..)| {
+        std::cmp::Reverse((
+            priority.copied().unwrap_or_default(),
+            order.copied().unwrap_or_default(),
+        ))
+    });
+    let fixed = 1 + passthrough as u32;
+    let room = match max {
+        0 => usize::MAX,
+        max => max.saturating_sub(fixed) as usize,
+    };
+    let previous = budget.state();
+    let mut flattened = 0;
+    for (index, (entity, quad, _, _, _, over_budget, flat)) in visible.iter().enumerate() {
+        let fits = index < room;
+        if !fits {
+            flattened += 1;
+        }
+        match (fits, *over_budget) {
+            (true, true) => {
+                commands.entity(*entity).remove::<XrLayerOverBudget>();
+            }
+            (false, false) => {
+                commands.entity(*entity).insert(XrLayerOverBudget);
+            }
+            _ => {}
+        }
+        let shown = !fits && budget.flatten;
+        let Some(flat) = flat else {
+            if shown {
+                let mesh = commands
+                    .spawn(PbrBundle {
+                        mesh: meshes.add(shape::Quad::new(quad.size).into()),
+                        material: materials.add(flat_material(&quad)),
+                        ..default()
+                    })
+                    .id();
+                commands
+                    .entity(*entity)
+                    .add_child(mesh)
+                    .insert(XrFlattenedLayer { mesh });
+            }
+            continue;
+        };
+        let Ok((mesh, material, mut visibility)) = flat_meshes.get_mut(flat.mesh) else {
+            continue;
+        };
+        visibility.set_if_neq(match shown {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        });
+        //the assets are updated in place, a new quad or image doesn't need a new mesh
+        if quad.is_changed() {
+            if let Some(mesh) = meshes.get_mut(mesh) {
+                *mesh = shape::Quad::new(quad.size).into();
+            }
+            if let Some(material) = materials.get_mut(material) {
+                *material = flat_material(&quad);
+            }
+        }
+    }
+    let layers = (visible.len() as u32).saturating_sub(flattened) + fixed;
+    budget.used = match max {
+        0 => layers,
+        max => layers.min(max),
+    };
+    budget.max = max;
+    budget.flattened = flattened;
+    let state = budget.state();
+    if state == previous {
+        return;
+    }
+    let (used, max) = (budget.used, budget.max);
+    events.send(match state {
+        XrLayerBudgetState::Within => XrLayerBudgetEvent::Within { used, max },
+        XrLayerBudgetState::Approaching => {
+            warn!("{} of {} composition layers are used", used, max);
+            XrLayerBudgetEvent::Approaching { used, max }
+        }
+        XrLayerBudgetState::Exceeded => {
+            warn!(
+                "{} quad layers don't fit into the {} composition layers of the runtime",
+                flattened, max
+            );
+            XrLayerBudgetEvent::Exceeded {
+                used,
+                max,
+                flattened,
+            }
+        }
+    });
+}
+
+fn flat_material(quad: &XrQuadLayer) -> StandardMaterial {
+    StandardMaterial {
+        base_color_texture: Some(quad.image.clone()),
+        unlit: true,
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    }
+}
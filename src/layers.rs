@@ -0,0 +1,305 @@
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{CommandEncoderDescriptor, Extent3d, TextureUsages};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::{Extract, Render, RenderApp};
+use bevy::utils::{HashMap, HashSet};
+use openxr as xr;
+
+use crate::convert::to_posef;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrRenderFrameSet;
+use crate::graphics;
+use crate::layer_budget::{
+    update_layer_budget, XrLayerBudget, XrLayerBudgetEvent, XrLayerOverBudget,
+};
+use crate::resources::{LayerSwapchain, XrSwapchain};
+use crate::xr_init::xr_only;
+use crate::xr_input::trackers::XrTrackingRoot;
+
+/// composition layers besides the projection the scene is rendered into. the compositor samples
+/// layers directly, so text and video on them stay sharper than in the scene. layers are
+/// submitted back to front by [`XrLayerOrder`], when a layer goes behind the projection the
+/// scene has to be cleared with a transparent color to let it through. the quads that don't fit
+/// into the layer count of the runtime are left to the [`XrLayerBudget`]
+pub struct XrLayersPlugin;
+
+impl Plugin for XrLayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrProjectionLayerOrder>();
+        app.init_resource::<XrLayerBudget>();
+        app.add_event::<XrLayerBudgetEvent>();
+        app.add_systems(PostUpdate, update_layer_budget.run_if(xr_only()));
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<XrLayerSwapchains>();
+        render_app.init_resource::<XrLayerSubmission>();
+        render_app.add_systems(ExtractSchedule, extract_xr_layers.run_if(xr_only()));
+        render_app.add_systems(
+            Render,
+            copy_xr_layer_images
+                .run_if(xr_only())
+                .in_set(XrRenderFrameSet::BeforeSubmit),
+        );
+    }
+}
+
+/// a flat layer at the entity's transform showing `image`, facing +z like a bevy quad mesh. the
+/// image is copied into the layer every frame, so it needs `TextureUsages::COPY_SRC`
+#[derive(Component, Clone, Debug)]
+pub struct XrQuadLayer {
+    pub image: Handle<Image>,
+    /// width and height in meters, scaled by the transform
+    pub size: Vec2,
+}
+
+/// the sort key of a layer, higher goes in front. layers without one are at 0, in front of the
+/// projection, and equal keys keep no particular order
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct XrLayerOrder(pub i32);
+
+/// the sort key of the projection layer, the scene
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrProjectionLayerOrder(pub i32);
+
+/// a quad layer in the tracking space
+#[derive(Clone, Debug)]
+pub struct ExtractedQuadLayer {
+    pub entity: Entity,
+    pub image: Handle<Image>,
+    pub pose: xr::Posef,
+    pub size: Vec2,
+    pub order: i32,
+}
+
+#[derive(Resource, Clone, Debug, Default)]
+pub struct ExtractedXrLayers {
+    pub quads: Vec<ExtractedQuadLayer>,
+    pub projection_order: i32,
+}
+
+/// the layer swapchains, one per layer entity
+#[derive(Resource, Default)]
+pub struct XrLayerSwapchains {
+    swapchains: HashMap<Entity, LayerSwapchain>,
+    // warned about, not retried until the entity goes away
+    failed: HashSet<Entity>,
+}
+
+/// what `end_frame` submits besides the projection, sorted by order
+#[derive(Resource, Default)]
+pub struct XrLayerSubmission {
+    pub(crate) quads: Vec<SubmittedQuad>,
+    pub(crate) projection_order: i32,
+    /// submitted behind everything else, see [`crate::passthrough_cutouts`]
+    pub(crate) passthrough: Option<xr::sys::PassthroughLayerFB>,
+    /// chained onto the projection views, see [`crate::environment_depth`]
+    pub(crate) depth: Option<SubmittedDepth>,
+}
+
+pub(crate) struct SubmittedQuad {
+    pub order: i32,
+    swapchain: xr::sys::Swapchain,
+    extent: UVec2,
+    pose: xr::Posef,
+    size: Vec2,
+}
+
+impl SubmittedQuad {
+    pub(crate) fn to_raw(&self, space: &xr::Space) -> xr::sys::CompositionLayerQuad {
+        xr::sys::CompositionLayerQuad {
+            ty: xr::sys::CompositionLayerQuad::TYPE,
+            next: std::ptr::null(),
+            layer_flags: xr::CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA,
+            space: space.as_raw(),
+            eye_visibility: xr::EyeVisibility::BOTH,
+            sub_image: xr::sys::SwapchainSubImage {
+                swapchain: self.swapchain,
+                image_rect: xr::Rect2Di {
+                    offset: xr::Offset2Di { x: 0, y: 0 },
+                    extent: xr::Extent2Di {
+                        width: self.extent.x as _,
+                        height: self.extent.y as _,
+                    },
+                },
+                image_array_index: 0,
+            },
+            pose: self.pose,
+            size: xr::Extent2Df {
+                width: self.size.x,
+                height: self.size.y,
+            },
+        }
+    }
+}
+
+/// the depth of both eyes, layer 0 of the swapchain is the left eye
+pub(crate) struct SubmittedDepth {
+    pub swapchain: xr::sys::Swapchain,
+    /// the distances of depth 0 and 1, `near_z` is the bigger one with reversed z
+    pub near_z: f32,
+    pub far_z: f32,
+}
+
+impl SubmittedDepth {
+    pub(crate) fn to_raw(
+        &self,
+        eye: u32,
+        rect: xr::Rect2Di,
+    ) -> xr::sys::CompositionLayerDepthInfoKHR {
+        xr::sys::CompositionLayerDepthInfoKHR {
+            ty: xr::sys::CompositionLayerDepthInfoKHR::TYPE,
+            next: std::ptr::null(),
+            sub_image: xr::sys::SwapchainSubImage {
+                swapchain: self.swapchain,
+                image_rect: rect,
+                image_array_index: eye,
+            },
+            min_depth: 0.0,
+            max_depth: 1.0,
+            near_z: self.near_z,
+            far_z: self.far_z,
+        }
+    }
+}
+
+pub fn extract_xr_layers(
+    mut commands: Commands,
+    quads: Extract<
+        Query<
+            (
+                Entity,
+                &XrQuadLayer,
+                &GlobalTransform,
+                Option<&XrLayerOrder>,
+                Option<&InheritedVisibility>,
+            ),
+            Without<XrLayerOverBudget>,
+        >,
+    >,
+    root: Extract<Query<&GlobalTransform, With<XrTrackingRoot>>>,
+    projection_order: Extract<Option<Res<XrProjectionLayerOrder>>>,
+) {
+    //the layers are placed in the stage space, which the tracking root moves around the world
+    let to_stage = root
+        .get_single()
+        .map(|root| root.compute_matrix().inverse())
+        .unwrap_or_default();
+    let quads = quads
+        .iter()
+        .filter(|(.., visibility)| visibility.map(InheritedVisibility::get).unwrap_or(true))
+        .map(|(entity, quad, transform, order, _)| {
+            let (scale, rotation, translation) =
+                (to_stage * transform.compute_matrix()).to_scale_rotation_translation();
+            ExtractedQuadLayer {
+                entity,
+                image: quad.image.clone(),
+                pose: to_posef(translation, rotation),
+                size: quad.size * scale.truncate(),
+                order: order.copied().unwrap_or_default().0,
+            }
+        })
+        .collect();
+    commands.insert_resource(ExtractedXrLayers {
+        quads,
+        projection_order: projection_order.as_deref().copied().unwrap_or_default().0,
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn copy_xr_layer_images(
+    extracted: Option<Res<ExtractedXrLayers>>,
+    mut layers: ResMut<XrLayerSwapchains>,
+    mut submission: ResMut<XrLayerSubmission>,
+    swapchain: Res<XrSwapchain>,
+    images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    error_log: Res<XrErrorLog>,
+) {
+    submission.quads.clear();
+    let Some(extracted) = extracted else {
+        return;
+    };
+    submission.projection_order = extracted.projection_order;
+    //the budget already left out the quads the runtime has no room for
+    let quads = &extracted.quads;
+    let layers = &mut *layers;
+    layers
+        .swapchains
+        .retain(|entity, _| quads.iter().any(|quad| quad.entity == *entity));
+    layers
+        .failed
+        .retain(|entity| quads.iter().any(|quad| quad.entity == *entity));
+    for quad in quads {
+        if layers.failed.contains(&quad.entity) {
+            continue;
+        }
+        let Some(image) = images.get(&quad.image) else {
+            continue;
+        };
+        if !image.texture.usage().contains(TextureUsages::COPY_SRC) {
+            warn!(
+                "the image of the layer {:?} needs COPY_SRC usage",
+                quad.entity
+            );
+            layers.failed.insert(quad.entity);
+            continue;
+        }
+        let size = image.size.as_uvec2();
+        let format = image.texture_format;
+        let recreate = match layers.swapchains.get(&quad.entity) {
+            Some(current) => current.size() != size || current.format() != format,
+            None => true,
+        };
+        if recreate {
+            match graphics::create_layer_swapchain(&swapchain, &render_device, size, format) {
+                Ok(layer_swapchain) => {
+                    layers.swapchains.insert(quad.entity, layer_swapchain);
+                }
+                Err(err) => {
+                    error_log.report(
+                        XrErrorSource::EndFrame,
+                        format!("couldn't create the layer {:?}: {}", quad.entity, err),
+                    );
+                    layers.failed.insert(quad.entity);
+                    continue;
+                }
+            }
+        }
+        let layer_swapchain = &layers.swapchains[&quad.entity];
+        let texture = match layer_swapchain.acquire_image() {
+            Ok(texture) => texture,
+            Err(err) => {
+                error_log.report_result(XrErrorSource::EndFrame, err);
+                continue;
+            }
+        };
+        let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("xr_layer_copy"),
+        });
+        encoder.copy_texture_to_texture(
+            image.texture.as_image_copy(),
+            texture.as_image_copy(),
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        render_queue.submit([encoder.finish()]);
+        if let Err(err) = layer_swapchain.release_image() {
+            error_log.report_result(XrErrorSource::EndFrame, err);
+            continue;
+        }
+        submission.quads.push(SubmittedQuad {
+            order: quad.order,
+            swapchain: layer_swapchain.as_raw(),
+            extent: size,
+            pose: quad.pose,
+            size: quad.size,
+        });
+    }
+    submission.quads.sort_by_key(|quad| quad.order);
+}
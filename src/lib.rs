@@ -1,17 +1,85 @@
+pub mod boundary_visibility;
+pub mod call_trace;
+pub mod capabilities;
+pub mod colocation;
+pub mod compositor_hold;
+pub mod convert;
+pub mod display_time;
+pub mod environment_depth;
+pub mod error_log;
+pub mod external_camera;
+pub mod foveation;
+pub mod frame_loop;
+pub mod frame_pacing;
+pub mod frame_timing;
 mod graphics;
+pub mod half_rate;
+pub mod idle_throttle;
 pub mod input;
+pub mod layer_budget;
+pub mod layers;
+pub mod mixed_reality_capture;
+pub mod panorama;
+pub mod passthrough_cutouts;
+pub mod perf_settings;
+pub mod quality_governor;
+pub mod raw_events;
+pub mod render_models;
+pub mod render_scale;
+pub mod render_scale_ramp;
+pub mod render_suspend;
 pub mod resource_macros;
 pub mod resources;
+pub mod runtime_info;
+pub mod scene;
+pub mod scene_occlusion;
+pub mod screen_fade;
+pub mod session_config;
+pub mod shader_warmup;
+pub mod swapchain_recreation;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+pub mod visibility_mask;
 pub mod xr_init;
 pub mod xr_input;
+pub mod xr_tasks;
 
 use std::sync::{Arc, Mutex};
 
+use crate::call_trace::trace;
+use crate::capabilities::{
+    extract_environment_blend_mode, validate_environment_blend_mode, XrCapabilities,
+};
+use crate::compositor_hold::XrCompositorHolding;
+use crate::display_time::{
+    extrapolate_to_display_time, update_predicted_display_time, XrPredictedDisplayTime,
+};
+use crate::error_log::{send_xr_error_events, XrErrorEvent, XrErrorLog, XrErrorSource};
+use crate::foveation::XrFoveationPlugin;
+use crate::frame_loop::{extract_frame_begun, XrFrameBegun, XrFrameSet, XrRenderFrameSet};
+use crate::frame_pacing::XrFramePacer;
+use crate::graphics::XrGraphicsContext;
+use crate::half_rate::XrHalfRate;
+use crate::layers::XrLayerSubmission;
+use crate::perf_settings::XrPerfSettingsChanged;
+use crate::raw_events::XrRawEvent;
+use crate::render_models::XrRenderModelSourcePlugin;
+use crate::render_scale::{extract_render_scale, update_xr_render_scale, XrRenderScale};
+use crate::render_suspend::{
+    extract_render_suspended, update_xr_render_suspended, XrRenderSuspended,
+};
+use crate::runtime_info::XrRuntimeInfo;
+use crate::screen_fade::{extract_screen_fade, update_screen_fade, XrScreenFade};
+use crate::session_config::{XrSessionConfig, XrSessionConfigPlugin};
+use crate::visibility_mask::XrVisibilityMaskChanged;
 use crate::xr_init::RenderRestartPlugin;
 use crate::xr_input::hands::hand_tracking::DisableHandTracking;
+use crate::xr_input::interaction_profiles::XrInteractionProfileChanged;
+#[allow(deprecated)]
 use crate::xr_input::oculus_touch::ActionSets;
+use crate::xr_tasks::XrAsyncRequests;
 use bevy::app::PluginGroupBuilder;
-use bevy::ecs::system::{RunSystemOnce, SystemState};
+use bevy::ecs::system::{RunSystemOnce, SystemParam, SystemState};
 use bevy::prelude::*;
 use bevy::render::camera::{
     CameraPlugin, ManualTextureView, ManualTextureViewHandle, ManualTextureViews,
@@ -23,36 +91,83 @@ use bevy::render::pipelined_rendering::PipelinedRenderingPlugin;
 use bevy::render::render_asset::RenderAssetDependency;
 use bevy::render::render_resource::ShaderLoader;
 use bevy::render::renderer::{
-    render_system, RenderAdapter, RenderAdapterInfo, RenderDevice, RenderInstance, RenderQueue,
+    RenderAdapter, RenderAdapterInfo, RenderDevice, RenderInstance, RenderQueue,
 };
 use bevy::render::settings::RenderCreation;
 use bevy::render::view::{self, ViewPlugin, WindowRenderPlugin};
-use bevy::render::{color, primitives, Render, RenderApp, RenderPlugin, RenderSet};
+use bevy::render::{color, primitives, Extract, ExtractSchedule, Render, RenderApp, RenderPlugin};
+use bevy::transform::TransformSystem;
+use bevy::utils::Instant;
 use bevy::window::{PresentMode, PrimaryWindow, RawHandleWrapper};
-use input::XrInput;
+use input::{apply_stage_origin, extract_xr_input, XrInput, XrStageOrigin};
 use openxr as xr;
 use resources::*;
-use xr::FormFactor;
 use xr_init::{
-    init_non_xr_graphics, update_xr_stuff, xr_only, RenderCreationData, XrEnableRequest,
-    XrEnableStatus, XrRenderData, XrRenderUpdate,
+    init_non_xr_graphics, setup_xr, update_xr_stuff, xr_only, RenderCreationData, XrDeferredInit,
+    XrEnableRequest, XrEnableStatus, XrFocusChanged, XrNextEnabledState, XrRenderData,
+    XrRenderUpdate, XrSessionState,
 };
 use xr_input::controllers::XrControllerType;
 use xr_input::hands::emulated::HandEmulationPlugin;
+use xr_input::hands::gesture_controller::GestureControllerEmulationPlugin;
 use xr_input::hands::hand_tracking::{HandTrackingData, HandTrackingPlugin};
 use xr_input::OpenXrInput;
 
-const VIEW_TYPE: xr::ViewConfigurationType = xr::ViewConfigurationType::PRIMARY_STEREO;
-
 pub const LEFT_XR_TEXTURE_HANDLE: ManualTextureViewHandle = ManualTextureViewHandle(1208214591);
 pub const RIGHT_XR_TEXTURE_HANDLE: ManualTextureViewHandle = ManualTextureViewHandle(3383858418);
 
-/// Adds OpenXR support to an App
-pub struct OpenXrPlugin;
+/// how the openxr instance and system are created, read once when [`OpenXrPlugin`] is built
+#[derive(Resource, Clone, Debug)]
+pub struct OpenXrSettings {
+    pub app_name: String,
+    pub app_version: u32,
+    pub engine_name: String,
+    pub engine_version: u32,
+    /// enabled on top of the ones this crate needs, the ones the runtime doesn't have are left out
+    pub extensions: xr::ExtensionSet,
+    /// the first one the runtime supports is used, otherwise the preferred one of the runtime
+    pub blend_modes: Vec<xr::EnvironmentBlendMode>,
+    pub form_factor: xr::FormFactor,
+    /// has to have two views, one for each eye camera
+    pub view_configuration: xr::ViewConfigurationType,
+    /// the space behind [`XrStageOrigin`], `STAGE` or `LOCAL` when the runtime doesn't have it
+    pub reference_space: xr::ReferenceSpaceType,
+    pub swapchain_usage: XrSwapchainUsage,
+    /// the first [`XrSupersampling`], the resource can be changed later
+    pub supersampling: XrSupersampling,
+    /// the first [`XrStageOrigin`], the resource can be changed later
+    pub stage_origin: XrStageOrigin,
+}
 
-impl Default for OpenXrPlugin {
+impl Default for OpenXrSettings {
     fn default() -> Self {
-        OpenXrPlugin
+        Self {
+            app_name: "Ambient".to_string(),
+            app_version: 1,
+            engine_name: "Ambient".to_string(),
+            engine_version: 1,
+            extensions: default(),
+            blend_modes: vec![],
+            form_factor: xr::FormFactor::HEAD_MOUNTED_DISPLAY,
+            view_configuration: xr::ViewConfigurationType::PRIMARY_STEREO,
+            reference_space: xr::ReferenceSpaceType::STAGE,
+            swapchain_usage: default(),
+            supersampling: default(),
+            stage_origin: default(),
+        }
+    }
+}
+
+/// Adds OpenXR support to an App. in [`DefaultXrPlugins`] its settings are changed with
+/// `.set(OpenXrPlugin::new(settings))`
+#[derive(Default)]
+pub struct OpenXrPlugin {
+    pub settings: OpenXrSettings,
+}
+
+impl OpenXrPlugin {
+    pub fn new(settings: OpenXrSettings) -> Self {
+        Self { settings }
     }
 }
 
@@ -66,7 +181,6 @@ pub struct FutureXrResources(
                 XrEnvironmentBlendMode,
                 XrResolution,
                 XrFormat,
-                XrSessionRunning,
                 XrFrameWaiter,
                 XrSwapchain,
                 XrInput,
@@ -79,59 +193,56 @@ pub struct FutureXrResources(
 
 impl Plugin for OpenXrPlugin {
     fn build(&self, app: &mut App) {
+        call_trace::enable_call_trace_from_env();
+        frame_timing::enable_frame_timing_log_from_env();
+        let mut settings = self.settings.clone();
+        //the saved config of a user wins over what the app asked for
+        if let Some(config) = app.world.get_resource::<XrSessionConfig>() {
+            config.apply_to_settings(&mut settings);
+        }
+        app.insert_resource(settings.clone());
+        app.insert_resource(settings.supersampling);
+        app.insert_resource(settings.stage_origin);
+        app.init_resource::<XrScreenFade>();
+        app.init_resource::<XrFrameBegun>();
+        app.init_resource::<XrRenderScale>();
+        app.init_resource::<XrRenderSuspended>();
+        app.init_resource::<XrErrorLog>();
+        app.init_resource::<XrPredictedDisplayTime>();
+        app.add_event::<XrErrorEvent>();
+        app.add_event::<XrVisibilityMaskChanged>();
+        app.add_event::<XrPerfSettingsChanged>();
+        app.add_event::<XrRawEvent>();
+        app.add_event::<XrInteractionProfileChanged>();
+        app.add_event::<XrFocusChanged>();
+        app.init_resource::<XrAsyncRequests>();
+        app.add_state::<XrSessionState>();
+        frame_loop::configure_frame_sets(app);
+        app.add_systems(Last, send_xr_error_events);
         let mut system_state: SystemState<Query<&RawHandleWrapper, With<PrimaryWindow>>> =
             SystemState::new(&mut app.world);
         let primary_window = system_state.get(&app.world).get_single().ok().cloned();
 
         #[cfg(not(target_arch = "wasm32"))]
-        match graphics::initialize_xr_graphics(primary_window.clone()) {
-            Ok((
-                device,
-                queue,
-                adapter_info,
-                render_adapter,
-                instance,
-                xr_instance,
-                session,
-                blend_mode,
-                resolution,
-                format,
-                session_running,
-                frame_waiter,
-                swapchain,
-                input,
-                views,
-                frame_state,
-            )) => {
+        match graphics::initialize_xr_device(primary_window.clone(), &settings) {
+            Ok((device, queue, adapter_info, render_adapter, instance, context)) => {
                 // std::thread::sleep(Duration::from_secs(5));
                 debug!("Configured wgpu adapter Limits: {:#?}", device.limits());
                 debug!("Configured wgpu adapter Features: {:#?}", device.features());
-                app.insert_resource(xr_instance.clone());
-                app.insert_resource(session.clone());
-                app.insert_resource(blend_mode.clone());
-                app.insert_resource(resolution.clone());
-                app.insert_resource(format.clone());
-                app.insert_resource(session_running.clone());
-                app.insert_resource(frame_waiter.clone());
-                app.insert_resource(swapchain.clone());
-                app.insert_resource(input.clone());
-                app.insert_resource(views.clone());
-                app.insert_resource(frame_state.clone());
-                let xr_data = XrRenderData {
-                    xr_instance,
-                    xr_session: session,
-                    xr_blend_mode: blend_mode,
-                    xr_resolution: resolution,
-                    xr_format: format,
-                    xr_session_running: session_running,
-                    xr_frame_waiter: frame_waiter,
-                    xr_swapchain: swapchain,
-                    xr_input: input,
-                    xr_views: views,
-                    xr_frame_state: frame_state,
-                };
-                app.insert_resource(xr_data);
-                app.insert_resource(ActionSets(vec![]));
+                match app.world.contains_resource::<XrDeferredInit>() {
+                    true => {
+                        info!("deferring the openxr session until it is requested");
+                        app.insert_resource(XrEnableStatus::Disabled);
+                    }
+                    false => match graphics::create_xr_session(&context, &device) {
+                        Ok(xr_data) => insert_xr_data(&mut app.world, xr_data),
+                        Err(err) => {
+                            warn!("OpenXR Failed to create a session: {}", err);
+                            app.insert_resource(XrEnableStatus::Disabled);
+                        }
+                    },
+                }
+                app.insert_resource(context);
                 app.add_plugins(RenderPlugin {
                     render_creation: RenderCreation::Manual(
                         device,
@@ -141,7 +252,6 @@ impl Plugin for OpenXrPlugin {
                         RenderInstance(Arc::new(instance)),
                     ),
                 });
-                app.insert_resource(XrEnableStatus::Enabled);
             }
             Err(err) => {
                 warn!("OpenXR Failed to initialize: {}", err);
@@ -164,67 +274,226 @@ impl Plugin for OpenXrPlugin {
     }
 
     fn finish(&self, app: &mut App) {
+        if !app.world.contains_resource::<XrGraphicsContext>() {
+            return;
+        }
         // TODO: Split this up into the indevidual resources
-        if let Some(data) = app.world.get_resource::<XrRenderData>().cloned() {
-            let hands = data.xr_instance.exts().ext_hand_tracking.is_some()
-                && data
-                    .xr_instance
-                    .supports_hand_tracking(
-                        data.xr_instance
-                            .system(FormFactor::HEAD_MOUNTED_DISPLAY)
-                            .unwrap(),
-                    )
-                    .is_ok_and(|v| v);
-            if hands {
-                app.insert_resource(HandTrackingData::new(&data.xr_session).unwrap());
-            } else {
-                app.insert_resource(DisableHandTracking::Both);
-            }
-
-            let (left, right) = data.xr_swapchain.get_render_views();
-            let left = ManualTextureView {
-                texture_view: left.into(),
-                size: *data.xr_resolution,
-                format: *data.xr_format,
-            };
-            let right = ManualTextureView {
-                texture_view: right.into(),
-                size: *data.xr_resolution,
-                format: *data.xr_format,
-            };
-            app.add_systems(PreUpdate, xr_begin_frame.run_if(xr_only()));
-            let mut manual_texture_views = app.world.resource_mut::<ManualTextureViews>();
-            manual_texture_views.insert(LEFT_XR_TEXTURE_HANDLE, left);
-            manual_texture_views.insert(RIGHT_XR_TEXTURE_HANDLE, right);
-            drop(manual_texture_views);
-            let render_app = app.sub_app_mut(RenderApp);
+        let data = app.world.get_resource::<XrRenderData>().cloned();
+        let capabilities = data
+            .as_ref()
+            .map(|data| setup_xr_data(&mut app.world, data));
+        //the stage is recreated before the views are located in it
+        app.add_systems(
+            PreUpdate,
+            (apply_stage_origin, xr_begin_frame)
+                .chain()
+                .run_if(xr_only())
+                .in_set(XrFrameSet::BeginFrame),
+        );
+        app.add_systems(
+            PreUpdate,
+            (update_predicted_display_time, update_xr_render_suspended)
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+        app.add_systems(
+            PostUpdate,
+            extrapolate_to_display_time
+                .run_if(xr_only())
+                .after(TransformSystem::TransformPropagate),
+        );
+        app.add_systems(
+            PostUpdate,
+            (validate_environment_blend_mode, update_xr_render_scale).run_if(xr_only()),
+        );
+        app.add_systems(Update, update_screen_fade);
+        app.add_systems(XrRenderUpdate, start_deferred_xr_session.before(setup_xr));
+        let error_log = app.world.resource::<XrErrorLog>().clone();
+        let swapchain_usage = app.world.resource::<XrGraphicsContext>().swapchain_usage;
+        let settings = app.world.resource::<OpenXrSettings>().clone();
+        let render_app = app.sub_app_mut(RenderApp);
 
-            render_app.insert_resource(data.xr_instance.clone());
-            render_app.insert_resource(data.xr_session.clone());
-            render_app.insert_resource(data.xr_blend_mode.clone());
-            render_app.insert_resource(data.xr_resolution.clone());
-            render_app.insert_resource(data.xr_format.clone());
-            render_app.insert_resource(data.xr_session_running.clone());
-            render_app.insert_resource(data.xr_frame_waiter.clone());
-            render_app.insert_resource(data.xr_swapchain.clone());
-            render_app.insert_resource(data.xr_input.clone());
-            render_app.insert_resource(data.xr_views.clone());
-            render_app.insert_resource(data.xr_frame_state.clone());
-            render_app.insert_resource(XrEnableStatus::Enabled);
-            render_app.add_systems(
-                Render,
+        if let (Some(data), Some(capabilities)) = (data, capabilities) {
+            insert_render_xr_data(&mut render_app.world, &data, capabilities);
+        }
+        render_app.init_resource::<XrScreenFade>();
+        render_app.init_resource::<XrFrameBegun>();
+        render_app.init_resource::<XrRenderScale>();
+        render_app.insert_resource(error_log);
+        render_app.insert_resource(settings);
+        render_app.insert_resource(swapchain_usage);
+        render_app.add_systems(
+            ExtractSchedule,
+            (
+                extract_deferred_xr_session,
                 (
-                    post_frame
-                        .run_if(xr_only())
-                        .before(render_system)
-                        .after(RenderSet::ExtractCommands),
-                    end_frame.run_if(xr_only()).after(render_system),
-                ),
+                    extract_environment_blend_mode,
+                    extract_screen_fade,
+                    extract_render_scale,
+                    extract_render_suspended,
+                    extract_frame_begun,
+                    extract_xr_input,
+                    extract_xr_views,
+                )
+                    .run_if(xr_only()),
+            ),
+        );
+        frame_loop::configure_render_frame_sets(render_app);
+        render_app.add_systems(
+            Render,
+            (
+                post_frame
+                    .run_if(xr_only())
+                    .run_if(resource_equals(XrFrameBegun(true)))
+                    .in_set(XrRenderFrameSet::AcquireImage),
+                end_frame
+                    .run_if(xr_only())
+                    .run_if(resource_equals(XrFrameBegun(true)))
+                    .in_set(XrRenderFrameSet::Submit),
+            ),
+        );
+    }
+}
+
+fn insert_xr_data(world: &mut World, data: XrRenderData) {
+    world.insert_resource(data.xr_instance.clone());
+    world.insert_resource(data.xr_session.clone());
+    world.insert_resource(data.xr_blend_mode.clone());
+    world.insert_resource(data.xr_resolution.clone());
+    world.insert_resource(data.xr_view_sizes);
+    world.insert_resource(data.xr_format.clone());
+    world.insert_resource(data.xr_frame_waiter.clone());
+    world.insert_resource(data.xr_swapchain.clone());
+    world.insert_resource(data.xr_input.clone());
+    world.insert_resource(data.xr_views.clone());
+    world.insert_resource(data.xr_frame_state.clone());
+    world.insert_resource(XrFramePacer::new(data.xr_frame_waiter.clone()));
+    world.insert_resource(data);
+    #[allow(deprecated)]
+    world.insert_resource(ActionSets(vec![]));
+    world.insert_resource(XrEnableStatus::Enabled);
+}
+
+// the main world resources that need a running session
+fn setup_xr_data(world: &mut World, data: &XrRenderData) -> XrCapabilities {
+    let settings = world.resource::<OpenXrSettings>().clone();
+    //the system the instance was created for, asking again could fail or pick another one
+    let context = world.resource::<XrGraphicsContext>();
+    let system = context.system;
+    let available_extensions = context.available_extensions.clone();
+    let capabilities = XrCapabilities::new(
+        available_extensions,
+        &data.xr_instance,
+        &data.xr_session,
+        system,
+        settings.view_configuration,
+    )
+    .unwrap_or_else(|err| {
+        warn!("failed to get xr capabilities: {}", err);
+        XrCapabilities::default()
+    });
+    info!(
+        "xr capabilities: hand tracking {}, eye tracking {}, passthrough {}, foveation {}",
+        capabilities.hand_tracking,
+        capabilities.eye_tracking,
+        capabilities.passthrough,
+        capabilities.foveation
+    );
+    world.insert_resource(XrRuntimeInfo::new(&capabilities));
+    world.insert_resource(capabilities.display.clone());
+    world.insert_resource(capabilities.clone());
+    let hands = data.xr_instance.exts().ext_hand_tracking.is_some()
+        && data
+            .xr_instance
+            .supports_hand_tracking(system)
+            .is_ok_and(|v| v);
+    if hands {
+        //the trackers are created once a hand is enabled, see `DisableHandTracking`
+        world.insert_resource(HandTrackingData::default());
+    } else {
+        world.insert_resource(DisableHandTracking::Both);
+    }
+
+    let (left, right) = data.xr_swapchain.get_render_views();
+    let left = ManualTextureView {
+        texture_view: left,
+        size: *data.xr_resolution,
+        format: *data.xr_format,
+    };
+    let right = ManualTextureView {
+        texture_view: right,
+        size: *data.xr_resolution,
+        format: *data.xr_format,
+    };
+    let mut manual_texture_views = world.resource_mut::<ManualTextureViews>();
+    manual_texture_views.insert(LEFT_XR_TEXTURE_HANDLE, left);
+    manual_texture_views.insert(RIGHT_XR_TEXTURE_HANDLE, right);
+    capabilities
+}
+
+fn insert_render_xr_data(world: &mut World, data: &XrRenderData, capabilities: XrCapabilities) {
+    world.insert_resource(data.xr_instance.clone());
+    world.insert_resource(data.xr_session.clone());
+    world.insert_resource(data.xr_blend_mode.clone());
+    world.insert_resource(data.xr_resolution.clone());
+    world.insert_resource(data.xr_view_sizes);
+    world.insert_resource(data.xr_format.clone());
+    world.insert_resource(data.xr_frame_waiter.clone());
+    world.insert_resource(data.xr_swapchain.clone());
+    world.insert_resource(data.xr_input.clone());
+    world.insert_resource(data.xr_views.clone());
+    world.insert_resource(data.xr_frame_state.clone());
+    world.insert_resource(XrEnableStatus::Enabled);
+    world.insert_resource(XrRuntimeInfo::new(&capabilities));
+    world.insert_resource(capabilities.display.clone());
+    world.insert_resource(capabilities);
+}
+
+/// creates the session for an app that started with [`XrDeferredInit`], once xr gets enabled
+fn start_deferred_xr_session(world: &mut World) {
+    if world.get_resource::<XrNextEnabledState>() != Some(&XrNextEnabledState::Enabled)
+        || world.contains_resource::<XrSession>()
+    {
+        return;
+    }
+    let Some(context) = world.get_resource::<XrGraphicsContext>().cloned() else {
+        return;
+    };
+    let device = world.resource::<RenderDevice>().clone();
+    match graphics::create_xr_session(&context, &device) {
+        Ok(data) => {
+            info!("started the deferred openxr session");
+            insert_xr_data(world, data.clone());
+            setup_xr_data(world, &data);
+        }
+        Err(err) => {
+            world.resource::<XrErrorLog>().report(
+                XrErrorSource::Other,
+                format!("failed to create a session: {}", err),
             );
+            world.insert_resource(XrEnableStatus::Disabled);
         }
     }
 }
 
+// the render world gets the session of a deferred start once the main world has it
+fn extract_deferred_xr_session(
+    mut commands: Commands,
+    data: Extract<Option<Res<XrRenderData>>>,
+    capabilities: Extract<Option<Res<XrCapabilities>>>,
+    status: Option<Res<XrEnableStatus>>,
+) {
+    if status.is_some_and(|status| *status == XrEnableStatus::Enabled) {
+        return;
+    }
+    let (Some(data), Some(capabilities)) = (data.as_deref(), capabilities.as_deref()) else {
+        return;
+    };
+    let data = data.clone();
+    let capabilities = capabilities.clone();
+    commands.add(move |world: &mut World| insert_render_xr_data(world, &data, capabilities));
+}
+
 pub struct DefaultXrPlugins;
 
 impl PluginGroup for DefaultXrPlugins {
@@ -233,11 +502,15 @@ impl PluginGroup for DefaultXrPlugins {
             .build()
             .disable::<RenderPlugin>()
             .disable::<PipelinedRenderingPlugin>()
-            .add_before::<RenderPlugin, _>(OpenXrPlugin)
+            .add_before::<RenderPlugin, _>(OpenXrPlugin::default())
+            .add_before::<OpenXrPlugin, _>(XrSessionConfigPlugin::default())
             .add_after::<OpenXrPlugin, _>(OpenXrInput::new(XrControllerType::OculusTouch))
+            .add_after::<OpenXrPlugin, _>(XrFoveationPlugin)
             .add_before::<OpenXrPlugin, _>(RenderRestartPlugin)
+            .add_before::<AssetPlugin, _>(XrRenderModelSourcePlugin)
             .add(HandEmulationPlugin)
             .add(HandTrackingPlugin)
+            .add(GestureControllerEmulationPlugin)
             .set(WindowPlugin {
                 #[cfg(not(target_os = "android"))]
                 primary_window: Some(Window {
@@ -255,79 +528,204 @@ impl PluginGroup for DefaultXrPlugins {
     }
 }
 
+/// the events `xr_begin_frame` turns the polled runtime events into
+#[derive(SystemParam)]
+pub struct XrFrameEventWriters<'w> {
+    visibility_mask_changed: EventWriter<'w, XrVisibilityMaskChanged>,
+    perf_settings_changed: EventWriter<'w, XrPerfSettingsChanged>,
+    raw_events: EventWriter<'w, XrRawEvent>,
+    interaction_profile_changed: EventWriter<'w, XrInteractionProfileChanged>,
+    focus_changed: EventWriter<'w, XrFocusChanged>,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn xr_begin_frame(
     instance: Res<XrInstance>,
     session: Res<XrSession>,
-    session_running: Res<XrSessionRunning>,
     frame_state: Res<XrFrameState>,
-    frame_waiter: Res<XrFrameWaiter>,
+    mut frame_pacer: ResMut<XrFramePacer>,
     swapchain: Res<XrSwapchain>,
-    views: Res<XrViews>,
+    mut views: ResMut<XrViews>,
     input: Res<XrInput>,
+    error_log: Res<XrErrorLog>,
+    mut events: XrFrameEventWriters,
+    async_requests: Res<XrAsyncRequests>,
+    session_state: Res<State<XrSessionState>>,
+    mut next_session_state: ResMut<NextState<XrSessionState>>,
+    settings: Res<OpenXrSettings>,
+    mut frame_begun: ResMut<XrFrameBegun>,
+    //the focus was lost after having it, the first focus isn't a regained one
+    mut focus_lost: Local<bool>,
 ) {
+    frame_begun.set_if_neq(XrFrameBegun(false));
+    //the state events are applied in `StateTransition`, this follows them within the frame
+    let mut state = **session_state;
     {
         let _span = info_span!("xr_poll_events");
-        while let Some(event) = instance.poll_event(&mut Default::default()).unwrap() {
+        let mut buffer = raw_events::event_buffer();
+        let mut unknown_events = vec![];
+        while let Some(event) = trace("xrPollEvent", String::new, || {
+            raw_events::poll_event(&instance, &mut buffer, &mut unknown_events)
+        })
+        .unwrap()
+        {
             use xr::Event::*;
             match event {
                 SessionStateChanged(e) => {
                     // Session state change is where we can begin and end sessions, as well as
                     // find quit messages!
                     info!("entered XR state {:?}", e.state());
+                    let previous = state;
+                    state = XrSessionState::from_xr(e.state());
+                    next_session_state.set(state);
+                    match (previous, state) {
+                        (XrSessionState::Focused, _) if state != previous => {
+                            *focus_lost = true;
+                            events.focus_changed.send(XrFocusChanged::Lost);
+                        }
+                        (_, XrSessionState::Focused) if *focus_lost => {
+                            *focus_lost = false;
+                            events.focus_changed.send(XrFocusChanged::Regained);
+                        }
+                        _ => {}
+                    }
                     match e.state() {
                         xr::SessionState::READY => {
-                            session.begin(VIEW_TYPE).unwrap();
-                            session_running.store(true, std::sync::atomic::Ordering::Relaxed);
+                            trace(
+                                "xrBeginSession",
+                                || format!("{:?}", settings.view_configuration),
+                                || session.begin(settings.view_configuration),
+                            )
+                            .unwrap();
                         }
                         xr::SessionState::STOPPING => {
-                            session.end().unwrap();
-                            session_running.store(false, std::sync::atomic::Ordering::Relaxed);
+                            frame_pacer.discard();
+                            trace("xrEndSession", String::new, || session.end()).unwrap();
+                        }
+                        xr::SessionState::EXITING | xr::SessionState::LOSS_PENDING => {
+                            call_trace::flush_call_trace();
+                            return;
                         }
-                        xr::SessionState::EXITING | xr::SessionState::LOSS_PENDING => return,
                         _ => {}
                     }
                 }
                 InstanceLossPending(_) => return,
+                VisibilityMaskChangedKHR(e) => {
+                    events
+                        .visibility_mask_changed
+                        .send(XrVisibilityMaskChanged {
+                            view_index: e.view_index(),
+                        });
+                }
+                InteractionProfileChanged(_) => events
+                    .interaction_profile_changed
+                    .send(XrInteractionProfileChanged),
+                PerfSettingsEXT(e) => {
+                    events.perf_settings_changed.send(XrPerfSettingsChanged {
+                        domain: e.domain(),
+                        sub_domain: e.sub_domain(),
+                        from: e.from_level(),
+                        to: e.to_level(),
+                    });
+                }
+                SpatialAnchorCreateCompleteFB(e) => {
+                    async_requests.complete_with_space(e.request_id(), e.result(), Some(e.space()))
+                }
+                SpaceSetStatusCompleteFB(e) => async_requests.complete(e.request_id(), e.result()),
+                SpaceQueryCompleteFB(e) => async_requests.complete(e.request_id(), e.result()),
+                SpaceSaveCompleteFB(e) => async_requests.complete(e.request_id(), e.result()),
+                SpaceEraseCompleteFB(e) => async_requests.complete(e.request_id(), e.result()),
+                SpaceShareCompleteFB(e) => async_requests.complete(e.request_id(), e.result()),
                 EventsLost(e) => {
-                    warn!("lost {} XR events", e.lost_event_count());
+                    error_log.report(
+                        XrErrorSource::PollEvents,
+                        format!("lost {} XR events", e.lost_event_count()),
+                    );
                 }
                 _ => {}
             }
         }
+        events.raw_events.send_batch(unknown_events);
+    }
+    //xrWaitFrame fails until the session is begun
+    if !state.is_running() {
+        return;
     }
     {
         let _span = info_span!("xr_wait_frame").entered();
-        *frame_state.lock().unwrap() = match frame_waiter.lock().unwrap().wait() {
-            Ok(a) => a,
+        //usually already waited for while the last update ran
+        let wait_started = Instant::now();
+        let Some(frame) = frame_pacer.wait() else {
+            error_log.report(XrErrorSource::WaitFrame, "the frame pacing thread stopped");
+            return;
+        };
+        let wait_finished = Instant::now();
+        *frame_state.lock().unwrap() = match frame.state {
+            Ok(a) => {
+                frame_timing::frame_waited(
+                    wait_started,
+                    wait_finished,
+                    frame.finished.saturating_duration_since(frame.started),
+                    &a,
+                );
+                a
+            }
             Err(e) => {
-                warn!("error: {}", e);
+                error_log.report_result(XrErrorSource::WaitFrame, e);
                 return;
             }
         };
     }
     {
         let _span = info_span!("xr_begin_frame").entered();
-        swapchain.begin().unwrap()
+        swapchain.begin().unwrap();
+        frame_begun.0 = true;
     }
+    //the next wait returns once this frame is begun
+    frame_pacer.wait_next();
     {
         let _span = info_span!("xr_locate_views").entered();
-        *views.lock().unwrap() = session
-            .locate_views(
-                VIEW_TYPE,
-                frame_state.lock().unwrap().predicted_display_time,
-                &input.stage,
-            )
-            .unwrap()
-            .1;
+        let display_time = frame_state.lock().unwrap().predicted_display_time;
+        let result = trace(
+            "xrLocateViews",
+            || format!("{:?}, {:?}", settings.view_configuration, display_time),
+            || session.locate_views(settings.view_configuration, display_time, &input.stage),
+        );
+        //the frame is rendered with the views of the last one
+        let (flags, located_views) = match result {
+            Ok(located) => located,
+            Err(err) => {
+                error_log.report_result(XrErrorSource::LocateViews, err);
+                return;
+            }
+        };
+        if !flags.contains(xr::ViewStateFlags::ORIENTATION_VALID) {
+            error_log.report(XrErrorSource::Tracking, "tracking lost");
+        }
+        views.0 = located_views;
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn post_frame(
+    mut commands: Commands,
     resolution: Res<XrResolution>,
     format: Res<XrFormat>,
     swapchain: Res<XrSwapchain>,
+    swapchain_usage: Res<XrSwapchainUsage>,
+    half_rate: Option<Res<XrHalfRate>>,
+    suspended: Option<Res<XrRenderSuspended>>,
     mut manual_texture_views: ResMut<ManualTextureViews>,
 ) {
+    frame_timing::render_started();
+    //no image is acquired, `end_frame` submits the frame without the projection
+    if suspended.is_some_and(|suspended| suspended.0) {
+        return;
+    }
+    //the last image gets submitted again, nothing renders into the swapchain this frame
+    if half_rate.is_some_and(|half_rate| half_rate.skips_frame()) {
+        return;
+    }
     {
         let _span = info_span!("xr_acquire_image").entered();
         swapchain.acquire_image().unwrap()
@@ -338,14 +736,24 @@ pub fn post_frame(
     }
     {
         let _span = info_span!("xr_update_manual_texture_views").entered();
+        //the views are cached per image, only the handles get pointed at the acquired one
         let (left, right) = swapchain.get_render_views();
+        commands.insert_resource(XrSwapchainImages {
+            index: swapchain.image_index(),
+            array: swapchain.get_array_view(),
+            left: left.clone(),
+            right: right.clone(),
+            resolution: **resolution,
+            format: **format,
+            storage: swapchain_usage.storage,
+        });
         let left = ManualTextureView {
-            texture_view: left.into(),
+            texture_view: left,
             size: **resolution,
             format: **format,
         };
         let right = ManualTextureView {
-            texture_view: right.into(),
+            texture_view: right,
             size: **resolution,
             format: **format,
         };
@@ -354,43 +762,94 @@ pub fn post_frame(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn end_frame(
     xr_frame_state: Res<XrFrameState>,
     views: Res<XrViews>,
     input: Res<XrInput>,
     swapchain: Res<XrSwapchain>,
     resolution: Res<XrResolution>,
+    render_scale: Res<XrRenderScale>,
     environment_blend_mode: Res<XrEnvironmentBlendMode>,
+    instance: Res<XrInstance>,
+    screen_fade: Res<XrScreenFade>,
+    error_log: Res<XrErrorLog>,
+    half_rate: Option<Res<XrHalfRate>>,
+    layers: Option<Res<XrLayerSubmission>>,
+    suspended: Option<Res<XrRenderSuspended>>,
+    holding: Option<Res<XrCompositorHolding>>,
+    mut rendered_views: Local<Vec<xr::View>>,
+    mut rendered_size: Local<UVec2>,
 ) {
-    {
+    if suspended.is_some_and(|suspended| suspended.0) {
+        let _span = info_span!("xr_end_frame").entered();
+        //a compositor hold keeps its quad layers
+        let result = swapchain.end_without_projection(
+            xr_frame_state.lock().unwrap().predicted_display_time,
+            &input.stage,
+            **environment_blend_mode,
+            layers
+                .as_deref()
+                .filter(|_| holding.is_some_and(|holding| holding.0)),
+        );
+        if let Err(e) = result {
+            error_log.report_result(XrErrorSource::EndFrame, e);
+        }
+        frame_timing::frame_ended();
+        return;
+    }
+    let skipped = half_rate.is_some_and(|half_rate| half_rate.skips_frame());
+    if !skipped {
         let _span = info_span!("xr_release_image").entered();
         swapchain.release_image().unwrap();
+        rendered_views.clone_from(&views.0);
+        *rendered_size = render_scale.scaled_resolution(**resolution);
     }
     {
         let _span = info_span!("xr_end_frame").entered();
+        //a skipped frame shows the last image with the poses it was rendered with, the
+        //compositor reprojects it to the current head pose
+        let (submitted_views, submitted_size) = match skipped && !rendered_views.is_empty() {
+            true => (&*rendered_views, *rendered_size),
+            false => (&views.0, render_scale.scaled_resolution(**resolution)),
+        };
         let result = swapchain.end(
             xr_frame_state.lock().unwrap().predicted_display_time,
-            &*views.lock().unwrap(),
+            submitted_views,
             &input.stage,
-            **resolution,
+            submitted_size,
             **environment_blend_mode,
+            instance
+                .exts()
+                .khr_composition_layer_color_scale_bias
+                .as_ref()
+                .and(screen_fade.color_scale_bias()),
+            layers.as_deref(),
         );
         match result {
             Ok(_) => {}
-            Err(e) => warn!("error: {}", e),
+            Err(e) => error_log.report_result(XrErrorSource::EndFrame, e),
         }
     }
+    frame_timing::frame_ended();
+}
+
+/// the render world keeps its own copy of the views, the main world locates the views of the
+/// next frame while this one is still rendered
+pub fn extract_xr_views(main_views: Extract<Res<XrViews>>, mut views: ResMut<XrViews>) {
+    views.0.clone_from(&main_views.0);
 }
 
 pub fn locate_views(
-    views: Res<XrViews>,
+    mut views: ResMut<XrViews>,
     input: Res<XrInput>,
     session: Res<XrSession>,
     xr_frame_state: Res<XrFrameState>,
+    settings: Res<OpenXrSettings>,
 ) {
     let _span = info_span!("xr_locate_views").entered();
-    *views.lock().unwrap() = match session.locate_views(
-        VIEW_TYPE,
+    views.0 = match session.locate_views(
+        settings.view_configuration,
         xr_frame_state.lock().unwrap().predicted_display_time,
         &input.stage,
     ) {
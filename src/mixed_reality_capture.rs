@@ -0,0 +1,414 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{
+    BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+    ImageDataLayout, Maintain, MapMode, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::{Render, RenderApp};
+use bevy::tasks::IoTaskPool;
+use bevy::transform::TransformSystem;
+use openxr::Fovf;
+
+use crate::frame_loop::XrRenderFrameSet;
+use crate::xr_init::{xr_only, XrSetup};
+use crate::xr_input::trackers::{OpenXRHMD, XrTrackingRoot};
+use crate::xr_input::xr_camera::{Eye, XRProjection, XrCameraBundle, XrCameraType};
+
+/// renders the scene for mixed reality capture: a spectator camera at the calibrated pose of a
+/// real camera renders it twice, split at the depth of the player. the background layer has
+/// everything behind the player, the foreground layer everything in front of them on a
+/// transparent background, so a keyed camera feed of the player goes in between. both layers
+/// are in [`XrMrcLayers`] for an app to composite or stream, a [`XrMrcSaveRequest`] saves
+/// the next frame of them as images
+pub struct XrMixedRealityCapturePlugin;
+
+impl Plugin for XrMixedRealityCapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrMrcCamera>();
+        app.add_event::<XrMrcSaveRequest>();
+        app.add_plugins(ExtractResourcePlugin::<XrMrcCapture>::default());
+        app.add_systems(XrSetup, spawn_mrc_cameras);
+        app.add_systems(
+            PostUpdate,
+            (
+                update_mrc_cameras
+                    .run_if(xr_only())
+                    .before(TransformSystem::TransformPropagate),
+                (start_mrc_capture, finish_mrc_capture).chain(),
+            ),
+        );
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(
+            Render,
+            read_back_mrc_layers.in_set(XrRenderFrameSet::BeforeSubmit),
+        );
+    }
+}
+
+/// the real camera, can be changed at runtime or loaded from a calibration file with
+/// [`XrExternalCameraPlugin`](crate::external_camera::XrExternalCameraPlugin)
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrMrcCamera {
+    pub enabled: bool,
+    /// relative to the tracking root, like the poses of the trackers
+    pub pose: Transform,
+    /// vertical fov of the lens, in radians
+    pub fov: f32,
+    /// size of both layers, should have the aspect ratio of the camera feed
+    pub resolution: UVec2,
+    pub near: f32,
+    pub far: f32,
+    /// moves the split away from the camera, so the player's hands in front of their head
+    /// still end up behind the feed
+    pub split_offset: f32,
+}
+
+impl Default for XrMrcCamera {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            pose: Transform::IDENTITY,
+            fov: 60f32.to_radians(),
+            resolution: UVec2::new(1920, 1080),
+            near: 0.1,
+            far: 1000.0,
+            split_offset: 0.0,
+        }
+    }
+}
+
+/// the layers of the last frame, only rendered while the camera is enabled
+#[derive(Resource, Clone, Debug)]
+pub struct XrMrcLayers {
+    /// everything behind the player
+    pub background: Handle<Image>,
+    /// everything in front of the player, transparent where nothing is
+    pub foreground: Handle<Image>,
+    /// distance of the split from the camera
+    pub split_depth: f32,
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrMrcLayer {
+    Background,
+    Foreground,
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct XrMrcSaveRequest {
+    /// where the background is saved, the format comes from the extension
+    pub background: PathBuf,
+    /// needs a format with alpha, like png
+    pub foreground: PathBuf,
+}
+
+#[derive(Default)]
+enum Readback {
+    #[default]
+    Pending,
+    Done(Vec<Vec<u8>>),
+    Taken,
+}
+
+/// the save in progress, only exists while one is running
+#[derive(Resource, Clone, ExtractResource)]
+pub struct XrMrcCapture {
+    request: XrMrcSaveRequest,
+    layers: [Handle<Image>; 2],
+    size: UVec2,
+    readback: Arc<Mutex<Readback>>,
+}
+
+fn layer_image(size: UVec2) -> Image {
+    let size = Extent3d {
+        width: size.x.max(1),
+        height: size.y.max(1),
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("xr_mrc_layer"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    image
+}
+
+pub fn spawn_mrc_cameras(
+    mut commands: Commands,
+    camera: Res<XrMrcCamera>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let background = images.add(layer_image(camera.resolution));
+    let foreground = images.add(layer_image(camera.resolution));
+    for (index, (layer, image)) in [
+        (XrMrcLayer::Background, &background),
+        (XrMrcLayer::Foreground, &foreground),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let mut bundle = XrCameraBundle::new(Eye::Left);
+        bundle.camera = Camera {
+            order: -30 - index as isize,
+            target: RenderTarget::Image(image.clone()),
+            is_active: false,
+            ..default()
+        };
+        if layer == XrMrcLayer::Foreground {
+            bundle.camera_3d.clear_color = ClearColorConfig::Custom(Color::NONE);
+        }
+        bundle.xr_camera_type = XrCameraType::Flatscreen;
+        commands.spawn((bundle, layer));
+    }
+    commands.insert_resource(XrMrcLayers {
+        background,
+        foreground,
+        split_depth: 0.0,
+    });
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_mrc_cameras(
+    camera: Res<XrMrcCamera>,
+    mut layers: ResMut<XrMrcLayers>,
+    mut images: ResMut<Assets<Image>>,
+    head: Query<&Transform, (With<OpenXRHMD>, Without<XrMrcLayer>)>,
+    root: Query<&Transform, (With<XrTrackingRoot>, Without<XrMrcLayer>)>,
+    mut cameras: Query<(&XrMrcLayer, &mut Camera, &mut Transform, &mut XRProjection)>,
+) {
+    if !camera.enabled {
+        for (_, mut render_camera, _, _) in &mut cameras {
+            if render_camera.is_active {
+                render_camera.is_active = false;
+            }
+        }
+        return;
+    }
+    let size = camera.resolution.max(UVec2::ONE);
+    for handle in [&layers.background, &layers.foreground] {
+        if let Some(image) = images.get_mut(handle) {
+            let current = image.texture_descriptor.size;
+            if current.width != size.x || current.height != size.y {
+                image.resize(Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                });
+            }
+        }
+    }
+    //the split is in the tracking root's space, the player moves with it
+    let split_depth = head.get_single().map_or(camera.near, |head| {
+        (head.translation - camera.pose.translation).dot(camera.pose.forward())
+    });
+    let split_depth = (split_depth + camera.split_offset).clamp(camera.near, camera.far);
+    if layers.split_depth != split_depth {
+        layers.split_depth = split_depth;
+    }
+    let root = root.get_single().copied().unwrap_or_default();
+    let tan_v = (camera.fov / 2.0).tan();
+    let tan_h = tan_v * size.x as f32 / size.y as f32;
+    let fov = Fovf {
+        angle_left: -tan_h.atan(),
+        angle_right: tan_h.atan(),
+        angle_up: tan_v.atan(),
+        angle_down: -tan_v.atan(),
+    };
+    for (layer, mut render_camera, mut transform, mut projection) in &mut cameras {
+        render_camera.is_active = true;
+        *transform = root.mul_transform(camera.pose);
+        projection.fov = fov;
+        //the near plane of the background and the far plane of the foreground are the split
+        match layer {
+            XrMrcLayer::Background => {
+                projection.near = split_depth;
+                projection.far = camera.far;
+                projection.clip_far = false;
+            }
+            XrMrcLayer::Foreground => {
+                projection.near = camera.near;
+                projection.far = split_depth;
+                projection.clip_far = true;
+            }
+        }
+    }
+}
+
+pub fn start_mrc_capture(
+    mut commands: Commands,
+    mut requests: EventReader<XrMrcSaveRequest>,
+    capture: Option<Res<XrMrcCapture>>,
+    camera: Res<XrMrcCamera>,
+    layers: Option<Res<XrMrcLayers>>,
+) {
+    let Some(request) = requests.read().last() else {
+        return;
+    };
+    if capture.is_some() {
+        warn!("the mixed reality layers are already being saved, ignoring the request");
+        return;
+    }
+    let (true, Some(layers)) = (camera.enabled, layers) else {
+        warn!("the mixed reality camera isn't enabled, there is nothing to save");
+        return;
+    };
+    commands.insert_resource(XrMrcCapture {
+        request: request.clone(),
+        layers: [layers.background.clone(), layers.foreground.clone()],
+        size: camera.resolution.max(UVec2::ONE),
+        readback: default(),
+    });
+}
+
+pub fn finish_mrc_capture(mut commands: Commands, capture: Option<Res<XrMrcCapture>>) {
+    let Some(capture) = capture else {
+        return;
+    };
+    let layers = {
+        let mut readback = capture.readback.lock().unwrap();
+        match std::mem::replace(&mut *readback, Readback::Taken) {
+            Readback::Done(layers) => layers,
+            other => {
+                *readback = other;
+                return;
+            }
+        }
+    };
+    commands.remove_resource::<XrMrcCapture>();
+
+    let size = capture.size;
+    let paths = [
+        capture.request.background.clone(),
+        capture.request.foreground.clone(),
+    ];
+    IoTaskPool::get()
+        .spawn(async move {
+            for (index, (data, path)) in layers.into_iter().zip(paths).enumerate() {
+                let image = Image::new(
+                    Extent3d {
+                        width: size.x,
+                        height: size.y,
+                        depth_or_array_layers: 1,
+                    },
+                    TextureDimension::D2,
+                    data,
+                    TextureFormat::Rgba8UnormSrgb,
+                );
+                //only the foreground keeps its alpha
+                let result = image
+                    .try_into_dynamic()
+                    .map_err(|err| err.to_string())
+                    .and_then(|image| match index {
+                        0 => image.to_rgb8().save(&path).map_err(|err| err.to_string()),
+                        _ => image.to_rgba8().save(&path).map_err(|err| err.to_string()),
+                    });
+                match result {
+                    Ok(()) => info!("saved mixed reality layer to {}", path.display()),
+                    Err(err) => warn!(
+                        "failed to save mixed reality layer to {}: {}",
+                        path.display(),
+                        err
+                    ),
+                }
+            }
+        })
+        .detach();
+}
+
+fn read_back_mrc_layers(
+    capture: Option<Res<XrMrcCapture>>,
+    images: Res<RenderAssets<Image>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    let Some(capture) = capture else {
+        return;
+    };
+    if !matches!(*capture.readback.lock().unwrap(), Readback::Pending) {
+        return;
+    }
+    let Some(textures) = capture
+        .layers
+        .iter()
+        .map(|layer| images.get(layer).map(|image| image.texture.clone()))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return;
+    };
+    let size = capture.size;
+    let row_bytes = size.x * 4;
+    let padded_row_bytes =
+        row_bytes.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("xr_mrc_readback"),
+    });
+    let buffers = textures
+        .iter()
+        .map(|texture| {
+            let buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("xr_mrc_readback"),
+                size: (padded_row_bytes * size.y) as u64,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            encoder.copy_texture_to_buffer(
+                texture.as_image_copy(),
+                ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_row_bytes),
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+            buffer
+        })
+        .collect::<Vec<_>>();
+    queue.submit([encoder.finish()]);
+    for buffer in &buffers {
+        buffer.slice(..).map_async(MapMode::Read, |_| {});
+    }
+    //blocks the render thread for a frame, saving is a one off
+    device.wgpu_device().poll(Maintain::Wait);
+    let layers = buffers
+        .iter()
+        .map(|buffer| {
+            let mapped = buffer.slice(..).get_mapped_range();
+            let mut layer = Vec::with_capacity((row_bytes * size.y) as usize);
+            for row in mapped.chunks(padded_row_bytes as usize) {
+                layer.extend_from_slice(&row[..row_bytes as usize]);
+            }
+            drop(mapped);
+            buffer.unmap();
+            layer
+        })
+        .collect();
+    *capture.readback.lock().unwrap() = Readback::Done(layers);
+}
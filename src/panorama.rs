@@ -0,0 +1,356 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{
+    BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+    ImageDataLayout, Maintain, MapMode, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::{Render, RenderApp};
+use bevy::tasks::IoTaskPool;
+use openxr::Fovf;
+
+use crate::frame_loop::XrRenderFrameSet;
+use crate::xr_input::trackers::{OpenXRHMD, XrTrackingRoot};
+use crate::xr_input::xr_camera::{Eye, XRProjection, XrCameraBundle, XrCameraType};
+
+/// how many frames the cube cameras render before the faces are read back, so anything that
+/// needs a frame to settle (shadows, temporal effects) has
+const SETTLE_FRAMES: u32 = 2;
+
+/// saves a 360 degree equirectangular image from the head position, rendered as a cubemap and
+/// then converted on the cpu. the image is aligned to the world axes, not to the head
+pub struct XrPanoramaPlugin;
+
+impl Plugin for XrPanoramaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<XrPanoramaRequest>();
+        app.add_plugins(ExtractResourcePlugin::<XrPanoramaCapture>::default());
+        app.add_systems(
+            PostUpdate,
+            (start_panorama_capture, finish_panorama_capture).chain(),
+        );
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(
+            Render,
+            read_back_panorama_faces.in_set(XrRenderFrameSet::BeforeSubmit),
+        );
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct XrPanoramaRequest {
+    /// where the image is saved, the format comes from the extension
+    pub path: PathBuf,
+    /// width of the image, the height is half of it
+    pub width: u32,
+}
+
+impl XrPanoramaRequest {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            width: 4096,
+        }
+    }
+}
+
+#[derive(Default)]
+enum Readback {
+    #[default]
+    Pending,
+    Done(Vec<Vec<u8>>),
+    Taken,
+}
+
+/// the capture in progress, only exists while one is running
+#[derive(Resource, Clone, ExtractResource)]
+pub struct XrPanoramaCapture {
+    path: PathBuf,
+    width: u32,
+    face_size: u32,
+    faces: Vec<Handle<Image>>,
+    cameras: Vec<Entity>,
+    frames: u32,
+    readback: Arc<Mutex<Readback>>,
+}
+
+impl XrPanoramaCapture {
+    fn ready(&self) -> bool {
+        self.frames >= SETTLE_FRAMES
+    }
+}
+
+// +x, -x, +y, -y, +z, -z
+fn face_rotations() -> [Quat; 6] {
+    [
+        Transform::IDENTITY.looking_to(Vec3::X, Vec3::Y).rotation,
+        Transform::IDENTITY
+            .looking_to(Vec3::NEG_X, Vec3::Y)
+            .rotation,
+        Transform::IDENTITY.looking_to(Vec3::Y, Vec3::Z).rotation,
+        Transform::IDENTITY
+            .looking_to(Vec3::NEG_Y, Vec3::NEG_Z)
+            .rotation,
+        Transform::IDENTITY.looking_to(Vec3::Z, Vec3::Y).rotation,
+        Transform::IDENTITY
+            .looking_to(Vec3::NEG_Z, Vec3::Y)
+            .rotation,
+    ]
+}
+
+pub fn start_panorama_capture(
+    mut commands: Commands,
+    mut requests: EventReader<XrPanoramaRequest>,
+    capture: Option<Res<XrPanoramaCapture>>,
+    mut images: ResMut<Assets<Image>>,
+    head: Query<&GlobalTransform, With<OpenXRHMD>>,
+    root: Query<&GlobalTransform, With<XrTrackingRoot>>,
+) {
+    let Some(request) = requests.read().last() else {
+        return;
+    };
+    if capture.is_some() {
+        warn!("a panorama is already being captured, ignoring the request");
+        return;
+    }
+    let position = head
+        .get_single()
+        .or(root.get_single())
+        .map(|transform| transform.translation())
+        .unwrap_or_default();
+    let width = request.width.max(4) & !1;
+    let face_size = width / 4;
+    let size = Extent3d {
+        width: face_size,
+        height: face_size,
+        depth_or_array_layers: 1,
+    };
+    let mut faces = vec![];
+    let mut cameras = vec![];
+    for (index, rotation) in face_rotations().into_iter().enumerate() {
+        let mut image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: Some("xr_panorama_face"),
+                size,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+            ..default()
+        };
+        image.resize(size);
+        let handle = images.add(image);
+        let mut bundle = XrCameraBundle::new(Eye::Left);
+        bundle.camera = Camera {
+            order: -10 - index as isize,
+            target: RenderTarget::Image(handle.clone()),
+            ..default()
+        };
+        bundle.xr_camera_type = XrCameraType::Flatscreen;
+        bundle.xr_projection = XRProjection {
+            fov: Fovf {
+                angle_left: -std::f32::consts::FRAC_PI_4,
+                angle_right: std::f32::consts::FRAC_PI_4,
+                angle_up: std::f32::consts::FRAC_PI_4,
+                angle_down: -std::f32::consts::FRAC_PI_4,
+            },
+            ..default()
+        };
+        bundle.transform = Transform::from_translation(position).with_rotation(rotation);
+        cameras.push(commands.spawn(bundle).id());
+        faces.push(handle);
+    }
+    info!("capturing a {}x{} panorama", width, width / 2);
+    commands.insert_resource(XrPanoramaCapture {
+        path: request.path.clone(),
+        width,
+        face_size,
+        faces,
+        cameras,
+        frames: 0,
+        readback: default(),
+    });
+}
+
+pub fn finish_panorama_capture(
+    mut commands: Commands,
+    capture: Option<ResMut<XrPanoramaCapture>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(mut capture) = capture else {
+        return;
+    };
+    if !capture.ready() {
+        capture.frames += 1;
+        return;
+    }
+    let faces = {
+        let mut readback = capture.readback.lock().unwrap();
+        match std::mem::replace(&mut *readback, Readback::Taken) {
+            Readback::Done(faces) => faces,
+            other => {
+                *readback = other;
+                return;
+            }
+        }
+    };
+    for camera in &capture.cameras {
+        commands.entity(*camera).despawn_recursive();
+    }
+    for face in &capture.faces {
+        images.remove(face);
+    }
+    commands.remove_resource::<XrPanoramaCapture>();
+
+    let width = capture.width;
+    let face_size = capture.face_size;
+    let path = capture.path.clone();
+    IoTaskPool::get()
+        .spawn(async move {
+            let data = equirectangular(&faces, face_size, width);
+            let image = Image::new(
+                Extent3d {
+                    width,
+                    height: width / 2,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                data,
+                TextureFormat::Rgba8UnormSrgb,
+            );
+            let result = image
+                .try_into_dynamic()
+                .map_err(|err| err.to_string())
+                .and_then(|image| image.to_rgb8().save(&path).map_err(|err| err.to_string()));
+            match result {
+                Ok(()) => info!("saved panorama to {}", path.display()),
+                Err(err) => warn!("failed to save panorama to {}: {}", path.display(), err),
+            }
+        })
+        .detach();
+}
+
+// samples the cube faces (rgba8, tightly packed) for every pixel of the equirectangular image
+fn equirectangular(faces: &[Vec<u8>], face_size: u32, width: u32) -> Vec<u8> {
+    let height = width / 2;
+    let inverse_rotations = face_rotations().map(|rotation| rotation.inverse());
+    let mut data = vec![0; (width * height * 4) as usize];
+    for y in 0..height {
+        let latitude =
+            std::f32::consts::FRAC_PI_2 - (y as f32 + 0.5) / height as f32 * std::f32::consts::PI;
+        for x in 0..width {
+            let longitude =
+                (x as f32 + 0.5) / width as f32 * std::f32::consts::TAU - std::f32::consts::PI;
+            let direction = Vec3::new(
+                latitude.cos() * longitude.sin(),
+                latitude.sin(),
+                -latitude.cos() * longitude.cos(),
+            );
+            //the face that looks the most along the direction
+            let (face, local) = inverse_rotations
+                .iter()
+                .map(|rotation| *rotation * direction)
+                .enumerate()
+                .max_by(|(_, a), (_, b)| (-a.z).total_cmp(&-b.z))
+                .unwrap();
+            let u = (local.x / -local.z + 1.0) / 2.0;
+            let v = (1.0 - local.y / -local.z) / 2.0;
+            let px = ((u * face_size as f32) as u32).min(face_size - 1);
+            let py = ((v * face_size as f32) as u32).min(face_size - 1);
+            let src = ((py * face_size + px) * 4) as usize;
+            let dst = ((y * width + x) * 4) as usize;
+            data[dst..dst + 4].copy_from_slice(&faces[face][src..src + 4]);
+        }
+    }
+    data
+}
+
+fn read_back_panorama_faces(
+    capture: Option<Res<XrPanoramaCapture>>,
+    images: Res<RenderAssets<Image>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    let Some(capture) = capture else {
+        return;
+    };
+    if !capture.ready() || !matches!(*capture.readback.lock().unwrap(), Readback::Pending) {
+        return;
+    }
+    let Some(textures) = capture
+        .faces
+        .iter()
+        .map(|face| images.get(face).map(|image| image.texture.clone()))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return;
+    };
+    let face_size = capture.face_size;
+    let row_bytes = face_size * 4;
+    let padded_row_bytes =
+        row_bytes.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("xr_panorama_readback"),
+    });
+    let buffers = textures
+        .iter()
+        .map(|texture| {
+            let buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("xr_panorama_readback"),
+                size: (padded_row_bytes * face_size) as u64,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            encoder.copy_texture_to_buffer(
+                texture.as_image_copy(),
+                ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_row_bytes),
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: face_size,
+                    height: face_size,
+                    depth_or_array_layers: 1,
+                },
+            );
+            buffer
+        })
+        .collect::<Vec<_>>();
+    queue.submit([encoder.finish()]);
+    for buffer in &buffers {
+        buffer.slice(..).map_async(MapMode::Read, |_| {});
+    }
+    //this blocks the render thread for a frame, which is fine for a screenshot
+    device.wgpu_device().poll(Maintain::Wait);
+    let faces = buffers
+        .iter()
+        .map(|buffer| {
+            let mapped = buffer.slice(..).get_mapped_range();
+            let mut face = Vec::with_capacity((row_bytes * face_size) as usize);
+            for row in mapped.chunks(padded_row_bytes as usize) {
+                face.extend_from_slice(&row[..row_bytes as usize]);
+            }
+            drop(mapped);
+            buffer.unmap();
+            face
+        })
+        .collect();
+    *capture.readback.lock().unwrap() = Readback::Done(faces);
+}
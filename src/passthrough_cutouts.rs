@@ -0,0 +1,456 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::{Extract, RenderApp};
+use bevy::transform::TransformSystem;
+use bevy::utils::HashMap;
+use openxr as xr;
+use openxr::sys;
+
+use crate::call_trace::{check, trace};
+use crate::convert::to_posef;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrRenderFrameSet;
+use crate::input::XrInput;
+use crate::layers::XrLayerSubmission;
+use crate::resources::{XrFrameState, XrInstance, XrSession};
+use crate::xr_init::xr_only;
+use crate::xr_input::trackers::XrTrackingRoot;
+
+/// shows the real world only through cutouts, through XR_FB_passthrough and XR_FB_triangle_mesh.
+/// the camera video is projected onto the mesh of every [`XrPassthroughCutout`] and submitted
+/// behind the scene, so e.g. a mesh on the real desk lets the user see their keyboard. the scene
+/// has to be cleared with a transparent color for the cutouts to show
+pub struct XrPassthroughCutoutPlugin;
+
+impl Plugin for XrPassthroughCutoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrPassthroughKey>();
+        app.add_systems(
+            PostUpdate,
+            (update_passthrough_cutouts, apply_passthrough_key)
+                .chain()
+                .run_if(xr_only())
+                .after(TransformSystem::TransformPropagate),
+        );
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<XrLayerSubmission>();
+        render_app.init_resource::<ExtractedPassthroughLayer>();
+        render_app.add_systems(ExtractSchedule, extract_passthrough_layer.run_if(xr_only()));
+        render_app.add_systems(
+            Render,
+            submit_passthrough_layer
+                .run_if(xr_only())
+                .in_set(XrRenderFrameSet::BeforeSubmit),
+        );
+    }
+}
+
+/// the real world shows through this entity's mesh, at its global transform
+#[derive(Component, Clone, Debug)]
+pub struct XrPassthroughCutout {
+    /// only the positions and indices are used, the other attributes are ignored
+    pub mesh: Handle<Mesh>,
+}
+
+/// keys the passthrough inside the cutouts by brightness. fb passthrough only keys the
+/// luminance of the camera image, there is no chroma key
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub enum XrPassthroughKey {
+    #[default]
+    Off,
+    /// only the pixels with a luminance between `min` and `max` (0 to 1) show, fading over
+    /// `softness`
+    Luminance { min: f32, max: f32, softness: f32 },
+}
+
+impl XrPassthroughKey {
+    // the alpha the color map gives a luminance
+    fn alpha(&self, luminance: f32) -> f32 {
+        match *self {
+            XrPassthroughKey::Off => 1.0,
+            XrPassthroughKey::Luminance { min, max, softness } => {
+                let softness = softness.max(f32::EPSILON);
+                let rise = ((luminance - min) / softness + 0.5).clamp(0.0, 1.0);
+                let fall = ((max - luminance) / softness + 0.5).clamp(0.0, 1.0);
+                rise.min(fall)
+            }
+        }
+    }
+}
+
+/// the passthrough and its projected layer, created with the first cutout. destroyed with the
+/// resource
+#[derive(Resource)]
+pub struct XrPassthroughCutouts {
+    passthrough_ext: xr::raw::PassthroughFB,
+    mesh_ext: xr::raw::TriangleMeshFB,
+    passthrough: sys::PassthroughFB,
+    layer: sys::PassthroughLayerFB,
+    cutouts: HashMap<Entity, Cutout>,
+    key: Option<XrPassthroughKey>,
+}
+
+struct Cutout {
+    mesh: AssetId<Mesh>,
+    triangle_mesh: sys::TriangleMeshFB,
+    instance: sys::GeometryInstanceFB,
+}
+
+impl XrPassthroughCutouts {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        instance.exts().fb_passthrough.is_some() && instance.exts().fb_triangle_mesh.is_some()
+    }
+
+    /// the passthrough layer is only submitted while there are cutouts
+    pub fn has_cutouts(&self) -> bool {
+        !self.cutouts.is_empty()
+    }
+}
+
+impl Drop for XrPassthroughCutouts {
+    fn drop(&mut self) {
+        for cutout in self.cutouts.values() {
+            cutout.destroy(&self.passthrough_ext, &self.mesh_ext);
+        }
+        //the layer belongs to the passthrough, so it goes first
+        unsafe {
+            (self.passthrough_ext.destroy_passthrough_layer)(self.layer);
+            (self.passthrough_ext.destroy_passthrough)(self.passthrough);
+        }
+    }
+}
+
+#[derive(Resource, Clone, Copy, Default)]
+pub struct ExtractedPassthroughLayer(Option<sys::PassthroughLayerFB>);
+
+impl ExtractedPassthroughLayer {
+    /// whether the layer gets submitted this frame
+    pub fn is_submitted(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_passthrough_cutouts(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    meshes: Res<Assets<Mesh>>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    error_log: Res<XrErrorLog>,
+    state: Option<ResMut<XrPassthroughCutouts>>,
+    root: Query<&GlobalTransform, With<XrTrackingRoot>>,
+    entities: Query<(Entity, &XrPassthroughCutout, &GlobalTransform)>,
+    mut unsupported: Local<bool>,
+) {
+    let exts = instance.exts();
+    let (Some(passthrough_ext), Some(mesh_ext)) =
+        (exts.fb_passthrough.as_ref(), exts.fb_triangle_mesh.as_ref())
+    else {
+        if !*unsupported && !entities.is_empty() {
+            warn!("passthrough cutouts need XR_FB_passthrough and XR_FB_triangle_mesh");
+            *unsupported = true;
+        }
+        return;
+    };
+    let mut state = match state {
+        Some(state) => state,
+        None if entities.is_empty() || *unsupported => return,
+        None => {
+            match create_projected_passthrough(passthrough_ext, mesh_ext, session.as_raw()) {
+                Ok(state) => commands.insert_resource(state),
+                Err(err) => {
+                    error_log.report_result(XrErrorSource::Other, err);
+                    *unsupported = true;
+                }
+            }
+            return;
+        }
+    };
+    let state = &mut *state;
+    let time = frame_state.lock().unwrap().predicted_display_time;
+    //the cutouts are placed in the stage space, which the tracking root moves around the world
+    let to_stage = root
+        .get_single()
+        .map(|root| root.compute_matrix().inverse())
+        .unwrap_or_default();
+    //the triangle mesh is a copy, a changed mesh gets a new one
+    let modified: Vec<AssetId<Mesh>> = mesh_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    state.cutouts.retain(|entity, cutout| {
+        let keep = !modified.contains(&cutout.mesh)
+            && entities
+                .get(*entity)
+                .is_ok_and(|(_, entity_cutout, _)| entity_cutout.mesh.id() == cutout.mesh);
+        if !keep {
+            cutout.destroy(passthrough_ext, mesh_ext);
+        }
+        keep
+    });
+    for (entity, cutout, transform) in &entities {
+        let (scale, rotation, translation) =
+            (to_stage * transform.compute_matrix()).to_scale_rotation_translation();
+        let pose = to_posef(translation, rotation);
+        let scale = sys::Vector3f {
+            x: scale.x,
+            y: scale.y,
+            z: scale.z,
+        };
+        match state.cutouts.get(&entity) {
+            Some(existing) => {
+                let transform = sys::GeometryInstanceTransformFB {
+                    ty: sys::GeometryInstanceTransformFB::TYPE,
+                    next: ptr::null(),
+                    base_space: input.stage.as_raw(),
+                    time,
+                    pose,
+                    scale,
+                };
+                let result = trace(
+                    "xrGeometryInstanceSetTransformFB",
+                    || format!("{:?}, {:?}", pose, scale),
+                    || unsafe {
+                        (passthrough_ext.geometry_instance_set_transform)(
+                            existing.instance,
+                            &transform,
+                        )
+                    },
+                );
+                if let Err(err) = check(result) {
+                    error_log.report_result(XrErrorSource::Other, err);
+                }
+            }
+            None => {
+                //the mesh may still be loading
+                let Some(mesh) = meshes.get(&cutout.mesh) else {
+                    continue;
+                };
+                let created = create_cutout(
+                    (passthrough_ext, mesh_ext),
+                    session.as_raw(),
+                    state.layer,
+                    mesh,
+                    (input.stage.as_raw(), pose, scale),
+                );
+                match created {
+                    Ok((triangle_mesh, instance)) => {
+                        state.cutouts.insert(
+                            entity,
+                            Cutout {
+                                mesh: cutout.mesh.id(),
+                                triangle_mesh,
+                                instance,
+                            },
+                        );
+                    }
+                    Err(err) => {
+                        warn!(
+                            "couldn't create the passthrough cutout {:?}: {}",
+                            entity, err
+                        );
+                        commands.entity(entity).remove::<XrPassthroughCutout>();
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn apply_passthrough_key(
+    instance: Res<XrInstance>,
+    key: Res<XrPassthroughKey>,
+    error_log: Res<XrErrorLog>,
+    state: Option<ResMut<XrPassthroughCutouts>>,
+) {
+    let (Some(mut state), Some(ext)) = (state, instance.exts().fb_passthrough.as_ref()) else {
+        return;
+    };
+    if state.key == Some(*key) {
+        return;
+    }
+    //not retried every frame when it fails
+    state.key = Some(*key);
+    let mut color_map = sys::PassthroughColorMapMonoToRgbaFB {
+        ty: sys::PassthroughColorMapMonoToRgbaFB::TYPE,
+        next: ptr::null(),
+        texture_color_map: [sys::Color4f {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+            a: 0.0,
+        }; 256],
+    };
+    for (index, color) in color_map.texture_color_map.iter_mut().enumerate() {
+        let luminance = index as f32 / 255.0;
+        *color = sys::Color4f {
+            r: luminance,
+            g: luminance,
+            b: luminance,
+            a: key.alpha(luminance),
+        };
+    }
+    let style = sys::PassthroughStyleFB {
+        ty: sys::PassthroughStyleFB::TYPE,
+        //without a color map the passthrough keeps its colors
+        next: match *key {
+            XrPassthroughKey::Off => ptr::null(),
+            XrPassthroughKey::Luminance { .. } => &color_map as *const _ as *const _,
+        },
+        texture_opacity_factor: 1.0,
+        edge_color: sys::Color4f {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+            a: 0.0,
+        },
+    };
+    let result = trace(
+        "xrPassthroughLayerSetStyleFB",
+        || format!("{:?}", *key),
+        || unsafe { (ext.passthrough_layer_set_style)(state.layer, &style) },
+    );
+    if let Err(err) = check(result) {
+        error_log.report_result(XrErrorSource::Other, err);
+    }
+}
+
+pub fn extract_passthrough_layer(
+    mut extracted: ResMut<ExtractedPassthroughLayer>,
+    state: Extract<Option<Res<XrPassthroughCutouts>>>,
+) {
+    //nothing to project onto without cutouts, the layer would only cost compositor time
+    extracted.0 = state
+        .as_ref()
+        .filter(|state| !state.cutouts.is_empty())
+        .map(|state| state.layer);
+}
+
+pub fn submit_passthrough_layer(
+    extracted: Res<ExtractedPassthroughLayer>,
+    mut submission: ResMut<XrLayerSubmission>,
+) {
+    submission.passthrough = extracted.0;
+}
+
+fn create_projected_passthrough(
+    ext: &xr::raw::PassthroughFB,
+    mesh_ext: &xr::raw::TriangleMeshFB,
+    session: sys::Session,
+) -> xr::Result<XrPassthroughCutouts> {
+    let info = sys::PassthroughCreateInfoFB {
+        ty: sys::PassthroughCreateInfoFB::TYPE,
+        next: ptr::null(),
+        flags: sys::PassthroughFlagsFB::IS_RUNNING_AT_CREATION,
+    };
+    let mut passthrough = sys::PassthroughFB::NULL;
+    check(trace("xrCreatePassthroughFB", String::new, || unsafe {
+        (ext.create_passthrough)(session, &info, &mut passthrough)
+    }))?;
+    let info = sys::PassthroughLayerCreateInfoFB {
+        ty: sys::PassthroughLayerCreateInfoFB::TYPE,
+        next: ptr::null(),
+        passthrough,
+        flags: sys::PassthroughFlagsFB::IS_RUNNING_AT_CREATION,
+        purpose: sys::PassthroughLayerPurposeFB::PROJECTED,
+    };
+    let mut layer = sys::PassthroughLayerFB::NULL;
+    let result = trace(
+        "xrCreatePassthroughLayerFB",
+        || "PROJECTED".to_string(),
+        || unsafe { (ext.create_passthrough_layer)(session, &info, &mut layer) },
+    );
+    if let Err(err) = check(result) {
+        unsafe { (ext.destroy_passthrough)(passthrough) };
+        return Err(err);
+    }
+    Ok(XrPassthroughCutouts {
+        passthrough_ext: *ext,
+        mesh_ext: *mesh_ext,
+        passthrough,
+        layer,
+        cutouts: default(),
+        key: None,
+    })
+}
+
+fn create_cutout(
+    (passthrough_ext, mesh_ext): (&xr::raw::PassthroughFB, &xr::raw::TriangleMeshFB),
+    session: sys::Session,
+    layer: sys::PassthroughLayerFB,
+    mesh: &Mesh,
+    (base_space, pose, scale): (sys::Space, sys::Posef, sys::Vector3f),
+) -> xr::Result<(sys::TriangleMeshFB, sys::GeometryInstanceFB)> {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return Err(sys::Result::ERROR_VALIDATION_FAILURE);
+    };
+    let vertices: Vec<sys::Vector3f> = positions
+        .iter()
+        .map(|&[x, y, z]| sys::Vector3f { x, y, z })
+        .collect();
+    let indices: Vec<u32> = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|&index| index as u32).collect(),
+        Some(Indices::U32(indices)) => indices.clone(),
+        None => (0..vertices.len() as u32).collect(),
+    };
+    let info = sys::TriangleMeshCreateInfoFB {
+        ty: sys::TriangleMeshCreateInfoFB::TYPE,
+        next: ptr::null(),
+        flags: sys::TriangleMeshFlagsFB::EMPTY,
+        //bevy's front faces
+        winding_order: sys::WindingOrderFB::CCW,
+        vertex_count: vertices.len() as u32,
+        vertex_buffer: vertices.as_ptr(),
+        triangle_count: indices.len() as u32 / 3,
+        index_buffer: indices.as_ptr(),
+    };
+    let mut triangle_mesh = sys::TriangleMeshFB::NULL;
+    check(trace(
+        "xrCreateTriangleMeshFB",
+        || format!("{} vertices", vertices.len()),
+        || unsafe { (mesh_ext.create_triangle_mesh)(session, &info, &mut triangle_mesh) },
+    ))?;
+    let info = sys::GeometryInstanceCreateInfoFB {
+        ty: sys::GeometryInstanceCreateInfoFB::TYPE,
+        next: ptr::null(),
+        layer,
+        mesh: triangle_mesh,
+        base_space,
+        pose,
+        scale,
+    };
+    let mut instance = sys::GeometryInstanceFB::NULL;
+    let result = trace("xrCreateGeometryInstanceFB", String::new, || unsafe {
+        (passthrough_ext.create_geometry_instance)(session, &info, &mut instance)
+    });
+    if let Err(err) = check(result) {
+        unsafe { (mesh_ext.destroy_triangle_mesh)(triangle_mesh) };
+        return Err(err);
+    }
+    Ok((triangle_mesh, instance))
+}
+
+impl Cutout {
+    fn destroy(
+        &self,
+        passthrough_ext: &xr::raw::PassthroughFB,
+        mesh_ext: &xr::raw::TriangleMeshFB,
+    ) {
+        unsafe {
+            (passthrough_ext.destroy_geometry_instance)(self.instance);
+            (mesh_ext.destroy_triangle_mesh)(self.triangle_mesh);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::call_trace::{check, trace};
+use crate::resources::{XrInstance, XrSession};
+
+/// keeps [`XrThermalState`] up to date from XR_EXT_performance_settings notifications, so apps
+/// can lower their quality before the system throttles them. the notifications are sent as
+/// [`XrPerfSettingsChanged`] events even without this plugin
+pub struct XrPerfSettingsPlugin;
+
+impl Plugin for XrPerfSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrThermalState>();
+        app.add_systems(PreUpdate, update_thermal_state);
+    }
+}
+
+/// the runtime moved a sub domain of the cpu or gpu to another notification level
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrPerfSettingsChanged {
+    pub domain: xr::PerfSettingsDomainEXT,
+    pub sub_domain: xr::PerfSettingsSubDomainEXT,
+    pub from: xr::PerfSettingsNotificationLevelEXT,
+    pub to: xr::PerfSettingsNotificationLevelEXT,
+}
+
+/// the last notification level of every sub domain, everything starts out as `NORMAL`
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrThermalState {
+    pub cpu: XrDomainPerfState,
+    pub gpu: XrDomainPerfState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrDomainPerfState {
+    pub compositing: xr::PerfSettingsNotificationLevelEXT,
+    pub rendering: xr::PerfSettingsNotificationLevelEXT,
+    pub thermal: xr::PerfSettingsNotificationLevelEXT,
+}
+
+impl Default for XrDomainPerfState {
+    fn default() -> Self {
+        Self {
+            compositing: xr::PerfSettingsNotificationLevelEXT::NORMAL,
+            rendering: xr::PerfSettingsNotificationLevelEXT::NORMAL,
+            thermal: xr::PerfSettingsNotificationLevelEXT::NORMAL,
+        }
+    }
+}
+
+impl XrDomainPerfState {
+    /// the most severe level of the sub domains
+    pub fn worst(&self) -> xr::PerfSettingsNotificationLevelEXT {
+        [self.compositing, self.rendering, self.thermal]
+            .into_iter()
+            .max_by_key(|level| level.into_raw())
+            .unwrap()
+    }
+}
+
+impl XrThermalState {
+    pub fn domain(&self, domain: xr::PerfSettingsDomainEXT) -> &XrDomainPerfState {
+        match domain {
+            xr::PerfSettingsDomainEXT::GPU => &self.gpu,
+            _ => &self.cpu,
+        }
+    }
+
+    /// the most severe level of both domains
+    pub fn worst(&self) -> xr::PerfSettingsNotificationLevelEXT {
+        let (cpu, gpu) = (self.cpu.worst(), self.gpu.worst());
+        match cpu.into_raw() >= gpu.into_raw() {
+            true => cpu,
+            false => gpu,
+        }
+    }
+
+    /// the device is warm enough that the system will throttle soon without a lighter workload
+    pub fn should_shed_quality(&self) -> bool {
+        self.worst() != xr::PerfSettingsNotificationLevelEXT::NORMAL
+    }
+}
+
+pub fn update_thermal_state(
+    mut state: ResMut<XrThermalState>,
+    mut events: EventReader<XrPerfSettingsChanged>,
+) {
+    for event in events.read() {
+        warn!(
+            "{:?} {:?} performance went from {:?} to {:?}",
+            event.domain, event.sub_domain, event.from, event.to
+        );
+        let domain = match event.domain {
+            xr::PerfSettingsDomainEXT::GPU => &mut state.gpu,
+            _ => &mut state.cpu,
+        };
+        match event.sub_domain {
+            xr::PerfSettingsSubDomainEXT::COMPOSITING => domain.compositing = event.to,
+            xr::PerfSettingsSubDomainEXT::RENDERING => domain.rendering = event.to,
+            _ => domain.thermal = event.to,
+        }
+    }
+}
+
+/// asks the runtime to run a domain at `level`, e.g. `SUSTAINED_LOW` to stay cool or `BOOST` for
+/// a short heavy scene. needs XR_EXT_performance_settings
+pub fn set_performance_level(
+    instance: &XrInstance,
+    session: &XrSession,
+    domain: xr::PerfSettingsDomainEXT,
+    level: xr::PerfSettingsLevelEXT,
+) -> xr::Result<()> {
+    let Some(ext) = instance.exts().ext_performance_settings.as_ref() else {
+        return Err(xr::sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    trace(
+        "xrPerfSettingsSetPerformanceLevelEXT",
+        || format!("{:?}, {:?}", domain, level),
+        || {
+            check(unsafe {
+                (ext.perf_settings_set_performance_level)(session.as_raw(), domain, level)
+            })
+        },
+    )
+}
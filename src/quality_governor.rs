@@ -0,0 +1,250 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+use crate::call_trace::trace;
+use crate::capabilities::XrCapabilities;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::foveation::{XrFoveation, XrFoveationLevel};
+use crate::frame_loop::XrFrameSet;
+use crate::render_scale::XrRenderScale;
+use crate::resources::{XrFrameState, XrInstance, XrSession};
+use crate::xr_init::xr_only;
+
+/// watches how much of each frame is spent waiting for the runtime and raises the
+/// [`XrFoveation`] level when the app runs out of headroom, then lowers the [`XrRenderScale`]
+/// and the refresh rate once the ones before are at their limit. undoes them in reverse while
+/// there is headroom to spare
+pub struct XrQualityGovernorPlugin;
+
+impl Plugin for XrQualityGovernorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrQualityGovernor>();
+        app.add_event::<XrQualityChanged>();
+        app.add_systems(
+            PreUpdate,
+            (
+                start_governor_frame.before(XrFrameSet::BeginFrame),
+                govern_xr_quality.in_set(XrFrameSet::AfterBeginFrame),
+            )
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// the bounds the governor stays in, can be changed at runtime
+#[derive(Resource, Clone, Debug)]
+pub struct XrQualityGovernor {
+    pub enabled: bool,
+    pub min_render_scale: f32,
+    pub max_render_scale: f32,
+    pub render_scale_step: f32,
+    /// quality drops when less than this share of a frame is left over
+    pub min_headroom: f32,
+    /// quality rises when more than this share of a frame is left over
+    pub raise_headroom: f32,
+    /// lets the governor change the foveation level, needs `XrFoveationPlugin` and the
+    /// extensions of [`XrFoveation::is_supported`]
+    pub adjust_foveation: bool,
+    pub max_foveation: XrFoveationLevel,
+    /// lets the governor change the display refresh rate, needs XR_FB_display_refresh_rate
+    pub adjust_refresh_rate: bool,
+    pub min_refresh_rate: f32,
+    /// seconds of frames averaged before each adjustment
+    pub interval: f32,
+    stats: GovernorStats,
+}
+
+impl Default for XrQualityGovernor {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_render_scale: 0.6,
+            max_render_scale: 1.0,
+            render_scale_step: 0.05,
+            min_headroom: 0.1,
+            raise_headroom: 0.3,
+            adjust_foveation: false,
+            max_foveation: XrFoveationLevel::High,
+            adjust_refresh_rate: false,
+            min_refresh_rate: 72.0,
+            interval: 1.0,
+            stats: default(),
+        }
+    }
+}
+
+impl XrQualityGovernor {
+    /// the average share of a frame spent waiting for the runtime in the current interval
+    pub fn headroom(&self) -> Option<f32> {
+        (self.stats.frames > 0).then(|| self.stats.headroom / self.stats.frames as f32)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct GovernorStats {
+    wait_started: Option<Instant>,
+    last_display_time: Option<i64>,
+    frames: u32,
+    missed: u32,
+    headroom: f32,
+    elapsed: f32,
+}
+
+/// sent when the governor changed the quality
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct XrQualityChanged {
+    pub render_scale: f32,
+    pub refresh_rate: Option<f32>,
+    pub foveation: Option<XrFoveationLevel>,
+}
+
+pub fn start_governor_frame(mut governor: ResMut<XrQualityGovernor>) {
+    if governor.enabled {
+        governor.stats.wait_started = Some(Instant::now());
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn govern_xr_quality(
+    mut governor: ResMut<XrQualityGovernor>,
+    mut render_scale: ResMut<XrRenderScale>,
+    mut foveation: Option<ResMut<XrFoveation>>,
+    frame_state: Res<XrFrameState>,
+    time: Res<Time>,
+    capabilities: Option<Res<XrCapabilities>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    error_log: Res<XrErrorLog>,
+    mut events: EventWriter<XrQualityChanged>,
+    mut refresh_rate: Local<Option<f32>>,
+) {
+    let governor = &mut *governor;
+    let Some(wait_started) = governor.stats.wait_started.take() else {
+        return;
+    };
+    if !governor.enabled {
+        governor.stats = default();
+        return;
+    }
+    let state = *frame_state.lock().unwrap();
+    let period = state.predicted_display_period.as_nanos();
+    let display_time = state.predicted_display_time.as_nanos();
+    if period <= 0 {
+        return;
+    }
+    //waiting in xrWaitFrame is time the frame didn't need
+    let waited = wait_started.elapsed().as_nanos() as f32;
+    let stats = &mut governor.stats;
+    stats.headroom += (waited / period as f32).min(1.0);
+    if let Some(last) = stats.last_display_time {
+        let frames = ((display_time - last) as f64 / period as f64).round() as i64;
+        stats.missed += (frames - 1).max(0) as u32;
+    }
+    stats.last_display_time = Some(display_time);
+    stats.frames += 1;
+    stats.elapsed += time.delta_seconds();
+    if stats.elapsed < governor.interval {
+        return;
+    }
+    let headroom = governor.headroom().unwrap_or_default();
+    let missed = governor.stats.missed;
+    governor.stats = GovernorStats {
+        last_display_time: governor.stats.last_display_time,
+        ..default()
+    };
+
+    //refresh rates the governor may use, from low to high
+    let mut rates = match (governor.adjust_refresh_rate, &capabilities) {
+        (true, Some(capabilities)) => capabilities
+            .refresh_rates
+            .iter()
+            .copied()
+            .filter(|rate| *rate >= governor.min_refresh_rate)
+            .collect::<Vec<_>>(),
+        _ => vec![],
+    };
+    rates.sort_by(f32::total_cmp);
+    let current_rate = match rates.is_empty() {
+        true => None,
+        false => refresh_rate.or_else(|| session.get_display_refresh_rate().ok()),
+    };
+
+    let current_foveation = foveation
+        .as_ref()
+        .filter(|_| governor.adjust_foveation && XrFoveation::is_supported(&instance))
+        .map(|foveation| foveation.level);
+
+    let scale = render_scale.0;
+    let (new_scale, new_rate, new_foveation) = if missed > 0 || headroom < governor.min_headroom {
+        //foveation goes up first, it only blurs what the user doesn't look at
+        let higher_foveation = current_foveation
+            .filter(|level| *level < governor.max_foveation)
+            .and_then(XrFoveationLevel::higher);
+        match (higher_foveation, scale > governor.min_render_scale) {
+            (Some(level), _) => (scale, current_rate, Some(level)),
+            (None, true) => (
+                (scale - governor.render_scale_step).max(governor.min_render_scale),
+                current_rate,
+                current_foveation,
+            ),
+            (None, false) => (
+                scale,
+                current_rate.and_then(|rate| rates.iter().rev().find(|r| **r < rate).copied()),
+                current_foveation,
+            ),
+        }
+    } else if headroom > governor.raise_headroom {
+        //the refresh rate comes back first, it matters more for comfort than resolution
+        let higher_rate = current_rate.and_then(|rate| rates.iter().find(|r| **r > rate).copied());
+        match higher_rate {
+            Some(rate) => (scale, Some(rate), current_foveation),
+            None if scale < governor.max_render_scale => (
+                (scale + governor.render_scale_step).min(governor.max_render_scale),
+                current_rate,
+                current_foveation,
+            ),
+            None => (
+                scale,
+                current_rate,
+                current_foveation.and_then(XrFoveationLevel::lower),
+            ),
+        }
+    } else {
+        return;
+    };
+    let new_rate = new_rate.or(current_rate);
+    let new_foveation = new_foveation.or(current_foveation);
+
+    let previous_rate = *refresh_rate;
+    if let (true, Some(rate)) = (new_rate != current_rate, new_rate) {
+        if instance.exts().fb_display_refresh_rate.is_some() {
+            match trace(
+                "xrRequestDisplayRefreshRateFB",
+                || rate.to_string(),
+                || session.request_display_refresh_rate(rate),
+            ) {
+                Ok(()) => *refresh_rate = Some(rate),
+                Err(err) => error_log.report_result(XrErrorSource::Other, err),
+            }
+        }
+    }
+    if new_scale != scale {
+        render_scale.0 = new_scale;
+    }
+    if let (Some(foveation), Some(level)) = (foveation.as_mut(), new_foveation) {
+        if foveation.level != level {
+            foveation.level = level;
+        }
+    }
+    if new_scale != scale || *refresh_rate != previous_rate || new_foveation != current_foveation {
+        debug!(
+            "xr quality: render scale {:.2}, refresh rate {:?}, foveation {:?}, headroom {:.2}, missed {}",
+            new_scale, *refresh_rate, new_foveation, headroom, missed
+        );
+        events.send(XrQualityChanged {
+            render_scale: new_scale,
+            refresh_rate: *refresh_rate,
+            foveation: new_foveation,
+        });
+    }
+}
@@ -0,0 +1,64 @@
+use std::mem::MaybeUninit;
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+use xr::sys;
+
+const EVENT_SIZE: usize = std::mem::size_of::<sys::EventDataBuffer>();
+
+/// an event the openxr crate has no bindings for, e.g. of newer vendor extensions. sent from
+/// `xr_begin_frame` like the other events, read it with [`XrRawEvent::read`] after checking
+/// [`XrRawEvent::ty`]
+#[derive(Event, Clone)]
+pub struct XrRawEvent {
+    pub ty: sys::StructureType,
+    //the bytes of the `EventDataBuffer`, which isn't `Send` because of its `next` pointer
+    data: Box<[u8; EVENT_SIZE]>,
+}
+
+impl XrRawEvent {
+    /// # Safety
+    /// `T` has to be the event struct of [`XrRawEvent::ty`]
+    pub unsafe fn read<T: Copy>(&self) -> T {
+        assert!(std::mem::size_of::<T>() <= EVENT_SIZE);
+        ptr::read_unaligned(self.data.as_ptr() as *const T)
+    }
+}
+
+/// the events storage of [`poll_event`], zeroed so unknown events can be copied out whole
+pub(crate) fn event_buffer() -> MaybeUninit<sys::EventDataBuffer> {
+    MaybeUninit::zeroed()
+}
+
+/// like `xr::Instance::poll_event`, but events the crate doesn't know are added to `unknown`
+/// instead of being dropped
+pub(crate) fn poll_event<'a>(
+    instance: &xr::Instance,
+    buffer: &'a mut MaybeUninit<sys::EventDataBuffer>,
+    unknown: &mut Vec<XrRawEvent>,
+) -> xr::Result<Option<xr::Event<'a>>> {
+    loop {
+        unsafe {
+            let raw = buffer.as_mut_ptr();
+            (*raw).ty = sys::StructureType::EVENT_DATA_BUFFER;
+            (*raw).next = ptr::null();
+        }
+        let result = unsafe { (instance.fp().poll_event)(instance.as_raw(), buffer.as_mut_ptr()) };
+        match result {
+            sys::Result::SUCCESS => {}
+            sys::Result::EVENT_UNAVAILABLE => return Ok(None),
+            err => return Err(err),
+        }
+        if unsafe { xr::Event::from_raw(buffer) }.is_some() {
+            break;
+        }
+        //the buffer is zeroed and the runtime only writes into it, so it's initialized
+        let data = unsafe { ptr::read(buffer.as_ptr() as *const [u8; EVENT_SIZE]) };
+        unknown.push(XrRawEvent {
+            ty: unsafe { (*buffer.as_ptr()).ty },
+            data: Box::new(data),
+        });
+    }
+    Ok(unsafe { xr::Event::from_raw(buffer) })
+}
@@ -0,0 +1,347 @@
+use std::path::Path;
+use std::ptr;
+
+use bevy::asset::io::memory::{Dir, MemoryAssetReader};
+use bevy::asset::io::AssetSource;
+use bevy::prelude::*;
+use openxr as xr;
+use xr::sys;
+
+use crate::call_trace::{check, trace};
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrFrameSet;
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::xr_only;
+use crate::xr_input::input_sources::XrInputSource;
+use crate::xr_input::trackers::{OpenXRLeftController, OpenXRRightController};
+use crate::xr_input::Hand;
+use crate::xr_tasks::{XrTask, XrTaskAppExt, XrTaskOutput};
+
+/// the asset source the models are loaded from, `openxr://model_fb/controller/left.glb`
+pub const RENDER_MODEL_SOURCE: &str = "openxr";
+
+/// registers the asset source of the render models, it has to be added before the
+/// `AssetPlugin` and is part of `DefaultXrPlugins`
+pub struct XrRenderModelSourcePlugin;
+
+impl Plugin for XrRenderModelSourcePlugin {
+    fn build(&self, app: &mut App) {
+        let dir = Dir::default();
+        app.insert_resource(XrRenderModelDir(dir.clone()));
+        app.register_asset_source(
+            RENDER_MODEL_SOURCE,
+            AssetSource::build()
+                .with_reader(move || Box::new(MemoryAssetReader { root: dir.clone() })),
+        );
+    }
+}
+
+/// the loaded glb files
+#[derive(Resource, Clone)]
+pub struct XrRenderModelDir(Dir);
+
+/// spawns the models the runtime has for tracked devices through XR_FB_render_model.
+/// [`XrRenderModel`] can be added to any entity that follows a device, e.g. the space of a
+/// tracked keyboard, the model is loaded in an [`XrTask`] and spawned as its child. with
+/// `devices` every enumerated model whose device has an entity, like the controllers and the hand
+/// input sources, is attached to it automatically
+#[derive(Default)]
+pub struct XrRenderModelPlugin {
+    pub devices: bool,
+}
+
+impl Plugin for XrRenderModelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrRenderModels>();
+        app.add_xr_task::<XrRenderModelGlb>();
+        app.add_systems(
+            PreUpdate,
+            (
+                enumerate_render_models,
+                load_render_models,
+                spawn_render_models,
+            )
+                .chain()
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+        if self.devices {
+            app.add_systems(
+                PreUpdate,
+                add_device_render_models
+                    .after(enumerate_render_models)
+                    .before(load_render_models)
+                    .run_if(xr_only())
+                    .in_set(XrFrameSet::AfterBeginFrame),
+            );
+        }
+    }
+}
+
+/// the render model paths the runtime knows, e.g. `/model_fb/controller/left`. a model can be
+/// listed before its device is connected, it's only loaded once the device is
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrRenderModels {
+    pub paths: Vec<String>,
+}
+
+impl XrRenderModels {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        instance.exts().fb_render_model.is_some()
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.paths.iter().any(|p| p == path)
+    }
+}
+
+/// the render model path of the device this entity follows
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+pub struct XrRenderModel(pub String);
+
+/// added once the model of [`XrRenderModel`] is handled, the spawned scene or `None` when the
+/// runtime has no model for the path
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrRenderModelScene(pub Option<Entity>);
+
+/// the output of the [`XrTask`] loading a model, the glb or `None` while the device isn't
+/// connected
+pub struct XrRenderModelGlb(pub Option<Vec<u8>>);
+
+pub fn enumerate_render_models(
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    error_log: Res<XrErrorLog>,
+    mut models: ResMut<XrRenderModels>,
+    mut done: Local<bool>,
+) {
+    if *done {
+        return;
+    }
+    *done = true;
+    match enumerate_render_model_paths(&instance, &session) {
+        Ok(paths) => {
+            info!("render models: {:?}", paths);
+            models.paths = paths;
+        }
+        Err(sys::Result::ERROR_EXTENSION_NOT_PRESENT) => {
+            warn!("render models need XR_FB_render_model")
+        }
+        Err(err) => error_log.report_result(XrErrorSource::Other, err),
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn load_render_models(
+    mut commands: Commands,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    models: Res<XrRenderModels>,
+    dir: Option<Res<XrRenderModelDir>>,
+    query: Query<
+        (Entity, &XrRenderModel),
+        (
+            Without<XrRenderModelScene>,
+            Without<XrTask<XrRenderModelGlb>>,
+            Without<XrTaskOutput<XrRenderModelGlb>>,
+        ),
+    >,
+    mut warned: Local<bool>,
+) {
+    if dir.is_none() {
+        if !query.is_empty() && !*warned {
+            warn!("render models need XrRenderModelSourcePlugin before the AssetPlugin");
+            *warned = true;
+        }
+        return;
+    }
+    for (entity, model) in &query {
+        if !models.contains(&model.0) {
+            commands.entity(entity).insert(XrRenderModelScene(None));
+            continue;
+        }
+        let instance = (**instance).clone();
+        let session = session.as_raw();
+        let path = model.0.clone();
+        commands.entity(entity).insert(XrTask::spawn(async move {
+            load_render_model(&instance, session, &path).map(XrRenderModelGlb)
+        }));
+    }
+}
+
+pub fn spawn_render_models(
+    mut commands: Commands,
+    error_log: Res<XrErrorLog>,
+    dir: Option<Res<XrRenderModelDir>>,
+    asset_server: Res<AssetServer>,
+    mut query: Query<(Entity, &XrRenderModel, &mut XrTaskOutput<XrRenderModelGlb>)>,
+) {
+    let Some(dir) = dir else {
+        return;
+    };
+    for (entity, model, mut output) in &mut query {
+        //without the output the model is loaded again, while the device isn't connected
+        commands
+            .entity(entity)
+            .remove::<XrTaskOutput<XrRenderModelGlb>>();
+        let result = std::mem::replace(&mut output.0, Ok(XrRenderModelGlb(None)));
+        let bytes = match result {
+            Ok(XrRenderModelGlb(Some(bytes))) => bytes,
+            //the device isn't connected yet
+            Ok(XrRenderModelGlb(None)) => continue,
+            Err(err) => {
+                error_log.report_result(XrErrorSource::Other, err);
+                commands.entity(entity).insert(XrRenderModelScene(None));
+                continue;
+            }
+        };
+        let file = format!("{}.glb", model.0.trim_start_matches('/'));
+        dir.0.insert_asset(Path::new(&file), bytes);
+        let scene = commands
+            .spawn(SceneBundle {
+                scene: asset_server.load(format!("{}://{}#Scene0", RENDER_MODEL_SOURCE, file)),
+                ..default()
+            })
+            .id();
+        commands
+            .entity(entity)
+            .add_child(scene)
+            .insert(XrRenderModelScene(Some(scene)));
+    }
+}
+
+/// gives every enumerated model without an [`XrRenderModel`] entity to the entity of its device,
+/// if that has no model yet. the controllers are preferred over the hand input sources, so a
+/// model isn't shown twice
+#[allow(clippy::type_complexity)]
+pub fn add_device_render_models(
+    mut commands: Commands,
+    models: Res<XrRenderModels>,
+    existing: Query<&XrRenderModel>,
+    left: Query<Entity, (With<OpenXRLeftController>, Without<XrRenderModel>)>,
+    right: Query<Entity, (With<OpenXRRightController>, Without<XrRenderModel>)>,
+    sources: Query<(Entity, &XrInputSource), Without<XrRenderModel>>,
+    mut unknown: Local<Vec<String>>,
+) {
+    let source = |hand| {
+        sources
+            .iter()
+            .find(|(_, source)| source.hand == Some(hand))
+            .map(|(entity, _)| entity)
+    };
+    for path in &models.paths {
+        if existing.iter().any(|model| model.0 == *path) {
+            continue;
+        }
+        let device = match path.as_str() {
+            "/model_fb/controller/left" => left.iter().next().or_else(|| source(Hand::Left)),
+            "/model_fb/controller/right" => right.iter().next().or_else(|| source(Hand::Right)),
+            _ => {
+                //e.g. the keyboards, their plugins add the model themselves
+                if !unknown.contains(path) {
+                    info!("no device entity for the render model {}", path);
+                    unknown.push(path.clone());
+                }
+                continue;
+            }
+        };
+        if let Some(entity) = device {
+            commands.entity(entity).insert(XrRenderModel(path.clone()));
+        }
+    }
+}
+
+/// needs XR_FB_render_model
+pub fn enumerate_render_model_paths(
+    instance: &XrInstance,
+    session: &XrSession,
+) -> xr::Result<Vec<String>> {
+    let Some(ext) = instance.exts().fb_render_model.as_ref() else {
+        return Err(sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let empty = sys::RenderModelPathInfoFB {
+        ty: sys::RenderModelPathInfoFB::TYPE,
+        next: ptr::null_mut(),
+        path: sys::Path::NULL,
+    };
+    let mut count = 0;
+    check(trace(
+        "xrEnumerateRenderModelPathsFB",
+        String::new,
+        || unsafe {
+            (ext.enumerate_render_model_paths)(session.as_raw(), 0, &mut count, ptr::null_mut())
+        },
+    ))?;
+    let mut paths = vec![empty; count as usize];
+    check(trace(
+        "xrEnumerateRenderModelPathsFB",
+        String::new,
+        || unsafe {
+            (ext.enumerate_render_model_paths)(
+                session.as_raw(),
+                paths.len() as u32,
+                &mut count,
+                paths.as_mut_ptr(),
+            )
+        },
+    ))?;
+    paths.truncate(count as usize);
+    paths
+        .iter()
+        .map(|info| instance.path_to_string(info.path))
+        .collect()
+}
+
+/// the glb of the model at `path`, `None` while the runtime has no model for it, e.g. because
+/// the device isn't connected
+pub fn load_render_model(
+    instance: &xr::Instance,
+    session: sys::Session,
+    path: &str,
+) -> xr::Result<Option<Vec<u8>>> {
+    let Some(ext) = instance.exts().fb_render_model.as_ref() else {
+        return Err(sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let xr_path = instance.string_to_path(path)?;
+    let mut properties: sys::RenderModelPropertiesFB = unsafe { std::mem::zeroed() };
+    properties.ty = sys::RenderModelPropertiesFB::TYPE;
+    let result = trace(
+        "xrGetRenderModelPropertiesFB",
+        || path.to_string(),
+        || unsafe { (ext.get_render_model_properties)(session, xr_path, &mut properties) },
+    );
+    if result == sys::Result::RENDER_MODEL_UNAVAILABLE_FB {
+        return Ok(None);
+    }
+    check(result)?;
+    if properties.model_key.into_raw() == 0 {
+        return Ok(None);
+    }
+    let info = sys::RenderModelLoadInfoFB {
+        ty: sys::RenderModelLoadInfoFB::TYPE,
+        next: ptr::null_mut(),
+        model_key: properties.model_key,
+    };
+    let mut buffer = sys::RenderModelBufferFB {
+        ty: sys::RenderModelBufferFB::TYPE,
+        next: ptr::null_mut(),
+        buffer_capacity_input: 0,
+        buffer_count_output: 0,
+        buffer: ptr::null_mut(),
+    };
+    check(trace(
+        "xrLoadRenderModelFB",
+        || path.to_string(),
+        || unsafe { (ext.load_render_model)(session, &info, &mut buffer) },
+    ))?;
+    let mut bytes = vec![0u8; buffer.buffer_count_output as usize];
+    buffer.buffer_capacity_input = bytes.len() as u32;
+    buffer.buffer = bytes.as_mut_ptr();
+    check(trace(
+        "xrLoadRenderModelFB",
+        || path.to_string(),
+        || unsafe { (ext.load_render_model)(session, &info, &mut buffer) },
+    ))?;
+    bytes.truncate(buffer.buffer_count_output as usize);
+    Ok(Some(bytes))
+}
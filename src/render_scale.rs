@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::render::Extract;
+
+use crate::resources::XrResolution;
+use crate::xr_input::xr_camera::XrCameraType;
+
+/// how much of the swapchain the eyes render to, 1.0 is the recommended resolution. a lower scale
+/// renders fewer pixels into the corner of the images and submits only that part, the compositor
+/// scales it up. can be changed every frame, the swapchain isn't recreated
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrRenderScale(pub f32);
+
+impl Default for XrRenderScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl XrRenderScale {
+    pub const MIN: f32 = 0.1;
+
+    /// the part of the swapchain that is rendered to
+    pub fn scaled_resolution(&self, resolution: UVec2) -> UVec2 {
+        (resolution.as_vec2() * self.0.clamp(Self::MIN, 1.0))
+            .as_uvec2()
+            .max(UVec2::ONE)
+    }
+}
+
+pub fn update_xr_render_scale(
+    scale: Res<XrRenderScale>,
+    resolution: Res<XrResolution>,
+    mut cameras: Query<(Ref<XrCameraType>, &mut Camera)>,
+) {
+    let size = scale.scaled_resolution(**resolution);
+    for (camera_type, mut camera) in cameras.iter_mut() {
+        if !matches!(*camera_type, XrCameraType::Xr(_))
+            || !(scale.is_changed() || resolution.is_changed() || camera_type.is_added())
+        {
+            continue;
+        }
+        camera.viewport = match size == **resolution {
+            true => None,
+            false => Some(Viewport {
+                physical_position: UVec2::ZERO,
+                physical_size: size,
+                ..default()
+            }),
+        };
+    }
+}
+
+pub fn extract_render_scale(mut commands: Commands, scale: Extract<Res<XrRenderScale>>) {
+    if scale.is_changed() {
+        commands.insert_resource(**scale);
+    }
+}
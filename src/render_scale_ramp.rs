@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+use crate::frame_loop::XrFrameSet;
+use crate::quality_governor::govern_xr_quality;
+use crate::render_scale::XrRenderScale;
+use crate::xr_init::{xr_only, XrSessionState};
+
+/// renders the first frames after the session becomes visible, when it begins or is resumed, at
+/// a lower [`XrRenderScale`] and ramps up to the full scale over the next frames. shaders are
+/// compiled and pipelines warmed up right then, fewer pixels keep those frames from missing
+/// their display time. the scale the app set is restored at the end of the ramp, a scale set
+/// during the ramp, like by the quality governor, ends it early and is kept
+pub struct XrRenderScaleRampPlugin;
+
+impl Plugin for XrRenderScaleRampPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrRenderScaleRamp>();
+        app.add_systems(
+            PreUpdate,
+            ramp_xr_render_scale
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame)
+                .before(govern_xr_quality),
+        );
+    }
+}
+
+/// can be changed at runtime, a change applies to the next ramp
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrRenderScaleRamp {
+    pub enabled: bool,
+    /// the share of the render scale the first frames render at
+    pub start: f32,
+    /// frames rendered at the start scale before the ramp begins
+    pub hold_frames: u32,
+    /// frames the ramp from the start scale to the full scale takes
+    pub ramp_frames: u32,
+    ramp: Option<Ramp>,
+}
+
+impl Default for XrRenderScaleRamp {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            start: 0.5,
+            hold_frames: 30,
+            ramp_frames: 60,
+            ramp: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Ramp {
+    /// the scale the app set, restored at the end
+    target: f32,
+    frame: u32,
+    /// the scale the ramp set last frame
+    applied: Option<f32>,
+}
+
+impl XrRenderScaleRamp {
+    pub fn is_ramping(&self) -> bool {
+        self.ramp.is_some()
+    }
+
+    /// the scale the running ramp ends at
+    pub fn target(&self) -> Option<f32> {
+        self.ramp.map(|ramp| ramp.target)
+    }
+
+    /// the share of the full scale rendered `frame` frames after the session became visible
+    pub fn factor(&self, frame: u32) -> f32 {
+        let start = self.start.clamp(0.0, 1.0);
+        let Some(frame) = frame.checked_sub(self.hold_frames) else {
+            return start;
+        };
+        let t = (frame as f32 / self.ramp_frames.max(1) as f32).min(1.0);
+        start + (1.0 - start) * t
+    }
+}
+
+pub fn ramp_xr_render_scale(
+    mut ramp: ResMut<XrRenderScaleRamp>,
+    mut scale: ResMut<XrRenderScale>,
+    session_state: Res<State<XrSessionState>>,
+    mut was_visible: Local<bool>,
+) {
+    let visible = session_state.is_visible();
+    let became_visible = visible && !*was_visible;
+    *was_visible = visible;
+    let ramp = &mut *ramp;
+    if !ramp.enabled {
+        if let Some(current) = ramp.ramp.take() {
+            if current.applied == Some(scale.0) {
+                scale.0 = current.target;
+            }
+        }
+        return;
+    }
+    if became_visible {
+        //resuming in the middle of a ramp starts over from the same target
+        let target = ramp.ramp.map_or(scale.0, |current| current.target);
+        ramp.ramp = Some(Ramp {
+            target,
+            frame: 0,
+            applied: None,
+        });
+    }
+    let Some(mut current) = ramp.ramp else {
+        return;
+    };
+    //frames aren't rendered while hidden, the ramp waits
+    if !visible {
+        return;
+    }
+    if current.applied.is_some_and(|applied| applied != scale.0) {
+        //someone else set a scale, theirs wins
+        ramp.ramp = None;
+        return;
+    }
+    let factor = ramp.factor(current.frame);
+    if factor >= 1.0 {
+        scale.0 = current.target;
+        ramp.ramp = None;
+        return;
+    }
+    let value = current.target * factor;
+    if scale.0 != value {
+        scale.0 = value;
+    }
+    current.applied = Some(value);
+    current.frame += 1;
+    ramp.ramp = Some(current);
+}
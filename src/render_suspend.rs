@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use bevy::render::Extract;
+
+use crate::compositor_hold::XrCompositorHold;
+use crate::resources::XrFrameState;
+use crate::xr_init::XrSessionState;
+use crate::xr_input::xr_camera::XrCameraType;
+
+/// whether nothing gets rendered this frame because the user can't see the app, e.g. while the
+/// system dashboard is open, or because `xrWaitFrame` said the frame shouldn't be rendered. the
+/// xr cameras are turned off and the frames are ended without layers, only the wait/begin/end
+/// loop the runtime needs keeps running. a compositor hold suspends rendering too, its frames
+/// keep the quad layers
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrRenderSuspended(pub bool);
+
+pub fn update_xr_render_suspended(
+    session_state: Res<State<XrSessionState>>,
+    next_session_state: Res<NextState<XrSessionState>>,
+    hold: Option<Res<XrCompositorHold>>,
+    frame_state: Res<XrFrameState>,
+    mut suspended: ResMut<XrRenderSuspended>,
+    mut cameras: Query<(Entity, &XrCameraType, &mut Camera)>,
+    //the cameras turned off here, so cameras the user turned off stay off
+    mut deactivated: Local<Vec<Entity>>,
+) {
+    //`xr_begin_frame` only queues the state changes of this frame
+    let state = next_session_state.0.unwrap_or(**session_state);
+    let suspend = !state.is_visible()
+        || !frame_state.lock().unwrap().should_render
+        || hold.is_some_and(|hold| hold.is_holding());
+    if suspended.0 != suspend {
+        info!(
+            "{} rendering",
+            match suspend {
+                true => "suspending",
+                false => "resuming",
+            }
+        );
+        suspended.0 = suspend;
+    }
+    match suspend {
+        true => {
+            for (entity, camera_type, mut camera) in cameras.iter_mut() {
+                if matches!(camera_type, XrCameraType::Xr(_)) && camera.is_active {
+                    camera.is_active = false;
+                    deactivated.push(entity);
+                }
+            }
+        }
+        false => {
+            for entity in deactivated.drain(..) {
+                if let Ok((_, _, mut camera)) = cameras.get_mut(entity) {
+                    camera.is_active = true;
+                }
+            }
+        }
+    }
+}
+
+pub fn extract_render_suspended(
+    mut commands: Commands,
+    suspended: Extract<Res<XrRenderSuspended>>,
+) {
+    commands.insert_resource(**suspended);
+}
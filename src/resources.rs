@@ -1,8 +1,10 @@
-use std::sync::atomic::AtomicBool;
 use std::sync::Mutex;
 
+use crate::call_trace::trace;
+use crate::layers::XrLayerSubmission;
 use crate::resource_macros::*;
 use bevy::prelude::*;
+use bevy::render::render_resource::TextureView;
 use openxr as xr;
 
 xr_resource_wrapper!(XrInstance, xr::Instance);
@@ -10,11 +12,95 @@ xr_resource_wrapper!(XrSession, xr::Session<xr::AnyGraphics>);
 xr_resource_wrapper!(XrEnvironmentBlendMode, xr::EnvironmentBlendMode);
 xr_resource_wrapper!(XrResolution, UVec2);
 xr_resource_wrapper!(XrFormat, wgpu::TextureFormat);
-xr_arc_resource_wrapper!(XrSessionRunning, AtomicBool);
 xr_arc_resource_wrapper!(XrFrameWaiter, Mutex<xr::FrameWaiter>);
 xr_arc_resource_wrapper!(XrSwapchain, Swapchain);
 xr_arc_resource_wrapper!(XrFrameState, Mutex<xr::FrameState>);
-xr_arc_resource_wrapper!(XrViews, Mutex<Vec<xr::View>>);
+
+/// the views located for the frame. the main world locates them in `xr_begin_frame`, the render
+/// world gets a copy extracted every frame, so the next frame can be located while this one is
+/// still rendered
+#[derive(Resource, Clone, Default, Deref, DerefMut)]
+pub struct XrViews(pub Vec<xr::View>);
+
+impl XrEnvironmentBlendMode {
+    /// changes the blend mode the next frames get submitted with, use this to switch between
+    /// fully virtual (`OPAQUE`) and mixed reality (`ALPHA_BLEND`/`ADDITIVE`) at runtime. modes
+    /// the system doesn't support are ignored, see [`XrCapabilities::blend_modes`]
+    ///
+    /// [`XrCapabilities::blend_modes`]: crate::capabilities::XrCapabilities::blend_modes
+    pub fn set(&mut self, blend_mode: xr::EnvironmentBlendMode) {
+        self.0 = blend_mode;
+    }
+}
+
+/// extra usages the swapchain images are created with, set through
+/// [`OpenXrSettings::swapchain_usage`](crate::OpenXrSettings::swapchain_usage). the runtime may
+/// refuse storage usage for srgb formats
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrSwapchainUsage {
+    /// lets compute shaders write into the images through [`XrSwapchainImages`]
+    pub storage: bool,
+}
+
+/// the eye image sizes the runtime recommends and the largest it allows, from the view
+/// configuration
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrViewSizes {
+    pub recommended: UVec2,
+    pub max: UVec2,
+    pub recommended_sample_count: u32,
+    pub max_sample_count: u32,
+}
+
+impl XrViewSizes {
+    pub(crate) fn from_view(view: &xr::ViewConfigurationView) -> Self {
+        Self {
+            recommended: UVec2::new(
+                view.recommended_image_rect_width,
+                view.recommended_image_rect_height,
+            ),
+            max: UVec2::new(view.max_image_rect_width, view.max_image_rect_height),
+            recommended_sample_count: view.recommended_swapchain_sample_count,
+            max_sample_count: view.max_swapchain_sample_count,
+        }
+    }
+
+    /// the recommended size scaled by `factor`, clamped to the maximum
+    pub fn supersampled(&self, factor: f32) -> UVec2 {
+        (self.recommended.as_vec2() * factor.max(0.0))
+            .round()
+            .as_uvec2()
+            .clamp(UVec2::ONE, self.max.max(UVec2::ONE))
+    }
+}
+
+/// the size of the swapchain relative to the recommended size, clamped to the maximum of
+/// [`XrViewSizes`]. the first one comes from [`OpenXrSettings`](crate::OpenXrSettings), changing
+/// it later needs `XrSwapchainRecreationPlugin`. unlike `XrRenderScale` this changes the size of
+/// the images, so it can go above 1.0 for a sharper image on fast gpus
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrSupersampling(pub f32);
+
+impl Default for XrSupersampling {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// the swapchain image acquired for the current frame, only exists in the render world. each
+/// image has its own views, they are only valid while the image with `index` is acquired
+#[derive(Resource, Clone)]
+pub struct XrSwapchainImages {
+    pub index: usize,
+    /// both eyes in one array view, layer 0 is the left eye
+    pub array: TextureView,
+    pub left: TextureView,
+    pub right: TextureView,
+    pub resolution: UVec2,
+    pub format: wgpu::TextureFormat,
+    /// whether the images were created with storage usage
+    pub storage: bool,
+}
 
 pub enum Swapchain {
     Vulkan(SwapchainInner<xr::Vulkan>),
@@ -27,12 +113,31 @@ impl Swapchain {
         }
     }
 
-    pub(crate) fn get_render_views(&self) -> (wgpu::TextureView, wgpu::TextureView) {
+    pub(crate) fn get_render_views(&self) -> (TextureView, TextureView) {
         match self {
             Swapchain::Vulkan(swapchain) => swapchain.get_render_views(),
         }
     }
 
+    pub(crate) fn get_array_view(&self) -> TextureView {
+        match self {
+            Swapchain::Vulkan(swapchain) => swapchain.get_array_view(),
+        }
+    }
+
+    pub(crate) fn image_index(&self) -> usize {
+        match self {
+            Swapchain::Vulkan(swapchain) => *swapchain.image_index.lock().unwrap(),
+        }
+    }
+
+    /// changes when the swapchain is recreated
+    pub(crate) fn as_raw(&self) -> xr::sys::Swapchain {
+        match self {
+            Swapchain::Vulkan(swapchain) => swapchain.handle.lock().unwrap().as_raw(),
+        }
+    }
+
     pub(crate) fn acquire_image(&self) -> xr::Result<()> {
         match self {
             Swapchain::Vulkan(swapchain) => swapchain.acquire_image(),
@@ -51,6 +156,25 @@ impl Swapchain {
         }
     }
 
+    /// ends the frame without the projection, only with the quads of `layers`. without layers
+    /// the runtime shows nothing of the app
+    pub(crate) fn end_without_projection(
+        &self,
+        predicted_display_time: xr::Time,
+        stage: &xr::Space,
+        environment_blend_mode: xr::EnvironmentBlendMode,
+        layers: Option<&XrLayerSubmission>,
+    ) -> xr::Result<()> {
+        match self {
+            Swapchain::Vulkan(swapchain) => swapchain.end_without_projection(
+                predicted_display_time,
+                stage,
+                environment_blend_mode,
+                layers,
+            ),
+        }
+    }
+
     pub(crate) fn end(
         &self,
         predicted_display_time: xr::Time,
@@ -58,6 +182,8 @@ impl Swapchain {
         stage: &xr::Space,
         resolution: UVec2,
         environment_blend_mode: xr::EnvironmentBlendMode,
+        color_scale_bias: Option<(xr::Color4f, xr::Color4f)>,
+        layers: Option<&XrLayerSubmission>,
     ) -> xr::Result<()> {
         match self {
             Swapchain::Vulkan(swapchain) => swapchain.end(
@@ -66,56 +192,132 @@ impl Swapchain {
                 stage,
                 resolution,
                 environment_blend_mode,
+                color_scale_bias,
+                layers,
             ),
         }
     }
 }
 
 pub struct SwapchainInner<G: xr::Graphics> {
+    pub(crate) session: xr::Session<G>,
     pub(crate) stream: Mutex<xr::FrameStream<G>>,
     pub(crate) handle: Mutex<xr::Swapchain<G>>,
-    pub(crate) buffers: Vec<wgpu::Texture>,
+    /// behind a lock like `handle`, so the images can be replaced without a new session
+    pub(crate) buffers: Mutex<Vec<wgpu::Texture>>,
+    /// the views of `buffers`, created once instead of every frame
+    pub(crate) views: Mutex<Vec<SwapchainImageViews>>,
     pub(crate) image_index: Mutex<usize>,
 }
 
+pub(crate) struct SwapchainImageViews {
+    left: TextureView,
+    right: TextureView,
+    array: TextureView,
+}
+
+impl SwapchainImageViews {
+    pub(crate) fn new(texture: &wgpu::Texture) -> Self {
+        let eye = |layer| -> TextureView {
+            texture
+                .create_view(&wgpu::TextureViewDescriptor {
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    array_layer_count: Some(1),
+                    base_array_layer: layer,
+                    ..Default::default()
+                })
+                .into()
+        };
+        let array = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            array_layer_count: Some(2),
+            ..Default::default()
+        });
+        Self {
+            left: eye(0),
+            right: eye(1),
+            array: array.into(),
+        }
+    }
+}
+
 impl<G: xr::Graphics> SwapchainInner<G> {
     fn begin(&self) -> xr::Result<()> {
-        self.stream.lock().unwrap().begin()
-    }
-
-    fn get_render_views(&self) -> (wgpu::TextureView, wgpu::TextureView) {
-        let texture = &self.buffers[*self.image_index.lock().unwrap()];
-
-        (
-            texture.create_view(&wgpu::TextureViewDescriptor {
-                dimension: Some(wgpu::TextureViewDimension::D2),
-                array_layer_count: Some(1),
-                ..Default::default()
-            }),
-            texture.create_view(&wgpu::TextureViewDescriptor {
-                dimension: Some(wgpu::TextureViewDimension::D2),
-                array_layer_count: Some(1),
-                base_array_layer: 1,
-                ..Default::default()
-            }),
-        )
+        trace("xrBeginFrame", String::new, || {
+            self.stream.lock().unwrap().begin()
+        })
+    }
+
+    fn get_render_views(&self) -> (TextureView, TextureView) {
+        let views = &self.views.lock().unwrap()[*self.image_index.lock().unwrap()];
+        (views.left.clone(), views.right.clone())
+    }
+
+    fn get_array_view(&self) -> TextureView {
+        self.views.lock().unwrap()[*self.image_index.lock().unwrap()]
+            .array
+            .clone()
     }
 
     fn acquire_image(&self) -> xr::Result<()> {
-        let image_index = self.handle.lock().unwrap().acquire_image()?;
+        let image_index = trace("xrAcquireSwapchainImage", String::new, || {
+            self.handle.lock().unwrap().acquire_image()
+        })?;
         *self.image_index.lock().unwrap() = image_index as _;
         Ok(())
     }
 
     fn wait_image(&self) -> xr::Result<()> {
-        self.handle
-            .lock()
-            .unwrap()
-            .wait_image(xr::Duration::INFINITE)
+        trace(
+            "xrWaitSwapchainImage",
+            || "INFINITE".to_string(),
+            || {
+                self.handle
+                    .lock()
+                    .unwrap()
+                    .wait_image(xr::Duration::INFINITE)
+            },
+        )
     }
 
     fn release_image(&self) -> xr::Result<()> {
-        self.handle.lock().unwrap().release_image()
+        trace("xrReleaseSwapchainImage", String::new, || {
+            self.handle.lock().unwrap().release_image()
+        })
+    }
+
+    fn end_without_projection(
+        &self,
+        predicted_display_time: xr::Time,
+        stage: &xr::Space,
+        environment_blend_mode: xr::EnvironmentBlendMode,
+        layers: Option<&XrLayerSubmission>,
+    ) -> xr::Result<()> {
+        let quads = layers.map(|layers| &layers.quads[..]).unwrap_or_default();
+        let quads: Vec<xr::CompositionLayerQuad<G>> = quads
+            .iter()
+            .map(|quad| unsafe { xr::CompositionLayerQuad::from_raw(quad.to_raw(stage)) })
+            .collect();
+        let submitted: Vec<&xr::CompositionLayerBase<G>> =
+            quads.iter().map(|quad| &**quad).collect();
+        trace(
+            "xrEndFrame",
+            || {
+                format!(
+                    "{:?}, {:?}, layers: {}",
+                    predicted_display_time,
+                    environment_blend_mode,
+                    submitted.len()
+                )
+            },
+            || {
+                self.stream.lock().unwrap().end(
+                    predicted_display_time,
+                    environment_blend_mode,
+                    &submitted,
+                )
+            },
+        )
     }
 
     fn end(
@@ -125,6 +327,8 @@ impl<G: xr::Graphics> SwapchainInner<G> {
         stage: &xr::Space,
         resolution: UVec2,
         environment_blend_mode: xr::EnvironmentBlendMode,
+        color_scale_bias: Option<(xr::Color4f, xr::Color4f)>,
+        layers: Option<&XrLayerSubmission>,
     ) -> xr::Result<()> {
         let rect = xr::Rect2Di {
             offset: xr::Offset2Di { x: 0, y: 0 },
@@ -138,29 +342,186 @@ impl<G: xr::Graphics> SwapchainInner<G> {
             warn!("views are len of 0");
             return Ok(());
         }
-        self.stream.lock().unwrap().end(
-            predicted_display_time,
-            environment_blend_mode,
-            &[&xr::CompositionLayerProjection::new().space(stage).views(&[
-                xr::CompositionLayerProjectionView::new()
-                    .pose(views[0].pose)
-                    .fov(views[0].fov)
-                    .sub_image(
-                        xr::SwapchainSubImage::new()
-                            .swapchain(&swapchain)
-                            .image_array_index(0)
-                            .image_rect(rect),
-                    ),
-                xr::CompositionLayerProjectionView::new()
-                    .pose(views[1].pose)
-                    .fov(views[1].fov)
-                    .sub_image(
-                        xr::SwapchainSubImage::new()
-                            .swapchain(&swapchain)
-                            .image_array_index(1)
-                            .image_rect(rect),
-                    ),
-            ])],
+        let mut projection_views = [
+            xr::CompositionLayerProjectionView::new()
+                .pose(views[0].pose)
+                .fov(views[0].fov)
+                .sub_image(
+                    xr::SwapchainSubImage::new()
+                        .swapchain(&swapchain)
+                        .image_array_index(0)
+                        .image_rect(rect),
+                ),
+            xr::CompositionLayerProjectionView::new()
+                .pose(views[1].pose)
+                .fov(views[1].fov)
+                .sub_image(
+                    xr::SwapchainSubImage::new()
+                        .swapchain(&swapchain)
+                        .image_array_index(1)
+                        .image_rect(rect),
+                ),
+        ];
+        //the depth info of XR_KHR_composition_layer_depth is chained onto each view
+        let depth_infos = layers
+            .and_then(|layers| layers.depth.as_ref())
+            .map(|depth| [depth.to_raw(0, rect), depth.to_raw(1, rect)]);
+        if let Some(depth_infos) = &depth_infos {
+            for (view, depth_info) in projection_views.iter_mut().zip(depth_infos) {
+                let mut raw = view.into_raw();
+                raw.next = depth_info as *const _ as _;
+                *view = unsafe { xr::CompositionLayerProjectionView::from_raw(raw) };
+            }
+        }
+        let quads = layers.map(|layers| &layers.quads[..]).unwrap_or_default();
+        //the quads are sorted, the projection goes in front of the ones with a lower order
+        let projection_index = layers.map_or(0, |layers| {
+            quads.partition_point(|quad| quad.order < layers.projection_order)
+        });
+        //the openxr crate can't chain extension structs onto layers, so it has to be done on the
+        //raw layers. every layer gets the same one, so the fade covers quads and passthrough too
+        let color_scale_bias =
+            color_scale_bias.map(|(scale, bias)| xr::sys::CompositionLayerColorScaleBiasKHR {
+                ty: xr::sys::CompositionLayerColorScaleBiasKHR::TYPE,
+                next: std::ptr::null(),
+                color_scale: scale,
+                color_bias: bias,
+            });
+        let color_scale_bias_next: *const std::ffi::c_void = match &color_scale_bias {
+            Some(color_scale_bias) => color_scale_bias as *const _ as _,
+            None => std::ptr::null(),
+        };
+        let passthrough = layers
+            .and_then(|layers| layers.passthrough)
+            .map(|layer_handle| xr::sys::CompositionLayerPassthroughFB {
+                ty: xr::sys::CompositionLayerPassthroughFB::TYPE,
+                next: color_scale_bias_next,
+                flags: xr::CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA,
+                space: xr::sys::Space::NULL,
+                layer_handle,
+            });
+        let mut layer = xr::CompositionLayerProjection::new()
+            .space(stage)
+            .views(&projection_views);
+        if projection_index > 0 || passthrough.is_some() {
+            layer = layer.layer_flags(xr::CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA);
+        }
+        let layer = match color_scale_bias {
+            Some(_) => {
+                let mut raw = layer.into_raw();
+                raw.next = color_scale_bias_next;
+                unsafe { xr::CompositionLayerProjection::from_raw(raw) }
+            }
+            None => layer,
+        };
+        let quads: Vec<xr::CompositionLayerQuad<G>> = quads
+            .iter()
+            .map(|quad| {
+                let mut raw = quad.to_raw(stage);
+                raw.next = color_scale_bias_next;
+                unsafe { xr::CompositionLayerQuad::from_raw(raw) }
+            })
+            .collect();
+        let mut submitted: Vec<&xr::CompositionLayerBase<G>> = Vec::with_capacity(quads.len() + 2);
+        if let Some(passthrough) = &passthrough {
+            //every layer starts with the base header, the openxr crate has no wrapper for this one
+            submitted.push(unsafe {
+                &*(passthrough as *const xr::sys::CompositionLayerPassthroughFB
+                    as *const xr::CompositionLayerBase<G>)
+            });
+        }
+        for quad in &quads[..projection_index] {
+            submitted.push(quad);
+        }
+        submitted.push(&layer);
+        for quad in &quads[projection_index..] {
+            submitted.push(quad);
+        }
+        trace(
+            "xrEndFrame",
+            || {
+                format!(
+                    "{:?}, {:?}, color_scale_bias: {}, depth: {}, layers: {}",
+                    predicted_display_time,
+                    environment_blend_mode,
+                    color_scale_bias.is_some(),
+                    depth_infos.is_some(),
+                    submitted.len()
+                )
+            },
+            || {
+                self.stream.lock().unwrap().end(
+                    predicted_display_time,
+                    environment_blend_mode,
+                    &submitted,
+                )
+            },
         )
     }
 }
+
+/// the swapchain of a composition layer besides the projection, see [`crate::layers`]
+pub enum LayerSwapchain {
+    Vulkan(LayerSwapchainInner<xr::Vulkan>),
+}
+
+impl LayerSwapchain {
+    pub(crate) fn size(&self) -> UVec2 {
+        match self {
+            LayerSwapchain::Vulkan(swapchain) => swapchain.size,
+        }
+    }
+
+    pub(crate) fn format(&self) -> wgpu::TextureFormat {
+        match self {
+            LayerSwapchain::Vulkan(swapchain) => swapchain.format,
+        }
+    }
+
+    pub(crate) fn as_raw(&self) -> xr::sys::Swapchain {
+        match self {
+            LayerSwapchain::Vulkan(swapchain) => swapchain.handle.lock().unwrap().as_raw(),
+        }
+    }
+
+    /// acquires the next image and waits until it can be written
+    pub(crate) fn acquire_image(&self) -> xr::Result<&wgpu::Texture> {
+        match self {
+            LayerSwapchain::Vulkan(swapchain) => swapchain.acquire_image(),
+        }
+    }
+
+    pub(crate) fn release_image(&self) -> xr::Result<()> {
+        match self {
+            LayerSwapchain::Vulkan(swapchain) => swapchain.release_image(),
+        }
+    }
+}
+
+pub struct LayerSwapchainInner<G: xr::Graphics> {
+    pub(crate) handle: Mutex<xr::Swapchain<G>>,
+    pub(crate) buffers: Vec<wgpu::Texture>,
+    pub(crate) size: UVec2,
+    pub(crate) format: wgpu::TextureFormat,
+}
+
+impl<G: xr::Graphics> LayerSwapchainInner<G> {
+    fn acquire_image(&self) -> xr::Result<&wgpu::Texture> {
+        let mut handle = self.handle.lock().unwrap();
+        let image_index = trace("xrAcquireSwapchainImage", String::new, || {
+            handle.acquire_image()
+        })?;
+        trace(
+            "xrWaitSwapchainImage",
+            || "INFINITE".to_string(),
+            || handle.wait_image(xr::Duration::INFINITE),
+        )?;
+        Ok(&self.buffers[image_index as usize])
+    }
+
+    fn release_image(&self) -> xr::Result<()> {
+        trace("xrReleaseSwapchainImage", String::new, || {
+            self.handle.lock().unwrap().release_image()
+        })
+    }
+}
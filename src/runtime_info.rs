@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use crate::capabilities::XrCapabilities;
+
+/// who made the openxr runtime the app is running on, guessed from the runtime name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrRuntimeVendor {
+    Meta,
+    Valve,
+    Microsoft,
+    Pico,
+    Htc,
+    Varjo,
+    Monado,
+    Other,
+}
+
+impl XrRuntimeVendor {
+    pub fn from_runtime_name(name: &str) -> Self {
+        let name = name.to_lowercase();
+        [
+            ("oculus", Self::Meta),
+            ("meta", Self::Meta),
+            ("steamvr", Self::Valve),
+            ("windows mixed reality", Self::Microsoft),
+            ("pico", Self::Pico),
+            ("vive", Self::Htc),
+            ("varjo", Self::Varjo),
+            ("monado", Self::Monado),
+        ]
+        .into_iter()
+        .find(|(word, _)| name.contains(word))
+        .map(|(_, vendor)| vendor)
+        .unwrap_or(Self::Other)
+    }
+}
+
+/// what the app runs on, available once the session exists. a standalone headset runs the
+/// runtime and the app itself, a pc runtime streams to a tethered headset, e.g. Quest Link
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub struct XrRuntimeInfo {
+    pub runtime_name: String,
+    pub runtime_version: String,
+    pub vendor: XrRuntimeVendor,
+    /// like "Meta Quest 3" or "SteamVR/OpenXR : lighthouse"
+    pub system_name: String,
+    pub vendor_id: u32,
+    pub standalone: bool,
+}
+
+impl XrRuntimeInfo {
+    pub fn new(capabilities: &XrCapabilities) -> Self {
+        Self {
+            runtime_name: capabilities.runtime_name.clone(),
+            runtime_version: capabilities.runtime_version.clone(),
+            vendor: XrRuntimeVendor::from_runtime_name(&capabilities.runtime_name),
+            system_name: capabilities.system_name.clone(),
+            vendor_id: capabilities.vendor_id,
+            //every android runtime runs on the headset, pc runtimes don't exist there
+            standalone: cfg!(target_os = "android"),
+        }
+    }
+
+    pub fn is_pc(&self) -> bool {
+        !self.standalone
+    }
+
+    /// a Quest through Quest Link or Air Link, the Meta runtime on a pc
+    pub fn is_quest_link(&self) -> bool {
+        self.is_pc() && self.vendor == XrRuntimeVendor::Meta
+    }
+
+    /// any Quest, standalone or through a pc
+    pub fn is_quest(&self) -> bool {
+        self.vendor == XrRuntimeVendor::Meta && self.system_name.contains("Quest")
+    }
+}
+
+/// runs the system only on standalone headsets, for mobile only optimizations
+pub fn on_standalone() -> impl FnMut(Option<Res<XrRuntimeInfo>>) -> bool + Clone {
+    |info: Option<Res<XrRuntimeInfo>>| info.is_some_and(|info| info.standalone)
+}
+
+/// runs the system only on pc runtimes
+pub fn on_pc() -> impl FnMut(Option<Res<XrRuntimeInfo>>) -> bool + Clone {
+    |info: Option<Res<XrRuntimeInfo>>| info.is_some_and(|info| info.is_pc())
+}
@@ -0,0 +1,712 @@
+use std::ffi::{c_void, CString};
+use std::ptr;
+use std::sync::Arc;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::utils::HashMap;
+use openxr as xr;
+use openxr::sys;
+
+use crate::call_trace::{check, trace};
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrFrameSet;
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::{xr_only, XrFocusChanged};
+use crate::xr_input::input_sources::{XrSpaceLocated, XrTrackedSpace};
+use crate::xr_input::trackers::OpenXRTracker;
+use crate::xr_tasks::{XrAsyncRequests, XrTask, XrTaskAppExt, XrTaskOutput};
+
+// more than any room setup has
+const MAX_SCENE_ANCHORS: u32 = 1024;
+
+pub(crate) const SPATIAL_ENTITY_MESH_EXTENSION: &str = "XR_META_spatial_entity_mesh";
+
+const TYPE_SPACE_TRIANGLE_MESH_GET_INFO: i32 = 1000269001;
+const TYPE_SPACE_TRIANGLE_MESH: i32 = 1000269002;
+
+#[repr(C)]
+struct SpaceTriangleMeshGetInfo {
+    ty: sys::StructureType,
+    next: *const c_void,
+}
+
+#[repr(C)]
+struct SpaceTriangleMesh {
+    ty: sys::StructureType,
+    next: *mut c_void,
+    vertex_capacity_input: u32,
+    vertex_count_output: u32,
+    vertices: *mut sys::Vector3f,
+    index_capacity_input: u32,
+    index_count_output: u32,
+    indices: *mut u32,
+}
+
+type GetSpaceTriangleMesh = unsafe extern "system" fn(
+    space: sys::Space,
+    info: *const SpaceTriangleMeshGetInfo,
+    mesh: *mut SpaceTriangleMesh,
+) -> sys::Result;
+
+/// loads the scene model the user set up for their room (walls, floor, furniture) through
+/// XR_FB_scene. every scene anchor gets an entity under the tracking root with an
+/// [`XrSceneAnchor`] and its [`XrSemanticLabels`], use [`XrSceneQuery`] to find them. the scene
+/// is reloaded whenever the app regains focus, the room setup runs outside of the app, and on
+/// [`XrReloadScene`]. the global mesh anchor also gets its triangles as an [`XrSceneMesh`] with
+/// XR_META_spatial_entity_mesh, which the openxr crate has no bindings for yet. on Quest the app
+/// needs the `com.oculus.permission.USE_SCENE` permission
+pub struct XrScenePlugin;
+
+impl Plugin for XrScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrScene>();
+        app.add_event::<XrReloadScene>();
+        app.add_event::<XrSceneChanged>();
+        app.add_xr_task::<XrSceneAnchors>();
+        app.add_systems(
+            PreUpdate,
+            (request_scene_reload, apply_scene_reload)
+                .chain()
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+    }
+}
+
+/// what a scene anchor is, the labels of XR_FB_scene
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrSemanticLabel {
+    Floor,
+    Ceiling,
+    WallFace,
+    InvisibleWallFace,
+    WallArt,
+    DoorFrame,
+    WindowFrame,
+    Table,
+    Couch,
+    Bed,
+    Lamp,
+    Plant,
+    Screen,
+    Storage,
+    GlobalMesh,
+    /// also labels newer than this crate
+    Other,
+}
+
+impl XrSemanticLabel {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "FLOOR" => Self::Floor,
+            "CEILING" => Self::Ceiling,
+            "WALL_FACE" => Self::WallFace,
+            "INVISIBLE_WALL_FACE" => Self::InvisibleWallFace,
+            "WALL_ART" => Self::WallArt,
+            "DOOR_FRAME" => Self::DoorFrame,
+            "WINDOW_FRAME" => Self::WindowFrame,
+            "TABLE" => Self::Table,
+            "COUCH" => Self::Couch,
+            "BED" => Self::Bed,
+            "LAMP" => Self::Lamp,
+            "PLANT" => Self::Plant,
+            "SCREEN" => Self::Screen,
+            "STORAGE" => Self::Storage,
+            "GLOBAL_MESH" => Self::GlobalMesh,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Floor => "FLOOR",
+            Self::Ceiling => "CEILING",
+            Self::WallFace => "WALL_FACE",
+            Self::InvisibleWallFace => "INVISIBLE_WALL_FACE",
+            Self::WallArt => "WALL_ART",
+            Self::DoorFrame => "DOOR_FRAME",
+            Self::WindowFrame => "WINDOW_FRAME",
+            Self::Table => "TABLE",
+            Self::Couch => "COUCH",
+            Self::Bed => "BED",
+            Self::Lamp => "LAMP",
+            Self::Plant => "PLANT",
+            Self::Screen => "SCREEN",
+            Self::Storage => "STORAGE",
+            Self::GlobalMesh => "GLOBAL_MESH",
+            Self::Other => "OTHER",
+        }
+    }
+}
+
+/// an anchor of the scene model, its transform follows the anchor
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrSceneAnchor {
+    pub uuid: u128,
+}
+
+/// changes when the runtime relabels the anchor, so `Changed<XrSemanticLabels>` works
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+pub struct XrSemanticLabels(pub Vec<XrSemanticLabel>);
+
+impl XrSemanticLabels {
+    pub fn contains(&self, label: XrSemanticLabel) -> bool {
+        self.0.contains(&label)
+    }
+}
+
+/// the extent of a scene anchor in its own space. planes like walls lie in the xy plane and face
+/// +z, volumes like tables are boxes. either can be missing
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrSceneBounds {
+    pub plane: Option<Rect>,
+    pub volume: Option<XrSceneVolume>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrSceneVolume {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+/// the triangles of the global mesh anchor in its own space, the scanned shape of the room
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct XrSceneMesh {
+    pub positions: Vec<Vec3>,
+    pub indices: Vec<u32>,
+}
+
+impl XrSceneMesh {
+    /// with flat normals, the vertices aren't shared between triangles
+    pub fn to_mesh(&self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        let positions: Vec<[f32; 3]> = self.positions.iter().map(|p| p.to_array()).collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.set_indices(Some(Indices::U32(self.indices.clone())));
+        mesh.duplicate_vertices();
+        mesh.compute_flat_normals();
+        mesh
+    }
+}
+
+/// the scene anchor entities by uuid
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrScene {
+    anchors: HashMap<u128, Entity>,
+    loaded: bool,
+}
+
+impl XrScene {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        let exts = instance.exts();
+        exts.fb_scene.is_some()
+            && exts.fb_spatial_entity.is_some()
+            && exts.fb_spatial_entity_query.is_some()
+    }
+
+    /// whether the scene was loaded at least once, an empty scene after that means the user
+    /// didn't set up their room
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub fn get(&self, uuid: u128) -> Option<Entity> {
+        self.anchors.get(&uuid).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.anchors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchors.is_empty()
+    }
+}
+
+/// loads the scene again, e.g. after asking the user to redo their room setup
+#[derive(Event, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrReloadScene;
+
+/// sent after every load that changed the scene. `removed` are the uuids of the despawned
+/// anchors
+#[derive(Event, Clone, Debug, Default, PartialEq, Eq)]
+pub struct XrSceneChanged {
+    pub added: Vec<Entity>,
+    pub relabeled: Vec<Entity>,
+    pub resized: Vec<Entity>,
+    pub removed: Vec<u128>,
+}
+
+/// finds scene anchors by label, e.g. `scene.with_label(XrSemanticLabel::WallFace)` for all the
+/// walls or `scene.floor()`
+#[derive(SystemParam)]
+pub struct XrSceneQuery<'w, 's> {
+    anchors: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static XrSceneAnchor,
+            &'static XrSemanticLabels,
+            &'static GlobalTransform,
+        ),
+    >,
+}
+
+impl<'w, 's> XrSceneQuery<'w, 's> {
+    pub fn with_label(&self, label: XrSemanticLabel) -> impl Iterator<Item = Entity> + '_ {
+        self.anchors
+            .iter()
+            .filter(move |(_, _, labels, _)| labels.contains(label))
+            .map(|(entity, ..)| entity)
+    }
+
+    /// the first anchor with `label`, for the ones a room only has once
+    pub fn single(&self, label: XrSemanticLabel) -> Option<Entity> {
+        self.with_label(label).next()
+    }
+
+    pub fn floor(&self) -> Option<Entity> {
+        self.single(XrSemanticLabel::Floor)
+    }
+
+    pub fn ceiling(&self) -> Option<Entity> {
+        self.single(XrSemanticLabel::Ceiling)
+    }
+
+    pub fn walls(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.with_label(XrSemanticLabel::WallFace)
+    }
+
+    /// the anchor with `label` closest to `position`, in world space
+    pub fn nearest(&self, label: XrSemanticLabel, position: Vec3) -> Option<Entity> {
+        self.anchors
+            .iter()
+            .filter(|(_, _, labels, _)| labels.contains(label))
+            .map(|(entity, _, _, transform)| {
+                (entity, transform.translation().distance_squared(position))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity)
+    }
+
+    pub fn labels(&self, entity: Entity) -> Option<&XrSemanticLabels> {
+        self.anchors
+            .get(entity)
+            .ok()
+            .map(|(_, _, labels, _)| labels)
+    }
+}
+
+/// the anchors a scene load found
+pub struct XrSceneAnchors(Vec<LoadedSceneAnchor>);
+
+struct LoadedSceneAnchor {
+    space: sys::Space,
+    uuid: u128,
+    labels: Vec<XrSemanticLabel>,
+    bounds: XrSceneBounds,
+    mesh: Option<XrSceneMesh>,
+}
+
+/// marks the entity of a running scene load
+#[derive(Component)]
+pub struct XrSceneTask;
+
+#[allow(clippy::too_many_arguments)]
+pub fn request_scene_reload(
+    mut commands: Commands,
+    mut reloads: EventReader<XrReloadScene>,
+    mut focus: EventReader<XrFocusChanged>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    async_requests: Res<XrAsyncRequests>,
+    error_log: Res<XrErrorLog>,
+    running: Query<(), With<XrSceneTask>>,
+    mut started: Local<bool>,
+) {
+    let reload =
+        reloads.read().count() > 0 || focus.read().any(|focus| *focus == XrFocusChanged::Regained);
+    if *started && !reload {
+        return;
+    }
+    if !XrScene::is_supported(&instance) {
+        if !*started {
+            warn!("the scene needs XR_FB_scene and XR_FB_spatial_entity_query");
+        }
+        *started = true;
+        return;
+    }
+    *started = true;
+    if !running.is_empty() {
+        return;
+    }
+    let instance = (**instance).clone();
+    let session = session.as_raw();
+    let request = match query_scene(&instance, session) {
+        Ok(request) => request,
+        Err(err) => {
+            error_log.report_result(XrErrorSource::Other, err);
+            return;
+        }
+    };
+    let async_requests = async_requests.clone();
+    commands.spawn((
+        XrSceneTask,
+        XrTask::spawn(async move {
+            async_requests.wait(request).await?;
+            let spaces = query_results(&instance, session, request)?;
+            let get_mesh = load_get_space_triangle_mesh(&instance);
+            let mut anchors = Vec::with_capacity(spaces.len());
+            for result in spaces {
+                let labels = semantic_labels(&instance, session, result.space);
+                let labels = match labels {
+                    Ok(labels) => labels,
+                    Err(err) => {
+                        warn!("couldn't get the labels of a scene anchor: {}", err);
+                        vec![XrSemanticLabel::Other]
+                    }
+                };
+                let mesh = match (get_mesh, labels.contains(&XrSemanticLabel::GlobalMesh)) {
+                    (Some(get_mesh), true) => match scene_mesh(get_mesh, result.space) {
+                        Ok(mesh) => Some(mesh),
+                        Err(err) => {
+                            warn!("couldn't get the triangles of the global mesh: {}", err);
+                            None
+                        }
+                    },
+                    _ => None,
+                };
+                anchors.push(LoadedSceneAnchor {
+                    space: result.space,
+                    uuid: u128::from_be_bytes(result.uuid.data),
+                    labels,
+                    bounds: scene_bounds(&instance, session, result.space),
+                    mesh,
+                });
+            }
+            Ok(XrSceneAnchors(anchors))
+        }),
+    ));
+}
+
+pub fn apply_scene_reload(
+    mut commands: Commands,
+    mut scene: ResMut<XrScene>,
+    session: Res<XrSession>,
+    error_log: Res<XrErrorLog>,
+    mut tasks: Query<(Entity, &mut XrTaskOutput<XrSceneAnchors>), With<XrSceneTask>>,
+    mut anchors: Query<
+        (
+            &mut XrTrackedSpace,
+            &mut XrSemanticLabels,
+            &mut XrSceneBounds,
+            Option<&XrSceneMesh>,
+        ),
+        With<XrSceneAnchor>,
+    >,
+    mut events: EventWriter<XrSceneChanged>,
+) {
+    for (entity, mut output) in &mut tasks {
+        commands.entity(entity).despawn();
+        let result = std::mem::replace(&mut output.0, Err(sys::Result::ERROR_RUNTIME_FAILURE));
+        let loaded = match result {
+            Ok(XrSceneAnchors(loaded)) => loaded,
+            Err(err) => {
+                error_log.report_result(XrErrorSource::Other, err);
+                continue;
+            }
+        };
+        let mut changed = XrSceneChanged::default();
+        let mut previous = std::mem::take(&mut scene.anchors);
+        for anchor in loaded {
+            let existing = previous
+                .remove(&anchor.uuid)
+                .and_then(|entity| Some((entity, anchors.get_mut(entity).ok()?)));
+            let Some((entity, (mut space, mut labels, mut bounds, mesh))) = existing else {
+                let space =
+                    unsafe { xr::Space::reference_from_raw((**session).clone(), anchor.space) };
+                let entity = commands
+                    .spawn((
+                        SpatialBundle::default(),
+                        Name::new(format!(
+                            "Scene {}",
+                            anchor
+                                .labels
+                                .first()
+                                .unwrap_or(&XrSemanticLabel::Other)
+                                .name()
+                        )),
+                        XrSceneAnchor { uuid: anchor.uuid },
+                        XrSemanticLabels(anchor.labels),
+                        anchor.bounds,
+                        XrTrackedSpace(Arc::new(space)),
+                        XrSpaceLocated::default(),
+                        OpenXRTracker,
+                    ))
+                    .id();
+                if let Some(mesh) = anchor.mesh {
+                    commands.entity(entity).insert(mesh);
+                }
+                scene.anchors.insert(anchor.uuid, entity);
+                changed.added.push(entity);
+                continue;
+            };
+            //a reload can hand out the same handle again, which the old space would destroy
+            if space.as_raw() != anchor.space {
+                space.0 = Arc::new(unsafe {
+                    xr::Space::reference_from_raw((**session).clone(), anchor.space)
+                });
+            }
+            if labels.0 != anchor.labels {
+                labels.0 = anchor.labels;
+                changed.relabeled.push(entity);
+            }
+            if *bounds != anchor.bounds {
+                *bounds = anchor.bounds;
+                changed.resized.push(entity);
+            }
+            if mesh != anchor.mesh.as_ref() {
+                match anchor.mesh {
+                    Some(mesh) => commands.entity(entity).insert(mesh),
+                    None => commands.entity(entity).remove::<XrSceneMesh>(),
+                };
+                if !changed.resized.contains(&entity) {
+                    changed.resized.push(entity);
+                }
+            }
+            scene.anchors.insert(anchor.uuid, entity);
+        }
+        for (uuid, entity) in previous {
+            commands.entity(entity).despawn_recursive();
+            changed.removed.push(uuid);
+        }
+        if !scene.loaded {
+            info!("loaded {} scene anchors", scene.anchors.len());
+        }
+        scene.loaded = true;
+        if changed != XrSceneChanged::default() {
+            events.send(changed);
+        }
+    }
+}
+
+// every local anchor with semantic labels, which are the scene anchors
+fn query_scene(
+    instance: &xr::Instance,
+    session: sys::Session,
+) -> xr::Result<sys::AsyncRequestIdFB> {
+    let ext = instance.exts().fb_spatial_entity_query.as_ref();
+    let ext = ext.ok_or(sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    let location_filter = sys::SpaceStorageLocationFilterInfoFB {
+        ty: sys::SpaceStorageLocationFilterInfoFB::TYPE,
+        next: ptr::null(),
+        location: sys::SpaceStorageLocationFB::LOCAL,
+    };
+    let filter = sys::SpaceComponentFilterInfoFB {
+        ty: sys::SpaceComponentFilterInfoFB::TYPE,
+        next: &location_filter as *const _ as *const _,
+        component_type: sys::SpaceComponentTypeFB::SEMANTIC_LABELS,
+    };
+    let info = sys::SpaceQueryInfoFB {
+        ty: sys::SpaceQueryInfoFB::TYPE,
+        next: ptr::null(),
+        query_action: sys::SpaceQueryActionFB::LOAD,
+        max_result_count: MAX_SCENE_ANCHORS,
+        timeout: xr::Duration::from_nanos(0),
+        filter: &filter as *const _ as *const _,
+        exclude_filter: ptr::null(),
+    };
+    let mut request = sys::AsyncRequestIdFB::from_raw(0);
+    check(trace(
+        "xrQuerySpacesFB",
+        || "scene".to_string(),
+        || unsafe { (ext.query_spaces)(session, &info as *const _ as *const _, &mut request) },
+    ))?;
+    Ok(request)
+}
+
+fn query_results(
+    instance: &xr::Instance,
+    session: sys::Session,
+    request: sys::AsyncRequestIdFB,
+) -> xr::Result<Vec<sys::SpaceQueryResultFB>> {
+    let ext = instance.exts().fb_spatial_entity_query.as_ref();
+    let ext = ext.ok_or(sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    let mut output = sys::SpaceQueryResultsFB {
+        ty: sys::SpaceQueryResultsFB::TYPE,
+        next: ptr::null_mut(),
+        result_capacity_input: 0,
+        result_count_output: 0,
+        results: ptr::null_mut(),
+    };
+    check(trace(
+        "xrRetrieveSpaceQueryResultsFB",
+        String::new,
+        || unsafe { (ext.retrieve_space_query_results)(session, request, &mut output) },
+    ))?;
+    let empty = sys::SpaceQueryResultFB {
+        space: sys::Space::NULL,
+        uuid: sys::UuidEXT { data: [0; 16] },
+    };
+    let mut results = vec![empty; output.result_count_output as usize];
+    output.result_capacity_input = results.len() as u32;
+    output.results = results.as_mut_ptr();
+    check(trace(
+        "xrRetrieveSpaceQueryResultsFB",
+        String::new,
+        || unsafe { (ext.retrieve_space_query_results)(session, request, &mut output) },
+    ))?;
+    results.truncate(output.result_count_output as usize);
+    Ok(results)
+}
+
+fn semantic_labels(
+    instance: &xr::Instance,
+    session: sys::Session,
+    space: sys::Space,
+) -> xr::Result<Vec<XrSemanticLabel>> {
+    let ext = instance.exts().fb_scene.as_ref();
+    let ext = ext.ok_or(sys::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+    let mut labels = sys::SemanticLabelsFB {
+        ty: sys::SemanticLabelsFB::TYPE,
+        next: ptr::null(),
+        buffer_capacity_input: 0,
+        buffer_count_output: 0,
+        buffer: ptr::null_mut(),
+    };
+    check(trace(
+        "xrGetSpaceSemanticLabelsFB",
+        String::new,
+        || unsafe { (ext.get_space_semantic_labels)(session, space, &mut labels) },
+    ))?;
+    let mut buffer = vec![0; labels.buffer_count_output as usize];
+    labels.buffer_capacity_input = buffer.len() as u32;
+    labels.buffer = buffer.as_mut_ptr();
+    check(trace(
+        "xrGetSpaceSemanticLabelsFB",
+        String::new,
+        || unsafe { (ext.get_space_semantic_labels)(session, space, &mut labels) },
+    ))?;
+    //a comma separated list, null terminated
+    let text: Vec<u8> = buffer
+        .iter()
+        .map(|&c| c as u8)
+        .take_while(|&c| c != 0)
+        .collect();
+    Ok(String::from_utf8_lossy(&text)
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(XrSemanticLabel::from_name)
+        .collect())
+}
+
+// the bounds the anchor has, the calls fail for the ones it doesn't
+fn scene_bounds(
+    instance: &xr::Instance,
+    session: sys::Session,
+    space: sys::Space,
+) -> XrSceneBounds {
+    let Some(ext) = instance.exts().fb_scene.as_ref() else {
+        return default();
+    };
+    let mut plane = sys::Rect2Df {
+        offset: sys::Offset2Df { x: 0.0, y: 0.0 },
+        extent: sys::Extent2Df {
+            width: 0.0,
+            height: 0.0,
+        },
+    };
+    let plane = check(trace("xrGetSpaceBoundingBox2DFB", String::new, || unsafe {
+        (ext.get_space_bounding_box2_d)(session, space, &mut plane)
+    }))
+    .ok()
+    .map(|_| {
+        let min = Vec2::new(plane.offset.x, plane.offset.y);
+        Rect::from_corners(
+            min,
+            min + Vec2::new(plane.extent.width, plane.extent.height),
+        )
+    });
+    let mut volume = sys::Rect3DfFB {
+        offset: sys::Vector3f::default(),
+        extent: sys::Extent3DfFB {
+            width: 0.0,
+            height: 0.0,
+            depth: 0.0,
+        },
+    };
+    let volume = check(trace("xrGetSpaceBoundingBox3DFB", String::new, || unsafe {
+        (ext.get_space_bounding_box3_d)(session, space, &mut volume)
+    }))
+    .ok()
+    .map(|_| {
+        let min = Vec3::new(volume.offset.x, volume.offset.y, volume.offset.z);
+        let extent = volume.extent;
+        XrSceneVolume {
+            min,
+            max: min + Vec3::new(extent.width, extent.height, extent.depth),
+        }
+    });
+    XrSceneBounds { plane, volume }
+}
+
+fn load_get_space_triangle_mesh(instance: &xr::Instance) -> Option<GetSpaceTriangleMesh> {
+    let enabled = instance
+        .exts()
+        .other
+        .iter()
+        .any(|ext| ext == SPATIAL_ENTITY_MESH_EXTENSION);
+    if !enabled {
+        return None;
+    }
+    let name = CString::new("xrGetSpaceTriangleMeshMETA").unwrap();
+    let function = unsafe {
+        instance
+            .entry()
+            .get_instance_proc_addr(instance.as_raw(), name.as_ptr())
+            .ok()?
+    };
+    Some(unsafe { std::mem::transmute::<sys::pfn::VoidFunction, GetSpaceTriangleMesh>(function) })
+}
+
+fn scene_mesh(get_mesh: GetSpaceTriangleMesh, space: sys::Space) -> xr::Result<XrSceneMesh> {
+    let info = SpaceTriangleMeshGetInfo {
+        ty: sys::StructureType::from_raw(TYPE_SPACE_TRIANGLE_MESH_GET_INFO),
+        next: ptr::null(),
+    };
+    let mut output = SpaceTriangleMesh {
+        ty: sys::StructureType::from_raw(TYPE_SPACE_TRIANGLE_MESH),
+        next: ptr::null_mut(),
+        vertex_capacity_input: 0,
+        vertex_count_output: 0,
+        vertices: ptr::null_mut(),
+        index_capacity_input: 0,
+        index_count_output: 0,
+        indices: ptr::null_mut(),
+    };
+    check(trace(
+        "xrGetSpaceTriangleMeshMETA",
+        String::new,
+        || unsafe { get_mesh(space, &info, &mut output) },
+    ))?;
+    let mut vertices = vec![sys::Vector3f::default(); output.vertex_count_output as usize];
+    let mut indices = vec![0; output.index_count_output as usize];
+    output.vertex_capacity_input = vertices.len() as u32;
+    output.vertices = vertices.as_mut_ptr();
+    output.index_capacity_input = indices.len() as u32;
+    output.indices = indices.as_mut_ptr();
+    check(trace(
+        "xrGetSpaceTriangleMeshMETA",
+        || format!("{} vertices, {} indices", vertices.len(), indices.len()),
+        || unsafe { get_mesh(space, &info, &mut output) },
+    ))?;
+    vertices.truncate(output.vertex_count_output as usize);
+    indices.truncate(output.index_count_output as usize);
+    Ok(XrSceneMesh {
+        positions: vertices.iter().map(|v| Vec3::new(v.x, v.y, v.z)).collect(),
+        indices,
+    })
+}
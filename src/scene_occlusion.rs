@@ -0,0 +1,156 @@
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey, NotShadowCaster};
+use bevy::prelude::*;
+use bevy::render::mesh::MeshVertexBufferLayout;
+use bevy::render::render_resource::{
+    AsBindGroup, ColorWrites, RenderPipelineDescriptor, SpecializedMeshPipelineError,
+};
+
+use crate::scene::{XrSceneAnchor, XrSceneBounds, XrSceneMesh, XrSemanticLabel, XrSemanticLabels};
+use crate::xr_init::xr_only;
+
+/// lets the real room hide virtual content in passthrough. every wall, floor, ceiling and piece
+/// of furniture of the scene gets a child drawn with [`XrOccluderMaterial`] from its bounds, and
+/// the global mesh one from its [`XrSceneMesh`], toggled with [`XrSceneOcclusion`]. needs
+/// `XrScenePlugin`
+pub struct XrSceneOcclusionPlugin;
+
+impl Plugin for XrSceneOcclusionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<XrOccluderMaterial>::default());
+        app.init_resource::<XrSceneOcclusion>();
+        app.add_systems(
+            Update,
+            (spawn_scene_occluders, update_scene_occluder_visibility)
+                .chain()
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// only writes depth, so whatever is behind it isn't drawn and the passthrough shows through. can
+/// be used on any mesh, e.g. a hand mesh or a tracked keyboard
+#[derive(Asset, AsBindGroup, TypePath, Clone, Debug, Default)]
+pub struct XrOccluderMaterial {}
+
+impl Material for XrOccluderMaterial {
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        //the global mesh is seen from inside the room
+        descriptor.primitive.cull_mode = None;
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            for target in fragment.targets.iter_mut().flatten() {
+                target.write_mask = ColorWrites::empty();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct XrSceneOcclusion {
+    pub enabled: bool,
+    /// the anchors with these labels don't occlude, by default the invisible walls the room
+    /// setup adds for open spaces
+    pub ignored_labels: Vec<XrSemanticLabel>,
+}
+
+impl Default for XrSceneOcclusion {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ignored_labels: vec![XrSemanticLabel::InvisibleWallFace],
+        }
+    }
+}
+
+/// the occluder child of a scene anchor
+#[derive(Component, Clone, Copy, Debug)]
+pub struct XrSceneOccluder {
+    pub anchor: Entity,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn spawn_scene_occluders(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut material: Local<Option<Handle<XrOccluderMaterial>>>,
+    mut materials: ResMut<Assets<XrOccluderMaterial>>,
+    anchors: Query<
+        (
+            Entity,
+            &XrSceneBounds,
+            Option<&XrSceneMesh>,
+            Option<&Children>,
+        ),
+        (
+            With<XrSceneAnchor>,
+            Or<(Changed<XrSceneBounds>, Changed<XrSceneMesh>)>,
+        ),
+    >,
+    occluders: Query<(), With<XrSceneOccluder>>,
+) {
+    let material = material
+        .get_or_insert_with(|| materials.add(XrOccluderMaterial::default()))
+        .clone();
+    for (anchor, bounds, scene_mesh, children) in &anchors {
+        for &child in children.iter().flat_map(|children| children.iter()) {
+            if occluders.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+        //the triangles of the global mesh are more exact than its bounds, which are the whole
+        //room. a volume covers the plane of the same anchor, e.g. the top of a table
+        let (mesh, transform) = match (scene_mesh, bounds.volume, bounds.plane) {
+            (Some(scene_mesh), _, _) => (scene_mesh.to_mesh(), Transform::IDENTITY),
+            (None, Some(volume), _) => (
+                Mesh::from(shape::Box::from_corners(volume.min, volume.max)),
+                Transform::IDENTITY,
+            ),
+            (None, None, Some(plane)) => (
+                Mesh::from(shape::Quad::new(plane.size())),
+                Transform::from_translation(plane.center().extend(0.0)),
+            ),
+            (None, None, None) => continue,
+        };
+        let occluder = commands
+            .spawn((
+                MaterialMeshBundle {
+                    mesh: meshes.add(mesh),
+                    material: material.clone(),
+                    transform,
+                    ..default()
+                },
+                NotShadowCaster,
+                XrSceneOccluder { anchor },
+                Name::new("Scene Occluder"),
+            ))
+            .id();
+        commands.entity(anchor).add_child(occluder);
+    }
+}
+
+pub fn update_scene_occluder_visibility(
+    occlusion: Res<XrSceneOcclusion>,
+    labels: Query<&XrSemanticLabels>,
+    mut occluders: Query<(&XrSceneOccluder, &mut Visibility)>,
+) {
+    for (occluder, mut visibility) in &mut occluders {
+        let ignored = labels.get(occluder.anchor).is_ok_and(|labels| {
+            occlusion
+                .ignored_labels
+                .iter()
+                .any(|&label| labels.contains(label))
+        });
+        let target = match occlusion.enabled && !ignored {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+}
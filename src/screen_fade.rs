@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use bevy::render::Extract;
+use openxr as xr;
+
+/// fades the whole view to a color in the compositor, using XR_KHR_composition_layer_color_scale_bias.
+/// this doesn't need anything in the scene, so it also works while a scene is loading.
+/// does nothing if the runtime doesn't support the extension
+#[derive(Resource, Clone, Debug)]
+pub struct XrScreenFade {
+    /// the color to fade to
+    pub color: Color,
+    /// how far the fade is, 0.0 shows the scene and 1.0 only shows `color`
+    pub amount: f32,
+    target: f32,
+    speed: f32,
+}
+
+impl Default for XrScreenFade {
+    fn default() -> Self {
+        Self {
+            color: Color::BLACK,
+            amount: 0.0,
+            target: 0.0,
+            speed: 0.0,
+        }
+    }
+}
+
+impl XrScreenFade {
+    /// fades to `amount` over `duration` seconds
+    pub fn fade_to(&mut self, amount: f32, duration: f32) {
+        self.target = amount.clamp(0.0, 1.0);
+        self.speed = match duration > 0.0 {
+            true => (self.target - self.amount).abs() / duration,
+            false => f32::INFINITY,
+        };
+    }
+
+    /// fades to `color` over `duration` seconds
+    pub fn fade_out(&mut self, duration: f32) {
+        self.fade_to(1.0, duration);
+    }
+
+    /// fades back to the scene over `duration` seconds
+    pub fn fade_in(&mut self, duration: f32) {
+        self.fade_to(0.0, duration);
+    }
+
+    /// sets the fade without tweening
+    pub fn set(&mut self, amount: f32) {
+        self.amount = amount.clamp(0.0, 1.0);
+        self.target = self.amount;
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.amount != self.target
+    }
+
+    pub fn is_faded_out(&self) -> bool {
+        self.amount >= 1.0
+    }
+
+    /// the color scale and bias for every submitted layer, none if nothing is faded
+    pub fn color_scale_bias(&self) -> Option<(xr::Color4f, xr::Color4f)> {
+        if self.amount <= 0.0 {
+            return None;
+        }
+        let [r, g, b, _] = self.color.as_linear_rgba_f32();
+        let scale = 1.0 - self.amount;
+        Some((
+            xr::Color4f {
+                r: scale,
+                g: scale,
+                b: scale,
+                a: 1.0,
+            },
+            xr::Color4f {
+                r: r * self.amount,
+                g: g * self.amount,
+                b: b * self.amount,
+                a: 0.0,
+            },
+        ))
+    }
+}
+
+pub fn update_screen_fade(time: Res<Time>, mut fade: ResMut<XrScreenFade>) {
+    if !fade.is_fading() {
+        return;
+    }
+    let step = fade.speed * time.delta_seconds();
+    let delta = fade.target - fade.amount;
+    fade.amount = match delta.abs() <= step {
+        true => fade.target,
+        false => fade.amount + step.copysign(delta),
+    };
+}
+
+pub fn extract_screen_fade(mut commands: Commands, fade: Extract<Res<XrScreenFade>>) {
+    if fade.is_changed() {
+        commands.insert_resource(fade.clone());
+    }
+}
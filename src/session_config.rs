@@ -0,0 +1,317 @@
+#[cfg(feature = "serialize")]
+use std::fs;
+#[cfg(feature = "serialize")]
+use std::io;
+#[cfg(feature = "serialize")]
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::call_trace::trace;
+use crate::capabilities::XrCapabilities;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::foveation::{XrFoveation, XrFoveationLevel};
+use crate::input::XrInput;
+use crate::render_scale::XrRenderScale;
+use crate::render_scale_ramp::XrRenderScaleRamp;
+use crate::resources::{XrEnvironmentBlendMode, XrInstance, XrSession};
+use crate::xr_init::xr_only;
+use crate::OpenXrSettings;
+
+/// keeps the graphics settings a user picked across runs. [`XrSessionConfig`] is applied to the
+/// running session whenever it changes, with a file (and the `serialize` feature) it's loaded on
+/// startup and saved as json. added before [`OpenXrPlugin`](crate::OpenXrPlugin), like
+/// [`DefaultXrPlugins`](crate::DefaultXrPlugins) does, the reference space, blend mode and
+/// extensions also go into the instance. the foveation level needs
+/// [`XrFoveationPlugin`](crate::foveation::XrFoveationPlugin), which applies it to every swapchain
+/// the session creates
+#[derive(Default)]
+pub struct XrSessionConfigPlugin {
+    #[cfg(feature = "serialize")]
+    pub file: Option<PathBuf>,
+}
+
+impl Plugin for XrSessionConfigPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "serialize")]
+        let config = match &self.file {
+            Some(file) => match XrSessionConfig::load(file) {
+                Ok(config) => config,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => default(),
+                Err(err) => {
+                    warn!(
+                        "couldn't load the session config from {}: {}",
+                        file.display(),
+                        err
+                    );
+                    default()
+                }
+            },
+            None => default(),
+        };
+        #[cfg(not(feature = "serialize"))]
+        let config = XrSessionConfig::default();
+        if let Some(scale) = config.render_scale {
+            app.insert_resource(XrRenderScale(scale));
+        }
+        app.insert_resource(config);
+        #[cfg(feature = "serialize")]
+        if let Some(file) = &self.file {
+            app.insert_resource(XrSessionConfigFile(file.clone()));
+        }
+        app.add_event::<XrSessionConfigRequest>();
+        app.add_systems(
+            PostUpdate,
+            (handle_session_config_requests, apply_session_config)
+                .chain()
+                .run_if(xr_only()),
+        );
+        #[cfg(feature = "serialize")]
+        app.add_systems(
+            PostUpdate,
+            save_session_config
+                .after(apply_session_config)
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// where the session config is saved
+#[cfg(feature = "serialize")]
+#[derive(Resource, Clone, Debug)]
+pub struct XrSessionConfigFile(pub PathBuf);
+
+/// the settings that are applied on top of the defaults, `None` leaves one to the app and the
+/// runtime. changes made by the crate itself, like a quality governor lowering the render scale,
+/// don't end up in here unless they are captured
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default))]
+pub struct XrSessionConfig {
+    #[cfg_attr(feature = "serialize", serde(with = "reference_space_name"))]
+    pub reference_space: Option<xr::ReferenceSpaceType>,
+    /// needs XR_FB_display_refresh_rate
+    pub refresh_rate: Option<f32>,
+    pub render_scale: Option<f32>,
+    pub foveation: Option<XrFoveationLevel>,
+    #[cfg_attr(feature = "serialize", serde(with = "blend_mode_name"))]
+    pub blend_mode: Option<xr::EnvironmentBlendMode>,
+    /// requested on top of the ones the crate needs
+    pub extensions: Vec<String>,
+}
+
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrSessionConfigRequest {
+    /// takes the settings the session runs with right now
+    Capture,
+    Reset,
+}
+
+impl XrSessionConfig {
+    /// points `settings` at the reference space, blend mode and extensions of the config
+    pub fn apply_to_settings(&self, settings: &mut OpenXrSettings) {
+        if let Some(reference_space) = self.reference_space {
+            settings.reference_space = reference_space;
+        }
+        if let Some(blend_mode) = self.blend_mode {
+            settings.blend_modes.retain(|mode| *mode != blend_mode);
+            settings.blend_modes.insert(0, blend_mode);
+        }
+        for extension in &self.extensions {
+            if !settings.extensions.other.contains(extension) {
+                settings.extensions.other.push(extension.clone());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl XrSessionConfig {
+    /// saves the config as json
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, text)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+//the openxr enums are saved by name, or as the raw value for the ones without a name here
+#[cfg(feature = "serialize")]
+mod reference_space_name {
+    use openxr as xr;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        space: &Option<xr::ReferenceSpaceType>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let name = space.map(|space| match space {
+            xr::ReferenceSpaceType::STAGE => "stage".to_string(),
+            xr::ReferenceSpaceType::LOCAL => "local".to_string(),
+            xr::ReferenceSpaceType::VIEW => "view".to_string(),
+            space => space.into_raw().to_string(),
+        });
+        name.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<xr::ReferenceSpaceType>, D::Error> {
+        let Some(name) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        match name.as_str() {
+            "stage" => Ok(Some(xr::ReferenceSpaceType::STAGE)),
+            "local" => Ok(Some(xr::ReferenceSpaceType::LOCAL)),
+            "view" => Ok(Some(xr::ReferenceSpaceType::VIEW)),
+            raw => raw
+                .parse()
+                .map(|raw| Some(xr::ReferenceSpaceType::from_raw(raw)))
+                .map_err(|_| serde::de::Error::custom(format!("invalid reference space {}", raw))),
+        }
+    }
+}
+
+#[cfg(feature = "serialize")]
+mod blend_mode_name {
+    use openxr as xr;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        mode: &Option<xr::EnvironmentBlendMode>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let name = mode.map(|mode| match mode {
+            xr::EnvironmentBlendMode::OPAQUE => "opaque".to_string(),
+            xr::EnvironmentBlendMode::ADDITIVE => "additive".to_string(),
+            xr::EnvironmentBlendMode::ALPHA_BLEND => "alpha_blend".to_string(),
+            mode => mode.into_raw().to_string(),
+        });
+        name.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<xr::EnvironmentBlendMode>, D::Error> {
+        let Some(name) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        match name.as_str() {
+            "opaque" => Ok(Some(xr::EnvironmentBlendMode::OPAQUE)),
+            "additive" => Ok(Some(xr::EnvironmentBlendMode::ADDITIVE)),
+            "alpha_blend" => Ok(Some(xr::EnvironmentBlendMode::ALPHA_BLEND)),
+            raw => raw
+                .parse()
+                .map(|raw| Some(xr::EnvironmentBlendMode::from_raw(raw)))
+                .map_err(|_| serde::de::Error::custom(format!("invalid blend mode {}", raw))),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_session_config_requests(
+    mut requests: EventReader<XrSessionConfigRequest>,
+    mut config: ResMut<XrSessionConfig>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    input: Res<XrInput>,
+    scale: Res<XrRenderScale>,
+    ramp: Option<Res<XrRenderScaleRamp>>,
+    foveation: Option<Res<XrFoveation>>,
+    blend_mode: Res<XrEnvironmentBlendMode>,
+) {
+    for request in requests.read() {
+        *config = match request {
+            XrSessionConfigRequest::Capture => XrSessionConfig {
+                reference_space: Some(input.stage_type),
+                refresh_rate: instance
+                    .exts()
+                    .fb_display_refresh_rate
+                    .and_then(|_| session.get_display_refresh_rate().ok()),
+                //not the reduced scale of the ramp after the session started
+                render_scale: Some(ramp.as_ref().and_then(|r| r.target()).unwrap_or(scale.0)),
+                foveation: foveation.as_ref().map(|foveation| foveation.level),
+                blend_mode: Some(**blend_mode),
+                extensions: instance.exts().other.clone(),
+            },
+            XrSessionConfigRequest::Reset => default(),
+        };
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn apply_session_config(
+    config: Res<XrSessionConfig>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    capabilities: Option<Res<XrCapabilities>>,
+    error_log: Res<XrErrorLog>,
+    mut scale: ResMut<XrRenderScale>,
+    mut foveation: Option<ResMut<XrFoveation>>,
+    mut blend_mode: ResMut<XrEnvironmentBlendMode>,
+    mut applied: Local<bool>,
+) {
+    //the first run applies what was loaded
+    if *applied && !config.is_changed() {
+        return;
+    }
+    *applied = true;
+    if let Some(render_scale) = config.render_scale {
+        if render_scale != scale.0 {
+            scale.0 = render_scale;
+        }
+    }
+    if let (Some(level), Some(foveation)) = (config.foveation, foveation.as_mut()) {
+        if level != foveation.level {
+            foveation.level = level;
+        }
+    }
+    //unsupported blend modes are reverted by `validate_environment_blend_mode`
+    if let Some(mode) = config.blend_mode.filter(|mode| *mode != **blend_mode) {
+        *blend_mode = XrEnvironmentBlendMode::new(mode);
+    }
+    let Some(refresh_rate) = config.refresh_rate else {
+        return;
+    };
+    if instance.exts().fb_display_refresh_rate.is_none() {
+        return;
+    }
+    let supported = capabilities.map_or(true, |capabilities| {
+        capabilities
+            .refresh_rates
+            .iter()
+            .any(|rate| (rate - refresh_rate).abs() < 0.5)
+    });
+    if !supported {
+        warn!("the display doesn't support {} hz", refresh_rate);
+        return;
+    }
+    if let Err(err) = trace(
+        "xrRequestDisplayRefreshRateFB",
+        || refresh_rate.to_string(),
+        || session.request_display_refresh_rate(refresh_rate),
+    ) {
+        error_log.report_result(XrErrorSource::Other, err);
+    }
+}
+
+#[cfg(feature = "serialize")]
+pub fn save_session_config(config: Res<XrSessionConfig>, file: Res<XrSessionConfigFile>) {
+    if !config.is_changed() || config.is_added() {
+        return;
+    }
+    if let Err(err) = config.save(&file.0) {
+        warn!(
+            "couldn't save the session config to {}: {}",
+            file.0.display(),
+            err
+        );
+    }
+}
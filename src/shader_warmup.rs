@@ -0,0 +1,214 @@
+use bevy::asset::RecursiveDependencyLoadState;
+use bevy::core_pipeline::tonemapping::{DebandDither, Tonemapping};
+use bevy::prelude::*;
+use bevy::render::camera::{ManualTextureViews, RenderTarget};
+use bevy::render::render_resource::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::view::RenderLayers;
+use openxr::Fovf;
+
+use crate::xr_init::{xr_only, XrSessionState};
+use crate::xr_input::xr_camera::{Eye, XrCameraBundle, XrCameraType};
+use crate::LEFT_XR_TEXTURE_HANDLE;
+
+/// the render layer the warm-up camera and its objects are on
+pub const WARMUP_RENDER_LAYER: u8 = 31;
+
+/// compiles the pipelines of the app's materials before the user gets to see them. entities with
+/// [`XrWarmupObject`], e.g. one mesh per material the app uses, are rendered by an off-screen
+/// camera with the settings of the xr cameras as soon as the session runs, which is usually
+/// while it is still synchronized or visible behind the system's loading screen. once they've
+/// been drawn for [`XrShaderWarmup::frames`] frames the camera and the objects are despawned.
+/// [`XrShaderWarmup::is_running`] can drive a compositor hold to cover warm-ups that are still
+/// running when the session becomes visible
+pub struct XrShaderWarmupPlugin;
+
+impl Plugin for XrShaderWarmupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrShaderWarmup>();
+        app.add_systems(PostUpdate, run_shader_warmup.run_if(xr_only()));
+    }
+}
+
+/// an entity without a parent that is only rendered by the warm-up. it is placed in front of the
+/// warm-up camera and despawned with its children when the warm-up is over
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct XrWarmupObject;
+
+#[derive(Component)]
+pub struct XrWarmupCamera;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum XrWarmupState {
+    #[default]
+    Waiting,
+    /// frames rendered so far
+    Running(u32),
+    Done,
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct XrShaderWarmup {
+    /// frames the objects are rendered for once their assets are loaded, pipelines that aren't
+    /// compiled yet are skipped the first time they're drawn
+    pub frames: u32,
+    /// the warm-up doesn't count frames while any of these assets or their dependencies is
+    /// still loading, like the meshes and materials of the objects
+    pub wait_for: Vec<UntypedHandle>,
+    /// the size of the off-screen image in pixels
+    pub resolution: UVec2,
+    state: XrWarmupState,
+}
+
+impl Default for XrShaderWarmup {
+    fn default() -> Self {
+        Self {
+            frames: 8,
+            wait_for: vec![],
+            resolution: UVec2::splat(64),
+            state: default(),
+        }
+    }
+}
+
+impl XrShaderWarmup {
+    pub fn state(&self) -> XrWarmupState {
+        self.state
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, XrWarmupState::Running(_))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == XrWarmupState::Done
+    }
+}
+
+fn warmup_image(size: UVec2, format: TextureFormat) -> Image {
+    let size = Extent3d {
+        width: size.x.max(1),
+        height: size.y.max(1),
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("xr_shader_warmup"),
+            size,
+            dimension: TextureDimension::D2,
+            format,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    image
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_shader_warmup(
+    mut commands: Commands,
+    mut warmup: ResMut<XrShaderWarmup>,
+    session_state: Res<State<XrSessionState>>,
+    asset_server: Res<AssetServer>,
+    manual_texture_views: Res<ManualTextureViews>,
+    mut images: ResMut<Assets<Image>>,
+    xr_cameras: Query<
+        (&XrCameraType, &Camera, &Tonemapping, &DebandDither),
+        Without<XrWarmupCamera>,
+    >,
+    warmup_cameras: Query<Entity, With<XrWarmupCamera>>,
+    mut objects: Query<(Entity, &mut Transform, Option<&RenderLayers>), With<XrWarmupObject>>,
+) {
+    if warmup.is_done() {
+        return;
+    }
+    //a row in front of the camera, close enough that nothing is culled. the xr cameras don't
+    //see the warm-up layer
+    let count = objects.iter().count().max(1) as f32;
+    for (index, (entity, mut transform, layers)) in objects.iter_mut().enumerate() {
+        let x = (index as f32 + 0.5) / count * 2.0 - 1.0;
+        let translation = Vec3::new(x, 0.0, -2.0);
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+        if layers != Some(&RenderLayers::layer(WARMUP_RENDER_LAYER)) {
+            commands
+                .entity(entity)
+                .insert(RenderLayers::layer(WARMUP_RENDER_LAYER));
+        }
+    }
+    match warmup.state {
+        XrWarmupState::Waiting => {
+            if !session_state.is_running() {
+                return;
+            }
+            //the pipelines are keyed by the view, so the camera copies the xr cameras. the
+            //msaa setting is global
+            let Some((_, camera, tonemapping, dither)) = xr_cameras
+                .iter()
+                .find(|(camera_type, ..)| matches!(camera_type, XrCameraType::Xr(_)))
+            else {
+                return;
+            };
+            let Some(view) = manual_texture_views.get(&LEFT_XR_TEXTURE_HANDLE) else {
+                return;
+            };
+            let image = images.add(warmup_image(warmup.resolution, view.format));
+            let mut bundle = XrCameraBundle::new(Eye::Left);
+            bundle.camera = Camera {
+                order: -40,
+                target: RenderTarget::Image(image),
+                hdr: camera.hdr,
+                ..default()
+            };
+            bundle.xr_projection.fov = Fovf {
+                angle_left: -0.8,
+                angle_right: 0.8,
+                angle_up: 0.8,
+                angle_down: -0.8,
+            };
+            bundle.tonemapping = *tonemapping;
+            bundle.dither = *dither;
+            bundle.xr_camera_type = XrCameraType::Flatscreen;
+            commands.spawn((
+                bundle,
+                RenderLayers::layer(WARMUP_RENDER_LAYER),
+                XrWarmupCamera,
+            ));
+            info!("warming up the shaders");
+            warmup.state = XrWarmupState::Running(0);
+        }
+        XrWarmupState::Running(frames) => {
+            let warmup = &mut *warmup;
+            warmup.wait_for.retain(|handle| {
+                !matches!(
+                    asset_server.get_recursive_dependency_load_state(handle.id()),
+                    Some(
+                        RecursiveDependencyLoadState::Loaded | RecursiveDependencyLoadState::Failed
+                    ) | None
+                )
+            });
+            if !warmup.wait_for.is_empty() {
+                return;
+            }
+            if frames < warmup.frames {
+                warmup.state = XrWarmupState::Running(frames + 1);
+                return;
+            }
+            for entity in warmup_cameras.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            for (entity, ..) in objects.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            info!("warmed up the shaders after {} frames", frames);
+            warmup.state = XrWarmupState::Done;
+        }
+        XrWarmupState::Done => {}
+    }
+}
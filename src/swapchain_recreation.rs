@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use bevy::render::camera::{ManualTextureView, ManualTextureViews};
+use bevy::render::renderer::RenderDevice;
+use bevy::render::{MainWorld, RenderApp};
+
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::graphics;
+use crate::half_rate::XrHalfRate;
+use crate::resources::{
+    XrFormat, XrResolution, XrSupersampling, XrSwapchain, XrSwapchainUsage, XrViewSizes,
+};
+use crate::xr_init::xr_only;
+use crate::{LEFT_XR_TEXTURE_HANDLE, RIGHT_XR_TEXTURE_HANDLE};
+
+/// changes the eye swapchain while the session keeps running, e.g. from a quality menu. the
+/// swapchain is recreated during extraction, when the last frame was submitted and no image is
+/// acquired, after the gpu finished with the old images. the frame it happens in hitches.
+/// changing [`XrSupersampling`] recreates it too
+pub struct XrSwapchainRecreationPlugin;
+
+impl Plugin for XrSwapchainRecreationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RecreateSwapchain>();
+        app.add_event::<XrSwapchainRecreated>();
+        app.init_resource::<XrPendingSwapchain>();
+        app.init_resource::<XrSupersampling>();
+        app.add_systems(
+            Last,
+            (apply_supersampling, queue_swapchain_recreation)
+                .chain()
+                .run_if(xr_only()),
+        );
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(ExtractSchedule, recreate_xr_swapchain.run_if(xr_only()));
+    }
+}
+
+/// recreates the swapchain, what is `None` stays as it is. several requests in one frame are
+/// merged, the later ones win
+#[derive(Event, Clone, Copy, Debug, Default, PartialEq)]
+pub struct RecreateSwapchain {
+    /// the size of each eye's image, clamped to the maximum of the runtime
+    pub resolution: Option<UVec2>,
+    /// has to be a format the runtime supports
+    pub format: Option<wgpu::TextureFormat>,
+    /// 1, 2, 4 or 8. applied as `Msaa` right away, the eyes render into multisampled textures
+    /// that get resolved into the swapchain, which itself has one sample
+    pub sample_count: Option<u32>,
+}
+
+/// the swapchain was recreated with these
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrSwapchainRecreated {
+    pub resolution: UVec2,
+    pub format: wgpu::TextureFormat,
+}
+
+/// the recreation the render world does at the next extraction
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct XrPendingSwapchain(Option<RecreateSwapchain>);
+
+/// recreates the swapchain when [`XrSupersampling`] changes, the first size comes from the
+/// session creation
+pub fn apply_supersampling(
+    supersampling: Res<XrSupersampling>,
+    sizes: Res<XrViewSizes>,
+    mut events: EventWriter<RecreateSwapchain>,
+) {
+    if !supersampling.is_changed() || supersampling.is_added() {
+        return;
+    }
+    events.send(RecreateSwapchain {
+        resolution: Some(sizes.supersampled(supersampling.0)),
+        ..default()
+    });
+}
+
+pub fn queue_swapchain_recreation(
+    mut events: EventReader<RecreateSwapchain>,
+    mut pending: ResMut<XrPendingSwapchain>,
+    mut msaa: ResMut<Msaa>,
+) {
+    for event in events.read() {
+        if let Some(sample_count) = event.sample_count {
+            match sample_count {
+                1 => *msaa = Msaa::Off,
+                2 => *msaa = Msaa::Sample2,
+                4 => *msaa = Msaa::Sample4,
+                8 => *msaa = Msaa::Sample8,
+                _ => warn!("{} samples aren't supported", sample_count),
+            }
+        }
+        if event.resolution.is_none() && event.format.is_none() {
+            continue;
+        }
+        let request = pending.0.get_or_insert_with(default);
+        request.resolution = event.resolution.or(request.resolution);
+        request.format = event.format.or(request.format);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn recreate_xr_swapchain(
+    mut main_world: ResMut<MainWorld>,
+    swapchain: Res<XrSwapchain>,
+    sizes: Res<XrViewSizes>,
+    render_device: Res<RenderDevice>,
+    usage: Res<XrSwapchainUsage>,
+    error_log: Res<XrErrorLog>,
+    mut resolution: ResMut<XrResolution>,
+    mut format: ResMut<XrFormat>,
+) {
+    //a half rate frame that is skipped would resubmit an image of the new swapchain that was
+    //never acquired, the recreation waits for a frame that renders
+    if main_world
+        .get_resource::<XrHalfRate>()
+        .is_some_and(|half_rate| half_rate.skips_frame())
+    {
+        return;
+    }
+    let Some(request) = main_world
+        .get_resource_mut::<XrPendingSwapchain>()
+        .and_then(|mut pending| pending.0.take())
+    else {
+        return;
+    };
+    let target_resolution = request.resolution.unwrap_or(**resolution);
+    let target_format = request.format.unwrap_or(**format);
+    if target_resolution == **resolution && target_format == **format {
+        return;
+    }
+    let created = graphics::recreate_swapchain(
+        &swapchain,
+        &render_device,
+        target_resolution,
+        sizes.max,
+        target_format,
+        usage.storage,
+    );
+    let new_resolution = match created {
+        Ok(new_resolution) => new_resolution,
+        Err(err) => {
+            error_log.report(
+                XrErrorSource::Other,
+                format!("couldn't recreate the swapchain: {}", err),
+            );
+            return;
+        }
+    };
+    info!(
+        "recreated the swapchain with {}x{}, {:?}",
+        new_resolution.x, new_resolution.y, target_format
+    );
+    *resolution = XrResolution::new(new_resolution);
+    *format = XrFormat::new(target_format);
+    main_world.insert_resource(XrResolution::new(new_resolution));
+    main_world.insert_resource(XrFormat::new(target_format));
+    //the cameras size their targets by the main world views, the render world ones get pointed
+    //at the acquired image every frame anyway
+    let (left, right) = swapchain.get_render_views();
+    let mut views = main_world.resource_mut::<ManualTextureViews>();
+    for (handle, texture_view) in [
+        (LEFT_XR_TEXTURE_HANDLE, left),
+        (RIGHT_XR_TEXTURE_HANDLE, right),
+    ] {
+        views.insert(
+            handle,
+            ManualTextureView {
+                texture_view,
+                size: new_resolution,
+                format: target_format,
+            },
+        );
+    }
+    main_world.send_event(XrSwapchainRecreated {
+        resolution: new_resolution,
+        format: target_format,
+    });
+}
@@ -0,0 +1,171 @@
+//! runs the crate headless against monado, so the session lifecycle, the frame loop and the
+//! input sync can be checked in ci without a headset. needs `XR_RUNTIME_JSON` pointing at
+//! monado's runtime manifest, and `monado-service` running if monado was built with the service
+
+use bevy::app::PluginsState;
+use bevy::prelude::*;
+use bevy::tasks::tick_global_task_pools_on_main_thread;
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
+
+use crate::error_log::{XrErrorEntry, XrErrorLog, XrErrorSource};
+use crate::resources::{XrFrameState, XrSession};
+use crate::xr_init::XrSessionState;
+use crate::DefaultXrPlugins;
+
+/// monado settings for a session without a display or devices, the simulated hmd and the null
+/// compositor. values that are already set aren't changed
+pub fn configure_monado_env() {
+    for (key, value) in [("XRT_COMPOSITOR_NULL", "1"), ("SIMULATED_ENABLE", "1")] {
+        if std::env::var_os(key).is_none() {
+            std::env::set_var(key, value);
+        }
+    }
+}
+
+/// a headless app with the [`DefaultXrPlugins`], updated by hand one frame at a time
+pub struct XrTestHarness {
+    pub app: App,
+}
+
+impl XrTestHarness {
+    pub fn new() -> Self {
+        Self::with_app(|_| {})
+    }
+
+    /// `setup` can add plugins and resources before the app is finished
+    pub fn with_app(setup: impl FnOnce(&mut App)) -> Self {
+        configure_monado_env();
+        let mut app = App::new();
+        app.add_plugins(
+            DefaultXrPlugins
+                .build()
+                .disable::<WinitPlugin>()
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    ..default()
+                }),
+        );
+        setup(&mut app);
+        while app.plugins_state() == PluginsState::Adding {
+            tick_global_task_pools_on_main_thread();
+        }
+        app.finish();
+        app.cleanup();
+        Self { app }
+    }
+
+    pub fn update(&mut self) {
+        self.app.update();
+    }
+
+    /// runs frames until `condition` is true, false if it wasn't within `max_frames`
+    pub fn run_until(
+        &mut self,
+        max_frames: u32,
+        mut condition: impl FnMut(&mut World) -> bool,
+    ) -> bool {
+        for _ in 0..max_frames {
+            self.update();
+            if condition(&mut self.app.world) {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn session_running(&self) -> bool {
+        self.session_state().is_running()
+    }
+
+    pub fn session_state(&self) -> XrSessionState {
+        **self.app.world.resource::<State<XrSessionState>>()
+    }
+
+    pub fn predicted_display_time(&self) -> Option<i64> {
+        let frame_state = self.app.world.get_resource::<XrFrameState>()?;
+        let time = frame_state.lock().unwrap().predicted_display_time;
+        Some(time.as_nanos())
+    }
+
+    /// the logged errors of `sources`, all errors if `sources` is empty
+    pub fn errors(&self, sources: &[XrErrorSource]) -> Vec<XrErrorEntry> {
+        let Some(log) = self.app.world.get_resource::<XrErrorLog>() else {
+            return vec![];
+        };
+        log.entries()
+            .into_iter()
+            .filter(|entry| sources.is_empty() || sources.contains(&entry.source))
+            .collect()
+    }
+
+    /// panics unless the session gets focused within `max_frames`
+    pub fn assert_session_starts(&mut self, max_frames: u32) {
+        assert!(
+            self.app.world.contains_resource::<XrSession>(),
+            "no openxr session was created, is XR_RUNTIME_JSON set?"
+        );
+        let started = self.run_until(max_frames, |world| {
+            **world.resource::<State<XrSessionState>>() == XrSessionState::Focused
+        });
+        assert!(
+            started,
+            "the session didn't start within {} frames",
+            max_frames
+        );
+    }
+
+    /// runs `frames` frames, panics if the display time doesn't advance every frame or the frame
+    /// loop or the action sync logged an error
+    pub fn assert_frame_loop(&mut self, frames: u32) {
+        let mut last_time = self.predicted_display_time();
+        for frame in 0..frames {
+            self.update();
+            let time = self.predicted_display_time();
+            assert!(
+                time > last_time,
+                "the display time didn't advance in frame {}: {:?} -> {:?}",
+                frame,
+                last_time,
+                time
+            );
+            last_time = time;
+        }
+        let errors = self.errors(&[
+            XrErrorSource::WaitFrame,
+            XrErrorSource::BeginFrame,
+            XrErrorSource::LocateViews,
+            XrErrorSource::EndFrame,
+            XrErrorSource::SyncActions,
+        ]);
+        assert!(
+            errors.is_empty(),
+            "the frame loop logged errors: {:#?}",
+            errors
+        );
+    }
+
+    /// asks the runtime to end the session, panics unless it stops within `max_frames`
+    pub fn assert_session_ends(&mut self, max_frames: u32) {
+        self.app
+            .world
+            .resource::<XrSession>()
+            .request_exit()
+            .expect("xrRequestExitSession failed");
+        let stopped = self.run_until(max_frames, |world| {
+            !world.resource::<State<XrSessionState>>().is_running()
+        });
+        assert!(
+            stopped,
+            "the session didn't stop within {} frames",
+            max_frames
+        );
+    }
+}
+
+impl Default for XrTestHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
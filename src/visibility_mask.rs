@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use openxr as xr;
+
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrFrameSet;
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::xr_only;
+use crate::OpenXrSettings;
+
+/// builds the hidden and visible area meshes of each eye from XR_KHR_visibility_mask into
+/// [`XrVisibilityMasks`], and rebuilds them when the runtime changes the mask (some do after
+/// the ipd was adjusted). the meshes are in view space on the z = -1 plane, so they can be
+/// rendered as children of the eye cameras. does nothing if the runtime doesn't support the
+/// extension
+pub struct XrVisibilityMaskPlugin;
+
+impl Plugin for XrVisibilityMaskPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrVisibilityMasks>();
+        app.add_systems(
+            PreUpdate,
+            update_visibility_masks
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+    }
+}
+
+/// sent when the runtime changed the mask of a view, the meshes are rebuilt in the same frame
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrVisibilityMaskChanged {
+    pub view_index: u32,
+}
+
+/// the masks of the left (0) and right (1) eye, empty until the session is running. the mesh
+/// handles stay the same when the masks get rebuilt
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrVisibilityMasks {
+    pub views: Vec<XrViewMask>,
+}
+
+#[derive(Clone, Debug)]
+pub struct XrViewMask {
+    /// the area the user can't see, drawing it to the depth buffer first saves fragment work
+    pub hidden: Handle<Mesh>,
+    /// the area the user can see
+    pub visible: Handle<Mesh>,
+}
+
+impl XrVisibilityMasks {
+    pub fn get(&self, view_index: u32) -> Option<&XrViewMask> {
+        self.views.get(view_index as usize)
+    }
+}
+
+pub fn update_visibility_masks(
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    error_log: Res<XrErrorLog>,
+    mut masks: ResMut<XrVisibilityMasks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut changed: EventReader<XrVisibilityMaskChanged>,
+    settings: Res<OpenXrSettings>,
+) {
+    if instance.exts().khr_visibility_mask.is_none() {
+        return;
+    }
+    let views = match masks.views.is_empty() {
+        true => vec![0, 1],
+        false => changed.read().map(|event| event.view_index).collect(),
+    };
+    changed.clear();
+    for view_index in views {
+        let hidden = visibility_mask_mesh(
+            &session,
+            settings.view_configuration,
+            view_index,
+            xr::VisibilityMaskTypeKHR::HIDDEN_TRIANGLE_MESH,
+        );
+        let visible = visibility_mask_mesh(
+            &session,
+            settings.view_configuration,
+            view_index,
+            xr::VisibilityMaskTypeKHR::VISIBLE_TRIANGLE_MESH,
+        );
+        let (hidden, visible) = match (hidden, visible) {
+            (Ok(hidden), Ok(visible)) => (hidden, visible),
+            (Err(err), _) | (_, Err(err)) => {
+                error_log.report(
+                    XrErrorSource::Other,
+                    format!(
+                        "failed to get the visibility mask of view {}: {}",
+                        view_index, err
+                    ),
+                );
+                continue;
+            }
+        };
+        match masks.views.get(view_index as usize) {
+            Some(mask) => {
+                meshes.insert(&mask.hidden, hidden);
+                meshes.insert(&mask.visible, visible);
+            }
+            None => {
+                let mask = XrViewMask {
+                    hidden: meshes.add(hidden),
+                    visible: meshes.add(visible),
+                };
+                if view_index as usize == masks.views.len() {
+                    masks.views.push(mask);
+                }
+            }
+        }
+        info!("built the visibility mask of view {}", view_index);
+    }
+}
+
+fn visibility_mask_mesh(
+    session: &XrSession,
+    view_configuration: xr::ViewConfigurationType,
+    view_index: u32,
+    mask_type: xr::VisibilityMaskTypeKHR,
+) -> xr::Result<Mesh> {
+    let mask = session.get_visibility_mask_khr(view_configuration, view_index, mask_type)?;
+    let positions = mask
+        .vertices
+        .iter()
+        .map(|vertex| [vertex.x, vertex.y, -1.0])
+        .collect::<Vec<_>>();
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_indices(Some(Indices::U32(mask.indices)));
+    Ok(mesh)
+}
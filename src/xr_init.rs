@@ -19,7 +19,7 @@ use crate::{
     input::XrInput,
     resources::{
         XrEnvironmentBlendMode, XrFormat, XrFrameState, XrFrameWaiter, XrInstance, XrResolution,
-        XrSession, XrSessionRunning, XrSwapchain, XrViews,
+        XrSession, XrSwapchain, XrViewSizes, XrViews,
     },
 };
 
@@ -38,8 +38,8 @@ pub struct XrRenderData {
     pub xr_session: XrSession,
     pub xr_blend_mode: XrEnvironmentBlendMode,
     pub xr_resolution: XrResolution,
+    pub xr_view_sizes: XrViewSizes,
     pub xr_format: XrFormat,
-    pub xr_session_running: XrSessionRunning,
     pub xr_frame_waiter: XrFrameWaiter,
     pub xr_swapchain: XrSwapchain,
     pub xr_input: XrInput,
@@ -47,6 +47,12 @@ pub struct XrRenderData {
     pub xr_frame_state: XrFrameState,
 }
 
+/// insert this before adding the plugins to start the app without an openxr session, e.g. for an
+/// "Enter VR" button. the graphics device is still made for openxr, the session is created once
+/// [`XrEnableRequest::TryEnable`] is sent
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct XrDeferredInit;
+
 #[derive(Event, Clone, Copy, Debug)]
 pub enum XrEnableRequest {
     TryEnable,
@@ -97,6 +103,74 @@ pub fn xr_only() -> impl FnMut(Option<Res<'_, XrEnableStatus>>) -> bool {
     resource_exists_and_equals(XrEnableStatus::Enabled)
 }
 
+/// the state of the openxr session, follows the session state events of the runtime. stays
+/// `Idle` without a session
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum XrSessionState {
+    #[default]
+    Idle,
+    Ready,
+    Synchronized,
+    Visible,
+    Focused,
+    Stopping,
+    LossPending,
+    Exiting,
+}
+
+impl XrSessionState {
+    pub fn from_xr(state: openxr::SessionState) -> Self {
+        match state {
+            openxr::SessionState::READY => Self::Ready,
+            openxr::SessionState::SYNCHRONIZED => Self::Synchronized,
+            openxr::SessionState::VISIBLE => Self::Visible,
+            openxr::SessionState::FOCUSED => Self::Focused,
+            openxr::SessionState::STOPPING => Self::Stopping,
+            openxr::SessionState::LOSS_PENDING => Self::LossPending,
+            openxr::SessionState::EXITING => Self::Exiting,
+            _ => Self::Idle,
+        }
+    }
+
+    /// the session was begun and frames are submitted. a stopping session is ended right away,
+    /// so it doesn't count as running
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            Self::Ready | Self::Synchronized | Self::Visible | Self::Focused
+        )
+    }
+
+    /// the frames are shown to the user
+    pub fn is_visible(&self) -> bool {
+        matches!(self, Self::Visible | Self::Focused)
+    }
+}
+
+/// the session lost the input focus to the system ui, like the dashboard or a notification, or
+/// got it back. the app stays visible behind the system ui, so this is the moment to pause and
+/// resume a game, unlike visibility changes which also happen when the headset is taken off
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrFocusChanged {
+    Lost,
+    Regained,
+}
+
+/// run condition for systems that need a running session
+pub fn xr_session_running(state: Option<Res<State<XrSessionState>>>) -> bool {
+    state.is_some_and(|state| state.is_running())
+}
+
+/// run condition for systems that only matter while the user sees the app
+pub fn xr_session_visible(state: Option<Res<State<XrSessionState>>>) -> bool {
+    state.is_some_and(|state| state.is_visible())
+}
+
+/// run condition for systems that read input, only the focused app gets input
+pub fn xr_session_focused(state: Option<Res<State<XrSessionState>>>) -> bool {
+    state.is_some_and(|state| **state == XrSessionState::Focused)
+}
+
 impl Plugin for RenderRestartPlugin {
     fn build(&self, app: &mut App) {
         add_schedules(app);
@@ -150,6 +224,10 @@ fn add_schedules(app: &mut App) {
 }
 
 pub fn setup_xr(world: &mut World) {
+    //the setup systems expect a session, there is none when a deferred start failed
+    if !world.contains_resource::<XrInstance>() || !world.contains_resource::<XrSession>() {
+        return;
+    }
     world.run_schedule(XrPreSetup);
     world.run_schedule(XrSetup);
     world.run_schedule(XrPrePostSetup);
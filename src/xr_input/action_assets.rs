@@ -0,0 +1,268 @@
+use std::path::Path;
+
+use anyhow::Context;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AssetPath, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::reflect::TypePath;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::xr_init::XrSetup;
+
+use super::action_manifest::{XrActionDeclaration, XrActionSetManifest};
+use super::actions::{ActionType, SetupActionSets, XrBinding};
+use super::single_controller::SingleControllerConfig;
+
+/// loads action sets and their bindings from a `.actions.ron` or `.actions.json` asset, so
+/// controls can be remapped without recompiling. the file is read when the session is set up,
+/// next to the action sets of the controller type. openxr doesn't let an app change bindings
+/// once the action sets are attached, so a changed file only takes effect with the next session:
+/// [`XrActionBindingsChanged`] is sent and the app can restart xr to apply it. the
+/// `single_controller` entry isn't an openxr binding, it applies right away
+pub struct XrActionBindingsPlugin {
+    /// relative to the assets folder, like `input/default.actions.ron`
+    pub path: String,
+}
+
+impl Plugin for XrActionBindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<XrActionBindings>();
+        app.register_asset_loader(XrActionBindingsLoader);
+        app.add_event::<XrActionBindingsChanged>();
+        app.insert_resource(XrActionBindingsFile {
+            path: self.path.clone(),
+            handle: None,
+            manifests: None,
+        });
+        app.add_systems(XrSetup, setup_asset_action_sets);
+        app.add_systems(Update, reload_action_bindings);
+    }
+}
+
+/// the contents of an action bindings file
+#[derive(Asset, TypePath, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct XrActionBindings {
+    pub action_sets: Vec<XrActionSetDescription>,
+    /// replaces the [`SingleControllerConfig`] resource
+    #[serde(default)]
+    pub single_controller: Option<SingleControllerConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct XrActionSetDescription {
+    pub name: String,
+    pub pretty_name: String,
+    #[serde(default)]
+    pub priority: u32,
+    pub actions: Vec<XrActionDescription>,
+    #[serde(default)]
+    pub bindings: Vec<XrProfileBindingsDescription>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct XrActionDescription {
+    pub name: String,
+    pub pretty_name: String,
+    pub action_type: ActionType,
+    /// like `/user/hand/left`, empty for one state across all devices
+    #[serde(default)]
+    pub subaction_paths: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct XrProfileBindingsDescription {
+    /// like `/interaction_profiles/oculus/touch_controller`
+    pub profile: String,
+    /// (action, input path)
+    pub bindings: Vec<(String, String)>,
+}
+
+impl XrActionBindings {
+    /// json for paths ending in `.json`, ron for everything else
+    pub fn parse(bytes: &[u8], path: &Path) -> anyhow::Result<Self> {
+        let bindings: Self = match path.extension().is_some_and(|ext| ext == "json") {
+            true => serde_json::from_slice(bytes)?,
+            false => ron::de::from_bytes(bytes)?,
+        };
+        bindings.validate()?;
+        Ok(bindings)
+    }
+
+    /// every binding has to name an action of its set, the action set setup panics otherwise
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for set in &self.action_sets {
+            for profile in &set.bindings {
+                for (action, path) in &profile.bindings {
+                    if !set.actions.iter().any(|a| a.name == *action) {
+                        anyhow::bail!(
+                            "{}: {} is bound to {} but isn't an action of the set",
+                            set.name,
+                            action,
+                            path
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// the action sets as manifests
+    pub fn manifests(&self) -> Vec<XrActionSetManifest> {
+        self.action_sets
+            .iter()
+            .map(|set| {
+                let mut manifest =
+                    XrActionSetManifest::new(set.name.clone(), &set.pretty_name, set.priority);
+                manifest.actions = set
+                    .actions
+                    .iter()
+                    .map(|action| XrActionDeclaration {
+                        name: action.name.clone().into(),
+                        pretty_name: action.pretty_name.clone(),
+                        action_type: action.action_type,
+                        subaction_paths: action
+                            .subaction_paths
+                            .iter()
+                            .map(|path| path.clone().into())
+                            .collect(),
+                    })
+                    .collect();
+                for profile in &set.bindings {
+                    let bindings = profile
+                        .bindings
+                        .iter()
+                        .map(|(action, path)| XrBinding::new(action.clone(), path.clone()))
+                        .collect::<Vec<_>>();
+                    manifest = manifest.with_bindings(profile.profile.clone(), &bindings);
+                }
+                manifest
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct XrActionBindingsLoader;
+
+impl AssetLoader for XrActionBindingsLoader {
+    type Asset = XrActionBindings;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<XrActionBindings, anyhow::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            XrActionBindings::parse(&bytes, load_context.path())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["actions.ron", "actions.json"]
+    }
+}
+
+/// the file the plugin loads and the action sets it had when it was last read
+#[derive(Resource, Debug)]
+pub struct XrActionBindingsFile {
+    pub path: String,
+    handle: Option<Handle<XrActionBindings>>,
+    manifests: Option<Vec<XrActionSetManifest>>,
+}
+
+/// the bindings file changed after the action sets were attached, the new bindings are used
+/// from the next session on
+#[derive(Event, Clone, Debug)]
+pub struct XrActionBindingsChanged {
+    pub path: String,
+}
+
+// the asset server loads in the background, but the action sets are created right after setup,
+// so the first read goes through the asset source directly
+fn read_bindings_now(asset_server: &AssetServer, path: &str) -> anyhow::Result<XrActionBindings> {
+    let asset_path = AssetPath::parse(path);
+    let source = asset_server
+        .get_source(asset_path.source().clone())
+        .map_err(|err| anyhow::anyhow!("{}", err))?;
+    futures_lite::future::block_on(async {
+        let mut reader = source
+            .reader()
+            .read(asset_path.path())
+            .await
+            .map_err(|err| anyhow::anyhow!("{}", err))?;
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        XrActionBindings::parse(&bytes, asset_path.path())
+    })
+    .with_context(|| format!("couldn't read the action bindings from {}", path))
+}
+
+pub fn setup_asset_action_sets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut file: ResMut<XrActionBindingsFile>,
+    mut action_sets: ResMut<SetupActionSets>,
+) {
+    if file.handle.is_none() {
+        file.handle = Some(asset_server.load(file.path.clone()));
+    }
+    if file.manifests.is_none() {
+        match read_bindings_now(&asset_server, &file.path) {
+            Ok(bindings) => {
+                file.manifests = Some(bindings.manifests());
+                if let Some(config) = bindings.single_controller {
+                    commands.insert_resource(config);
+                }
+            }
+            Err(err) => {
+                error!("{:#}", err);
+                return;
+            }
+        }
+    }
+    for manifest in file.manifests.iter().flatten() {
+        action_sets.add_manifest(manifest.clone());
+    }
+    info!("added the action sets of {}", file.path);
+}
+
+pub fn reload_action_bindings(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<XrActionBindings>>,
+    assets: Res<Assets<XrActionBindings>>,
+    mut file: ResMut<XrActionBindingsFile>,
+    mut changed: EventWriter<XrActionBindingsChanged>,
+) {
+    let Some(id) = file.handle.as_ref().map(|handle| handle.id()) else {
+        return;
+    };
+    for event in events.read() {
+        let AssetEvent::Modified { id: modified } = event else {
+            continue;
+        };
+        if *modified != id {
+            continue;
+        }
+        let Some(bindings) = assets.get(id) else {
+            continue;
+        };
+        file.manifests = Some(bindings.manifests());
+        if let Some(config) = bindings.single_controller {
+            commands.insert_resource(config);
+        }
+        info!(
+            "the action bindings in {} changed, they apply to the next session",
+            file.path
+        );
+        changed.send(XrActionBindingsChanged {
+            path: file.path.clone(),
+        });
+    }
+}
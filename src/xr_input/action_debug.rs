@@ -0,0 +1,332 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::ptr;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use openxr as xr;
+use xr::sys;
+
+use crate::call_trace::trace;
+use crate::resources::{XrFrameState, XrInstance, XrSession};
+use crate::xr_init::xr_only;
+
+use super::actions::{TypedAction, XrActionSets, XrSyncActions};
+use super::interaction_profiles::{XrInteractionProfileChanged, XrInteractionProfiles};
+use super::Hand;
+
+/// dumps the state of every action of every action set into [`XrActionDebug`] each frame: the
+/// value, whether it is active, the input paths the runtime bound it to and when it last
+/// changed. meant for reports like "my button does nothing on runtime x", an action without
+/// bound sources was never bound by that runtime. set [`XrActionDebug::overlay`] to show it as a
+/// table in the window
+pub struct XrActionDebugPlugin;
+
+impl Plugin for XrActionDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrActionDebug>();
+        app.add_systems(
+            PreUpdate,
+            update_action_debug.run_if(xr_only()).after(XrSyncActions),
+        );
+        app.add_systems(Update, update_action_debug_overlay);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XrActionDebugValue {
+    Bool(bool),
+    F32(f32),
+    Vec2(Vec2),
+    /// poses have no value, only whether they are active
+    Pose,
+    /// outputs have no state
+    Haptic,
+}
+
+/// one action for one hand, or for both if it has no subaction paths
+#[derive(Clone, Debug, PartialEq)]
+pub struct XrActionDebugEntry {
+    pub action_set: Cow<'static, str>,
+    pub action: Cow<'static, str>,
+    pub hand: Option<Hand>,
+    pub value: XrActionDebugValue,
+    pub is_active: bool,
+    /// when the runtime says the value last changed, zero if it never did
+    pub last_change_time: xr::Time,
+    /// the input paths the action is bound to with the current interaction profiles, for both
+    /// hands
+    pub bound_sources: Vec<String>,
+}
+
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrActionDebug {
+    /// shows the table in the top left corner of the window
+    pub overlay: bool,
+    entries: Vec<XrActionDebugEntry>,
+    display_time: Option<xr::Time>,
+}
+
+impl XrActionDebug {
+    pub fn iter(&self) -> impl Iterator<Item = &XrActionDebugEntry> {
+        self.entries.iter()
+    }
+
+    pub fn get(
+        &self,
+        action_set: &str,
+        action: &str,
+        hand: Option<Hand>,
+    ) -> Option<&XrActionDebugEntry> {
+        self.entries.iter().find(|entry| {
+            entry.action_set == action_set && entry.action == action && entry.hand == hand
+        })
+    }
+
+    /// one line per entry, sorted by action set and action
+    pub fn table(&self) -> String {
+        let mut table = String::new();
+        for entry in &self.entries {
+            let hand = match entry.hand {
+                Some(Hand::Left) => "left",
+                Some(Hand::Right) => "right",
+                None => "-",
+            };
+            let value = match entry.value {
+                XrActionDebugValue::Bool(value) => value.to_string(),
+                XrActionDebugValue::F32(value) => format!("{:.2}", value),
+                XrActionDebugValue::Vec2(value) => format!("{:.2} {:.2}", value.x, value.y),
+                XrActionDebugValue::Pose => "pose".to_string(),
+                XrActionDebugValue::Haptic => "haptic".to_string(),
+            };
+            let changed = match (self.display_time, entry.last_change_time.as_nanos()) {
+                (Some(now), changed) if changed > 0 => {
+                    format!("{:.1}s ago", (now.as_nanos() - changed) as f64 / 1e9)
+                }
+                _ => "never".to_string(),
+            };
+            let sources = match entry.bound_sources.is_empty() {
+                true => "unbound".to_string(),
+                false => entry.bound_sources.join(", "),
+            };
+            let _ = writeln!(
+                table,
+                "{}/{} {} {} {} {} [{}]",
+                entry.action_set,
+                entry.action,
+                hand,
+                match entry.is_active {
+                    true => "active",
+                    false => "inactive",
+                },
+                value,
+                changed,
+                sources
+            );
+        }
+        table
+    }
+}
+
+// the paths the runtime bound an action to, the openxr crate doesn't wrap
+// xrEnumerateBoundSourcesForAction
+fn bound_sources(
+    instance: &XrInstance,
+    session: &XrSession,
+    action: sys::Action,
+) -> xr::Result<Vec<String>> {
+    let enumerate = instance.fp().enumerate_bound_sources_for_action;
+    let info = sys::BoundSourcesForActionEnumerateInfo {
+        ty: sys::BoundSourcesForActionEnumerateInfo::TYPE,
+        next: ptr::null(),
+        action,
+    };
+    let mut count = 0;
+    let result = unsafe { enumerate(session.as_raw(), &info, 0, &mut count, ptr::null_mut()) };
+    if result.into_raw() < 0 {
+        return Err(result);
+    }
+    let mut paths = vec![xr::Path::NULL; count as usize];
+    let result = trace(
+        "xrEnumerateBoundSourcesForAction",
+        || format!("{} sources", count),
+        || unsafe {
+            enumerate(
+                session.as_raw(),
+                &info,
+                count,
+                &mut count,
+                paths.as_mut_ptr(),
+            )
+        },
+    );
+    if result.into_raw() < 0 {
+        return Err(result);
+    }
+    paths.truncate(count as usize);
+    paths
+        .into_iter()
+        .map(|path| instance.path_to_string(path))
+        .collect()
+}
+
+fn raw_action(action: &TypedAction) -> sys::Action {
+    match action {
+        TypedAction::F32(action) => action.as_raw(),
+        TypedAction::Bool(action) => action.as_raw(),
+        TypedAction::PoseF(action) => action.as_raw(),
+        TypedAction::Haptic(action) => action.as_raw(),
+        TypedAction::Vec2(action) => action.as_raw(),
+    }
+}
+
+// the value of an action for one subaction path, errors for paths the action wasn't created with
+fn action_state(
+    action: &TypedAction,
+    session: &XrSession,
+    path: xr::Path,
+) -> xr::Result<(XrActionDebugValue, bool, xr::Time)> {
+    Ok(match action {
+        TypedAction::F32(action) => {
+            let state = action.state(session, path)?;
+            (
+                XrActionDebugValue::F32(state.current_state),
+                state.is_active,
+                state.last_change_time,
+            )
+        }
+        TypedAction::Bool(action) => {
+            let state = action.state(session, path)?;
+            (
+                XrActionDebugValue::Bool(state.current_state),
+                state.is_active,
+                state.last_change_time,
+            )
+        }
+        TypedAction::Vec2(action) => {
+            let state = action.state(session, path)?;
+            (
+                XrActionDebugValue::Vec2(Vec2::new(state.current_state.x, state.current_state.y)),
+                state.is_active,
+                state.last_change_time,
+            )
+        }
+        TypedAction::PoseF(action) => (
+            XrActionDebugValue::Pose,
+            action.is_active(session, path)?,
+            xr::Time::from_nanos(0),
+        ),
+        TypedAction::Haptic(_) => (XrActionDebugValue::Haptic, true, xr::Time::from_nanos(0)),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_action_debug(
+    mut debug: ResMut<XrActionDebug>,
+    action_sets: Option<Res<XrActionSets>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    frame_state: Res<XrFrameState>,
+    profiles: Option<Res<XrInteractionProfiles>>,
+    mut profile_changes: EventReader<XrInteractionProfileChanged>,
+    mut sources: Local<Option<HashMap<(Cow<'static, str>, Cow<'static, str>), Vec<String>>>>,
+) {
+    let Some(action_sets) = action_sets else {
+        return;
+    };
+    let left = instance.string_to_path("/user/hand/left").unwrap();
+    let right = instance.string_to_path("/user/hand/right").unwrap();
+    //the bound sources only change with the interaction profiles
+    if profile_changes.read().count() > 0 || profiles.is_some_and(|p| p.is_changed()) {
+        *sources = None;
+    }
+    let sources = sources.get_or_insert_with(|| {
+        action_sets
+            .iter()
+            .map(|(set_name, action_name, action)| {
+                let bound =
+                    bound_sources(&instance, &session, raw_action(action)).unwrap_or_else(|err| {
+                        warn!(
+                            "couldn't get the bound sources of {}/{}: {}",
+                            set_name, action_name, err
+                        );
+                        vec![]
+                    });
+                ((set_name.clone(), action_name.clone()), bound)
+            })
+            .collect()
+    });
+    let mut entries = vec![];
+    for (set_name, action_name, action) in action_sets.iter() {
+        let bound_sources = sources
+            .get(&(set_name.clone(), action_name.clone()))
+            .cloned()
+            .unwrap_or_default();
+        let hands = [(Some(Hand::Left), left), (Some(Hand::Right), right)]
+            .into_iter()
+            .filter_map(|(hand, path)| {
+                action_state(action, &session, path)
+                    .ok()
+                    .map(|state| (hand, state))
+            })
+            .collect::<Vec<_>>();
+        //actions without subaction paths only have a state for the null path
+        let states = match hands.is_empty() {
+            true => action_state(action, &session, xr::Path::NULL)
+                .ok()
+                .map(|state| (None, state))
+                .into_iter()
+                .collect(),
+            false => hands,
+        };
+        for (hand, (value, is_active, last_change_time)) in states {
+            entries.push(XrActionDebugEntry {
+                action_set: set_name.clone(),
+                action: action_name.clone(),
+                hand,
+                value,
+                is_active,
+                last_change_time,
+                bound_sources: bound_sources.clone(),
+            });
+        }
+    }
+    entries.sort_by(|a, b| {
+        (&a.action_set, &a.action, a.hand).cmp(&(&b.action_set, &b.action, b.hand))
+    });
+    debug.entries = entries;
+    debug.display_time = Some(frame_state.lock().unwrap().predicted_display_time);
+}
+
+#[derive(Component)]
+pub struct XrActionDebugOverlay;
+
+pub fn update_action_debug_overlay(
+    mut commands: Commands,
+    debug: Res<XrActionDebug>,
+    mut overlay: Query<(Entity, &mut Text), With<XrActionDebugOverlay>>,
+) {
+    match (debug.overlay, overlay.get_single_mut()) {
+        (true, Ok((_, mut text))) => {
+            if debug.is_changed() {
+                text.sections[0].value = debug.table();
+            }
+        }
+        (true, Err(_)) => {
+            commands.spawn((
+                TextBundle::from_section(
+                    debug.table(),
+                    TextStyle {
+                        font_size: 14.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)),
+                XrActionDebugOverlay,
+            ));
+        }
+        (false, Ok((entity, _))) => commands.entity(entity).despawn_recursive(),
+        (false, Err(_)) => {}
+    }
+}
@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+
+use bevy::prelude::*;
+
+use crate::xr_init::XrSetup;
+
+use super::actions::{ActionType, SetupActionSet, SetupActionSets, XrBinding};
+
+/// an action set declared up front: its actions, their subaction paths and the bindings
+/// suggested for each interaction profile. the actions plugin creates the `xr::ActionSet`,
+/// suggests the bindings (merged with the ones other sets suggest for the same profile) and
+/// attaches it with the others. the actions are read through
+/// [`XrActionSets`](super::actions::XrActionSets) by the names used here
+#[derive(Clone, Debug)]
+pub struct XrActionSetManifest {
+    pub name: Cow<'static, str>,
+    pub pretty_name: String,
+    /// sets with a higher priority win when they bind the same input
+    pub priority: u32,
+    pub actions: Vec<XrActionDeclaration>,
+    pub bindings: Vec<(Cow<'static, str>, Vec<XrBinding>)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct XrActionDeclaration {
+    pub name: Cow<'static, str>,
+    pub pretty_name: String,
+    pub action_type: ActionType,
+    /// the paths the action is read with, empty for one state across all devices
+    pub subaction_paths: Vec<Cow<'static, str>>,
+}
+
+impl XrActionSetManifest {
+    pub fn new(
+        name: impl Into<Cow<'static, str>>,
+        pretty_name: impl Into<String>,
+        priority: u32,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            pretty_name: pretty_name.into(),
+            priority,
+            actions: vec![],
+            bindings: vec![],
+        }
+    }
+
+    pub fn with_action(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        pretty_name: impl Into<String>,
+        action_type: ActionType,
+        subaction_paths: &[&'static str],
+    ) -> Self {
+        self.actions.push(XrActionDeclaration {
+            name: name.into(),
+            pretty_name: pretty_name.into(),
+            action_type,
+            subaction_paths: subaction_paths
+                .iter()
+                .map(|path| Cow::Borrowed(*path))
+                .collect(),
+        });
+        self
+    }
+
+    /// the bindings of the actions for one interaction profile, like
+    /// `/interaction_profiles/oculus/touch_controller`. can be called more than once per profile
+    pub fn with_bindings(
+        mut self,
+        profile: impl Into<Cow<'static, str>>,
+        bindings: &[XrBinding],
+    ) -> Self {
+        let profile = profile.into();
+        match self
+            .bindings
+            .iter_mut()
+            .find(|(other, _)| *other == profile)
+        {
+            Some((_, existing)) => existing.extend_from_slice(bindings),
+            None => self.bindings.push((profile, bindings.to_vec())),
+        }
+        self
+    }
+}
+
+impl SetupActionSets {
+    /// adds the action set the manifest declares, with its actions and bindings
+    pub fn add_manifest(&mut self, manifest: XrActionSetManifest) -> &mut SetupActionSet {
+        let action_set =
+            self.add_action_set(manifest.name, manifest.pretty_name, manifest.priority);
+        for action in manifest.actions {
+            action_set.new_action_with_subaction_paths(
+                action.name,
+                action.pretty_name,
+                action.action_type,
+                action.subaction_paths.as_slice(),
+            );
+        }
+        for (profile, bindings) in manifest.bindings {
+            action_set.suggest_binding(profile, &bindings);
+        }
+        action_set
+    }
+}
+
+/// adds an action set from a manifest, next to the ones of the controller type
+pub struct XrActionSetPlugin(pub XrActionSetManifest);
+
+impl Plugin for XrActionSetPlugin {
+    fn build(&self, app: &mut App) {
+        let manifest = self.0.clone();
+        app.add_systems(XrSetup, move |mut action_sets: ResMut<SetupActionSets>| {
+            action_sets.add_manifest(manifest.clone());
+        });
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::error::Error;
 
 use bevy::{prelude::*, utils::HashMap};
@@ -5,10 +6,12 @@ use openxr as xr;
 use xr::{Action, Binding, Haptic, Posef, Vector2f};
 
 use crate::{
+    call_trace::trace,
     resources::{XrInstance, XrSession},
     xr_init::XrPrePostSetup,
 };
 
+#[allow(deprecated)]
 use super::oculus_touch::ActionSets;
 
 pub struct OpenXrActionsPlugin;
@@ -17,89 +20,110 @@ impl Plugin for OpenXrActionsPlugin {
         app.insert_resource(SetupActionSets {
             sets: HashMap::new(),
         });
+        app.init_resource::<XrActionSyncMode>();
         app.add_systems(XrPrePostSetup, setup_oxr_actions);
     }
 }
 
+/// the system set xrSyncActions runs in, in `PreUpdate` after the frame was waited on. order
+/// systems that read actions after it to get the state of this frame
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XrSyncActions;
+
+/// with `Manual` the actions aren't synced automatically, the app calls [`XrActionSets::sync`]
+/// whenever it wants fresh input, e.g. right before the systems that read it
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum XrActionSyncMode {
+    #[default]
+    Automatic,
+    Manual,
+}
+
+/// the subaction paths of actions that exist once per hand
+pub const HAND_SUBACTION_PATHS: [&str; 2] = ["/user/hand/left", "/user/hand/right"];
+
 #[inline(always)]
 fn create_action<T: xr::ActionTy>(
     action: &SetupAction,
-    action_name: &'static str,
+    action_name: &str,
     oxr_action_set: &xr::ActionSet,
-    hands: &[xr::Path],
+    instance: &xr::Instance,
 ) -> xr::Action<T> {
-    match action.handednes {
-        ActionHandednes::Single => oxr_action_set
-            .create_action(action_name, &action.pretty_name, &[])
-            .unwrap_or_else(|_| panic!("Unable to create action: {}", action_name)),
-        ActionHandednes::Double => oxr_action_set
-            .create_action(action_name, &action.pretty_name, hands)
-            .unwrap_or_else(|_| panic!("Unable to create action: {}", action_name)),
-    }
+    let subaction_paths = action
+        .subaction_paths
+        .iter()
+        .map(|path| instance.string_to_path(path).unwrap())
+        .collect::<Vec<_>>();
+    trace(
+        "xrCreateAction",
+        || format!("{}, {:?}", action_name, action.subaction_paths),
+        || oxr_action_set.create_action(action_name, &action.pretty_name, &subaction_paths),
+    )
+    .unwrap_or_else(|_| panic!("Unable to create action: {}", action_name))
 }
 pub fn setup_oxr_actions(world: &mut World) {
     let actions = world.remove_resource::<SetupActionSets>().unwrap();
     let instance = world.get_resource::<XrInstance>().unwrap();
     let session = world.get_resource::<XrSession>().unwrap();
-    let left_path = instance.string_to_path("/user/hand/left").unwrap();
-    let right_path = instance.string_to_path("/user/hand/right").unwrap();
-    let hands = [left_path, right_path];
 
     let mut oxr_action_sets = Vec::new();
     let mut action_sets = XrActionSets { sets: default() };
     // let mut action_bindings: HashMap<&'static str, Vec<xr::Path>> = HashMap::new();
     let mut action_bindings: HashMap<
-        (&'static str, &'static str),
-        HashMap<&'static str, Vec<xr::Path>>,
+        (Cow<'static, str>, Cow<'static, str>),
+        HashMap<Cow<'static, str>, Vec<xr::Path>>,
     > = HashMap::new();
     for (set_name, set) in actions.sets.into_iter() {
-        let mut actions: HashMap<&'static str, TypedAction> = default();
-        let oxr_action_set = instance
-            .create_action_set(set_name, &set.pretty_name, set.priority)
-            .expect("Unable to create action set");
+        let mut actions: HashMap<Cow<'static, str>, TypedAction> = default();
+        let oxr_action_set = trace(
+            "xrCreateActionSet",
+            || format!("{}, priority {}", set_name, set.priority),
+            || instance.create_action_set(&set_name, &set.pretty_name, set.priority),
+        )
+        .expect("Unable to create action set");
         for (action_name, action) in set.actions.into_iter() {
             use self::create_action as ca;
             let typed_action = match action.action_type {
                 ActionType::Vec2 => {
-                    TypedAction::Vec2(ca(&action, action_name, &oxr_action_set, &hands))
+                    TypedAction::Vec2(ca(&action, &action_name, &oxr_action_set, instance))
                 }
                 ActionType::F32 => {
-                    TypedAction::F32(ca(&action, action_name, &oxr_action_set, &hands))
+                    TypedAction::F32(ca(&action, &action_name, &oxr_action_set, instance))
                 }
                 ActionType::Bool => {
-                    TypedAction::Bool(ca(&action, action_name, &oxr_action_set, &hands))
+                    TypedAction::Bool(ca(&action, &action_name, &oxr_action_set, instance))
                 }
                 ActionType::PoseF => {
-                    TypedAction::PoseF(ca(&action, action_name, &oxr_action_set, &hands))
+                    TypedAction::PoseF(ca(&action, &action_name, &oxr_action_set, instance))
                 }
                 ActionType::Haptic => {
-                    TypedAction::Haptic(ca(&action, action_name, &oxr_action_set, &hands))
+                    TypedAction::Haptic(ca(&action, &action_name, &oxr_action_set, instance))
                 }
             };
-            actions.insert(action_name, typed_action);
             for (device_path, bindings) in action.bindings.into_iter() {
                 for b in bindings {
                     info!("binding {} to {}", action_name, b);
                     action_bindings
-                        .entry((set_name, action_name))
+                        .entry((set_name.clone(), action_name.clone()))
                         .or_default()
-                        .entry(device_path)
+                        .entry(device_path.clone())
                         .or_default()
-                        .push(instance.string_to_path(b).unwrap());
+                        .push(instance.string_to_path(&b).unwrap());
                 }
             }
+            actions.insert(action_name, typed_action);
         }
-        oxr_action_sets.push(oxr_action_set);
+        oxr_action_sets.push(oxr_action_set.clone());
         action_sets.sets.insert(
             set_name,
             ActionSet {
-                // oxr_action_set,
+                oxr_action_set,
                 actions,
                 enabled: true,
             },
         );
     }
-    let mut b_indings: HashMap<&'static str, Vec<Binding>> = HashMap::new();
+    let mut b_indings: HashMap<&str, Vec<Binding>> = HashMap::new();
     for (dev, mut bindings) in action_sets
         .sets
         .iter()
@@ -110,11 +134,12 @@ pub fn setup_oxr_actions(world: &mut World) {
         })
         .zip([&action_bindings].into_iter().cycle())
         .flat_map(move |((set_name, action_name, action), bindings)| {
+            //actions without bindings are still created, they just never get suggested
             bindings
-                .get(&(set_name as &'static str, action_name as &'static str))
-                .unwrap()
-                .iter()
-                .map(move |(dev, bindings)| (action, dev, bindings))
+                .get(&(set_name.clone(), action_name.clone()))
+                .into_iter()
+                .flatten()
+                .map(move |(dev, bindings)| (action, &**dev, bindings))
         })
         .map(|(action, dev, bindings)| {
             info!("Hi");
@@ -135,16 +160,31 @@ pub fn setup_oxr_actions(world: &mut World) {
     {
         b_indings.entry(dev).or_default().append(&mut bindings);
     }
+    //every profile is suggested, the runtime picks the one that fits the connected devices. a
+    //profile the runtime doesn't know only loses its own bindings
     for (dev, bindings) in b_indings.into_iter() {
-        info!(dev);
-        instance
-            .suggest_interaction_profile_bindings(instance.string_to_path(dev).unwrap(), &bindings)
-            .expect("Unable to suggest interaction bindings!");
+        let result = trace(
+            "xrSuggestInteractionProfileBindings",
+            || format!("{}, {} bindings", dev, bindings.len()),
+            || {
+                instance.string_to_path(dev).and_then(|profile| {
+                    instance.suggest_interaction_profile_bindings(profile, &bindings)
+                })
+            },
+        );
+        match result {
+            Ok(()) => info!("suggested bindings for {}", dev),
+            Err(err) => warn!("couldn't suggest the bindings for {}: {}", dev, err),
+        }
     }
-    session
-        .attach_action_sets(&oxr_action_sets.iter().collect::<Vec<_>>())
-        .expect("Unable to attach action sets!");
+    trace(
+        "xrAttachSessionActionSets",
+        || format!("{} action sets", oxr_action_sets.len()),
+        || session.attach_action_sets(&oxr_action_sets.iter().collect::<Vec<_>>()),
+    )
+    .expect("Unable to attach action sets!");
 
+    #[allow(deprecated)]
     world.insert_resource(ActionSets(oxr_action_sets));
     world.insert_resource(action_sets);
 }
@@ -154,7 +194,8 @@ pub enum ActionHandednes {
     Double,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum ActionType {
     F32,
     Bool,
@@ -174,94 +215,122 @@ pub enum TypedAction {
 pub struct SetupAction {
     pretty_name: String,
     action_type: ActionType,
-    handednes: ActionHandednes,
-    bindings: HashMap<&'static str, Vec<&'static str>>,
+    subaction_paths: Vec<Cow<'static, str>>,
+    bindings: HashMap<Cow<'static, str>, Vec<Cow<'static, str>>>,
 }
 
 pub struct SetupActionSet {
     pretty_name: String,
     priority: u32,
-    actions: HashMap<&'static str, SetupAction>,
+    actions: HashMap<Cow<'static, str>, SetupAction>,
 }
 
 impl SetupActionSet {
     pub fn new_action(
         &mut self,
-        name: &'static str,
+        name: impl Into<Cow<'static, str>>,
         pretty_name: String,
         action_type: ActionType,
         handednes: ActionHandednes,
+    ) {
+        let subaction_paths = match handednes {
+            ActionHandednes::Single => &[][..],
+            ActionHandednes::Double => &HAND_SUBACTION_PATHS[..],
+        };
+        self.new_action_with_subaction_paths(name, pretty_name, action_type, subaction_paths);
+    }
+    /// an action that can be read per subaction path, like `/user/hand/left` or `/user/gamepad`
+    pub fn new_action_with_subaction_paths<P: Clone + Into<Cow<'static, str>>>(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        pretty_name: String,
+        action_type: ActionType,
+        subaction_paths: &[P],
     ) {
         self.actions.insert(
-            name,
+            name.into(),
             SetupAction {
                 pretty_name,
                 action_type,
-                handednes,
+                subaction_paths: subaction_paths.iter().cloned().map(Into::into).collect(),
                 bindings: default(),
             },
         );
     }
-    pub fn suggest_binding(&mut self, device_path: &'static str, bindings: &[XrBinding]) {
+    pub fn suggest_binding(
+        &mut self,
+        device_path: impl Into<Cow<'static, str>>,
+        bindings: &[XrBinding],
+    ) {
+        let device_path = device_path.into();
         for binding in bindings {
             self.actions
-                .get_mut(binding.action)
+                .get_mut(&binding.action)
                 .ok_or(anyhow::anyhow!("Missing Action: {}", binding.action))
                 .unwrap()
                 .bindings
-                .entry(device_path)
+                .entry(device_path.clone())
                 .or_default()
-                .push(binding.path);
+                .push(binding.path.clone());
         }
     }
 }
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct XrBinding {
-    action: &'static str,
-    path: &'static str,
+    action: Cow<'static, str>,
+    path: Cow<'static, str>,
 }
 
 impl XrBinding {
-    pub fn new(action_name: &'static str, binding_path: &'static str) -> XrBinding {
+    pub fn new(
+        action_name: impl Into<Cow<'static, str>>,
+        binding_path: impl Into<Cow<'static, str>>,
+    ) -> XrBinding {
         XrBinding {
-            action: action_name,
-            path: binding_path,
+            action: action_name.into(),
+            path: binding_path.into(),
         }
     }
 }
 
 #[derive(Resource)]
 pub struct SetupActionSets {
-    sets: HashMap<&'static str, SetupActionSet>,
+    sets: HashMap<Cow<'static, str>, SetupActionSet>,
 }
 
 impl SetupActionSets {
     pub fn add_action_set(
         &mut self,
-        name: &'static str,
+        name: impl Into<Cow<'static, str>>,
         pretty_name: String,
         priority: u32,
     ) -> &mut SetupActionSet {
+        let name = name.into();
         self.sets.insert(
-            name,
+            name.clone(),
             SetupActionSet {
                 pretty_name,
                 priority,
                 actions: HashMap::new(),
             },
         );
-        self.sets.get_mut(name).unwrap()
+        self.sets.get_mut(&name).unwrap()
+    }
+    /// an action set another plugin added, to suggest more bindings for it
+    pub fn get_action_set_mut(&mut self, name: &str) -> Option<&mut SetupActionSet> {
+        self.sets.get_mut(name)
     }
 }
 
 pub struct ActionSet {
-    // add functionality to enable/disable action sets
+    oxr_action_set: xr::ActionSet,
     enabled: bool,
-    actions: HashMap<&'static str, TypedAction>,
+    actions: HashMap<Cow<'static, str>, TypedAction>,
 }
 
 #[derive(Resource)]
 pub struct XrActionSets {
-    sets: HashMap<&'static str, ActionSet>,
+    sets: HashMap<Cow<'static, str>, ActionSet>,
 }
 
 use std::fmt::Display as FmtDisplay;
@@ -284,10 +353,49 @@ pub enum ActionError {
 }
 
 impl XrActionSets {
+    /// disabled action sets aren't synced, their actions become inactive
+    pub fn set_enabled(&mut self, action_set: &str, enabled: bool) -> Result<(), ActionError> {
+        self.sets
+            .get_mut(action_set)
+            .ok_or(ActionError::NoActionSet)?
+            .enabled = enabled;
+        Ok(())
+    }
+    pub fn is_enabled(&self, action_set: &str) -> Result<bool, ActionError> {
+        Ok(self
+            .sets
+            .get(action_set)
+            .ok_or(ActionError::NoActionSet)?
+            .enabled)
+    }
+    /// every action with the names of its set and itself
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&Cow<'static, str>, &Cow<'static, str>, &TypedAction)> {
+        self.sets.iter().flat_map(|(set_name, set)| {
+            set.actions
+                .iter()
+                .map(move |(action_name, action)| (set_name, action_name, action))
+        })
+    }
+    /// calls xrSyncActions with the enabled action sets, can be called more than once a frame
+    pub fn sync(&self, session: &XrSession) -> xr::Result<()> {
+        let active_action_sets = self
+            .sets
+            .values()
+            .filter(|set| set.enabled)
+            .map(|set| xr::ActiveActionSet::new(&set.oxr_action_set))
+            .collect::<Vec<_>>();
+        trace(
+            "xrSyncActions",
+            || format!("{} action sets", active_action_sets.len()),
+            || session.sync_actions(&active_action_sets),
+        )
+    }
     pub fn get_action_vec2(
         &self,
-        action_set: &'static str,
-        action_name: &'static str,
+        action_set: &str,
+        action_name: &str,
     ) -> Result<&Action<Vector2f>, ActionError> {
         let action = self
             .sets
@@ -303,8 +411,8 @@ impl XrActionSets {
     }
     pub fn get_action_f32(
         &self,
-        action_set: &'static str,
-        action_name: &'static str,
+        action_set: &str,
+        action_name: &str,
     ) -> Result<&Action<f32>,ActionError> {
         let action = self
             .sets
@@ -320,8 +428,8 @@ impl XrActionSets {
     }
     pub fn get_action_bool(
         &self,
-        action_set: &'static str,
-        action_name: &'static str,
+        action_set: &str,
+        action_name: &str,
     ) -> Result<&Action<bool>,ActionError> {
         let action = self
             .sets
@@ -337,8 +445,8 @@ impl XrActionSets {
     }
     pub fn get_action_posef(
         &self,
-        action_set: &'static str,
-        action_name: &'static str,
+        action_set: &str,
+        action_name: &str,
     ) -> Result<&Action<Posef>,ActionError> {
         let action = self
             .sets
@@ -354,8 +462,8 @@ impl XrActionSets {
     }
     pub fn get_action_haptic(
         &self,
-        action_set: &'static str,
-        action_name: &'static str,
+        action_set: &str,
+        action_name: &str,
     ) -> Result<&Action<Haptic>,ActionError> {
         let action = self
             .sets
@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+
+use crate::xr_init::xr_only;
+
+use super::input_sources::{XrGripSpace, XrInputSource, XrSpaceLocated};
+use super::trackers::{OpenXRHMD, XrTrackingRoot};
+use super::Hand;
+
+/// estimates where the shoulders and elbows are from the head and the hands, for avatar arms on
+/// devices without body tracking. each arm gets a shoulder and an elbow entity under the
+/// [`XrTrackingRoot`] with an [`XrArmJoint`], their -z points along the bone to the next joint.
+/// the elbows are solved with two bone ik and bent down and out, like relaxed arms
+pub struct XrArmEstimationPlugin;
+
+impl Plugin for XrArmEstimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrArmEstimation>();
+        app.add_systems(
+            Update,
+            (spawn_arm_joints, update_arm_joints)
+                .chain()
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// the body measurements the estimate uses, in meters. the defaults fit an average adult
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrArmEstimation {
+    pub upper_arm_length: f32,
+    pub forearm_length: f32,
+    /// between the two shoulders
+    pub shoulder_width: f32,
+    /// how far the shoulders are below the eyes
+    pub shoulder_drop: f32,
+    /// how far the shoulders are behind the eyes
+    pub shoulder_depth: f32,
+}
+
+impl Default for XrArmEstimation {
+    fn default() -> Self {
+        Self {
+            upper_arm_length: 0.29,
+            forearm_length: 0.27,
+            shoulder_width: 0.36,
+            shoulder_drop: 0.22,
+            shoulder_depth: 0.08,
+        }
+    }
+}
+
+impl XrArmEstimation {
+    /// scales the defaults to the height of the user
+    pub fn from_height(height: f32) -> Self {
+        let scale = height / 1.75;
+        let default = Self::default();
+        Self {
+            upper_arm_length: default.upper_arm_length * scale,
+            forearm_length: default.forearm_length * scale,
+            shoulder_width: default.shoulder_width * scale,
+            shoulder_drop: default.shoulder_drop * scale,
+            shoulder_depth: default.shoulder_depth * scale,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrArmJointKind {
+    Shoulder,
+    Elbow,
+}
+
+/// an estimated joint of an arm, the transform is relative to the tracking root
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrArmJoint {
+    pub hand: Hand,
+    pub kind: XrArmJointKind,
+}
+
+pub fn spawn_arm_joints(
+    mut commands: Commands,
+    root: Query<Entity, With<XrTrackingRoot>>,
+    joints: Query<(), With<XrArmJoint>>,
+) {
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    if !joints.is_empty() {
+        return;
+    }
+    for hand in [Hand::Left, Hand::Right] {
+        for kind in [XrArmJointKind::Shoulder, XrArmJointKind::Elbow] {
+            let joint = commands
+                .spawn((
+                    SpatialBundle::default(),
+                    XrArmJoint { hand, kind },
+                    Name::new(format!("{:?} {:?}", hand, kind)),
+                ))
+                .id();
+            commands.entity(root).add_child(joint);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_arm_joints(
+    estimation: Res<XrArmEstimation>,
+    head: Query<&Transform, (With<OpenXRHMD>, Without<XrArmJoint>)>,
+    hands: Query<
+        (&XrInputSource, &Transform, &XrSpaceLocated),
+        (With<XrGripSpace>, Without<XrArmJoint>),
+    >,
+    mut joints: Query<(&XrArmJoint, &mut Transform)>,
+) {
+    let Ok(head) = head.get_single() else {
+        return;
+    };
+    //the torso follows the yaw of the head, looking down doesn't move the shoulders forward
+    let forward = head.forward();
+    let forward = Vec3::new(forward.x, 0.0, forward.z)
+        .try_normalize()
+        .unwrap_or(Vec3::NEG_Z);
+    let right = forward.cross(Vec3::Y);
+    let center =
+        head.translation - Vec3::Y * estimation.shoulder_drop - forward * estimation.shoulder_depth;
+    for (source, wrist, located) in &hands {
+        let Some(hand) = source.hand.filter(|_| located.0) else {
+            continue;
+        };
+        let side = match hand {
+            Hand::Left => -right,
+            Hand::Right => right,
+        };
+        let shoulder = center + side * estimation.shoulder_width * 0.5;
+        //the elbow bends toward this, down and out
+        let pole = (Vec3::NEG_Y + side * 0.5 - forward * 0.3).normalize();
+        let elbow = solve_elbow(
+            shoulder,
+            wrist.translation,
+            pole,
+            estimation.upper_arm_length,
+            estimation.forearm_length,
+        );
+        for (joint, mut transform) in &mut joints {
+            if joint.hand != hand {
+                continue;
+            }
+            *transform = match joint.kind {
+                XrArmJointKind::Shoulder => {
+                    Transform::from_translation(shoulder).looking_at(elbow, pole)
+                }
+                XrArmJointKind::Elbow => {
+                    Transform::from_translation(elbow).looking_at(wrist.translation, pole)
+                }
+            };
+        }
+    }
+}
+
+/// two bone ik, the elbow of an arm from `shoulder` to `wrist` bent toward `pole`. a wrist out of
+/// reach stretches the arm straight toward it
+pub fn solve_elbow(shoulder: Vec3, wrist: Vec3, pole: Vec3, upper: f32, lower: f32) -> Vec3 {
+    let to_wrist = wrist - shoulder;
+    let Some(direction) = to_wrist.try_normalize() else {
+        return shoulder + pole * upper;
+    };
+    let distance = to_wrist
+        .length()
+        .clamp((upper - lower).abs() + 0.001, upper + lower - 0.001);
+    //how far along the arm the elbow is, and how far it sticks out of it
+    let along = (upper * upper - lower * lower + distance * distance) / (2.0 * distance);
+    let out = (upper * upper - along * along).max(0.0).sqrt();
+    let bend = (pole - direction * pole.dot(direction))
+        .try_normalize()
+        .unwrap_or_else(|| direction.any_orthonormal_vector());
+    shoulder + direction * along + bend * out
+}
@@ -0,0 +1,403 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy::utils::HashMap;
+
+use crate::xr_init::xr_only;
+
+use super::arm_estimation::{XrArmEstimationPlugin, XrArmJoint, XrArmJointKind};
+use super::hands::HandBone;
+use super::trackers::{OpenXRHMD, XrTrackingRoot};
+use super::Hand;
+
+/// drives a humanoid rig, e.g. a gltf avatar, with the tracked head, the estimated arms from
+/// [`XrArmEstimationPlugin`], the hand bones and anything else tagged with an [`XrAvatarTarget`],
+/// like body tracking joints. put an [`XrAvatarRig`] on the entity the rig's scene spawns under,
+/// its bones are found by name once they exist. the rig stays in its rest pose until it's
+/// calibrated with [`CalibrateAvatar`], while the user holds that pose
+pub struct XrAvatarRigPlugin;
+
+impl Plugin for XrAvatarRigPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<XrArmEstimationPlugin>() {
+            app.add_plugins(XrArmEstimationPlugin);
+        }
+        app.add_event::<CalibrateAvatar>();
+        app.add_systems(
+            PostUpdate,
+            (
+                tag_avatar_targets,
+                bind_avatar_rigs,
+                calibrate_avatar_rigs,
+                drive_avatar_rigs,
+            )
+                .chain()
+                .run_if(xr_only())
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// a humanoid bone, the [`Hand`] is the side for the arms and legs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrAvatarBone {
+    Hips,
+    Spine,
+    Chest,
+    Neck,
+    Head,
+    Shoulder(Hand),
+    UpperArm(Hand),
+    LowerArm(Hand),
+    Hand(Hand),
+    Finger(Hand, HandBone),
+    UpperLeg(Hand),
+    LowerLeg(Hand),
+    Foot(Hand),
+}
+
+/// maps the bones of a rig to the names of its entities
+#[derive(Component, Clone, Debug)]
+pub struct XrAvatarRig {
+    pub bones: HashMap<XrAvatarBone, String>,
+    /// moves the rig under the head on the floor of the tracking space, turned where the head
+    /// looks
+    pub follow_head: bool,
+    /// scales the rig to the height of the user when calibrating
+    pub match_height: bool,
+}
+
+impl Default for XrAvatarRig {
+    fn default() -> Self {
+        Self {
+            bones: HashMap::new(),
+            follow_head: true,
+            match_height: true,
+        }
+    }
+}
+
+impl XrAvatarRig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_bone(mut self, bone: XrAvatarBone, name: impl Into<String>) -> Self {
+        self.bones.insert(bone, name.into());
+        self
+    }
+
+    /// the bone names of rigs exported from mixamo
+    pub fn mixamo() -> Self {
+        let mut rig = Self::new()
+            .with_bone(XrAvatarBone::Hips, "mixamorig:Hips")
+            .with_bone(XrAvatarBone::Spine, "mixamorig:Spine")
+            .with_bone(XrAvatarBone::Chest, "mixamorig:Spine2")
+            .with_bone(XrAvatarBone::Neck, "mixamorig:Neck")
+            .with_bone(XrAvatarBone::Head, "mixamorig:Head");
+        let fingers = [
+            (HandBone::ThumbMetacarpal, "Thumb1"),
+            (HandBone::ThumbProximal, "Thumb2"),
+            (HandBone::ThumbDistal, "Thumb3"),
+            (HandBone::IndexProximal, "Index1"),
+            (HandBone::IndexIntermediate, "Index2"),
+            (HandBone::IndexDistal, "Index3"),
+            (HandBone::MiddleProximal, "Middle1"),
+            (HandBone::MiddleIntermediate, "Middle2"),
+            (HandBone::MiddleDistal, "Middle3"),
+            (HandBone::RingProximal, "Ring1"),
+            (HandBone::RingIntermediate, "Ring2"),
+            (HandBone::RingDistal, "Ring3"),
+            (HandBone::LittleProximal, "Pinky1"),
+            (HandBone::LittleIntermediate, "Pinky2"),
+            (HandBone::LittleDistal, "Pinky3"),
+        ];
+        for (hand, side) in [(Hand::Left, "Left"), (Hand::Right, "Right")] {
+            rig = rig
+                .with_bone(
+                    XrAvatarBone::Shoulder(hand),
+                    format!("mixamorig:{side}Shoulder"),
+                )
+                .with_bone(XrAvatarBone::UpperArm(hand), format!("mixamorig:{side}Arm"))
+                .with_bone(
+                    XrAvatarBone::LowerArm(hand),
+                    format!("mixamorig:{side}ForeArm"),
+                )
+                .with_bone(XrAvatarBone::Hand(hand), format!("mixamorig:{side}Hand"))
+                .with_bone(
+                    XrAvatarBone::UpperLeg(hand),
+                    format!("mixamorig:{side}UpLeg"),
+                )
+                .with_bone(XrAvatarBone::LowerLeg(hand), format!("mixamorig:{side}Leg"))
+                .with_bone(XrAvatarBone::Foot(hand), format!("mixamorig:{side}Foot"));
+            for (bone, finger) in fingers {
+                rig = rig.with_bone(
+                    XrAvatarBone::Finger(hand, bone),
+                    format!("mixamorig:{side}Hand{finger}"),
+                );
+            }
+        }
+        rig
+    }
+}
+
+/// a tracked entity that drives a bone of every [`XrAvatarRig`], whatever its rotation is in
+/// the calibration pose becomes the bone's rest rotation
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrAvatarTarget(pub XrAvatarBone);
+
+/// the bones found for an [`XrAvatarRig`], parents before their children
+#[derive(Component, Clone, Debug)]
+pub struct XrAvatarRigBones {
+    pub bones: Vec<(XrAvatarBone, Entity)>,
+    /// the local transforms of the bones when they were found
+    pub rest: Vec<Transform>,
+    pub rest_scale: Vec3,
+}
+
+/// how the targets map onto a rig, from the last [`CalibrateAvatar`]
+#[derive(Component, Clone, Debug)]
+pub struct XrAvatarCalibration {
+    /// from the rotation of a target to the rotation of its bone
+    pub offsets: HashMap<XrAvatarBone, Quat>,
+    /// the rotation of the rig relative to where the head looked
+    pub facing: Quat,
+    /// the head bone relative to the rig
+    pub head: Vec3,
+    pub scale: f32,
+}
+
+/// calibrates every rig, the user should hold the rest pose of the rigs, e.g. a t-pose, while
+/// looking straight ahead
+#[derive(Event, Clone, Copy, Debug, Default)]
+pub struct CalibrateAvatar;
+
+#[allow(clippy::type_complexity)]
+pub fn tag_avatar_targets(
+    mut commands: Commands,
+    head: Query<Entity, (With<OpenXRHMD>, Without<XrAvatarTarget>)>,
+    arms: Query<(Entity, &XrArmJoint), Without<XrAvatarTarget>>,
+    hand_bones: Query<(Entity, &Hand, &HandBone), Without<XrAvatarTarget>>,
+) {
+    for entity in &head {
+        commands
+            .entity(entity)
+            .insert(XrAvatarTarget(XrAvatarBone::Head));
+    }
+    for (entity, joint) in &arms {
+        let bone = match joint.kind {
+            XrArmJointKind::Shoulder => XrAvatarBone::UpperArm(joint.hand),
+            XrArmJointKind::Elbow => XrAvatarBone::LowerArm(joint.hand),
+        };
+        commands.entity(entity).insert(XrAvatarTarget(bone));
+    }
+    for (entity, hand, hand_bone) in &hand_bones {
+        let bone = match hand_bone {
+            HandBone::Wrist => XrAvatarBone::Hand(*hand),
+            HandBone::Palm => continue,
+            _ => XrAvatarBone::Finger(*hand, *hand_bone),
+        };
+        commands.entity(entity).insert(XrAvatarTarget(bone));
+    }
+}
+
+pub fn bind_avatar_rigs(
+    mut commands: Commands,
+    rigs: Query<(Entity, &XrAvatarRig, &Transform), Without<XrAvatarRigBones>>,
+    children: Query<&Children>,
+    names: Query<(&Name, &Transform)>,
+) {
+    for (entity, rig, transform) in &rigs {
+        let names_to_bones: HashMap<&str, XrAvatarBone> = rig
+            .bones
+            .iter()
+            .map(|(bone, name)| (name.as_str(), *bone))
+            .collect();
+        let mut bones = Vec::new();
+        let mut rest = Vec::new();
+        //breadth first, so parents come before their children
+        let mut queue = VecDeque::from([entity]);
+        while let Some(current) = queue.pop_front() {
+            if let Ok((name, transform)) = names.get(current) {
+                if let Some(bone) = names_to_bones.get(name.as_str()) {
+                    bones.push((*bone, current));
+                    rest.push(*transform);
+                }
+            }
+            if let Ok(children) = children.get(current) {
+                queue.extend(children.iter());
+            }
+        }
+        //the scene hasn't spawned yet
+        if bones.is_empty() {
+            continue;
+        }
+        for (bone, name) in &rig.bones {
+            if !bones.iter().any(|(found, _)| found == bone) {
+                warn!("the avatar rig has no bone named {} for {:?}", name, bone);
+            }
+        }
+        commands.entity(entity).insert(XrAvatarRigBones {
+            bones,
+            rest,
+            rest_scale: transform.scale,
+        });
+    }
+}
+
+pub fn calibrate_avatar_rigs(
+    mut commands: Commands,
+    mut events: EventReader<CalibrateAvatar>,
+    rigs: Query<(Entity, &XrAvatarRig, &XrAvatarRigBones)>,
+    targets: Query<(Entity, &XrAvatarTarget)>,
+    tracking_root: Query<Entity, With<XrTrackingRoot>>,
+    mut transforms: Query<(&mut Transform, Option<&Parent>)>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    let targets = collect_targets(&targets);
+    let Some(&head) = targets.get(&XrAvatarBone::Head) else {
+        warn!("can't calibrate the avatar rigs without a tracked head");
+        return;
+    };
+    let head = global_transform(head, &transforms);
+    let yaw = heading(&head);
+    let floor = tracking_root.get_single().map_or(0.0, |root| {
+        global_transform(root, &transforms).translation.y
+    });
+    for (entity, rig, rig_bones) in &rigs {
+        //measure the rig in its rest pose
+        if let Ok((mut transform, _)) = transforms.get_mut(entity) {
+            transform.scale = rig_bones.rest_scale;
+        }
+        for ((_, bone_entity), rest) in rig_bones.bones.iter().zip(&rig_bones.rest) {
+            if let Ok((mut transform, _)) = transforms.get_mut(*bone_entity) {
+                *transform = *rest;
+            }
+        }
+        let root = global_transform(entity, &transforms);
+        let head_bone = rig_bones
+            .bones
+            .iter()
+            .find(|(bone, _)| *bone == XrAvatarBone::Head)
+            .map(|(_, entity)| global_transform(*entity, &transforms).translation)
+            .unwrap_or(root.translation);
+        let mut scale = 1.0;
+        let rig_height = head_bone.y - root.translation.y;
+        let user_height = head.translation.y - floor;
+        if rig.match_height && rig_height > 0.0 && user_height > 0.0 {
+            scale = user_height / rig_height;
+        }
+        let mut offsets = HashMap::new();
+        for (bone, bone_entity) in &rig_bones.bones {
+            let Some(&target) = targets.get(bone) else {
+                continue;
+            };
+            let target = global_transform(target, &transforms);
+            let rotation = global_transform(*bone_entity, &transforms).rotation;
+            offsets.insert(*bone, target.rotation.inverse() * rotation);
+        }
+        commands.entity(entity).insert(XrAvatarCalibration {
+            offsets,
+            facing: yaw.inverse() * root.rotation,
+            head: root.compute_matrix().inverse().transform_point3(head_bone),
+            scale,
+        });
+    }
+}
+
+pub fn drive_avatar_rigs(
+    rigs: Query<(
+        Entity,
+        &XrAvatarRig,
+        &XrAvatarRigBones,
+        &XrAvatarCalibration,
+    )>,
+    targets: Query<(Entity, &XrAvatarTarget)>,
+    tracking_root: Query<Entity, With<XrTrackingRoot>>,
+    mut transforms: Query<(&mut Transform, Option<&Parent>)>,
+) {
+    let targets = collect_targets(&targets);
+    let floor = tracking_root.get_single().map_or(0.0, |root| {
+        global_transform(root, &transforms).translation.y
+    });
+    for (entity, rig, rig_bones, calibration) in &rigs {
+        let head = targets.get(&XrAvatarBone::Head).filter(|_| rig.follow_head);
+        if let Some(&head) = head {
+            let head = global_transform(head, &transforms);
+            let mut root = Transform {
+                translation: Vec3::ZERO,
+                rotation: heading(&head) * calibration.facing,
+                scale: rig_bones.rest_scale * calibration.scale,
+            };
+            let offset = root.transform_point(calibration.head);
+            root.translation = Vec3::new(
+                head.translation.x - offset.x,
+                floor,
+                head.translation.z - offset.z,
+            );
+            set_global_transform(entity, root, &mut transforms);
+        }
+        for (bone, bone_entity) in &rig_bones.bones {
+            let (Some(&target), Some(offset)) = (targets.get(bone), calibration.offsets.get(bone))
+            else {
+                continue;
+            };
+            let rotation = global_transform(target, &transforms).rotation * *offset;
+            let mut global = global_transform(*bone_entity, &transforms);
+            global.rotation = rotation;
+            set_global_transform(*bone_entity, global, &mut transforms);
+        }
+    }
+}
+
+fn collect_targets(targets: &Query<(Entity, &XrAvatarTarget)>) -> HashMap<XrAvatarBone, Entity> {
+    targets
+        .iter()
+        .map(|(entity, target)| (target.0, entity))
+        .collect()
+}
+
+/// the yaw of the head, looking up or down doesn't turn the body
+fn heading(head: &Transform) -> Quat {
+    let forward = head.forward();
+    let forward = Vec3::new(forward.x, 0.0, forward.z)
+        .try_normalize()
+        .unwrap_or(Vec3::NEG_Z);
+    Transform::IDENTITY.looking_to(forward, Vec3::Y).rotation
+}
+
+/// the global transform of this frame, `GlobalTransform` is only updated after this runs
+fn global_transform(
+    entity: Entity,
+    transforms: &Query<(&mut Transform, Option<&Parent>)>,
+) -> Transform {
+    let mut global = Transform::IDENTITY;
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        let Ok((transform, parent)) = transforms.get(entity) else {
+            break;
+        };
+        global = transform.mul_transform(global);
+        current = parent.map(|parent| parent.get());
+    }
+    global
+}
+
+fn set_global_transform(
+    entity: Entity,
+    global: Transform,
+    transforms: &mut Query<(&mut Transform, Option<&Parent>)>,
+) {
+    let parent = match transforms.get(entity) {
+        Ok((_, Some(parent))) => global_transform(parent.get(), transforms),
+        Ok((_, None)) => Transform::IDENTITY,
+        Err(_) => return,
+    };
+    let local = Transform::from_matrix(parent.compute_matrix().inverse() * global.compute_matrix());
+    if let Ok((mut transform, _)) = transforms.get_mut(entity) {
+        *transform = local;
+    }
+}
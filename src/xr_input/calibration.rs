@@ -0,0 +1,193 @@
+#[cfg(feature = "serialize")]
+use std::fs;
+#[cfg(feature = "serialize")]
+use std::io;
+#[cfg(feature = "serialize")]
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+
+use crate::xr_init::xr_only;
+
+use super::trackers::{OpenXRHMD, XrTrackingRoot};
+
+/// a user calibration applied to the tracking root on top of whatever the app does with it, for
+/// seated experiences and for players that can't stand. with a file (and the `serialize`
+/// feature) the calibration is loaded on startup and saved as json whenever it changes
+#[derive(Default)]
+pub struct XrCalibrationPlugin {
+    #[cfg(feature = "serialize")]
+    pub file: Option<PathBuf>,
+}
+
+impl Plugin for XrCalibrationPlugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "serialize")]
+        let calibration = match &self.file {
+            Some(file) => match XrCalibration::load(file) {
+                Ok(calibration) => calibration,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => default(),
+                Err(err) => {
+                    warn!(
+                        "couldn't load the calibration from {}: {}",
+                        file.display(),
+                        err
+                    );
+                    default()
+                }
+            },
+            None => default(),
+        };
+        #[cfg(not(feature = "serialize"))]
+        let calibration = XrCalibration::default();
+        app.insert_resource(calibration);
+        #[cfg(feature = "serialize")]
+        if let Some(file) = &self.file {
+            app.insert_resource(XrCalibrationFile(file.clone()));
+        }
+        app.add_event::<XrCalibrationRequest>();
+        app.add_systems(
+            PreUpdate,
+            (handle_calibration_requests, apply_xr_calibration)
+                .chain()
+                .run_if(xr_only()),
+        );
+        #[cfg(feature = "serialize")]
+        app.add_systems(
+            PreUpdate,
+            save_xr_calibration
+                .after(apply_xr_calibration)
+                .run_if(xr_only()),
+        );
+    }
+}
+
+/// where the calibration is saved
+#[cfg(feature = "serialize")]
+#[derive(Resource, Clone, Debug)]
+pub struct XrCalibrationFile(pub PathBuf);
+
+/// moves the tracking space by `translation` (meters) after turning it by `yaw` (radians)
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct XrCalibration {
+    pub translation: Vec3,
+    pub yaw: f32,
+}
+
+/// captures a calibration from the current head pose
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub enum XrCalibrationRequest {
+    /// puts the head at the origin at `eye_height` facing -z, for seated simulators
+    Seated {
+        eye_height: f32,
+    },
+    /// only moves the floor so the head is at `eye_height`, e.g. to play standing height
+    /// experiences from a wheelchair
+    Standing {
+        eye_height: f32,
+    },
+    Reset,
+}
+
+impl XrCalibration {
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.translation).with_rotation(Quat::from_rotation_y(self.yaw))
+    }
+
+    /// `head` is the head pose in the uncalibrated tracking space
+    pub fn seated(head: &Transform, eye_height: f32) -> Self {
+        let forward = head.forward();
+        let head_yaw = Vec3::new(forward.x, 0.0, forward.z)
+            .try_normalize()
+            .map(|forward| (-forward.x).atan2(-forward.z))
+            .unwrap_or_default();
+        let yaw = -head_yaw;
+        Self {
+            translation: Vec3::Y * eye_height - Quat::from_rotation_y(yaw) * head.translation,
+            yaw,
+        }
+    }
+
+    /// `head` is the head pose in the uncalibrated tracking space
+    pub fn standing(head: &Transform, eye_height: f32) -> Self {
+        Self {
+            translation: Vec3::Y * (eye_height - head.translation.y),
+            yaw: 0.0,
+        }
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl XrCalibration {
+    /// saves the calibration as json
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, text)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+pub fn handle_calibration_requests(
+    mut requests: EventReader<XrCalibrationRequest>,
+    mut calibration: ResMut<XrCalibration>,
+    hmd: Query<&Transform, With<OpenXRHMD>>,
+) {
+    for request in requests.read() {
+        let Ok(head) = hmd.get_single() else {
+            warn!("can't calibrate without a head");
+            continue;
+        };
+        //the hmd is a child of the root, so its transform is still in the uncalibrated space
+        *calibration = match *request {
+            XrCalibrationRequest::Seated { eye_height } => XrCalibration::seated(head, eye_height),
+            XrCalibrationRequest::Standing { eye_height } => {
+                XrCalibration::standing(head, eye_height)
+            }
+            XrCalibrationRequest::Reset => default(),
+        };
+    }
+}
+
+pub fn apply_xr_calibration(
+    calibration: Res<XrCalibration>,
+    mut root: Query<&mut Transform, With<XrTrackingRoot>>,
+    //the calibration that is part of the root transform right now
+    mut applied: Local<Option<Transform>>,
+) {
+    let Ok(mut root) = root.get_single_mut() else {
+        return;
+    };
+    let target = calibration.transform();
+    let current = applied.unwrap_or_default();
+    if applied.is_some() && current == target {
+        return;
+    }
+    //swap the old calibration for the new one, keeping what the app did to the root
+    let app_root = root.compute_matrix() * current.compute_matrix().inverse();
+    *root = Transform::from_matrix(app_root).mul_transform(target);
+    *applied = Some(target);
+}
+
+#[cfg(feature = "serialize")]
+pub fn save_xr_calibration(calibration: Res<XrCalibration>, file: Option<Res<XrCalibrationFile>>) {
+    let Some(file) = file else {
+        return;
+    };
+    if !calibration.is_changed() || calibration.is_added() {
+        return;
+    }
+    if let Err(err) = calibration.save(&file.0) {
+        warn!(
+            "couldn't save the calibration to {}: {}",
+            file.0.display(),
+            err
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::xr_init::xr_only;
+
+use super::trackers::{
+    AimPose, OpenXRLeftController, OpenXRRightController, XrTrackingRoot, XrTrackingState,
+};
+use super::Hand;
+
+/// keeps entities with a [`ControllerAttached`] at a pose relative to a controller, like a watch
+/// menu, an ammo counter or a tool palette. they can follow lazily, trailing behind the
+/// controller instead of being rigidly fixed to it
+pub struct ControllerAttachedPlugin;
+
+impl Plugin for ControllerAttachedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            update_controller_attached
+                .run_if(xr_only())
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// the pose of the controller an entity is attached to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ControllerPose {
+    /// the pose of the hand holding the controller
+    #[default]
+    Grip,
+    /// the pose the controller points with, forward is -z
+    Aim,
+}
+
+/// attaches the entity to a controller. the transform is set in world space, so the entity must
+/// not have a parent
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ControllerAttached {
+    pub hand: Hand,
+    /// the pose relative to the controller
+    pub offset: Transform,
+    pub pose: ControllerPose,
+    /// seconds until the entity has caught up halfway with the controller, 0 follows it rigidly
+    pub follow_half_life: f32,
+    /// hides the entity while the controller isn't tracked
+    pub hide_when_untracked: bool,
+}
+
+impl ControllerAttached {
+    pub fn new(hand: Hand) -> Self {
+        Self {
+            hand,
+            offset: Transform::IDENTITY,
+            pose: ControllerPose::Grip,
+            follow_half_life: 0.0,
+            hide_when_untracked: false,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: Transform) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_pose(mut self, pose: ControllerPose) -> Self {
+        self.pose = pose;
+        self
+    }
+
+    /// follows the controller lazily, see [`ControllerAttached::follow_half_life`]
+    pub fn lazy(mut self, half_life: f32) -> Self {
+        self.follow_half_life = half_life;
+        self
+    }
+
+    pub fn hide_when_untracked(mut self) -> Self {
+        self.hide_when_untracked = true;
+        self
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_controller_attached(
+    time: Res<Time>,
+    mut attached: Query<(
+        Ref<ControllerAttached>,
+        &mut Transform,
+        Option<&mut Visibility>,
+    )>,
+    root: Query<&Transform, (With<XrTrackingRoot>, Without<ControllerAttached>)>,
+    left_controller: Query<
+        (&Transform, Option<&AimPose>, Option<&XrTrackingState>),
+        (With<OpenXRLeftController>, Without<ControllerAttached>),
+    >,
+    right_controller: Query<
+        (&Transform, Option<&AimPose>, Option<&XrTrackingState>),
+        (With<OpenXRRightController>, Without<ControllerAttached>),
+    >,
+) {
+    let root = root.get_single().copied().unwrap_or_default();
+    let dt = time.delta_seconds();
+    for (attached, mut transform, visibility) in attached.iter_mut() {
+        let controller = match attached.hand {
+            Hand::Left => left_controller.get_single(),
+            Hand::Right => right_controller.get_single(),
+        };
+        let Ok((grip, aim, state)) = controller else {
+            continue;
+        };
+        let tracked = state.map_or(true, |state| state.is_valid());
+        if let (true, Some(mut visibility)) = (attached.hide_when_untracked, visibility) {
+            let wanted = match tracked {
+                true => Visibility::Inherited,
+                false => Visibility::Hidden,
+            };
+            if *visibility != wanted {
+                *visibility = wanted;
+            }
+        }
+        //an untracked controller keeps its last pose, so does the entity
+        if !tracked {
+            continue;
+        }
+        //controllers are children of the root
+        let pose = match (attached.pose, aim) {
+            (ControllerPose::Aim, Some(aim)) => aim.0,
+            _ => *grip,
+        };
+        let target = root.mul_transform(pose).mul_transform(attached.offset);
+        //a lazy entity that was just attached starts at its place instead of flying in
+        if attached.follow_half_life <= 0.0 || attached.is_added() {
+            *transform = target;
+            continue;
+        }
+        let t = 1.0 - 0.5f32.powf(dt / attached.follow_half_life);
+        transform.translation = transform.translation.lerp(target.translation, t);
+        transform.rotation = transform.rotation.slerp(target.rotation, t);
+        transform.scale = target.scale;
+    }
+}
@@ -10,5 +10,14 @@ pub struct Handed<T> {
 }
 #[derive(Copy, Clone)]
 pub enum XrControllerType {
+    /// the oculus touch action set, also bound on vive, valve index and windows mixed reality
+    /// controllers and the khr simple controller as a fallback. which one the runtime picked is in
+    /// `XrInteractionProfiles`
     OculusTouch,
+    /// the oculus touch action set plus the inputs only valve index controllers have, see
+    /// [`XrValveIndexPlugin`](crate::xr_input::valve_index::XrValveIndexPlugin)
+    ValveIndex,
+    /// the oculus touch action set plus the inputs of quest touch pro and touch plus
+    /// controllers, see [`XrTouchProPlugin`](crate::xr_input::touch_pro::XrTouchProPlugin)
+    TouchPro,
 }
@@ -18,7 +18,7 @@ use crate::xr_input::{
 
 use super::{
     actions::XrActionSets,
-    trackers::{OpenXRLeftController, OpenXRRightController, OpenXRTrackingRoot},
+    trackers::{OpenXRLeftController, OpenXRRightController, XrTrackingRoot},
 };
 
 /// add debug renderer for controllers
@@ -41,7 +41,7 @@ pub fn draw_gizmos(
     tracking_root_query: Query<
         &mut Transform,
         (
-            With<OpenXRTrackingRoot>,
+            With<XrTrackingRoot>,
             Without<OpenXRLeftController>,
             Without<OpenXRRightController>,
         ),
@@ -51,7 +51,7 @@ pub fn draw_gizmos(
         (
             With<OpenXRLeftController>,
             Without<OpenXRRightController>,
-            Without<OpenXRTrackingRoot>,
+            Without<XrTrackingRoot>,
         ),
     >,
     right_controller_query: Query<
@@ -59,7 +59,7 @@ pub fn draw_gizmos(
         (
             With<OpenXRRightController>,
             Without<OpenXRLeftController>,
-            Without<OpenXRTrackingRoot>,
+            Without<XrTrackingRoot>,
         ),
     >,
     action_sets: Res<XrActionSets>,
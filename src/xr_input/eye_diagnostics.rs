@@ -0,0 +1,390 @@
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bevy::core_pipeline::core_3d::{self, AlphaMask3d, Opaque3d, Transparent3d};
+use bevy::diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic};
+use bevy::ecs::query::QueryItem;
+use bevy::pbr::{RenderMeshInstances, Shadow, ViewLightEntities};
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponentPlugin;
+use bevy::render::mesh::GpuBufferInfo;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_phase::{PhaseItem, RenderPhase};
+use bevy::render::renderer::{render_system, RenderContext, RenderDevice, RenderQueue};
+use bevy::render::{Render, RenderApp, RenderSet};
+
+use super::xr_camera::{Eye, XrCameraType};
+
+/// draw calls and triangles of one eye camera in the last rendered frame
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EyeRenderStats {
+    /// draw calls of the main passes (opaque, alpha mask and transparent)
+    pub draw_calls: u32,
+    /// triangles of the main passes
+    pub triangles: u64,
+    /// draw calls of the shadow passes of the lights this eye sees
+    pub shadow_draw_calls: u32,
+    /// gpu time from the start of the prepasses to the end of the main passes, a few frames
+    /// late. `None` when the device has no timestamp queries
+    pub pass_duration: Option<Duration>,
+}
+
+/// the stats of both eyes, shared between the main and the render world
+#[derive(Resource, Clone, Default)]
+pub struct XrEyeRenderStats(Arc<Mutex<[EyeRenderStats; 2]>>);
+
+impl XrEyeRenderStats {
+    pub fn get(&self, eye: Eye) -> EyeRenderStats {
+        self.0.lock().unwrap()[eye as usize]
+    }
+}
+
+/// adds diagnostics for the draw calls, triangles and pass durations of each eye, to check
+/// whether the eyes actually share work in a scene. the durations are measured with timestamp
+/// queries, which are only enabled when the adapter supports them
+pub struct XrEyeRenderDiagnosticsPlugin;
+
+impl XrEyeRenderDiagnosticsPlugin {
+    pub const LEFT_DRAW_CALLS: DiagnosticId =
+        DiagnosticId::from_u128(0x6f1c_5d7e_20a4_4c43_9a8e_71e1_0b39_2d01);
+    pub const RIGHT_DRAW_CALLS: DiagnosticId =
+        DiagnosticId::from_u128(0x6f1c_5d7e_20a4_4c43_9a8e_71e1_0b39_2d02);
+    pub const LEFT_TRIANGLES: DiagnosticId =
+        DiagnosticId::from_u128(0x6f1c_5d7e_20a4_4c43_9a8e_71e1_0b39_2d03);
+    pub const RIGHT_TRIANGLES: DiagnosticId =
+        DiagnosticId::from_u128(0x6f1c_5d7e_20a4_4c43_9a8e_71e1_0b39_2d04);
+    pub const LEFT_SHADOW_DRAW_CALLS: DiagnosticId =
+        DiagnosticId::from_u128(0x6f1c_5d7e_20a4_4c43_9a8e_71e1_0b39_2d05);
+    pub const RIGHT_SHADOW_DRAW_CALLS: DiagnosticId =
+        DiagnosticId::from_u128(0x6f1c_5d7e_20a4_4c43_9a8e_71e1_0b39_2d06);
+    /// in milliseconds
+    pub const LEFT_PASS_DURATION: DiagnosticId =
+        DiagnosticId::from_u128(0x6f1c_5d7e_20a4_4c43_9a8e_71e1_0b39_2d07);
+    /// in milliseconds
+    pub const RIGHT_PASS_DURATION: DiagnosticId =
+        DiagnosticId::from_u128(0x6f1c_5d7e_20a4_4c43_9a8e_71e1_0b39_2d08);
+}
+
+const BEGIN_EYE_PASS_TIMESTAMP: &str = "xr_begin_eye_pass_timestamp";
+const END_EYE_PASS_TIMESTAMP: &str = "xr_end_eye_pass_timestamp";
+
+impl Plugin for XrEyeRenderDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        let stats = XrEyeRenderStats::default();
+        app.insert_resource(stats.clone());
+        if !app.is_plugin_added::<ExtractComponentPlugin<XrCameraType>>() {
+            app.add_plugins(ExtractComponentPlugin::<XrCameraType>::default());
+        }
+        for (id, name) in [
+            (Self::LEFT_DRAW_CALLS, "xr_left_eye_draw_calls"),
+            (Self::RIGHT_DRAW_CALLS, "xr_right_eye_draw_calls"),
+            (Self::LEFT_TRIANGLES, "xr_left_eye_triangles"),
+            (Self::RIGHT_TRIANGLES, "xr_right_eye_triangles"),
+            (
+                Self::LEFT_SHADOW_DRAW_CALLS,
+                "xr_left_eye_shadow_draw_calls",
+            ),
+            (
+                Self::RIGHT_SHADOW_DRAW_CALLS,
+                "xr_right_eye_shadow_draw_calls",
+            ),
+            (Self::LEFT_PASS_DURATION, "xr_left_eye_pass_ms"),
+            (Self::RIGHT_PASS_DURATION, "xr_right_eye_pass_ms"),
+        ] {
+            app.register_diagnostic(Diagnostic::new(id, name, 20));
+        }
+        app.add_systems(Update, update_eye_render_diagnostics);
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.insert_resource(stats);
+        render_app.add_systems(
+            Render,
+            (
+                collect_eye_render_stats.before(render_system),
+                read_eye_pass_durations.after(render_system),
+            )
+                .in_set(RenderSet::Render),
+        );
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<EyePassTimestampNode<false>>>(
+                core_3d::graph::NAME,
+                BEGIN_EYE_PASS_TIMESTAMP,
+            )
+            .add_render_graph_node::<ViewNodeRunner<EyePassTimestampNode<true>>>(
+                core_3d::graph::NAME,
+                END_EYE_PASS_TIMESTAMP,
+            )
+            .add_render_graph_edge(
+                core_3d::graph::NAME,
+                BEGIN_EYE_PASS_TIMESTAMP,
+                core_3d::graph::node::PREPASS,
+            )
+            .add_render_graph_edges(
+                core_3d::graph::NAME,
+                &[
+                    core_3d::graph::node::END_MAIN_PASS,
+                    END_EYE_PASS_TIMESTAMP,
+                    core_3d::graph::node::TONEMAPPING,
+                ],
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        let render_device = render_app.world.resource::<RenderDevice>();
+        if !render_device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+        {
+            return;
+        }
+        let timer = EyePassTimer::new(
+            render_device,
+            render_app
+                .world
+                .resource::<RenderQueue>()
+                .get_timestamp_period(),
+        );
+        render_app.insert_resource(timer);
+    }
+}
+
+fn update_eye_render_diagnostics(stats: Res<XrEyeRenderStats>, mut diagnostics: Diagnostics) {
+    let [left, right] = *stats.0.lock().unwrap();
+    use XrEyeRenderDiagnosticsPlugin as P;
+    diagnostics.add_measurement(P::LEFT_DRAW_CALLS, || left.draw_calls as f64);
+    diagnostics.add_measurement(P::RIGHT_DRAW_CALLS, || right.draw_calls as f64);
+    diagnostics.add_measurement(P::LEFT_TRIANGLES, || left.triangles as f64);
+    diagnostics.add_measurement(P::RIGHT_TRIANGLES, || right.triangles as f64);
+    diagnostics.add_measurement(P::LEFT_SHADOW_DRAW_CALLS, || left.shadow_draw_calls as f64);
+    diagnostics.add_measurement(P::RIGHT_SHADOW_DRAW_CALLS, || {
+        right.shadow_draw_calls as f64
+    });
+    if let Some(duration) = left.pass_duration {
+        diagnostics.add_measurement(P::LEFT_PASS_DURATION, || duration.as_secs_f64() * 1000.0);
+    }
+    if let Some(duration) = right.pass_duration {
+        diagnostics.add_measurement(P::RIGHT_PASS_DURATION, || duration.as_secs_f64() * 1000.0);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn collect_eye_render_stats(
+    stats: Res<XrEyeRenderStats>,
+    views: Query<(
+        &XrCameraType,
+        Option<&RenderPhase<Opaque3d>>,
+        Option<&RenderPhase<AlphaMask3d>>,
+        Option<&RenderPhase<Transparent3d>>,
+        Option<&ViewLightEntities>,
+    )>,
+    shadow_phases: Query<&RenderPhase<Shadow>>,
+    mesh_instances: Res<RenderMeshInstances>,
+    meshes: Res<RenderAssets<Mesh>>,
+) {
+    let mut eye_stats = [EyeRenderStats::default(); 2];
+    for (camera_type, opaque, alpha_mask, transparent, lights) in views.iter() {
+        let XrCameraType::Xr(eye) = camera_type else {
+            continue;
+        };
+        let eye_stats = &mut eye_stats[*eye as usize];
+        let triangles = |entity: Entity| -> u64 {
+            let Some(mesh) = mesh_instances
+                .get(&entity)
+                .and_then(|instance| meshes.get(instance.mesh_asset_id))
+            else {
+                return 0;
+            };
+            let count = match &mesh.buffer_info {
+                GpuBufferInfo::Indexed { count, .. } => *count,
+                GpuBufferInfo::NonIndexed => mesh.vertex_count,
+            };
+            count as u64 / 3
+        };
+        if let Some(phase) = opaque {
+            count_phase(&phase.items, eye_stats, triangles);
+        }
+        if let Some(phase) = alpha_mask {
+            count_phase(&phase.items, eye_stats, triangles);
+        }
+        if let Some(phase) = transparent {
+            count_phase(&phase.items, eye_stats, triangles);
+        }
+        for light in lights.iter().flat_map(|lights| lights.lights.iter()) {
+            if let Ok(phase) = shadow_phases.get(*light) {
+                eye_stats.shadow_draw_calls +=
+                    draw_calls(phase.items.iter().map(|i| i.batch_range()));
+            }
+        }
+    }
+    //the durations come from `read_eye_pass_durations`
+    for (stats, eye_stats) in stats.0.lock().unwrap().iter_mut().zip(eye_stats) {
+        *stats = EyeRenderStats {
+            pass_duration: stats.pass_duration,
+            ..eye_stats
+        };
+    }
+}
+
+fn count_phase<I: PhaseItem>(
+    items: &[I],
+    stats: &mut EyeRenderStats,
+    triangles: impl Fn(Entity) -> u64,
+) {
+    stats.draw_calls += draw_calls(items.iter().map(|i| i.batch_range()));
+    stats.triangles += items.iter().map(|i| triangles(i.entity())).sum::<u64>();
+}
+
+// batched items get an empty batch range, only the first item of a batch is drawn
+fn draw_calls<'a>(ranges: impl Iterator<Item = &'a Range<u32>>) -> u32 {
+    ranges.filter(|range| !range.is_empty()).count() as u32
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Readback {
+    Idle,
+    Mapping,
+    Mapped,
+}
+
+/// two timestamps per eye, resolved and copied into a mapped buffer. no timestamps are written
+/// while the buffer is mapped, so a duration can be from a few frames ago
+#[derive(Resource)]
+struct EyePassTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// nanoseconds per tick
+    period: f32,
+    // the eyes whose timestamps are in the readback buffer
+    written: Mutex<[bool; 2]>,
+    readback: Arc<Mutex<Readback>>,
+}
+
+impl EyePassTimer {
+    fn new(render_device: &RenderDevice, period: f32) -> Self {
+        let device = render_device.wgpu_device();
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("xr_eye_pass_timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: 4,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("xr_eye_pass_timestamps_resolve"),
+                size: 2 * wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("xr_eye_pass_timestamps_readback"),
+                size: 4 * wgpu::QUERY_SIZE as u64,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            period,
+            written: Mutex::new([false; 2]),
+            readback: Arc::new(Mutex::new(Readback::Idle)),
+        }
+    }
+}
+
+/// writes the timestamp before or after the passes of an eye, the end also resolves them
+#[derive(Default)]
+struct EyePassTimestampNode<const END: bool>;
+
+impl<const END: bool> ViewNode for EyePassTimestampNode<END> {
+    type ViewQuery = &'static XrCameraType;
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        camera_type: QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let (XrCameraType::Xr(eye), Some(timer)) =
+            (camera_type, world.get_resource::<EyePassTimer>())
+        else {
+            return Ok(());
+        };
+        if *timer.readback.lock().unwrap() != Readback::Idle {
+            return Ok(());
+        }
+        let eye = *eye as u32;
+        let encoder = render_context.command_encoder();
+        encoder.write_timestamp(&timer.query_set, eye * 2 + END as u32);
+        if END {
+            let size = 2 * wgpu::QUERY_SIZE as u64;
+            encoder.resolve_query_set(
+                &timer.query_set,
+                eye * 2..eye * 2 + 2,
+                &timer.resolve_buffer,
+                eye as u64 * wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT,
+            );
+            encoder.copy_buffer_to_buffer(
+                &timer.resolve_buffer,
+                eye as u64 * wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT,
+                &timer.readback_buffer,
+                eye as u64 * size,
+                size,
+            );
+            timer.written.lock().unwrap()[eye as usize] = true;
+        }
+        Ok(())
+    }
+}
+
+fn read_eye_pass_durations(timer: Option<Res<EyePassTimer>>, stats: Res<XrEyeRenderStats>) {
+    let Some(timer) = timer else {
+        return;
+    };
+    let readback = *timer.readback.lock().unwrap();
+    let mut written = timer.written.lock().unwrap();
+    match readback {
+        Readback::Idle if written.contains(&true) => {
+            //the copy was submitted by `render_system`, the mapping finishes on a later submit
+            *timer.readback.lock().unwrap() = Readback::Mapping;
+            let state = timer.readback.clone();
+            timer
+                .readback_buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    *state.lock().unwrap() = match result {
+                        Ok(()) => Readback::Mapped,
+                        Err(_) => Readback::Idle,
+                    };
+                });
+        }
+        Readback::Mapped => {
+            let timestamps: Vec<u64> = timer
+                .readback_buffer
+                .slice(..)
+                .get_mapped_range()
+                .chunks_exact(8)
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+                .collect();
+            timer.readback_buffer.unmap();
+            let mut stats = stats.0.lock().unwrap();
+            for (eye, stats) in stats.iter_mut().enumerate() {
+                if written[eye] {
+                    let ticks = timestamps[eye * 2 + 1].saturating_sub(timestamps[eye * 2]);
+                    stats.pass_duration = Some(Duration::from_nanos(
+                        (ticks as f64 * timer.period as f64) as u64,
+                    ));
+                }
+            }
+            *written = [false; 2];
+            *timer.readback.lock().unwrap() = Readback::Idle;
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+use super::views::XrView;
+
+/// [`XrIpdChanged`] is only sent when the ipd moved more than this, in meters. the located eyes
+/// jitter a little every frame
+const IPD_CHANGE_THRESHOLD: f32 = 0.0005;
+
+/// the eye positions of the user, derived from the located views every frame. all values are in
+/// meters in the tracking space, so the world scale doesn't change them
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrEyeMetrics {
+    /// interpupillary distance
+    pub ipd: f32,
+    /// the offset of the left eye from the center between the eyes, in head space
+    pub left_offset: Vec3,
+    /// the offset of the right eye from the center between the eyes, in head space
+    pub right_offset: Vec3,
+}
+
+/// sent when the ipd changed, e.g. when the user moved the lens slider or someone else put the
+/// headset on
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct XrIpdChanged {
+    pub previous: f32,
+    pub ipd: f32,
+}
+
+pub fn update_xr_eye_metrics(
+    views: Query<(&XrView, &Transform)>,
+    mut metrics: ResMut<XrEyeMetrics>,
+    mut events: EventWriter<XrIpdChanged>,
+    //the ipd the last event was sent for, small changes add up until they pass the threshold
+    mut reported: Local<Option<f32>>,
+) {
+    let find = |index| views.iter().find(|(view, _)| view.index == index);
+    let (Some((_, left)), Some((_, right))) = (find(0), find(1)) else {
+        return;
+    };
+    //the views aren't located yet
+    if left.translation == right.translation {
+        return;
+    }
+    let center = (left.translation + right.translation) / 2.0;
+    let head_rotation = left.rotation.slerp(right.rotation, 0.5).inverse();
+    let new_metrics = XrEyeMetrics {
+        ipd: left.translation.distance(right.translation),
+        left_offset: head_rotation * (left.translation - center),
+        right_offset: head_rotation * (right.translation - center),
+    };
+    if *metrics != new_metrics {
+        *metrics = new_metrics;
+    }
+    match *reported {
+        Some(previous) if (previous - new_metrics.ipd).abs() <= IPD_CHANGE_THRESHOLD => {}
+        previous => {
+            //the first ipd isn't a change, apps read it from the resource
+            if let Some(previous) = previous {
+                events.send(XrIpdChanged {
+                    previous,
+                    ipd: new_metrics.ipd,
+                });
+            }
+            *reported = Some(new_metrics.ipd);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::capabilities::XrCapabilities;
+use crate::convert::PosefConv;
+use crate::input::XrInput;
+use crate::resources::{XrFrameState, XrInstance, XrSession};
+use crate::xr_init::{xr_only, XrPostSetup, XrSetup};
+
+use super::action_manifest::XrActionSetManifest;
+use super::actions::{ActionType, SetupActionSets, XrActionSets, XrBinding, XrSyncActions};
+use super::trackers::XrTrackingState;
+
+const ACTION_SET: &str = "eye_gaze";
+const ACTION: &str = "gaze_pose";
+const PROFILE: &str = "/interaction_profiles/ext/eye_gaze_interaction";
+
+/// reads where the user looks through XR_EXT_eye_gaze_interaction, which is enabled whenever
+/// the runtime offers it, into [`XrEyeGaze`]. on systems without eye tracking the gaze stays
+/// inactive. some runtimes, like the quest's, only report a gaze once the user granted the app
+/// the eye tracking permission
+pub struct EyeTrackingPlugin;
+
+impl Plugin for EyeTrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrEyeGaze>();
+        app.add_systems(XrSetup, setup_eye_gaze_actions);
+        app.add_systems(XrPostSetup, setup_eye_gaze_space);
+        app.add_systems(
+            PreUpdate,
+            update_xr_eye_gaze.run_if(xr_only()).after(XrSyncActions),
+        );
+    }
+}
+
+/// the combined gaze of both eyes this frame, in the stage space like the controllers. the gaze
+/// looks along the forward (-z) of the pose
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrEyeGaze {
+    /// the runtime tracks the eyes and reports a gaze to the app
+    pub active: bool,
+    /// the last valid pose, relative to the tracking root
+    pub pose: Transform,
+    pub tracking: XrTrackingState,
+}
+
+impl XrEyeGaze {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        instance.exts().ext_eye_gaze_interaction.is_some()
+    }
+
+    /// the gaze ray in the tracking space, `None` while the eyes aren't tracked
+    pub fn ray(&self) -> Option<Ray> {
+        (self.active && self.tracking.is_valid()).then(|| Ray {
+            origin: self.pose.translation,
+            direction: self.pose.forward(),
+        })
+    }
+}
+
+/// the action space of the gaze pose
+#[derive(Resource)]
+pub struct XrEyeGazeSpace(pub xr::Space);
+
+pub fn setup_eye_gaze_actions(
+    instance: Res<XrInstance>,
+    capabilities: Option<Res<XrCapabilities>>,
+    mut action_sets: ResMut<SetupActionSets>,
+) {
+    //suggesting the profile without the extension fails
+    if !XrEyeGaze::is_supported(&instance) {
+        return;
+    }
+    if capabilities.is_some_and(|capabilities| !capabilities.eye_tracking) {
+        info!("the system has no eye tracking");
+        return;
+    }
+    action_sets.add_manifest(
+        XrActionSetManifest::new(ACTION_SET, "Eye Gaze", 0)
+            .with_action(ACTION, "Gaze Pose", ActionType::PoseF, &[])
+            .with_bindings(
+                PROFILE,
+                &[XrBinding::new(ACTION, "/user/eyes_ext/input/gaze_ext/pose")],
+            ),
+    );
+}
+
+pub fn setup_eye_gaze_space(
+    mut commands: Commands,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+) {
+    let Ok(action) = action_sets.get_action_posef(ACTION_SET, ACTION) else {
+        return;
+    };
+    match action.create_space(
+        xr::Session::<xr::AnyGraphics>::clone(&session),
+        xr::Path::NULL,
+        xr::Posef::IDENTITY,
+    ) {
+        Ok(space) => commands.insert_resource(XrEyeGazeSpace(space)),
+        Err(err) => warn!("couldn't create the eye gaze space: {}", err),
+    }
+}
+
+pub fn update_xr_eye_gaze(
+    space: Option<Res<XrEyeGazeSpace>>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Res<XrSession>,
+    input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    mut gaze: ResMut<XrEyeGaze>,
+) {
+    let (Some(space), Some(action_sets)) = (space, action_sets) else {
+        return;
+    };
+    let Ok(action) = action_sets.get_action_posef(ACTION_SET, ACTION) else {
+        return;
+    };
+    let mut new = *gaze;
+    new.active = action.is_active(&session, xr::Path::NULL).unwrap_or(false);
+    let time = frame_state.lock().unwrap().predicted_display_time;
+    match space.0.locate(&input.stage, time) {
+        Ok(location) => {
+            new.tracking = XrTrackingState::from_flags(location.location_flags);
+            //a lost gaze keeps its last pose
+            if new.tracking.is_valid() {
+                new.pose = location.pose.to_transform();
+            }
+        }
+        Err(_) => new.tracking = default(),
+    }
+    if *gaze != new {
+        *gaze = new;
+    }
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::xr_init::xr_only;
+
+use super::trackers::{OpenXRHMD, XrTrackingRoot};
+
+/// turns entities with a [`FaceHmd`] towards the head every frame, for labels, name tags and
+/// world space ui. runs after the head pose of the frame is known
+pub struct FaceHmdPlugin;
+
+impl Plugin for FaceHmdPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            update_face_hmd
+                .run_if(xr_only())
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FaceHmdAxis {
+    /// the front faces the head from every direction
+    #[default]
+    Full,
+    /// only turns around the world y axis and stays upright, like a sign on a post
+    Y,
+}
+
+/// turns the front (+z, the side text and ui are drawn on) of the entity to the head. the
+/// rotation is set in world space, so the entity must not have a parent
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct FaceHmd {
+    pub axis: FaceHmdAxis,
+    /// scales the entity with its distance to the head so it looks as big from everywhere as it
+    /// does from this distance
+    pub constant_size_distance: Option<f32>,
+}
+
+impl FaceHmd {
+    pub fn y_axis() -> Self {
+        Self {
+            axis: FaceHmdAxis::Y,
+            ..default()
+        }
+    }
+
+    pub fn with_constant_size(mut self, distance: f32) -> Self {
+        self.constant_size_distance = Some(distance);
+        self
+    }
+
+    /// the rotation that turns the front of an entity at `translation` to `head`
+    pub fn rotation(&self, translation: Vec3, head: Vec3) -> Option<Quat> {
+        let mut to_head = head - translation;
+        if self.axis == FaceHmdAxis::Y {
+            to_head.y = 0.0;
+        }
+        if to_head.length_squared() < 1e-8 {
+            return None;
+        }
+        //`looking_to` points -z along the direction, the front has to point the other way
+        Some(
+            Transform::IDENTITY
+                .looking_to(-to_head.normalize(), Vec3::Y)
+                .rotation,
+        )
+    }
+}
+
+/// the scale an entity had before [`FaceHmd::constant_size_distance`] changed it
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct FaceHmdBaseScale(pub Vec3);
+
+pub fn update_face_hmd(
+    mut commands: Commands,
+    mut billboards: Query<(Entity, &FaceHmd, &mut Transform, Option<&FaceHmdBaseScale>)>,
+    root: Query<&Transform, (With<XrTrackingRoot>, Without<FaceHmd>)>,
+    hmd: Query<&Transform, (With<OpenXRHMD>, Without<FaceHmd>)>,
+) {
+    let root = root.get_single().copied().unwrap_or_default();
+    let Ok(head) = hmd.get_single().map(|hmd| root.mul_transform(*hmd)) else {
+        return;
+    };
+    for (entity, billboard, mut transform, base_scale) in billboards.iter_mut() {
+        if let Some(rotation) = billboard.rotation(transform.translation, head.translation) {
+            if transform.rotation != rotation {
+                transform.rotation = rotation;
+            }
+        }
+        let Some(reference) = billboard.constant_size_distance else {
+            continue;
+        };
+        let base = match base_scale {
+            Some(base) => base.0,
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(FaceHmdBaseScale(transform.scale));
+                transform.scale
+            }
+        };
+        let distance = transform.translation.distance(head.translation);
+        let scale = base * (distance / reference.max(1e-4));
+        if transform.scale != scale {
+            transform.scale = scale;
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::ptr;
+
+use bevy::prelude::*;
+use openxr as xr;
+use xr::sys;
+
+use crate::call_trace::trace;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrFrameSet;
+use crate::resources::{XrFrameState, XrInstance, XrSession};
+use crate::xr_init::{xr_only, XrPreCleanup};
+
+use super::lip_sync::{XrLipSync, XrMouthShape};
+
+/// XR_FACE_EXPRESSION_COUNT_FB
+pub const FACE_EXPRESSION_COUNT: usize = 63;
+/// XR_FACE_CONFIDENCE_COUNT_FB
+pub const FACE_CONFIDENCE_COUNT: usize = 2;
+
+/// tracks the face of the user through XR_FB_face_tracking, on quest pro. the blendshape weights
+/// are published as [`XrFaceTracking`] every frame, and written as the tracked mouth of every
+/// [`XrLipSync`] with an [`XrLocalFace`]. the tracker is created once the session runs, the
+/// runtime may ask the user for the face tracking permission first
+pub struct XrFaceTrackingPlugin;
+
+impl Plugin for XrFaceTrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrFaceTracking>();
+        app.add_systems(
+            PreUpdate,
+            (update_xr_face_tracking, apply_local_face)
+                .chain()
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+        app.add_systems(XrPreCleanup, cleanup_xr_face_tracker);
+    }
+}
+
+/// the blendshapes of XR_FB_face_tracking, in the order of `XrFaceExpressionFB`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrFaceExpression {
+    BrowLowererL,
+    BrowLowererR,
+    CheekPuffL,
+    CheekPuffR,
+    CheekRaiserL,
+    CheekRaiserR,
+    CheekSuckL,
+    CheekSuckR,
+    ChinRaiserB,
+    ChinRaiserT,
+    DimplerL,
+    DimplerR,
+    EyesClosedL,
+    EyesClosedR,
+    EyesLookDownL,
+    EyesLookDownR,
+    EyesLookLeftL,
+    EyesLookLeftR,
+    EyesLookRightL,
+    EyesLookRightR,
+    EyesLookUpL,
+    EyesLookUpR,
+    InnerBrowRaiserL,
+    InnerBrowRaiserR,
+    JawDrop,
+    JawSidewaysLeft,
+    JawSidewaysRight,
+    JawThrust,
+    LidTightenerL,
+    LidTightenerR,
+    LipCornerDepressorL,
+    LipCornerDepressorR,
+    LipCornerPullerL,
+    LipCornerPullerR,
+    LipFunnelerLb,
+    LipFunnelerLt,
+    LipFunnelerRb,
+    LipFunnelerRt,
+    LipPressorL,
+    LipPressorR,
+    LipPuckerL,
+    LipPuckerR,
+    LipStretcherL,
+    LipStretcherR,
+    LipSuckLb,
+    LipSuckLt,
+    LipSuckRb,
+    LipSuckRt,
+    LipTightenerL,
+    LipTightenerR,
+    LipsToward,
+    LowerLipDepressorL,
+    LowerLipDepressorR,
+    MouthLeft,
+    MouthRight,
+    NoseWrinklerL,
+    NoseWrinklerR,
+    OuterBrowRaiserL,
+    OuterBrowRaiserR,
+    UpperLidRaiserL,
+    UpperLidRaiserR,
+    UpperLipRaiserL,
+    UpperLipRaiserR,
+}
+
+/// the face of the user this frame
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrFaceTracking {
+    /// the weight of each [`XrFaceExpression`], from 0 to 1
+    pub weights: [f32; FACE_EXPRESSION_COUNT],
+    /// how sure the runtime is about the lower and the upper face, from 0 to 1
+    pub confidences: [f32; FACE_CONFIDENCE_COUNT],
+    /// the weights are tracked this frame, they keep their last values otherwise
+    pub is_valid: bool,
+    /// the eye blendshapes follow the eye gaze
+    pub eye_following_valid: bool,
+}
+
+impl Default for XrFaceTracking {
+    fn default() -> Self {
+        Self {
+            weights: [0.0; FACE_EXPRESSION_COUNT],
+            confidences: [0.0; FACE_CONFIDENCE_COUNT],
+            is_valid: false,
+            eye_following_valid: false,
+        }
+    }
+}
+
+impl XrFaceTracking {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        instance.exts().fb_face_tracking.is_some()
+    }
+
+    pub fn weight(&self, expression: XrFaceExpression) -> f32 {
+        self.weights[expression as usize]
+    }
+
+    /// the mouth lip sync animates, `None` while the face isn't tracked
+    pub fn mouth_shape(&self) -> Option<XrMouthShape> {
+        use XrFaceExpression::*;
+        let average = |expressions: &[XrFaceExpression]| {
+            expressions.iter().map(|e| self.weight(*e)).sum::<f32>() / expressions.len() as f32
+        };
+        self.is_valid.then(|| XrMouthShape {
+            jaw_open: self.weight(JawDrop),
+            lips_closed: self.weight(LipsToward),
+            lips_funnel: average(&[LipFunnelerLb, LipFunnelerLt, LipFunnelerRb, LipFunnelerRt]),
+            lips_pucker: average(&[LipPuckerL, LipPuckerR]),
+            mouth_stretch: average(&[LipStretcherL, LipStretcherR]),
+        })
+    }
+}
+
+/// an [`XrLipSync`] that gets the tracked face of the local user
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct XrLocalFace;
+
+/// the face tracker the runtime created
+#[derive(Resource)]
+pub struct XrFaceTracker {
+    functions: xr::raw::FaceTrackingFB,
+    tracker: sys::FaceTrackerFB,
+}
+
+impl XrFaceTracker {
+    pub fn new(instance: &XrInstance, session: &XrSession) -> xr::Result<Self> {
+        let Some(functions) = instance.exts().fb_face_tracking else {
+            return Err(sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+        };
+        let info = sys::FaceTrackerCreateInfoFB {
+            ty: sys::FaceTrackerCreateInfoFB::TYPE,
+            next: ptr::null(),
+            face_expression_set: sys::FaceExpressionSetFB::DEFAULT,
+        };
+        let mut tracker = sys::FaceTrackerFB::NULL;
+        let result = trace("xrCreateFaceTrackerFB", String::new, || unsafe {
+            (functions.create_face_tracker)(session.as_raw(), &info, &mut tracker)
+        });
+        if result.into_raw() < 0 {
+            return Err(result);
+        }
+        Ok(Self { functions, tracker })
+    }
+
+    /// the weights at `time`
+    pub fn weights(&self, time: xr::Time) -> xr::Result<XrFaceTracking> {
+        let mut face = XrFaceTracking::default();
+        let info = sys::FaceExpressionInfoFB {
+            ty: sys::FaceExpressionInfoFB::TYPE,
+            next: ptr::null(),
+            time,
+        };
+        let mut weights = sys::FaceExpressionWeightsFB {
+            ty: sys::FaceExpressionWeightsFB::TYPE,
+            next: ptr::null_mut(),
+            weight_count: FACE_EXPRESSION_COUNT as u32,
+            weights: face.weights.as_mut_ptr(),
+            confidence_count: FACE_CONFIDENCE_COUNT as u32,
+            confidences: face.confidences.as_mut_ptr(),
+            status: sys::FaceExpressionStatusFB {
+                is_valid: sys::FALSE,
+                is_eye_following_blendshapes_valid: sys::FALSE,
+            },
+            time: xr::Time::from_nanos(0),
+        };
+        let result = trace(
+            "xrGetFaceExpressionWeightsFB",
+            || format!("{:?}", time),
+            || unsafe {
+                (self.functions.get_face_expression_weights)(self.tracker, &info, &mut weights)
+            },
+        );
+        if result.into_raw() < 0 {
+            return Err(result);
+        }
+        face.is_valid = weights.status.is_valid != sys::FALSE;
+        face.eye_following_valid = weights.status.is_eye_following_blendshapes_valid != sys::FALSE;
+        Ok(face)
+    }
+}
+
+impl Drop for XrFaceTracker {
+    fn drop(&mut self) {
+        unsafe { (self.functions.destroy_face_tracker)(self.tracker) };
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_xr_face_tracking(
+    mut commands: Commands,
+    tracker: Option<Res<XrFaceTracker>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    frame_state: Res<XrFrameState>,
+    error_log: Res<XrErrorLog>,
+    mut face: ResMut<XrFaceTracking>,
+    //the session creating the tracker failed for
+    mut failed: Local<Option<sys::Session>>,
+) {
+    let Some(tracker) = tracker else {
+        if *failed == Some(session.as_raw()) || !XrFaceTracking::is_supported(&instance) {
+            return;
+        }
+        //creation isn't retried in the same session, the runtime won't change its mind
+        match XrFaceTracker::new(&instance, &session) {
+            Ok(tracker) => commands.insert_resource(tracker),
+            Err(err) => {
+                *failed = Some(session.as_raw());
+                error_log.report_result(XrErrorSource::Other, err);
+            }
+        }
+        return;
+    };
+    let time = frame_state.lock().unwrap().predicted_display_time;
+    match tracker.weights(time) {
+        //an untracked face keeps its last weights
+        Ok(new) if !new.is_valid => {
+            if face.is_valid {
+                face.is_valid = false;
+            }
+        }
+        Ok(new) => {
+            if *face != new {
+                *face = new;
+            }
+        }
+        Err(err) => error_log.report_result(XrErrorSource::Other, err),
+    }
+}
+
+pub fn apply_local_face(
+    face: Res<XrFaceTracking>,
+    mut lip_syncs: Query<&mut XrLipSync, With<XrLocalFace>>,
+) {
+    let mouth = face.mouth_shape();
+    for mut lip_sync in lip_syncs.iter_mut() {
+        if lip_sync.tracked != mouth {
+            lip_sync.tracked = mouth;
+        }
+    }
+}
+
+/// the tracker belongs to the session, the next one creates its own
+pub fn cleanup_xr_face_tracker(mut commands: Commands, mut face: ResMut<XrFaceTracking>) {
+    commands.remove_resource::<XrFaceTracker>();
+    *face = default();
+}
@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::frame_loop::XrFrameSet;
+use crate::input::XrInput;
+use crate::xr_init::xr_only;
+
+use super::spaces::XrSpaces;
+use super::trackers::XrTrackingRoot;
+
+/// the standing eye height the floor is estimated from without a stage space
+pub const DEFAULT_EYE_HEIGHT: f32 = 1.6;
+
+/// keeps the floor at y=0 on runtimes without a stage space. their local space starts at the
+/// head, so the floor is estimated from the head height once it's tracked and the tracking root
+/// is lifted by it. [`XrFloorHeight::calibrate`] replaces the estimate with a measured one
+pub struct XrFloorHeightPlugin;
+
+impl Plugin for XrFloorHeightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrFloorHeight>();
+        app.add_systems(
+            PreUpdate,
+            (estimate_floor_height, apply_floor_height)
+                .chain()
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum XrFloorHeightSource {
+    /// the runtime has a stage space, the floor is its origin
+    #[default]
+    Stage,
+    /// guessed from [`DEFAULT_EYE_HEIGHT`]
+    Estimated,
+    /// set by the app or the player
+    Calibrated,
+}
+
+/// how far the floor is below the origin of the stage space, in meters. 0 with a real stage
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrFloorHeight {
+    pub height: f32,
+    pub source: XrFloorHeightSource,
+}
+
+impl XrFloorHeight {
+    /// `head` is the head pose in the uncalibrated tracking space, e.g. the transform of the
+    /// hmd entity, `eye_height` is the player's eye height while it was taken
+    pub fn calibrate(&mut self, head: &Transform, eye_height: f32) {
+        self.height = eye_height - head.translation.y;
+        self.source = XrFloorHeightSource::Calibrated;
+    }
+
+    pub fn set(&mut self, height: f32) {
+        self.height = height;
+        self.source = XrFloorHeightSource::Calibrated;
+    }
+}
+
+pub fn estimate_floor_height(
+    input: Res<XrInput>,
+    spaces: XrSpaces,
+    mut floor: ResMut<XrFloorHeight>,
+    mut estimated: Local<bool>,
+) {
+    if *estimated || input.stage_type == xr::ReferenceSpaceType::STAGE {
+        return;
+    }
+    if floor.source == XrFloorHeightSource::Calibrated {
+        *estimated = true;
+        return;
+    }
+    let Some(head) = spaces.locate_with_velocity(spaces.view(), spaces.stage()) else {
+        return;
+    };
+    if !head.tracked {
+        return;
+    }
+    *estimated = true;
+    floor.height = DEFAULT_EYE_HEIGHT - head.transform.translation.y;
+    floor.source = XrFloorHeightSource::Estimated;
+    info!(
+        "no stage space, estimated the floor {}m below the head",
+        floor.height
+    );
+}
+
+pub fn apply_floor_height(
+    floor: Res<XrFloorHeight>,
+    mut root: Query<&mut Transform, With<XrTrackingRoot>>,
+    //the offset that is part of the root transform right now
+    mut applied: Local<f32>,
+) {
+    let Ok(mut root) = root.get_single_mut() else {
+        return;
+    };
+    if *applied == floor.height {
+        return;
+    }
+    //a vertical offset commutes with the yaw and translation of `XrCalibration`, so both can
+    //swap their own part of the root transform
+    let app_root = root.compute_matrix() * Mat4::from_translation(Vec3::Y * *applied).inverse();
+    *root = Transform::from_matrix(app_root * Mat4::from_translation(Vec3::Y * floor.height));
+    *applied = floor.height;
+}
@@ -0,0 +1,246 @@
+use bevy::prelude::*;
+use openxr::Path;
+
+use crate::resources::XrSession;
+use crate::xr_init::{xr_only, XrSetup};
+
+use super::actions::{
+    ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding, XrSyncActions,
+};
+
+const ACTION_SET: &str = "gamepad_input";
+const PROFILE: &str = "/interaction_profiles/microsoft/xbox_controller";
+
+/// gamepad input for seated apps through `/interaction_profiles/microsoft/xbox_controller`, on
+/// runtimes that route a connected gamepad to openxr. the actions live in their own action set,
+/// `gamepad_input`, since they're bound to `/user/gamepad` instead of the hands. they can be read
+/// through [`XrActionSets`] like the controller actions, or as [`XrGamepad`]
+pub struct XrGamepadPlugin;
+
+impl Plugin for XrGamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrGamepad>();
+        app.add_systems(XrSetup, setup_gamepad_actions);
+        app.add_systems(
+            PreUpdate,
+            update_xr_gamepad.run_if(xr_only()).after(XrSyncActions),
+        );
+    }
+}
+
+// (action, pretty name, binding)
+const BUTTONS: [(&str, &str, &str); 14] = [
+    ("a_button", "A Button", "/user/gamepad/input/a/click"),
+    ("b_button", "B Button", "/user/gamepad/input/b/click"),
+    ("x_button", "X Button", "/user/gamepad/input/x/click"),
+    ("y_button", "Y Button", "/user/gamepad/input/y/click"),
+    (
+        "menu_button",
+        "Menu Button",
+        "/user/gamepad/input/menu/click",
+    ),
+    (
+        "view_button",
+        "View Button",
+        "/user/gamepad/input/view/click",
+    ),
+    ("dpad_up", "D-Pad Up", "/user/gamepad/input/dpad_up/click"),
+    (
+        "dpad_down",
+        "D-Pad Down",
+        "/user/gamepad/input/dpad_down/click",
+    ),
+    (
+        "dpad_left",
+        "D-Pad Left",
+        "/user/gamepad/input/dpad_left/click",
+    ),
+    (
+        "dpad_right",
+        "D-Pad Right",
+        "/user/gamepad/input/dpad_right/click",
+    ),
+    (
+        "left_shoulder",
+        "Left Shoulder",
+        "/user/gamepad/input/shoulder_left/click",
+    ),
+    (
+        "right_shoulder",
+        "Right Shoulder",
+        "/user/gamepad/input/shoulder_right/click",
+    ),
+    (
+        "left_thumbstick_click",
+        "Left Thumbstick Click",
+        "/user/gamepad/input/thumbstick_left/click",
+    ),
+    (
+        "right_thumbstick_click",
+        "Right Thumbstick Click",
+        "/user/gamepad/input/thumbstick_right/click",
+    ),
+];
+
+const AXES: [(&str, &str, &str); 6] = [
+    (
+        "left_trigger",
+        "Left Trigger",
+        "/user/gamepad/input/trigger_left/value",
+    ),
+    (
+        "right_trigger",
+        "Right Trigger",
+        "/user/gamepad/input/trigger_right/value",
+    ),
+    (
+        "left_thumbstick_x",
+        "Left Thumbstick X",
+        "/user/gamepad/input/thumbstick_left/x",
+    ),
+    (
+        "left_thumbstick_y",
+        "Left Thumbstick Y",
+        "/user/gamepad/input/thumbstick_left/y",
+    ),
+    (
+        "right_thumbstick_x",
+        "Right Thumbstick X",
+        "/user/gamepad/input/thumbstick_right/x",
+    ),
+    (
+        "right_thumbstick_y",
+        "Right Thumbstick Y",
+        "/user/gamepad/input/thumbstick_right/y",
+    ),
+];
+
+const HAPTICS: [(&str, &str, &str); 4] = [
+    (
+        "left_rumble",
+        "Left Rumble",
+        "/user/gamepad/output/haptic_left",
+    ),
+    (
+        "right_rumble",
+        "Right Rumble",
+        "/user/gamepad/output/haptic_right",
+    ),
+    (
+        "left_trigger_rumble",
+        "Left Trigger Rumble",
+        "/user/gamepad/output/haptic_left_trigger",
+    ),
+    (
+        "right_trigger_rumble",
+        "Right Trigger Rumble",
+        "/user/gamepad/output/haptic_right_trigger",
+    ),
+];
+
+pub fn setup_gamepad_actions(mut action_sets: ResMut<SetupActionSets>) {
+    let action_set = action_sets.add_action_set(ACTION_SET, "Gamepad Input".into(), 0);
+    let mut bindings = Vec::new();
+    for (actions, action_type) in [
+        (&BUTTONS[..], ActionType::Bool),
+        (&AXES[..], ActionType::F32),
+        (&HAPTICS[..], ActionType::Haptic),
+    ] {
+        for &(name, pretty_name, binding) in actions {
+            action_set.new_action(
+                name,
+                pretty_name.into(),
+                action_type,
+                ActionHandednes::Single,
+            );
+            bindings.push(XrBinding::new(name, binding));
+        }
+    }
+    action_set.suggest_binding(PROFILE, &bindings);
+}
+
+/// the gamepad state of this frame, everything reads as released while no gamepad is active.
+/// rumble goes through the haptic actions, e.g. `left_rumble`, with [`XrActionSets`]
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrGamepad {
+    /// whether the runtime routes a gamepad to the app
+    pub active: bool,
+    pub a: bool,
+    pub b: bool,
+    pub x: bool,
+    pub y: bool,
+    pub menu: bool,
+    pub view: bool,
+    pub dpad_up: bool,
+    pub dpad_down: bool,
+    pub dpad_left: bool,
+    pub dpad_right: bool,
+    pub left_shoulder: bool,
+    pub right_shoulder: bool,
+    pub left_thumbstick_click: bool,
+    pub right_thumbstick_click: bool,
+    /// 0 to 1
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    /// -1 to 1 on both axes, up is positive y
+    pub left_thumbstick: Vec2,
+    pub right_thumbstick: Vec2,
+}
+
+pub fn update_xr_gamepad(
+    action_sets: Option<Res<XrActionSets>>,
+    session: Res<XrSession>,
+    mut gamepad: ResMut<XrGamepad>,
+) {
+    let Some(action_sets) = action_sets else {
+        return;
+    };
+    let mut active = false;
+    let mut button = |name| {
+        let Ok(state) = action_sets
+            .get_action_bool(ACTION_SET, name)
+            .map(|action| action.state(&session, Path::NULL))
+        else {
+            return false;
+        };
+        let state = state.ok().filter(|state| state.is_active);
+        active |= state.is_some();
+        state.is_some_and(|state| state.current_state)
+    };
+    let mut new = XrGamepad {
+        a: button("a_button"),
+        b: button("b_button"),
+        x: button("x_button"),
+        y: button("y_button"),
+        menu: button("menu_button"),
+        view: button("view_button"),
+        dpad_up: button("dpad_up"),
+        dpad_down: button("dpad_down"),
+        dpad_left: button("dpad_left"),
+        dpad_right: button("dpad_right"),
+        left_shoulder: button("left_shoulder"),
+        right_shoulder: button("right_shoulder"),
+        left_thumbstick_click: button("left_thumbstick_click"),
+        right_thumbstick_click: button("right_thumbstick_click"),
+        ..default()
+    };
+    let mut axis = |name| {
+        let Ok(state) = action_sets
+            .get_action_f32(ACTION_SET, name)
+            .map(|action| action.state(&session, Path::NULL))
+        else {
+            return 0.0;
+        };
+        let state = state.ok().filter(|state| state.is_active);
+        active |= state.is_some();
+        state.map_or(0.0, |state| state.current_state)
+    };
+    new.left_trigger = axis("left_trigger");
+    new.right_trigger = axis("right_trigger");
+    new.left_thumbstick = Vec2::new(axis("left_thumbstick_x"), axis("left_thumbstick_y"));
+    new.right_thumbstick = Vec2::new(axis("right_thumbstick_x"), axis("right_thumbstick_y"));
+    new.active = active;
+    if *gamepad != new {
+        *gamepad = new;
+    }
+}
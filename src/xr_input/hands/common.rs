@@ -3,7 +3,7 @@ use bevy::prelude::{
     Query, Resource, SpatialBundle, Startup, Transform,
 };
 
-use crate::xr_input::{Hand, trackers::OpenXRTracker};
+use crate::xr_input::Hand;
 
 use super::{HandBone, BoneTrackingStatus};
 
@@ -159,11 +159,12 @@ pub fn spawn_hand_entities(mut commands: Commands) {
     let mut hand_resource = HandsResource { ..default() };
     for hand in hands.iter() {
         for bone in bones.iter() {
+            //the bones aren't parented to the tracking root, their transforms already have the
+            //tracking root applied when they get updated
             let boneid = commands
                 .spawn((
                     SpatialBundle::default(),
                     bone.clone(),
-                    OpenXRTracker,
                     hand.clone(),
                     BoneTrackingStatus::Emulated,
                     HandBoneRadius(0.1),
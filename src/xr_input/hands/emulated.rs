@@ -12,7 +12,7 @@ use crate::{
             ActionHandednes, ActionType, SetupActionSet, SetupActionSets, XrActionSets, XrBinding,
         },
         hand_poses::get_simulated_open_hand_transforms,
-        trackers::{OpenXRLeftController, OpenXRRightController, OpenXRTrackingRoot},
+        trackers::{OpenXRLeftController, OpenXRRightController, XrTrackingRoot},
         Hand,
     },
 };
@@ -131,7 +131,7 @@ pub(crate) fn update_hand_skeleton_from_emulated(
     action_sets: Res<XrActionSets>,
     left_controller_transform: Query<&Transform, With<OpenXRLeftController>>,
     right_controller_transform: Query<&Transform, With<OpenXRRightController>>,
-    tracking_root_transform: Query<&Transform, With<OpenXRTrackingRoot>>,
+    tracking_root_transform: Query<&Transform, With<XrTrackingRoot>>,
     mut bones: Query<
         (
             &mut Transform,
@@ -143,7 +143,7 @@ pub(crate) fn update_hand_skeleton_from_emulated(
         (
             Without<OpenXRLeftController>,
             Without<OpenXRRightController>,
-            Without<OpenXRTrackingRoot>,
+            Without<XrTrackingRoot>,
         ),
     >,
 ) {
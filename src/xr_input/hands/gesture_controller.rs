@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use openxr::Posef;
+
+use crate::{
+    convert::to_posef,
+    input::XrInput,
+    resources::{XrFrameState, XrSession},
+    xr_init::xr_only,
+    xr_input::{
+        action_set_system,
+        actions::XrActionSets,
+        oculus_touch::{subaction_path, OculusController},
+        Hand,
+    },
+};
+
+use super::{
+    hand_tracking::{HandJoints, HandTrackingData},
+    HandBone,
+};
+
+/// the inverse of [`HandEmulationPlugin`](super::emulated::HandEmulationPlugin), drives the
+/// controller inputs from tracked hands while no controller is held, so games that only read
+/// controllers also work with bare hands. pinching pulls the trigger and making a fist squeezes
+/// the grip, the grip and aim poses follow the palm.
+pub struct GestureControllerEmulationPlugin;
+
+impl Plugin for GestureControllerEmulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GestureControllerConfig>();
+        app.add_systems(
+            PreUpdate,
+            update_gesture_controller_emulation
+                .run_if(xr_only())
+                .after(action_set_system),
+        );
+    }
+}
+
+/// distances are in meters between the joints
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct GestureControllerConfig {
+    /// thumb tip to index tip distance at which the trigger is fully pulled
+    pub pinch_closed: f32,
+    /// thumb tip to index tip distance at which the trigger is released
+    pub pinch_open: f32,
+    /// finger tip to palm distance at which the grip is fully squeezed
+    pub fist_closed: f32,
+    /// finger tip to palm distance at which the grip is released
+    pub fist_open: f32,
+}
+
+impl Default for GestureControllerConfig {
+    fn default() -> Self {
+        Self {
+            pinch_closed: 0.015,
+            pinch_open: 0.05,
+            fist_closed: 0.035,
+            fist_open: 0.09,
+        }
+    }
+}
+
+/// controller inputs made up from a tracked hand, in tracking space
+#[derive(Clone, Copy, Debug)]
+pub struct EmulatedControllerState {
+    pub trigger: f32,
+    pub squeeze: f32,
+    pub grip: Posef,
+    pub aim: Posef,
+}
+
+pub fn update_gesture_controller_emulation(
+    config: Res<GestureControllerConfig>,
+    hand_tracking: Option<Res<HandTrackingData>>,
+    mut controller: ResMut<OculusController>,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+    xr_input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+) {
+    let Some(hand_tracking) = hand_tracking else {
+        controller.emulated.left = None;
+        controller.emulated.right = None;
+        return;
+    };
+    let hand_ref = hand_tracking.get_ref(&xr_input, &frame_state);
+    let grip_action = action_sets.get_action_posef("oculus_input", "hand_pose");
+    for hand in [Hand::Left, Hand::Right] {
+        //a held controller always wins over the hand
+        let controller_active = grip_action
+            .as_ref()
+            .is_ok_and(|a| a.is_active(&session, subaction_path(hand)).unwrap_or(false));
+        let state = match controller_active {
+            true => None,
+            false => hand_ref
+                .get_poses(hand)
+                .and_then(|joints| emulate_controller(&joints, &config)),
+        };
+        match hand {
+            Hand::Left => controller.emulated.left = state,
+            Hand::Right => controller.emulated.right = state,
+        }
+    }
+}
+
+fn emulate_controller(
+    joints: &HandJoints,
+    config: &GestureControllerConfig,
+) -> Option<EmulatedControllerState> {
+    let palm = joints.get_joint(HandBone::Palm);
+    if !(palm.position_valid && palm.orientation_valid) {
+        return None;
+    }
+    let distance = |a: HandBone, b: HandBone| {
+        joints
+            .get_joint(a)
+            .position
+            .distance(joints.get_joint(b).position)
+    };
+    let pinch = distance(HandBone::ThumbTip, HandBone::IndexTip);
+    let trigger = closedness(pinch, config.pinch_closed, config.pinch_open);
+    let squeeze = [HandBone::MiddleTip, HandBone::RingTip, HandBone::LittleTip]
+        .into_iter()
+        .map(|tip| {
+            closedness(
+                distance(tip, HandBone::Palm),
+                config.fist_closed,
+                config.fist_open,
+            )
+        })
+        .sum::<f32>()
+        / 3.0;
+    let aim_origin = joints.get_joint(HandBone::IndexProximal).position;
+    Some(EmulatedControllerState {
+        trigger,
+        squeeze,
+        grip: to_posef(palm.position, palm.orientation),
+        aim: to_posef(aim_origin, palm.orientation),
+    })
+}
+
+// 0.0 at or above `open`, 1.0 at or below `closed`
+fn closedness(distance: f32, closed: f32, open: f32) -> f32 {
+    (1.0 - (distance - closed) / (open - closed)).clamp(0.0, 1.0)
+}
@@ -6,26 +6,72 @@ use crate::{
 
     resources::{XrFrameState, XrSession},
     xr_input::{
-         hands::HandBone, trackers::OpenXRTrackingRoot, Hand, QuatConv,
+         hands::HandBone, trackers::XrTrackingRoot, Hand, QuatConv,
         Vec3Conv,
     }, xr_init::xr_only,
 };
+use crate::xr_input::pose_filter::{PoseFilter, PoseFilterState};
 use super::common::HandBoneRadius;
 
 use super::BoneTrackingStatus;
 
-#[derive(Resource, PartialEq)]
+/// turns hand tracking off for one or both hands, the hand trackers of disabled hands are
+/// destroyed and only created again when the hand is enabled. without this resource both hands
+/// are tracked
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DisableHandTracking {
     OnlyLeft,
     OnlyRight,
     Both,
 }
+
+impl DisableHandTracking {
+    /// the resource for the hands that should be tracked, `None` when both are
+    pub fn from_enabled(left: bool, right: bool) -> Option<Self> {
+        match (left, right) {
+            (true, true) => None,
+            (false, true) => Some(Self::OnlyLeft),
+            (true, false) => Some(Self::OnlyRight),
+            (false, false) => Some(Self::Both),
+        }
+    }
+    pub fn disables(&self, hand: Hand) -> bool {
+        matches!(
+            (self, hand),
+            (Self::Both, _) | (Self::OnlyLeft, Hand::Left) | (Self::OnlyRight, Hand::Right)
+        )
+    }
+}
 pub struct HandTrackingPlugin;
 
-#[derive(Resource)]
+/// smooths the tracked joint poses of each hand, raw joints jitter too much for fine
+/// interactions on some devices. both hands are unfiltered by default
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct HandJointSmoothing {
+    pub left: PoseFilter,
+    pub right: PoseFilter,
+}
+
+impl HandJointSmoothing {
+    pub fn both(filter: PoseFilter) -> Self {
+        Self {
+            left: filter,
+            right: filter,
+        }
+    }
+    pub fn get(&self, hand: Hand) -> PoseFilter {
+        match hand {
+            Hand::Left => self.left,
+            Hand::Right => self.right,
+        }
+    }
+}
+
+/// the hand trackers, a tracker only exists while its hand is enabled
+#[derive(Resource, Default)]
 pub struct HandTrackingData {
-    left_hand: HandTracker,
-    right_hand: HandTracker,
+    left_hand: Option<HandTracker>,
+    right_hand: Option<HandTracker>,
 }
 
 impl HandTrackingData {
@@ -33,10 +79,34 @@ impl HandTrackingData {
         let left = session.create_hand_tracker(openxr::HandEXT::LEFT)?;
         let right = session.create_hand_tracker(openxr::HandEXT::RIGHT)?;
         Ok(HandTrackingData {
-            left_hand: left,
-            right_hand: right,
+            left_hand: Some(left),
+            right_hand: Some(right),
         })
     }
+    pub fn is_enabled(&self, hand: Hand) -> bool {
+        match hand {
+            Hand::Left => self.left_hand.is_some(),
+            Hand::Right => self.right_hand.is_some(),
+        }
+    }
+    /// creates or destroys the tracker of `hand`
+    pub fn set_enabled(&mut self, session: &XrSession, hand: Hand, enabled: bool) -> Result<()> {
+        let tracker = match hand {
+            Hand::Left => &mut self.left_hand,
+            Hand::Right => &mut self.right_hand,
+        };
+        match (enabled, tracker.is_some()) {
+            (true, false) => {
+                *tracker = Some(session.create_hand_tracker(match hand {
+                    Hand::Left => openxr::HandEXT::LEFT,
+                    Hand::Right => openxr::HandEXT::RIGHT,
+                })?)
+            }
+            (false, true) => *tracker = None,
+            _ => {}
+        }
+        Ok(())
+    }
     pub fn get_ref<'a>(
         &'a self,
         input: &'a XrInput,
@@ -79,17 +149,30 @@ impl HandJoints {
     pub fn get_joint(&self, bone: HandBone) -> &HandJoint {
         &self.inner[bone.get_index_from_bone()]
     }
+    /// runs the joint poses through `filter`, `states` has the filter state of each joint.
+    /// joints that aren't located start over
+    pub fn smooth(&mut self, filter: &PoseFilter, states: &mut [PoseFilterState; 26], dt: f32) {
+        for (joint, state) in self.inner.iter_mut().zip(states) {
+            if *filter == PoseFilter::None || !joint.position_valid || !joint.orientation_valid {
+                state.reset();
+                continue;
+            }
+            (joint.position, joint.orientation) =
+                state.filter(filter, joint.position, joint.orientation, dt);
+        }
+    }
 }
 
 impl<'a> HandTrackingRef<'a> {
     pub fn get_poses(&self, side: Hand) -> Option<HandJoints> {
+        let tracker = match side {
+            Hand::Left => self.tracking.left_hand.as_ref(),
+            Hand::Right => self.tracking.right_hand.as_ref(),
+        }?;
         self.input
             .stage
             .locate_hand_joints(
-                match side {
-                    Hand::Left => &self.tracking.left_hand,
-                    Hand::Right => &self.tracking.right_hand,
-                },
+                tracker,
                 self.frame_state.lock().unwrap().predicted_display_time,
             )
             .unwrap()
@@ -123,9 +206,13 @@ impl<'a> HandTrackingRef<'a> {
 
 impl Plugin for HandTrackingPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<HandJointSmoothing>();
         app.add_systems(
             PreUpdate,
             (
+                sync_hand_trackers
+                    .run_if(xr_only())
+                    .before(update_hand_bones),
                 update_hand_bones.run_if(|dh: Option<Res<DisableHandTracking>>| {
                     !dh.is_some_and(|v| *v == DisableHandTracking::Both)
                 }).run_if(xr_only()),
@@ -135,6 +222,34 @@ impl Plugin for HandTrackingPlugin {
     }
 }
 
+/// creates the trackers of enabled hands and destroys the ones of disabled hands
+pub fn sync_hand_trackers(
+    disabled_tracking: Option<Res<DisableHandTracking>>,
+    hand_tracking: Option<ResMut<HandTrackingData>>,
+    session: Res<XrSession>,
+    //hands whose tracker couldn't be created, not retried until the hand is disabled again
+    mut failed: Local<[bool; 2]>,
+) {
+    let Some(mut hand_tracking) = hand_tracking else {
+        return;
+    };
+    for (index, hand) in [Hand::Left, Hand::Right].into_iter().enumerate() {
+        let enabled = !disabled_tracking
+            .as_ref()
+            .is_some_and(|disabled| disabled.disables(hand));
+        if !enabled {
+            failed[index] = false;
+        }
+        if hand_tracking.is_enabled(hand) == enabled || failed[index] {
+            continue;
+        }
+        if let Err(err) = hand_tracking.set_enabled(&session, hand, enabled) {
+            warn!("failed to create the {:?} hand tracker: {}", hand, err);
+            failed[index] = true;
+        }
+    }
+}
+
 fn update_tracking_state_on_disable(
     mut is_off: Local<bool>,
     disabled_tracking: Option<Res<DisableHandTracking>>,
@@ -154,12 +269,16 @@ fn update_tracking_state_on_disable(
         .is_some_and(|t| **t == DisableHandTracking::Both);
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_hand_bones(
     disabled_tracking: Option<Res<DisableHandTracking>>,
     hand_tracking: Option<Res<HandTrackingData>>,
     xr_input: Res<XrInput>,
     xr_frame_state: Res<XrFrameState>,
-    root_query: Query<(&Transform, With<OpenXRTrackingRoot>, Without<HandBone>)>,
+    smoothing: Res<HandJointSmoothing>,
+    time: Res<Time>,
+    mut filter_states: Local<[[PoseFilterState; 26]; 2]>,
+    root_query: Query<(&Transform, With<XrTrackingRoot>, Without<HandBone>)>,
     mut bones: Query<(
         &mut Transform,
         &Hand,
@@ -176,8 +295,17 @@ pub fn update_hand_bones(
         }
     };
     let (root_transform, _, _) = root_query.get_single().unwrap();
-    let left_hand_data = hand_ref.get_poses(Hand::Left);
-    let right_hand_data = hand_ref.get_poses(Hand::Right);
+    let [left_states, right_states] = &mut *filter_states;
+    let smoothed = |hand, states: &mut [PoseFilterState; 26]| {
+        let Some(mut joints) = hand_ref.get_poses(hand) else {
+            states.iter_mut().for_each(PoseFilterState::reset);
+            return None;
+        };
+        joints.smooth(&smoothing.get(hand), states, time.delta_seconds());
+        Some(joints)
+    };
+    let left_hand_data = smoothed(Hand::Left, left_states);
+    let right_hand_data = smoothed(Hand::Right, right_states);
     bones
         .par_iter_mut()
         .for_each(|(mut transform, hand, bone, mut radius, mut status)| {
@@ -3,7 +3,10 @@ use bevy::{app::PluginGroupBuilder, prelude::*};
 use self::{emulated::HandEmulationPlugin, hand_tracking::HandTrackingPlugin};
 
 pub mod emulated;
+pub mod gesture_controller;
 pub mod hand_tracking;
+pub mod multimodal;
+pub mod recorder;
 pub mod common;
 
 pub struct XrHandPlugins;
@@ -23,7 +26,7 @@ pub enum BoneTrackingStatus {
     Tracked,
 }
 
-#[derive(Component, Debug, Clone, Copy)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum HandBone {
     Palm,
     Wrist,
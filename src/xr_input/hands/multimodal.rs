@@ -0,0 +1,294 @@
+use std::ffi::CString;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::call_trace::trace;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrFrameSet;
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::xr_only;
+use crate::xr_input::trackers::{
+    update_open_xr_controllers, OpenXRLeftController, OpenXRRightController, XrTrackingState,
+};
+use crate::xr_input::Hand;
+
+use super::{BoneTrackingStatus, HandBone};
+
+pub(crate) const SIMULTANEOUS_HANDS_AND_CONTROLLERS_EXTENSION: &str =
+    "XR_META_simultaneous_hands_and_controllers";
+
+const TYPE_SIMULTANEOUS_HANDS_AND_CONTROLLERS_TRACKING_RESUME_INFO: i32 = 1000532002;
+const TYPE_SIMULTANEOUS_HANDS_AND_CONTROLLERS_TRACKING_PAUSE_INFO: i32 = 1000532003;
+
+// the resume and pause infos have no fields besides the header
+#[repr(C)]
+struct SimultaneousTrackingInfo {
+    ty: xr::sys::StructureType,
+    next: *const std::ffi::c_void,
+}
+
+type SetSimultaneousTracking = unsafe extern "system" fn(
+    session: xr::sys::Session,
+    info: *const SimultaneousTrackingInfo,
+) -> xr::sys::Result;
+
+/// shows hands and controllers at the same time, e.g. a hand holding a controller. without
+/// XR_META_simultaneous_hands_and_controllers quest runtimes only track what the user holds,
+/// with it both are tracked once [`XrHandsAndControllers::simultaneous`] is on. the visibility of
+/// the controller and hand bone entities follows [`XrHandControllerBlend`], what the runtime
+/// tracks is in [`XrHandModalities`]
+pub struct XrHandsAndControllersPlugin;
+
+impl Plugin for XrHandsAndControllersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrHandsAndControllers>();
+        app.init_resource::<XrHandControllerBlend>();
+        app.init_resource::<XrHandModalities>();
+        app.add_systems(
+            PreUpdate,
+            set_simultaneous_tracking
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+        app.add_systems(
+            Update,
+            (update_hand_modalities, apply_hand_controller_blend)
+                .chain()
+                .run_if(xr_only())
+                .after(update_open_xr_controllers),
+        );
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrHandsAndControllers {
+    /// whether both should be tracked at once, on by default
+    pub simultaneous: bool,
+    active: bool,
+}
+
+impl Default for XrHandsAndControllers {
+    fn default() -> Self {
+        Self {
+            simultaneous: true,
+            active: false,
+        }
+    }
+}
+
+impl XrHandsAndControllers {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        instance
+            .exts()
+            .other
+            .iter()
+            .any(|ext| ext == SIMULTANEOUS_HANDS_AND_CONTROLLERS_EXTENSION)
+    }
+    /// whether the runtime tracks hands and controllers at once
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+/// what is shown of a hand that has both a tracked hand and a tracked controller
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum XrHandControllerBlend {
+    /// the hand is shown holding the controller
+    #[default]
+    HandHoldingController,
+    /// only the hand, the controller is hidden
+    PreferHand,
+    /// only the controller, the hand is hidden
+    PreferController,
+    /// the visibility is left to the app
+    Manual,
+}
+
+impl XrHandControllerBlend {
+    /// whether the hand and the controller are shown, for whatever of them is tracked
+    pub fn apply(&self, hand_tracked: bool, controller_tracked: bool) -> (bool, bool) {
+        match (self, hand_tracked && controller_tracked) {
+            (XrHandControllerBlend::PreferHand, true) => (true, false),
+            (XrHandControllerBlend::PreferController, true) => (false, true),
+            _ => (hand_tracked, controller_tracked),
+        }
+    }
+}
+
+/// what the runtime tracks of a hand and what of it [`XrHandControllerBlend`] shows
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrHandModality {
+    pub hand_tracked: bool,
+    pub controller_tracked: bool,
+    pub show_hand: bool,
+    pub show_controller: bool,
+}
+
+impl XrHandModality {
+    /// the hand is tracked around a tracked controller
+    pub fn is_holding_controller(&self) -> bool {
+        self.hand_tracked && self.controller_tracked
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrHandModalities {
+    pub left: XrHandModality,
+    pub right: XrHandModality,
+}
+
+impl XrHandModalities {
+    pub fn get(&self, hand: Hand) -> XrHandModality {
+        match hand {
+            Hand::Left => self.left,
+            Hand::Right => self.right,
+        }
+    }
+}
+
+pub fn set_simultaneous_tracking(
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    error_log: Res<XrErrorLog>,
+    mut setting: ResMut<XrHandsAndControllers>,
+    mut functions: Local<Option<Option<(SetSimultaneousTracking, SetSimultaneousTracking)>>>,
+    mut requested: Local<Option<bool>>,
+) {
+    if *requested == Some(setting.simultaneous) || (requested.is_none() && !setting.simultaneous) {
+        return;
+    }
+    *requested = Some(setting.simultaneous);
+    let functions = *functions.get_or_insert_with(|| load_simultaneous_tracking(&instance));
+    let Some((resume, pause)) = functions else {
+        if setting.simultaneous {
+            info!(
+                "no {}, hands and controllers are tracked one at a time",
+                SIMULTANEOUS_HANDS_AND_CONTROLLERS_EXTENSION
+            );
+        }
+        return;
+    };
+    let (name, function, ty) = match setting.simultaneous {
+        true => (
+            "xrResumeSimultaneousHandsAndControllersTrackingMETA",
+            resume,
+            TYPE_SIMULTANEOUS_HANDS_AND_CONTROLLERS_TRACKING_RESUME_INFO,
+        ),
+        false => (
+            "xrPauseSimultaneousHandsAndControllersTrackingMETA",
+            pause,
+            TYPE_SIMULTANEOUS_HANDS_AND_CONTROLLERS_TRACKING_PAUSE_INFO,
+        ),
+    };
+    let info = SimultaneousTrackingInfo {
+        ty: xr::sys::StructureType::from_raw(ty),
+        next: std::ptr::null(),
+    };
+    let result = trace(name, String::new, || unsafe {
+        function(session.as_raw(), &info)
+    });
+    if result.into_raw() < 0 {
+        error_log.report_result(XrErrorSource::Other, result);
+        return;
+    }
+    setting.active = setting.simultaneous;
+}
+
+pub fn update_hand_modalities(
+    blend: Res<XrHandControllerBlend>,
+    mut modalities: ResMut<XrHandModalities>,
+    bones: Query<(&Hand, &HandBone, &BoneTrackingStatus)>,
+    left: Query<&XrTrackingState, With<OpenXRLeftController>>,
+    right: Query<&XrTrackingState, With<OpenXRRightController>>,
+) {
+    let hand_tracked = |hand| {
+        bones.iter().any(|(bone_hand, bone, status)| {
+            *bone_hand == hand
+                && matches!(bone, HandBone::Wrist)
+                && *status == BoneTrackingStatus::Tracked
+        })
+    };
+    let modality = |hand, controller: Option<&XrTrackingState>| {
+        let hand_tracked = hand_tracked(hand);
+        let controller_tracked = controller.is_some_and(|state| state.is_tracked());
+        let (show_hand, show_controller) = blend.apply(hand_tracked, controller_tracked);
+        XrHandModality {
+            hand_tracked,
+            controller_tracked,
+            show_hand,
+            show_controller,
+        }
+    };
+    let new = XrHandModalities {
+        left: modality(Hand::Left, left.get_single().ok()),
+        right: modality(Hand::Right, right.get_single().ok()),
+    };
+    if *modalities != new {
+        *modalities = new;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn apply_hand_controller_blend(
+    blend: Res<XrHandControllerBlend>,
+    modalities: Res<XrHandModalities>,
+    mut bones: Query<(&Hand, &mut Visibility), With<HandBone>>,
+    mut left: Query<
+        &mut Visibility,
+        (
+            With<OpenXRLeftController>,
+            Without<OpenXRRightController>,
+            Without<HandBone>,
+        ),
+    >,
+    mut right: Query<
+        &mut Visibility,
+        (
+            With<OpenXRRightController>,
+            Without<OpenXRLeftController>,
+            Without<HandBone>,
+        ),
+    >,
+) {
+    if *blend == XrHandControllerBlend::Manual || !modalities.is_changed() {
+        return;
+    }
+    let visibility = |shown: bool| match shown {
+        true => Visibility::Inherited,
+        false => Visibility::Hidden,
+    };
+    for (hand, mut bone_visibility) in &mut bones {
+        *bone_visibility = visibility(modalities.get(*hand).show_hand);
+    }
+    for mut controller_visibility in &mut left {
+        *controller_visibility = visibility(modalities.left.show_controller);
+    }
+    for mut controller_visibility in &mut right {
+        *controller_visibility = visibility(modalities.right.show_controller);
+    }
+}
+
+fn load_simultaneous_tracking(
+    instance: &XrInstance,
+) -> Option<(SetSimultaneousTracking, SetSimultaneousTracking)> {
+    if !XrHandsAndControllers::is_supported(instance) {
+        return None;
+    }
+    let load = |name: &str| {
+        let name = CString::new(name).unwrap();
+        let function = unsafe {
+            instance
+                .entry()
+                .get_instance_proc_addr(instance.as_raw(), name.as_ptr())
+                .ok()?
+        };
+        Some(unsafe {
+            std::mem::transmute::<xr::sys::pfn::VoidFunction, SetSimultaneousTracking>(function)
+        })
+    };
+    Some((
+        load("xrResumeSimultaneousHandsAndControllersTrackingMETA")?,
+        load("xrPauseSimultaneousHandsAndControllersTrackingMETA")?,
+    ))
+}
@@ -0,0 +1,313 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+use crate::resources::XrFrameState;
+use crate::xr_input::trackers::{OpenXRHMD, XrTrackingRoot};
+use crate::xr_input::Hand;
+
+use super::common::HandBoneRadius;
+use super::{BoneTrackingStatus, HandBone};
+
+/// writes the joints of both hands to a file every frame while a recording is running, for
+/// building gesture datasets. start and stop recordings through [`HandRecorder`]
+pub struct HandRecorderPlugin;
+
+impl Plugin for HandRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HandRecorder>();
+        //the bones are set in PreUpdate by the tracking or emulation plugin
+        app.add_systems(
+            PostUpdate,
+            record_hand_joints.run_if(|recorder: Res<HandRecorder>| recorder.is_recording()),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum HandRecordingFormat {
+    /// one row per joint and frame:
+    /// `time,display_time_ns,source,joint,px,py,pz,qx,qy,qz,qw,radius,tracked`.
+    /// head rows use `head` as source and joint
+    #[default]
+    Csv,
+    /// one json object per frame and line, with a `left`, `right` and optionally `head` field.
+    /// needs the `serialize` feature
+    #[cfg(feature = "serialize")]
+    JsonLines,
+}
+
+impl HandRecordingFormat {
+    /// picks the format from the file extension, `.json` and `.jsonl` record json lines
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "serialize")]
+            Some("json") | Some("jsonl") => HandRecordingFormat::JsonLines,
+            _ => HandRecordingFormat::Csv,
+        }
+    }
+}
+
+struct Recording {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    format: HandRecordingFormat,
+    include_head: bool,
+    start: Instant,
+    frames: u64,
+}
+
+#[derive(Resource, Default)]
+pub struct HandRecorder {
+    recording: Option<Recording>,
+}
+
+impl HandRecorder {
+    /// starts recording to `path`, stopping the current recording first
+    pub fn start(
+        &mut self,
+        path: impl Into<PathBuf>,
+        format: HandRecordingFormat,
+        include_head: bool,
+    ) -> std::io::Result<()> {
+        self.stop();
+        let path = path.into();
+        let mut writer = BufWriter::new(File::create(&path)?);
+        if format == HandRecordingFormat::Csv {
+            writeln!(
+                writer,
+                "time,display_time_ns,source,joint,px,py,pz,qx,qy,qz,qw,radius,tracked"
+            )?;
+        }
+        info!("recording hand joints to {}", path.display());
+        self.recording = Some(Recording {
+            writer,
+            path,
+            format,
+            include_head,
+            start: Instant::now(),
+            frames: 0,
+        });
+        Ok(())
+    }
+
+    /// stops the recording and flushes the file
+    pub fn stop(&mut self) {
+        if let Some(mut recording) = self.recording.take() {
+            if let Err(err) = recording.writer.flush() {
+                warn!(
+                    "failed to write hand recording {}: {}",
+                    recording.path.display(),
+                    err
+                );
+            }
+            info!(
+                "recorded {} frames to {}",
+                recording.frames,
+                recording.path.display()
+            );
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// the file currently recorded to
+    pub fn path(&self) -> Option<&Path> {
+        self.recording.as_ref().map(|r| r.path.as_path())
+    }
+}
+
+struct JointSample {
+    hand: Hand,
+    bone: HandBone,
+    transform: Transform,
+    radius: f32,
+    tracked: bool,
+}
+
+pub fn record_hand_joints(
+    mut recorder: ResMut<HandRecorder>,
+    frame_state: Option<Res<XrFrameState>>,
+    bones: Query<(
+        &Transform,
+        &Hand,
+        &HandBone,
+        &HandBoneRadius,
+        &BoneTrackingStatus,
+    )>,
+    root: Query<&Transform, (With<XrTrackingRoot>, Without<HandBone>)>,
+    hmd: Query<&Transform, (With<OpenXRHMD>, Without<HandBone>)>,
+) {
+    let Some(recording) = recorder.recording.as_mut() else {
+        return;
+    };
+    let time = recording.start.elapsed().as_secs_f64();
+    let display_time = frame_state
+        .map(|state| state.lock().unwrap().predicted_display_time.as_nanos())
+        .unwrap_or_default();
+    let mut joints = bones
+        .iter()
+        .map(|(transform, hand, bone, radius, status)| JointSample {
+            hand: *hand,
+            bone: *bone,
+            transform: *transform,
+            radius: radius.0,
+            tracked: *status == BoneTrackingStatus::Tracked,
+        })
+        .collect::<Vec<_>>();
+    joints.sort_by_key(|joint| (joint.hand, joint.bone.get_index_from_bone()));
+    //hand bones are in world space already, the hmd is a child of the root
+    let head = match recording.include_head {
+        true => hmd.get_single().ok().map(|hmd| {
+            root.get_single()
+                .copied()
+                .unwrap_or_default()
+                .mul_transform(*hmd)
+        }),
+        false => None,
+    };
+    let result = match recording.format {
+        HandRecordingFormat::Csv => {
+            write_csv(&mut recording.writer, time, display_time, &joints, head)
+        }
+        #[cfg(feature = "serialize")]
+        HandRecordingFormat::JsonLines => {
+            write_json_line(&mut recording.writer, time, display_time, &joints, head)
+        }
+    };
+    match result {
+        Ok(()) => recording.frames += 1,
+        Err(err) => {
+            warn!(
+                "failed to write hand recording {}, stopping: {}",
+                recording.path.display(),
+                err
+            );
+            recorder.recording = None;
+        }
+    }
+}
+
+fn hand_name(hand: Hand) -> &'static str {
+    match hand {
+        Hand::Left => "left",
+        Hand::Right => "right",
+    }
+}
+
+fn write_csv(
+    writer: &mut impl Write,
+    time: f64,
+    display_time: i64,
+    joints: &[JointSample],
+    head: Option<Transform>,
+) -> std::io::Result<()> {
+    let mut row = |source: &str, joint: &str, transform: &Transform, radius: f32, tracked: bool| {
+        let p = transform.translation;
+        let q = transform.rotation;
+        writeln!(
+            writer,
+            "{:.6},{},{},{},{},{},{},{},{},{},{},{},{}",
+            time,
+            display_time,
+            source,
+            joint,
+            p.x,
+            p.y,
+            p.z,
+            q.x,
+            q.y,
+            q.z,
+            q.w,
+            radius,
+            tracked as u8
+        )
+    };
+    if let Some(head) = head {
+        row("head", "head", &head, 0.0, true)?;
+    }
+    for joint in joints {
+        row(
+            hand_name(joint.hand),
+            &format!("{:?}", joint.bone),
+            &joint.transform,
+            joint.radius,
+            joint.tracked,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "serialize")]
+#[derive(serde::Serialize)]
+struct JsonFrame {
+    time: f64,
+    display_time_ns: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head: Option<JsonPose>,
+    left: Vec<JsonJoint>,
+    right: Vec<JsonJoint>,
+}
+
+#[cfg(feature = "serialize")]
+#[derive(serde::Serialize)]
+struct JsonPose {
+    position: [f32; 3],
+    rotation: [f32; 4],
+}
+
+#[cfg(feature = "serialize")]
+impl From<&Transform> for JsonPose {
+    fn from(transform: &Transform) -> Self {
+        JsonPose {
+            position: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+        }
+    }
+}
+
+#[cfg(feature = "serialize")]
+#[derive(serde::Serialize)]
+struct JsonJoint {
+    joint: String,
+    #[serde(flatten)]
+    pose: JsonPose,
+    radius: f32,
+    tracked: bool,
+}
+
+#[cfg(feature = "serialize")]
+fn write_json_line(
+    writer: &mut impl Write,
+    time: f64,
+    display_time: i64,
+    joints: &[JointSample],
+    head: Option<Transform>,
+) -> std::io::Result<()> {
+    let hand_joints = |hand: Hand| {
+        joints
+            .iter()
+            .filter(|joint| joint.hand == hand)
+            .map(|joint| JsonJoint {
+                joint: format!("{:?}", joint.bone),
+                pose: JsonPose::from(&joint.transform),
+                radius: joint.radius,
+                tracked: joint.tracked,
+            })
+            .collect()
+    };
+    let frame = JsonFrame {
+        time,
+        display_time_ns: display_time,
+        head: head.as_ref().map(JsonPose::from),
+        left: hand_joints(Hand::Left),
+        right: hand_joints(Hand::Right),
+    };
+    serde_json::to_writer(&mut *writer, &frame)?;
+    writeln!(writer)
+}
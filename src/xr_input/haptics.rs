@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::xr_only;
+
+use super::actions::{XrActionSets, XrSyncActions};
+use super::Hand;
+
+/// plays vibrations on the controllers through [`XrHaptics`], one pulse at a time or queued
+/// sequences of pulses. pulses with a changing amplitude are sent again every frame, so they
+/// follow their envelope at the frame rate
+pub struct XrHapticsPlugin;
+
+impl Plugin for XrHapticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrHaptics>();
+        app.add_systems(
+            PreUpdate,
+            play_xr_haptics.run_if(xr_only()).after(XrSyncActions),
+        );
+    }
+}
+
+/// one vibration, see [`XrHaptics`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrHapticPulse {
+    /// in seconds
+    pub duration: f32,
+    /// in hz, `None` lets the runtime pick
+    pub frequency: Option<f32>,
+    /// from 0 to 1 at the start of the pulse
+    pub amplitude: f32,
+    /// from 0 to 1 at the end, the amplitude in between is interpolated linearly
+    pub end_amplitude: f32,
+}
+
+impl XrHapticPulse {
+    pub fn new(duration: f32, amplitude: f32) -> Self {
+        Self {
+            duration,
+            frequency: None,
+            amplitude,
+            end_amplitude: amplitude,
+        }
+    }
+
+    /// a pulse that fades from `from` to `to`
+    pub fn ramp(duration: f32, from: f32, to: f32) -> Self {
+        Self {
+            end_amplitude: to,
+            ..Self::new(duration, from)
+        }
+    }
+
+    /// no vibration for `duration`, for gaps in a sequence
+    pub fn pause(duration: f32) -> Self {
+        Self::new(duration, 0.0)
+    }
+
+    pub fn with_frequency(mut self, frequency: f32) -> Self {
+        self.frequency = Some(frequency);
+        self
+    }
+
+    pub fn amplitude_at(&self, time: f32) -> f32 {
+        let t = match self.duration > 0.0 {
+            true => (time / self.duration).clamp(0.0, 1.0),
+            false => 1.0,
+        };
+        (self.amplitude + (self.end_amplitude - self.amplitude) * t).clamp(0.0, 1.0)
+    }
+
+    fn is_flat(&self) -> bool {
+        self.amplitude == self.end_amplitude
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct HapticChannel {
+    queue: VecDeque<XrHapticPulse>,
+    /// the pulse that plays and how long it has played
+    current: Option<(XrHapticPulse, f32)>,
+    stop: bool,
+}
+
+/// the vibrations of both controllers. the pulses go to the `haptic_feedback` action of the
+/// oculus touch action set unless [`XrHaptics::action_set`] and [`XrHaptics::action`] name
+/// another haptic action with hand subaction paths
+#[derive(Resource, Clone, Debug)]
+pub struct XrHaptics {
+    pub action_set: &'static str,
+    pub action: &'static str,
+    left: HapticChannel,
+    right: HapticChannel,
+}
+
+impl Default for XrHaptics {
+    fn default() -> Self {
+        Self {
+            action_set: "oculus_input",
+            action: "haptic_feedback",
+            left: default(),
+            right: default(),
+        }
+    }
+}
+
+impl XrHaptics {
+    fn channel_mut(&mut self, hand: Hand) -> &mut HapticChannel {
+        match hand {
+            Hand::Left => &mut self.left,
+            Hand::Right => &mut self.right,
+        }
+    }
+
+    /// vibrates right away, replacing whatever plays on that controller
+    pub fn pulse(&mut self, hand: Hand, duration: f32, frequency: Option<f32>, amplitude: f32) {
+        self.play(
+            hand,
+            [XrHapticPulse {
+                frequency,
+                ..XrHapticPulse::new(duration, amplitude)
+            }],
+        );
+    }
+
+    /// plays the pulses one after the other, replacing whatever plays on that controller
+    pub fn play(&mut self, hand: Hand, pulses: impl IntoIterator<Item = XrHapticPulse>) {
+        let channel = self.channel_mut(hand);
+        channel.queue = pulses.into_iter().collect();
+        channel.current = None;
+        channel.stop = true;
+    }
+
+    /// plays the pulses once everything queued on that controller has played
+    pub fn queue(&mut self, hand: Hand, pulses: impl IntoIterator<Item = XrHapticPulse>) {
+        self.channel_mut(hand).queue.extend(pulses);
+    }
+
+    pub fn stop(&mut self, hand: Hand) {
+        let channel = self.channel_mut(hand);
+        channel.queue.clear();
+        channel.current = None;
+        channel.stop = true;
+    }
+
+    pub fn is_playing(&self, hand: Hand) -> bool {
+        let channel = match hand {
+            Hand::Left => &self.left,
+            Hand::Right => &self.right,
+        };
+        channel.current.is_some() || !channel.queue.is_empty()
+    }
+}
+
+pub fn play_xr_haptics(
+    time: Res<Time>,
+    mut haptics: ResMut<XrHaptics>,
+    action_sets: Option<Res<XrActionSets>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    mut paths: Local<Option<[xr::Path; 2]>>,
+) {
+    let Some(action_sets) = action_sets else {
+        return;
+    };
+    let haptics = &mut *haptics;
+    let Ok(action) = action_sets.get_action_haptic(haptics.action_set, haptics.action) else {
+        return;
+    };
+    let [left, right] = *paths.get_or_insert_with(|| {
+        [
+            instance.string_to_path("/user/hand/left").unwrap(),
+            instance.string_to_path("/user/hand/right").unwrap(),
+        ]
+    });
+    let delta = time.delta_seconds();
+    for (channel, path) in [(&mut haptics.left, left), (&mut haptics.right, right)] {
+        let mut result = Ok(());
+        if std::mem::take(&mut channel.stop) {
+            result = action.stop_feedback(&session, path);
+        }
+        //the time left over from a finished pulse goes to the next one
+        let mut started = false;
+        let mut elapsed = match channel.current.take() {
+            Some((pulse, elapsed)) if elapsed + delta < pulse.duration => {
+                channel.current = Some((pulse, elapsed + delta));
+                0.0
+            }
+            Some((pulse, elapsed)) => elapsed + delta - pulse.duration,
+            None => 0.0,
+        };
+        while channel.current.is_none() {
+            let Some(pulse) = channel.queue.pop_front() else {
+                break;
+            };
+            if elapsed < pulse.duration {
+                channel.current = Some((pulse, elapsed));
+                started = true;
+            } else {
+                elapsed -= pulse.duration;
+            }
+        }
+        let Some((pulse, elapsed)) = channel.current else {
+            if let Err(err) = result {
+                warn!("couldn't stop the haptics: {}", err);
+            }
+            continue;
+        };
+        //flat pulses are sent once with their whole duration, the runtime plays them out
+        if started || !pulse.is_flat() {
+            let amplitude = pulse.amplitude_at(elapsed);
+            let remaining = (pulse.duration - elapsed).max(0.0);
+            result = result.and_then(|_| match amplitude > 0.0 {
+                true => action.apply_feedback(
+                    &session,
+                    path,
+                    &xr::HapticVibration::new()
+                        .amplitude(amplitude)
+                        .frequency(pulse.frequency.unwrap_or(xr::FREQUENCY_UNSPECIFIED))
+                        .duration(xr::Duration::from_nanos((remaining * 1e9) as i64)),
+                ),
+                false => action.stop_feedback(&session, path),
+            });
+        }
+        if let Err(err) = result {
+            warn!("couldn't play the haptics: {}", err);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::call_trace::trace;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::input::XrInput;
+use crate::resources::XrFrameState;
+
+use super::Vec3Conv;
+
+/// the velocity of the head in the tracking space at the predicted display time, for doppler
+/// audio, comfort vignettes and the like. the velocities are `None` when the runtime doesn't
+/// know them this frame
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrHeadVelocity {
+    /// meters per second
+    pub linear: Option<Vec3>,
+    /// radians per second around the axis, like `SpaceVelocity::angular_velocity`
+    pub angular: Option<Vec3>,
+}
+
+impl XrHeadVelocity {
+    /// the linear velocity in world space, `root` is the transform of the `XrTrackingRoot`
+    pub fn world_linear(&self, root: &GlobalTransform) -> Option<Vec3> {
+        self.linear
+            .map(|linear| root.affine().transform_vector3(linear))
+    }
+
+    /// the angular velocity in world space, the world scale doesn't change it
+    pub fn world_angular(&self, root: &GlobalTransform) -> Option<Vec3> {
+        let (_, rotation, _) = root.to_scale_rotation_translation();
+        self.angular.map(|angular| rotation * angular)
+    }
+
+    /// how fast the head turns, in radians per second
+    pub fn angular_speed(&self) -> f32 {
+        self.angular.map(Vec3::length).unwrap_or_default()
+    }
+}
+
+pub fn update_xr_head_velocity(
+    mut head_velocity: ResMut<XrHeadVelocity>,
+    input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    error_log: Res<XrErrorLog>,
+) {
+    let time = frame_state.lock().unwrap().predicted_display_time;
+    let relation = trace(
+        "xrLocateSpace",
+        || format!("head, {:?}", time),
+        || input.head.relate(&input.stage, time),
+    );
+    let velocity = match relation {
+        Ok((_, velocity)) => velocity,
+        Err(err) => {
+            error_log.report_result(XrErrorSource::Tracking, err);
+            *head_velocity = default();
+            return;
+        }
+    };
+    let flags = velocity.velocity_flags;
+    *head_velocity = XrHeadVelocity {
+        linear: flags
+            .contains(xr::SpaceVelocityFlags::LINEAR_VALID)
+            .then(|| velocity.linear_velocity.to_vec3()),
+        angular: flags
+            .contains(xr::SpaceVelocityFlags::ANGULAR_VALID)
+            .then(|| velocity.angular_velocity.to_vec3()),
+    };
+}
@@ -0,0 +1,155 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::convert::PosefConv;
+use crate::resources::{XrInstance, XrSession};
+
+use super::actions::XrActionSets;
+use super::spaces::XrSpaces;
+use super::trackers::{AimPose, OpenXRTracker, XrTrackingState};
+use super::Hand;
+
+/// an entity per tracked input source, `XrInput` only has the reference spaces. each hand gets
+/// one with its grip space as [`XrTrackedSpace`] and its aim space as [`XrAimSpace`]. a new
+/// source, e.g. a body tracker or an elbow, only needs an entity with an [`XrTrackedSpace`] of
+/// its action space
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrInputSource {
+    pub hand: Option<Hand>,
+}
+
+/// the entity's transform follows this space, relative to the tracking root. entities with it
+/// need `OpenXRTracker` to be moved under the root
+#[derive(Component, Clone, Deref)]
+pub struct XrTrackedSpace(pub Arc<xr::Space>);
+
+/// the grip space of a hand, also the [`XrTrackedSpace`] of the hand's entity
+#[derive(Component, Clone, Deref)]
+pub struct XrGripSpace(pub Arc<xr::Space>);
+
+/// the aim space of a hand, written to its `AimPose`
+#[derive(Component, Clone, Deref)]
+pub struct XrAimSpace(pub Arc<xr::Space>);
+
+/// the pose actions the hand entities' spaces are created from, set it before the session starts
+/// to use an action set of your own instead of the oculus touch one
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub struct XrHandInputActions {
+    pub action_set: Cow<'static, str>,
+    /// the action of [`XrGripSpace`] and [`XrTrackedSpace`]
+    pub grip: Cow<'static, str>,
+    /// the action of [`XrAimSpace`]
+    pub aim: Cow<'static, str>,
+}
+
+impl Default for XrHandInputActions {
+    fn default() -> Self {
+        Self {
+            action_set: "oculus_input".into(),
+            grip: "hand_pose".into(),
+            aim: "pointer_pose".into(),
+        }
+    }
+}
+
+/// whether [`XrTrackedSpace`] was located this frame, the transform keeps the last pose if not.
+/// `XrTrackingState` has the details
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrSpaceLocated(pub bool);
+
+/// spawns the hand entities from the [`XrHandInputActions`], again for every session
+pub fn spawn_hand_input_sources(
+    mut commands: Commands,
+    actions: Res<XrHandInputActions>,
+    action_sets: Res<XrActionSets>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    existing: Query<Entity, (With<XrInputSource>, With<XrGripSpace>)>,
+) {
+    for entity in &existing {
+        commands.entity(entity).despawn_recursive();
+    }
+    let (Ok(grip), Ok(aim)) = (
+        action_sets.get_action_posef(&actions.action_set, &actions.grip),
+        action_sets.get_action_posef(&actions.action_set, &actions.aim),
+    ) else {
+        warn!(
+            "no {} and {} pose actions in {}, not spawning the hand input sources",
+            actions.grip, actions.aim, actions.action_set
+        );
+        return;
+    };
+    for (hand, path) in [
+        (Hand::Left, "/user/hand/left"),
+        (Hand::Right, "/user/hand/right"),
+    ] {
+        let path = match instance.string_to_path(path) {
+            Ok(path) => path,
+            Err(err) => {
+                warn!("couldn't get the path of the {:?} hand: {}", hand, err);
+                continue;
+            }
+        };
+        let spaces = grip
+            .create_space((**session).clone(), path, xr::Posef::IDENTITY)
+            .and_then(|grip| {
+                let aim = aim.create_space((**session).clone(), path, xr::Posef::IDENTITY)?;
+                Ok((Arc::new(grip), Arc::new(aim)))
+            });
+        let (grip, aim) = match spaces {
+            Ok(spaces) => spaces,
+            Err(err) => {
+                warn!("couldn't create the spaces of the {:?} hand: {}", hand, err);
+                continue;
+            }
+        };
+        commands.spawn((
+            SpatialBundle::default(),
+            Name::new(format!("{:?} Hand Input", hand)),
+            XrInputSource { hand: Some(hand) },
+            XrTrackedSpace(grip.clone()),
+            XrGripSpace(grip),
+            XrAimSpace(aim),
+            XrSpaceLocated::default(),
+            AimPose(Transform::IDENTITY),
+            OpenXRTracker,
+        ));
+    }
+}
+
+pub fn update_xr_tracked_spaces(
+    spaces: XrSpaces,
+    mut tracked: Query<(
+        &XrTrackedSpace,
+        &mut Transform,
+        Option<&mut XrSpaceLocated>,
+        Option<&mut XrTrackingState>,
+    )>,
+    mut aimed: Query<(&XrAimSpace, &mut AimPose)>,
+) {
+    for (space, mut transform, located, tracking_state) in &mut tracked {
+        let location = space.locate(spaces.stage(), spaces.display_time()).ok();
+        let state = location
+            .map(|location| XrTrackingState::from_flags(location.location_flags))
+            .unwrap_or_default();
+        if let Some(location) = location.filter(|_| state.is_valid()) {
+            let pose = location.pose.to_transform();
+            transform.translation = pose.translation;
+            transform.rotation = pose.rotation;
+        }
+        if let Some(mut located) = located {
+            located.0 = state.is_valid();
+        }
+        if let Some(mut tracking_state) = tracking_state {
+            *tracking_state = state;
+        }
+    }
+    for (space, mut aim) in &mut aimed {
+        if let Some(pose) = spaces.locate(space, spaces.stage()) {
+            aim.0 = pose;
+        }
+    }
+}
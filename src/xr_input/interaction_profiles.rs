@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::resources::{XrInstance, XrSession};
+
+/// the runtime switched the interaction profile of a hand, e.g. because other controllers were
+/// connected. sent from `xr_begin_frame`, [`XrInteractionProfiles`] has the new profiles
+#[derive(Event, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrInteractionProfileChanged;
+
+/// the interaction profile the runtime picked for each hand out of the suggested ones, like
+/// `/interaction_profiles/oculus/touch_controller`. `None` until the runtime picked one, which
+/// only happens once the session is focused
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq)]
+pub struct XrInteractionProfiles {
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+impl XrInteractionProfiles {
+    pub fn contains(&self, profile: &str) -> bool {
+        self.left.as_deref() == Some(profile) || self.right.as_deref() == Some(profile)
+    }
+}
+
+pub fn update_interaction_profiles(
+    mut events: EventReader<XrInteractionProfileChanged>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    mut profiles: ResMut<XrInteractionProfiles>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    let profile = |hand| -> xr::Result<Option<String>> {
+        let path = session.current_interaction_profile(instance.string_to_path(hand)?)?;
+        if path == xr::Path::NULL {
+            return Ok(None);
+        }
+        instance.path_to_string(path).map(Some)
+    };
+    match (profile("/user/hand/left"), profile("/user/hand/right")) {
+        (Ok(left), Ok(right)) => {
+            info!("interaction profiles: left {:?}, right {:?}", left, right);
+            *profiles = XrInteractionProfiles { left, right };
+        }
+        (Err(err), _) | (_, Err(err)) => {
+            warn!("couldn't get the interaction profiles: {}", err)
+        }
+    }
+}
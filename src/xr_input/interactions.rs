@@ -2,11 +2,12 @@ use std::f32::consts::PI;
 
 use bevy::log::info;
 use bevy::prelude::{
-    Color, Component, Entity, Event, EventReader, EventWriter, Gizmos, GlobalTransform, Quat,
-    Query, Transform, Vec3, With, Without,
+    Color, Component, Entity, Event, EventReader, EventWriter, Gizmos, GlobalTransform, Local,
+    Quat, Query, Res, Time, Transform, Vec2, Vec3, With, Without,
 };
+use bevy::utils::HashMap;
 
-use super::trackers::{AimPose, OpenXRTrackingRoot};
+use super::trackers::{AimPose, XrTrackingRoot};
 
 #[derive(Component)]
 pub struct XRDirectInteractor;
@@ -17,6 +18,47 @@ pub struct XRRayInteractor;
 #[derive(Component)]
 pub struct XRSocketInteractor;
 
+/// a small sphere (usually put on the index finger tip or the tip of a controller) that presses
+/// pokeables when it is physically pushed into them
+#[derive(Component, Clone, Copy, Debug)]
+pub struct XRPokeInteractor {
+    pub radius: f32,
+}
+
+impl Default for XRPokeInteractor {
+    fn default() -> Self {
+        Self { radius: 0.01 }
+    }
+}
+
+/// selects interactables by looking at them for `dwell_time` seconds, for controller free setups
+/// and users that can't use their hands. put it on the head ([`OpenXRHMD`](super::trackers::OpenXRHMD)),
+/// it gazes along its forward direction
+#[derive(Component, Clone, Copy, Debug)]
+pub struct XRGazeInteractor {
+    pub dwell_time: f32,
+    /// how big interactables are for the gaze ray
+    pub radius: f32,
+}
+
+impl Default for XRGazeInteractor {
+    fn default() -> Self {
+        Self {
+            dwell_time: 1.0,
+            radius: 0.1,
+        }
+    }
+}
+
+/// what the gaze interactor is dwelling on, progress goes from 0.0 to 1.0 at selection
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct XRGazeDwellState {
+    pub target: Option<Entity>,
+    pub progress: f32,
+    /// set after the dwell completed, the user has to look away before selecting again
+    pub completed: bool,
+}
+
 #[derive(Component)]
 pub struct Touched(pub bool);
 
@@ -57,6 +99,32 @@ impl Default for XRSelection {
 #[derive(Component)]
 pub struct XRInteractable;
 
+/// a flat button or panel that can be poked, the surface lies in the local XY plane facing +Z
+#[derive(Component, Clone, Copy, Debug)]
+pub struct XRPokeable {
+    /// half of the width and height of the surface
+    pub half_extents: Vec2,
+    /// how far the surface has to be pushed along -Z before it counts as pressed
+    pub press_depth: f32,
+}
+
+impl Default for XRPokeable {
+    fn default() -> Self {
+        Self {
+            half_extents: Vec2::splat(0.05),
+            press_depth: 0.01,
+        }
+    }
+}
+
+/// current press state of a pokeable, progress goes from 0.0 (untouched) to 1.0 (fully pressed)
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct XRPokeState {
+    pub progress: f32,
+    pub pressed: bool,
+    pub interactor: Option<Entity>,
+}
+
 pub fn draw_socket_gizmos(
     mut gizmos: Gizmos,
     interactor_query: Query<(
@@ -93,7 +161,7 @@ pub fn draw_interaction_gizmos(
         ),
         Without<XRInteractable>,
     >,
-    tracking_root_query: Query<(&mut Transform, With<OpenXRTrackingRoot>)>,
+    tracking_root_query: Query<(&mut Transform, With<XrTrackingRoot>)>,
 ) {
     let root = tracking_root_query.get_single().unwrap().0;
     for (global_transform, interactable_state) in interactable_query.iter() {
@@ -136,7 +204,7 @@ pub fn draw_interaction_gizmos(
                         XRInteractorState::Selecting => Color::PURPLE,
                     };
                     gizmos.ray(
-                        root.translation + root.rotation.mul_vec3(aim.0.translation),
+                        root.transform_point(aim.0.translation),
                         root.rotation.mul_vec3(aim.0.forward()),
                         color,
                     );
@@ -155,6 +223,165 @@ pub struct InteractionEvent {
     pub interactable_state: XRInteractableState,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PokeEventKind {
+    Pressed,
+    Released,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PokeEvent {
+    pub interactor: Entity,
+    pub pokeable: Entity,
+    pub kind: PokeEventKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GazeDwellEventKind {
+    Started,
+    Progress(f32),
+    Completed,
+    Cancelled,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct GazeDwellEvent {
+    pub interactor: Entity,
+    pub interactable: Entity,
+    pub kind: GazeDwellEventKind,
+}
+
+/// the progress where a pressed pokeable gets released again, so it doesn't flicker at full depth
+const POKE_RELEASE_PROGRESS: f32 = 0.5;
+
+/// sweeps the interactors from where they were last frame, so a fast poke can't skip through a
+/// thin pokeable, and keeps a pokeable fully pressed while its interactor is pushed past it
+pub fn poke_interactions(
+    interactor_query: Query<(&GlobalTransform, &XRPokeInteractor, Entity)>,
+    mut pokeable_query: Query<
+        (&GlobalTransform, &XRPokeable, &mut XRPokeState, Entity),
+        Without<XRPokeInteractor>,
+    >,
+    mut writer: EventWriter<PokeEvent>,
+    mut last_positions: Local<HashMap<Entity, Vec3>>,
+) {
+    for (pokeable_global_transform, pokeable, mut poke_state, pokeable_entity) in
+        pokeable_query.iter_mut()
+    {
+        let to_local = pokeable_global_transform.affine().inverse();
+        //find the interactor that pushes the surface the deepest
+        let mut deepest: Option<(Entity, f32)> = None;
+        for (interactor_global_transform, interactor, interactor_entity) in interactor_query.iter()
+        {
+            let local = to_local.transform_point3(interactor_global_transform.translation());
+            let radius = interactor.radius;
+            let over_surface = |point: Vec3| {
+                point.x.abs() <= pokeable.half_extents.x + radius
+                    && point.y.abs() <= pokeable.half_extents.y + radius
+            };
+            //has to be over the surface and touching the front
+            if !over_surface(local) || local.z > radius {
+                continue;
+            }
+            //only the interactor that started the poke can keep pressing
+            if poke_state
+                .interactor
+                .is_some_and(|e| e != interactor_entity)
+            {
+                continue;
+            }
+            //past the back it only counts when it came in through the front, either in an
+            //earlier frame or by crossing the front since the last frame
+            if local.z < -pokeable.press_depth && poke_state.interactor.is_none() {
+                let crossed_front = last_positions
+                    .get(&interactor_entity)
+                    .map(|last| to_local.transform_point3(*last))
+                    .is_some_and(|last| {
+                        last.z > radius
+                            && over_surface(
+                                last.lerp(local, (last.z - radius) / (last.z - local.z)),
+                            )
+                    });
+                if !crossed_front {
+                    continue;
+                }
+            }
+            let depth = (radius - local.z).min(pokeable.press_depth);
+            if deepest.map_or(true, |(_, d)| depth > d) {
+                deepest = Some((interactor_entity, depth));
+            }
+        }
+        match deepest {
+            Some((interactor_entity, depth)) => {
+                poke_state.interactor = Some(interactor_entity);
+                poke_state.progress = (depth / pokeable.press_depth).clamp(0.0, 1.0);
+                if !poke_state.pressed && poke_state.progress >= 1.0 {
+                    poke_state.pressed = true;
+                    writer.send(PokeEvent {
+                        interactor: interactor_entity,
+                        pokeable: pokeable_entity,
+                        kind: PokeEventKind::Pressed,
+                    });
+                } else if poke_state.pressed && poke_state.progress < POKE_RELEASE_PROGRESS {
+                    poke_state.pressed = false;
+                    writer.send(PokeEvent {
+                        interactor: interactor_entity,
+                        pokeable: pokeable_entity,
+                        kind: PokeEventKind::Released,
+                    });
+                }
+            }
+            None => {
+                if let Some(interactor_entity) = poke_state.interactor {
+                    if poke_state.pressed {
+                        writer.send(PokeEvent {
+                            interactor: interactor_entity,
+                            pokeable: pokeable_entity,
+                            kind: PokeEventKind::Released,
+                        });
+                    }
+                }
+                *poke_state = XRPokeState::default();
+            }
+        }
+    }
+    last_positions.clear();
+    for (interactor_global_transform, _, interactor_entity) in interactor_query.iter() {
+        last_positions.insert(interactor_entity, interactor_global_transform.translation());
+    }
+}
+
+pub fn draw_poke_gizmos(
+    mut gizmos: Gizmos,
+    pokeable_query: Query<(&GlobalTransform, &XRPokeable, &XRPokeState)>,
+    interactor_query: Query<(&GlobalTransform, &XRPokeInteractor)>,
+) {
+    for (global_transform, pokeable, state) in pokeable_query.iter() {
+        let transform = global_transform.compute_transform();
+        let color = match state.pressed {
+            true => Color::GREEN,
+            false => Color::RED * (1.0 - state.progress) + Color::YELLOW * state.progress,
+        };
+        //push the drawn surface in with the press progress
+        let offset = transform.rotation * Vec3::NEG_Z * (state.progress * pokeable.press_depth);
+        gizmos.rect(
+            transform.translation + offset,
+            transform.rotation,
+            pokeable.half_extents * 2.0,
+            color,
+        );
+    }
+    for (global_transform, interactor) in interactor_query.iter() {
+        let transform = global_transform.compute_transform();
+        gizmos.sphere(
+            transform.translation,
+            transform.rotation,
+            interactor.radius,
+            Color::BLUE,
+        );
+    }
+}
+
 pub fn socket_interactions(
     interactable_query: Query<
         (&GlobalTransform, &mut XRInteractableState, Entity),
@@ -229,7 +456,7 @@ pub fn interactions(
         ),
         Without<XRInteractable>,
     >,
-    tracking_root_query: Query<(&mut Transform, With<OpenXRTrackingRoot>)>,
+    tracking_root_query: Query<(&mut Transform, With<XrTrackingRoot>)>,
     mut writer: EventWriter<InteractionEvent>,
 ) {
     for (xr_interactable_global_transform, interactable_entity) in interactable_query.iter() {
@@ -283,8 +510,7 @@ pub fn interactions(
                     let root = tracking_root_query.get_single().unwrap().0;
                     match aim {
                         Some(aim) => {
-                            let ray_origin =
-                                root.translation + root.rotation.mul_vec3(aim.0.translation);
+                            let ray_origin = root.transform_point(aim.0.translation);
                             let ray_dir = root.rotation.mul_vec3(aim.0.forward());
 
                             if ray_sphere_intersection(
@@ -323,6 +549,101 @@ pub fn interactions(
     }
 }
 
+/// hovers the looked at interactable and selects it for one frame once the dwell time is up,
+/// sending [`GazeDwellEvent`]s on the way. runs before [`update_interactable_states`] like the
+/// other interactors
+pub fn gaze_interactions(
+    time: Res<Time>,
+    mut interactor_query: Query<
+        (
+            &GlobalTransform,
+            &XRGazeInteractor,
+            &mut XRGazeDwellState,
+            Option<&mut XRInteractorState>,
+            Entity,
+        ),
+        Without<XRInteractable>,
+    >,
+    interactable_query: Query<(&GlobalTransform, Entity), With<XRInteractable>>,
+    mut writer: EventWriter<InteractionEvent>,
+    mut dwell_writer: EventWriter<GazeDwellEvent>,
+) {
+    for (interactor_global_transform, gaze, mut dwell, interactor_state, interactor_entity) in
+        interactor_query.iter_mut()
+    {
+        let origin = interactor_global_transform.translation();
+        let dir = interactor_global_transform.forward().normalize_or_zero();
+        //the closest interactable along the gaze
+        let target = interactable_query
+            .iter()
+            .filter_map(|(global_transform, entity)| {
+                ray_sphere_distance(global_transform.translation(), gaze.radius, origin, dir)
+                    .map(|distance| (entity, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entity, _)| entity);
+
+        if dwell.target != target {
+            if let (Some(old), false) = (dwell.target, dwell.completed) {
+                dwell_writer.send(GazeDwellEvent {
+                    interactor: interactor_entity,
+                    interactable: old,
+                    kind: GazeDwellEventKind::Cancelled,
+                });
+            }
+            *dwell = XRGazeDwellState {
+                target,
+                ..Default::default()
+            };
+            if let Some(new) = target {
+                dwell_writer.send(GazeDwellEvent {
+                    interactor: interactor_entity,
+                    interactable: new,
+                    kind: GazeDwellEventKind::Started,
+                });
+            }
+        }
+
+        let mut selecting = false;
+        if let Some(interactable) = dwell.target {
+            if !dwell.completed {
+                dwell.progress = match gaze.dwell_time > 0.0 {
+                    true => (dwell.progress + time.delta_seconds() / gaze.dwell_time).min(1.0),
+                    false => 1.0,
+                };
+                dwell_writer.send(GazeDwellEvent {
+                    interactor: interactor_entity,
+                    interactable,
+                    kind: GazeDwellEventKind::Progress(dwell.progress),
+                });
+                if dwell.progress >= 1.0 {
+                    dwell.completed = true;
+                    selecting = true;
+                    dwell_writer.send(GazeDwellEvent {
+                        interactor: interactor_entity,
+                        interactable,
+                        kind: GazeDwellEventKind::Completed,
+                    });
+                }
+            }
+            writer.send(InteractionEvent {
+                interactor: interactor_entity,
+                interactable,
+                interactable_state: match selecting {
+                    true => XRInteractableState::Select,
+                    false => XRInteractableState::Hover,
+                },
+            });
+        }
+        if let Some(mut state) = interactor_state {
+            *state = match selecting {
+                true => XRInteractorState::Selecting,
+                false => XRInteractorState::Idle,
+            };
+        }
+    }
+}
+
 pub fn update_interactable_states(
     mut events: EventReader<InteractionEvent>,
     mut interactable_query: Query<
@@ -360,6 +681,16 @@ pub fn update_interactable_states(
     }
 }
 
+// distance along the ray to the closest point to the center, if the ray hits the sphere
+fn ray_sphere_distance(center: Vec3, radius: f32, ray_origin: Vec3, ray_dir: Vec3) -> Option<f32> {
+    let l = center - ray_origin;
+    let adj = l.dot(ray_dir);
+    if adj < 0.0 || l.length_squared() - adj * adj > radius * radius {
+        return None;
+    }
+    Some(adj)
+}
+
 fn ray_sphere_intersection(center: Vec3, radius: f32, ray_origin: Vec3, ray_dir: Vec3) -> bool {
     let l = center - ray_origin;
     let adj = l.dot(ray_dir);
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::xr_init::xr_only;
+
+use super::face_hmd::update_face_hmd;
+use super::trackers::{OpenXRHMD, XrTrackingRoot};
+
+/// scales world space text and ui with an [`XrLegibleSize`] so it covers the same angle of the
+/// view from any distance, within limits. a label across the room stays readable and one right
+/// in front of the user doesn't fill the view. replaces the constant size scaling of
+/// [`FaceHmd`](super::face_hmd::FaceHmd) on entities that have both
+pub struct XrLegibilityPlugin;
+
+impl Plugin for XrLegibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            update_legible_sizes
+                .run_if(xr_only())
+                .after(update_face_hmd)
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// the uniform scale is set so that `height` covers `angular_height` seen from the head
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct XrLegibleSize {
+    /// the height of the entity at a scale of 1, like the line height of a text, in world units
+    pub height: f32,
+    /// in radians
+    pub angular_height: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+}
+
+impl XrLegibleSize {
+    /// `degrees` is the angle `height` covers, around 1 degree is a comfortable text size
+    pub fn new(height: f32, degrees: f32) -> Self {
+        Self {
+            height,
+            angular_height: degrees.to_radians(),
+            min_scale: 0.0,
+            max_scale: f32::INFINITY,
+        }
+    }
+
+    pub fn with_limits(mut self, min_scale: f32, max_scale: f32) -> Self {
+        self.min_scale = min_scale;
+        self.max_scale = max_scale;
+        self
+    }
+
+    pub fn scale_at(&self, distance: f32) -> f32 {
+        let height = 2.0 * distance * (self.angular_height * 0.5).tan();
+        (height / self.height.max(1e-6)).clamp(self.min_scale, self.max_scale.max(self.min_scale))
+    }
+}
+
+pub fn update_legible_sizes(
+    mut legible: Query<(&XrLegibleSize, &mut Transform, &GlobalTransform)>,
+    root: Query<&Transform, (With<XrTrackingRoot>, Without<XrLegibleSize>)>,
+    hmd: Query<&Transform, (With<OpenXRHMD>, Without<XrLegibleSize>)>,
+) {
+    let root = root.get_single().copied().unwrap_or_default();
+    let Ok(head) = hmd.get_single().map(|hmd| root.mul_transform(*hmd)) else {
+        return;
+    };
+    for (size, mut transform, global) in legible.iter_mut() {
+        //the global position is from the last frame, close enough for a size, and works for
+        //children of unscaled entities like a panel on a controller
+        let distance = global.translation().distance(head.translation);
+        let scale = Vec3::splat(size.scale_at(distance));
+        if transform.scale != scale {
+            transform.scale = scale;
+        }
+    }
+}
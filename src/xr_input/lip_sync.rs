@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use bevy::render::mesh::morph::MorphWeights;
+
+/// animates avatar mouths the same way whether the weights come from face tracking or only a
+/// voice is heard. put an [`XrLipSync`] on an avatar and feed it every frame, face tracking
+/// weights (local or received over the network) win over the voice amplitude while there are
+/// any. an [`XrMouthMorphTargets`] next to it writes the result into the mesh's morph weights
+pub struct XrLipSyncPlugin;
+
+impl Plugin for XrLipSyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (update_lip_sync, apply_mouth_morph_targets).chain(),
+        );
+    }
+}
+
+/// the jaw and mouth blendshapes lip sync needs, each from 0 to 1
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrMouthShape {
+    pub jaw_open: f32,
+    /// lips closed while the jaw is open, like `m` or `b`
+    pub lips_closed: f32,
+    /// lips rounded and open, like `o`
+    pub lips_funnel: f32,
+    /// lips rounded and pushed forward, like `u`
+    pub lips_pucker: f32,
+    /// the corners of the mouth pulled apart, like `e`
+    pub mouth_stretch: f32,
+}
+
+impl XrMouthShape {
+    /// a rough mouth for a voice as loud as `energy`, from 0 to 1
+    pub fn from_energy(energy: f32) -> Self {
+        let energy = energy.clamp(0.0, 1.0);
+        Self {
+            jaw_open: energy,
+            lips_funnel: energy * 0.3,
+            mouth_stretch: energy * 0.2,
+            ..default()
+        }
+    }
+
+    pub fn lerp(&self, target: &Self, alpha: f32) -> Self {
+        let lerp = |from: f32, to: f32| from + (to - from) * alpha;
+        Self {
+            jaw_open: lerp(self.jaw_open, target.jaw_open),
+            lips_closed: lerp(self.lips_closed, target.lips_closed),
+            lips_funnel: lerp(self.lips_funnel, target.lips_funnel),
+            lips_pucker: lerp(self.lips_pucker, target.lips_pucker),
+            mouth_stretch: lerp(self.mouth_stretch, target.mouth_stretch),
+        }
+    }
+}
+
+/// where the mouth of an [`XrLipSync`] comes from this frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum XrLipSyncSource {
+    #[default]
+    None,
+    FaceTracking,
+    Voice,
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct XrLipSync {
+    /// the mouth from face tracking, `None` while there is no face tracking
+    pub tracked: Option<XrMouthShape>,
+    /// the rms amplitude of the voice, from 0 to 1. used when nothing is tracked
+    pub voice_amplitude: f32,
+    /// voices quieter than this keep the mouth closed
+    pub noise_floor: f32,
+    /// voices this loud open the mouth all the way
+    pub full_amplitude: f32,
+    /// seconds until half of an opening went through
+    pub attack_half_life: f32,
+    /// seconds until half of a closing went through
+    pub release_half_life: f32,
+    mouth: XrMouthShape,
+    source: XrLipSyncSource,
+}
+
+impl Default for XrLipSync {
+    fn default() -> Self {
+        Self {
+            tracked: None,
+            voice_amplitude: 0.0,
+            noise_floor: 0.02,
+            full_amplitude: 0.3,
+            attack_half_life: 0.02,
+            release_half_life: 0.06,
+            mouth: default(),
+            source: default(),
+        }
+    }
+}
+
+impl XrLipSync {
+    /// the smoothed mouth
+    pub fn mouth(&self) -> XrMouthShape {
+        self.mouth
+    }
+
+    pub fn source(&self) -> XrLipSyncSource {
+        self.source
+    }
+
+    /// how far the voice opens the mouth, from 0 to 1
+    pub fn voice_energy(&self) -> f32 {
+        let range = (self.full_amplitude - self.noise_floor).max(f32::EPSILON);
+        ((self.voice_amplitude - self.noise_floor) / range).clamp(0.0, 1.0)
+    }
+
+    /// moves the mouth toward its target, `dt` is the time since the last call in seconds
+    pub fn update(&mut self, dt: f32) {
+        let (target, source) = match self.tracked {
+            Some(tracked) => (tracked, XrLipSyncSource::FaceTracking),
+            None if self.voice_energy() > 0.0 => (
+                XrMouthShape::from_energy(self.voice_energy()),
+                XrLipSyncSource::Voice,
+            ),
+            None => (XrMouthShape::default(), XrLipSyncSource::None),
+        };
+        //tracked weights go through the same filter, so network jitter and switching between
+        //the sources don't make the mouth jump
+        self.source = source;
+        let half_life = match target.jaw_open > self.mouth.jaw_open {
+            true => self.attack_half_life,
+            false => self.release_half_life,
+        };
+        let alpha = match half_life > 0.0 {
+            true => 1.0 - 0.5f32.powf(dt / half_life),
+            false => 1.0,
+        };
+        self.mouth = self.mouth.lerp(&target, alpha);
+    }
+}
+
+/// the morph targets of a mesh each mouth blendshape drives, `None` for the ones it doesn't
+/// have. needs an [`XrLipSync`] and the `MorphWeights` of the mesh on the same entity
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrMouthMorphTargets {
+    pub jaw_open: Option<usize>,
+    pub lips_closed: Option<usize>,
+    pub lips_funnel: Option<usize>,
+    pub lips_pucker: Option<usize>,
+    pub mouth_stretch: Option<usize>,
+}
+
+pub fn update_lip_sync(time: Res<Time>, mut lip_syncs: Query<&mut XrLipSync>) {
+    for mut lip_sync in &mut lip_syncs {
+        lip_sync.update(time.delta_seconds());
+    }
+}
+
+pub fn apply_mouth_morph_targets(
+    mut meshes: Query<(&XrLipSync, &XrMouthMorphTargets, &mut MorphWeights)>,
+) {
+    for (lip_sync, targets, mut weights) in &mut meshes {
+        let mouth = lip_sync.mouth();
+        let weights = weights.weights_mut();
+        for (index, weight) in [
+            (targets.jaw_open, mouth.jaw_open),
+            (targets.lips_closed, mouth.lips_closed),
+            (targets.lips_funnel, mouth.lips_funnel),
+            (targets.lips_pucker, mouth.lips_pucker),
+            (targets.mouth_stretch, mouth.mouth_stretch),
+        ] {
+            if let Some(slot) = index.and_then(|index| weights.get_mut(index)) {
+                *slot = weight;
+            }
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::window::PrimaryWindow;
+use openxr::Fovf;
+
+use crate::xr_init::{xr_only, XrSetup};
+
+use super::trackers::XrTrackingRoot;
+use super::views::XrView;
+use super::xr_camera::{xr_camera_head_sync, Eye, XRProjection, XrCameraBundle, XrCameraType};
+
+/// shows what the headset sees in the primary window. the mirror renders the scene again from
+/// the eye poses, it doesn't copy the swapchain images
+pub struct XrMirrorPlugin;
+
+impl Plugin for XrMirrorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrMirrorSettings>();
+        app.add_systems(XrSetup, spawn_xr_mirror_cameras);
+        app.add_systems(
+            PreUpdate,
+            update_xr_mirror_cameras
+                .run_if(xr_only())
+                .after(xr_camera_head_sync),
+        );
+    }
+}
+
+/// can be changed at runtime
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrMirrorSettings {
+    pub mode: XrMirrorMode,
+    pub aspect: XrMirrorAspect,
+    /// vertical fov of the stabilized view, in radians
+    pub stabilized_fov: f32,
+    /// how fast the stabilized view follows the head, higher is snappier
+    pub stabilization: f32,
+}
+
+impl Default for XrMirrorSettings {
+    fn default() -> Self {
+        Self {
+            mode: XrMirrorMode::Left,
+            aspect: XrMirrorAspect::Crop,
+            stabilized_fov: 60f32.to_radians(),
+            stabilization: 5.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrMirrorMode {
+    Disabled,
+    Left,
+    Right,
+    /// both eyes side by side
+    Both,
+    /// a smoothed view from between the eyes without roll, nicer to watch for spectators
+    Stabilized,
+}
+
+/// what to do when the window doesn't have the aspect ratio of the eye
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrMirrorAspect {
+    /// shows the whole eye with black bars
+    Letterbox,
+    /// fills the window and cuts off the edges of the eye
+    Crop,
+}
+
+/// one of the two mirror cameras, the second one is only used to show both eyes
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XrMirrorCamera {
+    pub index: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MirrorSource {
+    Eye(Eye),
+    Stabilized,
+}
+
+fn mirror_source(mode: XrMirrorMode, index: usize) -> Option<MirrorSource> {
+    match (mode, index) {
+        (XrMirrorMode::Left, 0) | (XrMirrorMode::Both, 0) => Some(MirrorSource::Eye(Eye::Left)),
+        (XrMirrorMode::Right, 0) | (XrMirrorMode::Both, 1) => Some(MirrorSource::Eye(Eye::Right)),
+        (XrMirrorMode::Stabilized, 0) => Some(MirrorSource::Stabilized),
+        _ => None,
+    }
+}
+
+pub fn spawn_xr_mirror_cameras(mut commands: Commands) {
+    for index in 0..2 {
+        let mut bundle = XrCameraBundle::new(Eye::Left);
+        bundle.camera = Camera {
+            order: 1 + index as isize,
+            is_active: false,
+            ..default()
+        };
+        //the second camera draws next to the first one, it must not clear its half
+        bundle.camera_3d.clear_color = match index {
+            0 => ClearColorConfig::Custom(Color::BLACK),
+            _ => ClearColorConfig::None,
+        };
+        bundle.xr_camera_type = XrCameraType::Flatscreen;
+        commands.spawn((bundle, XrMirrorCamera { index }));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_xr_mirror_cameras(
+    settings: Res<XrMirrorSettings>,
+    time: Res<Time>,
+    views: Query<(&XrView, &Transform), Without<XrMirrorCamera>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    root: Query<&Transform, (With<XrTrackingRoot>, Without<XrMirrorCamera>)>,
+    mut cameras: Query<(
+        &XrMirrorCamera,
+        &mut Camera,
+        &mut Transform,
+        &mut XRProjection,
+    )>,
+) {
+    let find = |index| views.iter().find(|(view, _)| view.index == index);
+    let window_size = windows
+        .get_single()
+        .ok()
+        .map(|window| UVec2::new(window.physical_width(), window.physical_height()))
+        .filter(|size| size.x > 1 && size.y > 0);
+    let root = root.get_single().copied().unwrap_or_default();
+    for (mirror, mut camera, mut transform, mut projection) in cameras.iter_mut() {
+        let source = mirror_source(settings.mode, mirror.index);
+        let (Some(source), Some(window_size), Some(left), Some(right)) =
+            (source, window_size, find(0), find(1))
+        else {
+            if camera.is_active {
+                camera.is_active = false;
+            }
+            continue;
+        };
+        camera.is_active = true;
+        //both eyes split the window in halves
+        let (region_position, region_size) = match settings.mode {
+            XrMirrorMode::Both => (
+                UVec2::new(mirror.index as u32 * window_size.x / 2, 0),
+                UVec2::new(window_size.x / 2, window_size.y),
+            ),
+            _ => (UVec2::ZERO, window_size),
+        };
+        match source {
+            MirrorSource::Eye(eye) => {
+                let view = match eye {
+                    Eye::Left => left,
+                    Eye::Right => right,
+                };
+                *transform = root.mul_transform(*view.1);
+                let (fov, viewport) =
+                    fit_fov(view.0.fov, region_position, region_size, settings.aspect);
+                projection.fov = fov;
+                camera.viewport = Some(viewport);
+            }
+            MirrorSource::Stabilized => {
+                let position = (left.1.translation + right.1.translation) / 2.0;
+                let forward = left.1.forward();
+                //drop the roll, it is the most uncomfortable part to watch
+                let target = root.mul_transform(
+                    Transform::from_translation(position).looking_to(forward, Vec3::Y),
+                );
+                let t = 1.0 - (-settings.stabilization * time.delta_seconds()).exp();
+                transform.rotation = transform.rotation.slerp(target.rotation, t);
+                transform.translation = target.translation;
+                let tan_v = (settings.stabilized_fov / 2.0).tan();
+                let tan_h = tan_v * region_size.x as f32 / region_size.y as f32;
+                projection.fov = Fovf {
+                    angle_left: -tan_h.atan(),
+                    angle_right: tan_h.atan(),
+                    angle_up: tan_v.atan(),
+                    angle_down: -tan_v.atan(),
+                };
+                camera.viewport = Some(Viewport {
+                    physical_position: region_position,
+                    physical_size: region_size,
+                    ..default()
+                });
+            }
+        }
+    }
+}
+
+// fits the eye fov into the window region, either by shrinking the viewport (letterbox) or by
+// cutting the fov down to the region aspect (crop)
+fn fit_fov(
+    fov: Fovf,
+    region_position: UVec2,
+    region_size: UVec2,
+    aspect: XrMirrorAspect,
+) -> (Fovf, Viewport) {
+    let (left, right) = (fov.angle_left.tan(), fov.angle_right.tan());
+    let (down, up) = (fov.angle_down.tan(), fov.angle_up.tan());
+    let eye_aspect = (right - left) / (up - down);
+    let region_aspect = region_size.x as f32 / region_size.y as f32;
+    match aspect {
+        XrMirrorAspect::Letterbox => {
+            let size = match region_aspect > eye_aspect {
+                true => UVec2::new((region_size.y as f32 * eye_aspect) as u32, region_size.y),
+                false => UVec2::new(region_size.x, (region_size.x as f32 / eye_aspect) as u32),
+            }
+            .max(UVec2::ONE);
+            let viewport = Viewport {
+                physical_position: region_position + (region_size - size) / 2,
+                physical_size: size,
+                ..default()
+            };
+            (fov, viewport)
+        }
+        XrMirrorAspect::Crop => {
+            let viewport = Viewport {
+                physical_position: region_position,
+                physical_size: region_size,
+                ..default()
+            };
+            let fov = match region_aspect > eye_aspect {
+                true => {
+                    let half = (right - left) / region_aspect / 2.0;
+                    let center = (up + down) / 2.0;
+                    Fovf {
+                        angle_down: (center - half).atan(),
+                        angle_up: (center + half).atan(),
+                        ..fov
+                    }
+                }
+                false => {
+                    let half = (up - down) * region_aspect / 2.0;
+                    let center = (right + left) / 2.0;
+                    Fovf {
+                        angle_left: (center - half).atan(),
+                        angle_right: (center + half).atan(),
+                        ..fov
+                    }
+                }
+            };
+            (fov, viewport)
+        }
+    }
+}
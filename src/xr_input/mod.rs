@@ -1,46 +1,108 @@
+#[cfg(feature = "action-assets")]
+pub mod action_assets;
+pub mod action_debug;
+pub mod action_manifest;
 pub mod actions;
+pub mod arm_estimation;
+pub mod avatar_rig;
+pub mod calibration;
+pub mod controller_attached;
 pub mod controllers;
 pub mod debug_gizmos;
+pub mod eye_diagnostics;
+pub mod eye_metrics;
+pub mod eye_tracking;
+pub mod face_hmd;
+pub mod face_tracking;
+pub mod floor_height;
+pub mod gamepad;
 pub mod hand_poses;
 pub mod hands;
+pub mod haptics;
+pub mod head_velocity;
+pub mod input_sources;
+pub mod interaction_profiles;
 pub mod interactions;
+pub mod legibility;
+pub mod lip_sync;
+pub mod mirror;
 pub mod oculus_touch;
+pub mod pose_filter;
+pub mod pose_snapshot;
+pub mod press_gestures;
 pub mod prototype_locomotion;
+pub mod radial_menu;
+pub mod shared_eye_phases;
+pub mod single_controller;
+pub mod spaces;
+pub mod touch_pro;
 pub mod trackers;
+pub mod ui_pointers;
+pub mod valve_index;
+pub mod views;
+pub mod virtual_keyboard;
+pub mod wrist_anchor;
 pub mod xr_camera;
+pub mod xr_camera_settings;
+pub mod xr_render_graph;
+pub mod xr_shadows;
+pub mod xr_taa;
+pub mod xr_view_matrices;
 
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrFrameSet;
 use crate::resources::{XrInstance, XrSession};
-use crate::xr_begin_frame;
 use crate::xr_init::{xr_only, XrPostSetup, XrSetup};
 use crate::xr_input::controllers::XrControllerType;
 use crate::xr_input::oculus_touch::setup_oculus_controller;
-use crate::xr_input::xr_camera::{xr_camera_head_sync, Eye, XRProjection, XrCameraBundle};
+use crate::xr_input::touch_pro::XrTouchProPlugin;
+use crate::xr_input::valve_index::XrValveIndexPlugin;
+use crate::xr_input::xr_camera::{
+    share_xr_visible_entities, update_xr_stereo_frustum, xr_camera_head_sync, Eye, XRProjection,
+    XrCameraBundle,
+};
+use crate::xr_input::xr_shadows::XrSharedShadowsPlugin;
 use bevy::app::{App, PostUpdate, Startup};
 use bevy::ecs::entity::Entity;
 use bevy::ecs::query::With;
+use bevy::ecs::schedule::common_conditions::resource_equals;
 use bevy::ecs::system::Query;
-use bevy::log::{info, warn};
+use bevy::log::info;
 use bevy::math::Vec2;
 use bevy::prelude::{BuildChildren, Component, Deref, DerefMut, IntoSystemConfigs, Resource};
 use bevy::prelude::{Commands, Plugin, PreUpdate, Quat, Res, SpatialBundle, Update, Vec3};
 use bevy::render::camera::CameraProjectionPlugin;
+use bevy::render::primitives::Frustum;
 use bevy::render::view::{update_frusta, VisibilitySystems};
 use bevy::transform::TransformSystem;
 use bevy::utils::HashMap;
 use openxr::Binding;
 
-use self::actions::{setup_oxr_actions, OpenXrActionsPlugin};
-use self::oculus_touch::{post_action_setup_oculus_controller, ActionSets};
+use self::actions::{
+    setup_oxr_actions, OpenXrActionsPlugin, XrActionSets, XrActionSyncMode, XrSyncActions,
+};
+use self::eye_metrics::{update_xr_eye_metrics, XrEyeMetrics, XrIpdChanged};
+use self::floor_height::XrFloorHeightPlugin;
+use self::head_velocity::{update_xr_head_velocity, XrHeadVelocity};
+use self::input_sources::{spawn_hand_input_sources, update_xr_tracked_spaces, XrHandInputActions};
+use self::interaction_profiles::{update_interaction_profiles, XrInteractionProfiles};
+use self::oculus_touch::post_action_setup_oculus_controller;
 use self::trackers::{
-    adopt_open_xr_trackers, update_open_xr_controllers, OpenXRLeftEye, OpenXRRightEye,
-    OpenXRTrackingRoot,
+    adopt_open_xr_trackers, update_hmd_tracking_state, update_open_xr_controllers,
+    update_open_xr_hmd, update_xr_world_scale, OpenXRHMD, OpenXRLeftEye, OpenXRRightEye,
+    OpenXRTracker, XrTrackingRoot, XrWorldScale,
 };
+use self::views::{sync_xr_view_entities, XrViewEntitiesPlugin};
+
+//the conversions used to live here
+pub use crate::convert::{QuatConv, Vec2Conv, Vec3Conv};
 
 #[derive(Copy, Clone)]
 pub struct OpenXrInput {
     pub controller_type: XrControllerType,
 }
-#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Component)]
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Component)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum Hand {
     Left,
     Right,
@@ -56,18 +118,74 @@ impl Plugin for OpenXrInput {
     fn build(&self, app: &mut App) {
         app.add_plugins(CameraProjectionPlugin::<XRProjection>::default());
         app.add_plugins(OpenXrActionsPlugin);
-        app.add_systems(XrPostSetup, post_action_setup_oculus_controller);
+        app.add_plugins(XrViewEntitiesPlugin);
+        app.add_plugins(XrFloorHeightPlugin);
+        app.add_plugins(XrSharedShadowsPlugin);
+        app.add_systems(
+            XrPostSetup,
+            (
+                post_action_setup_oculus_controller,
+                spawn_hand_input_sources.after(post_action_setup_oculus_controller),
+            ),
+        );
         match self.controller_type {
             XrControllerType::OculusTouch => {
                 app.add_systems(XrSetup, setup_oculus_controller);
             }
+            XrControllerType::ValveIndex => {
+                app.add_systems(XrSetup, setup_oculus_controller);
+                app.add_plugins(XrValveIndexPlugin);
+            }
+            XrControllerType::TouchPro => {
+                app.add_systems(XrSetup, setup_oculus_controller);
+                app.add_plugins(XrTouchProPlugin);
+            }
         }
+        app.init_resource::<XrWorldScale>();
+        app.init_resource::<XrHeadVelocity>();
+        app.init_resource::<XrEyeMetrics>();
+        app.init_resource::<XrInteractionProfiles>();
+        app.init_resource::<XrHandInputActions>();
+        app.add_event::<XrIpdChanged>();
         //adopt any new trackers
         app.add_systems(PreUpdate, adopt_open_xr_trackers.run_if(xr_only()));
-        app.add_systems(PreUpdate, action_set_system.run_if(xr_only()));
+        app.configure_sets(PreUpdate, XrSyncActions.in_set(XrFrameSet::AfterBeginFrame));
+        app.add_systems(
+            PreUpdate,
+            action_set_system
+                .run_if(xr_only())
+                .run_if(resource_equals(XrActionSyncMode::Automatic))
+                .in_set(XrSyncActions),
+        );
+        app.add_systems(PreUpdate, update_xr_world_scale.run_if(xr_only()));
+        app.add_systems(
+            PreUpdate,
+            update_interaction_profiles
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+        app.add_systems(
+            PreUpdate,
+            update_xr_tracked_spaces
+                .run_if(xr_only())
+                .after(XrSyncActions),
+        );
+        app.add_systems(
+            PreUpdate,
+            update_xr_head_velocity
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
         app.add_systems(
             PreUpdate,
-            xr_camera_head_sync.run_if(xr_only()).after(xr_begin_frame),
+            (
+                xr_camera_head_sync,
+                update_open_xr_hmd,
+                update_hmd_tracking_state,
+                update_xr_eye_metrics,
+            )
+                .run_if(xr_only())
+                .after(sync_xr_view_entities),
         );
         //update controller trackers
         app.add_systems(Update, update_open_xr_controllers.run_if(xr_only()));
@@ -77,6 +195,15 @@ impl Plugin for OpenXrInput {
                 .after(TransformSystem::TransformPropagate)
                 .before(VisibilitySystems::UpdatePerspectiveFrusta),
         );
+        app.add_systems(
+            PostUpdate,
+            (
+                update_xr_stereo_frustum
+                    .after(update_frusta::<XRProjection>)
+                    .before(VisibilitySystems::CheckVisibility),
+                share_xr_visible_entities.after(VisibilitySystems::CheckVisibility),
+            ),
+        );
         app.add_systems(XrSetup, setup_xr_cameras);
     }
 }
@@ -94,7 +221,7 @@ fn setup_binding_recommendations(
 
 fn setup_xr_cameras(
     mut commands: Commands,
-    tracking_root_query: Query<Entity, With<OpenXRTrackingRoot>>,
+    tracking_root_query: Query<Entity, With<XrTrackingRoot>>,
 ) {
     //this needs to do the whole xr tracking volume not just cameras
     //get the root?
@@ -102,56 +229,35 @@ fn setup_xr_cameras(
     let tracking_root = match tracking_root_query.get_single() {
         Ok(e) => e,
         Err(_) => commands
-            .spawn((SpatialBundle::default(), OpenXRTrackingRoot))
+            .spawn((SpatialBundle::default(), XrTrackingRoot))
             .id(),
     };
+    //the right eye uses the visibility of the left eye, which culls with a frustum covering both
     let right = commands
         .spawn((XrCameraBundle::new(Eye::Right), OpenXRRightEye))
+        .remove::<Frustum>()
         .id();
     let left = commands
         .spawn((XrCameraBundle::new(Eye::Left), OpenXRLeftEye))
         .id();
-    commands.entity(tracking_root).push_children(&[right, left]);
-}
-
-pub fn action_set_system(action_sets: Res<ActionSets>, session: Res<XrSession>) {
-    let mut active_action_sets = vec![];
-    for i in &action_sets.0 {
-        active_action_sets.push(openxr::ActiveActionSet::new(i));
-    }
-    //info!("action sets: {:#?}", action_sets.0.len());
-    match session.sync_actions(&active_action_sets) {
-        Err(err) => {
-            warn!("{}", err);
-        }
-        _ => {}
-    }
-}
-
-pub trait Vec2Conv {
-    fn to_vec2(&self) -> Vec2;
-}
-
-impl Vec2Conv for openxr::Vector2f {
-    fn to_vec2(&self) -> Vec2 {
-        Vec2::new(self.x, self.y)
-    }
-}
-pub trait Vec3Conv {
-    fn to_vec3(&self) -> Vec3;
-}
-
-impl Vec3Conv for openxr::Vector3f {
-    fn to_vec3(&self) -> Vec3 {
-        Vec3::new(self.x, self.y, self.z)
-    }
-}
-pub trait QuatConv {
-    fn to_quat(&self) -> Quat;
+    let hmd = commands
+        .spawn((SpatialBundle::default(), OpenXRHMD, OpenXRTracker))
+        .id();
+    commands
+        .entity(tracking_root)
+        .push_children(&[right, left, hmd]);
 }
 
-impl QuatConv for openxr::Quaternionf {
-    fn to_quat(&self) -> Quat {
-        Quat::from_xyzw(self.x, self.y, self.z, self.w)
+pub fn action_set_system(
+    action_sets: Option<Res<XrActionSets>>,
+    session: Res<XrSession>,
+    error_log: Res<XrErrorLog>,
+) {
+    //the action sets only exist once the session is set up
+    let Some(action_sets) = action_sets else {
+        return;
+    };
+    if let Err(err) = action_sets.sync(&session) {
+        error_log.report_result(XrErrorSource::SyncActions, err);
     }
 }
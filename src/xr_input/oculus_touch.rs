@@ -1,16 +1,20 @@
+use crate::call_trace::trace;
 use crate::input::XrInput;
 use crate::resources::{XrInstance, XrSession};
 use crate::xr_input::controllers::Handed;
+use crate::xr_input::hands::gesture_controller::EmulatedControllerState;
+use crate::xr_input::single_controller::{SingleControllerButton, SingleControllerRemap};
 use crate::xr_input::Hand;
 use bevy::prelude::{Commands, Res, ResMut, Resource};
 use openxr::{
     ActionSet, AnyGraphics, FrameState, Instance, Path, Posef, Session, Space, SpaceLocation,
-    SpaceVelocity,
+    SpaceLocationFlags, SpaceVelocity, SpaceVelocityFlags,
 };
 
 use std::sync::OnceLock;
 
-use super::actions::{ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding};
+use super::action_manifest::XrActionSetManifest;
+use super::actions::{ActionType, SetupActionSets, XrActionSets, XrBinding, HAND_SUBACTION_PATHS};
 
 pub fn post_action_setup_oculus_controller(
     action_sets: Res<XrActionSets>,
@@ -54,6 +58,8 @@ pub fn setup_oculus_controller(
     commands.insert_resource(oculus_controller);
 }
 
+/// the attached openxr action sets
+#[deprecated(note = "use the sets of `XrActionSets`, which also have their actions")]
 #[derive(Resource, Clone)]
 pub struct ActionSets(pub Vec<ActionSet>);
 
@@ -81,115 +87,150 @@ pub fn subaction_path(hand: Hand) -> Path {
 }
 
 impl OculusControllerRef<'_> {
-    pub fn grip_space(&self, hand: Hand) -> (SpaceLocation, SpaceVelocity) {
+    fn emulated(&self, hand: Hand) -> Option<&EmulatedControllerState> {
         match hand {
-            Hand::Left => self
-                .oculus_controller
-                .grip_space
-                .as_ref()
-                .unwrap()
-                .left
-                .relate(
-                    &self.xr_input.stage,
-                    self.frame_state.predicted_display_time,
-                ),
-            Hand::Right => self
-                .oculus_controller
-                .grip_space
-                .as_ref()
-                .unwrap()
-                .right
-                .relate(
-                    &self.xr_input.stage,
-                    self.frame_state.predicted_display_time,
-                ),
+            Hand::Left => self.oculus_controller.emulated.left.as_ref(),
+            Hand::Right => self.oculus_controller.emulated.right.as_ref(),
         }
-        .unwrap()
     }
-    pub fn aim_space(&self, hand: Hand) -> (SpaceLocation, SpaceVelocity) {
-        match hand {
-            Hand::Left => self
-                .oculus_controller
-                .aim_space
-                .as_ref()
-                .unwrap()
-                .left
-                .relate(
-                    &self.xr_input.stage,
-                    self.frame_state.predicted_display_time,
-                ),
-            Hand::Right => self
-                .oculus_controller
-                .aim_space
-                .as_ref()
-                .unwrap()
-                .right
-                .relate(
-                    &self.xr_input.stage,
-                    self.frame_state.predicted_display_time,
-                ),
+    // the physical controller to read for `hand`, none if the hand reads as idle
+    fn source(&self, hand: Hand) -> Option<Hand> {
+        match &self.oculus_controller.remap {
+            Some(remap) => remap.source(hand),
+            None => Some(hand),
         }
-        .unwrap()
     }
-    pub fn squeeze(&self, hand: Hand) -> f32 {
-        let action = &self
-            .action_sets
-            .get_action_f32("oculus_input", "squeeze")
-            .unwrap();
-        action
-            .state(&self.session, subaction_path(hand))
-            .unwrap()
-            .current_state
+    // in single controller mode both hands follow the held controller
+    fn pose_source(&self, hand: Hand) -> Hand {
+        match &self.oculus_controller.remap {
+            Some(remap) => remap.controller,
+            None => hand,
+        }
     }
-    pub fn trigger(&self, hand: Hand) -> f32 {
+    fn is_muted(&self, button: SingleControllerButton) -> bool {
+        self.oculus_controller
+            .remap
+            .is_some_and(|remap| remap.is_muted(button))
+    }
+    fn bool_state(&self, action: &str, path: Path) -> bool {
         self.action_sets
-            .get_action_f32("oculus_input", "trigger")
+            .get_action_bool("oculus_input", action)
             .unwrap()
-            .state(&self.session, subaction_path(hand))
+            .state(&self.session, path)
             .unwrap()
             .current_state
     }
-    pub fn trigger_touched(&self, hand: Hand) -> bool {
+    fn f32_state(&self, action: &str, path: Path) -> f32 {
         self.action_sets
-            .get_action_bool("oculus_input", "trigger_touched")
+            .get_action_f32("oculus_input", action)
             .unwrap()
-            .state(&self.session, subaction_path(hand))
+            .state(&self.session, path)
             .unwrap()
             .current_state
     }
+    // a/x and b/y, remapped to the held controller
+    fn face_button(&self, hand: Hand, button: SingleControllerButton, touch: bool) -> bool {
+        let Some(source) = self.source(hand) else {
+            return false;
+        };
+        if self.is_muted(button) {
+            return false;
+        }
+        let action = match (source, button, touch) {
+            (Hand::Left, SingleControllerButton::Primary, false) => "x_button",
+            (Hand::Left, SingleControllerButton::Primary, true) => "x_button_touch",
+            (Hand::Left, _, false) => "y_button",
+            (Hand::Left, _, true) => "y_button_touch",
+            (Hand::Right, SingleControllerButton::Primary, false) => "a_button",
+            (Hand::Right, SingleControllerButton::Primary, true) => "a_button_touch",
+            (Hand::Right, _, false) => "b_button",
+            (Hand::Right, _, true) => "b_button_touch",
+        };
+        self.bool_state(action, Path::NULL)
+    }
+    pub fn grip_space(&self, hand: Hand) -> (SpaceLocation, SpaceVelocity) {
+        if let Some(emulated) = self.emulated(hand) {
+            return emulated_location(emulated.grip);
+        }
+        let spaces = self.oculus_controller.grip_space.as_ref().unwrap();
+        let space = match self.pose_source(hand) {
+            Hand::Left => &spaces.left,
+            Hand::Right => &spaces.right,
+        };
+        let time = self.frame_state.predicted_display_time;
+        trace(
+            "xrLocateSpace",
+            || format!("{:?} grip, {:?}", hand, time),
+            || space.relate(&self.xr_input.stage, time),
+        )
+        .unwrap()
+    }
+    pub fn aim_space(&self, hand: Hand) -> (SpaceLocation, SpaceVelocity) {
+        if let Some(emulated) = self.emulated(hand) {
+            return emulated_location(emulated.aim);
+        }
+        let spaces = self.oculus_controller.aim_space.as_ref().unwrap();
+        let space = match self.pose_source(hand) {
+            Hand::Left => &spaces.left,
+            Hand::Right => &spaces.right,
+        };
+        let time = self.frame_state.predicted_display_time;
+        trace(
+            "xrLocateSpace",
+            || format!("{:?} aim, {:?}", hand, time),
+            || space.relate(&self.xr_input.stage, time),
+        )
+        .unwrap()
+    }
+    pub fn squeeze(&self, hand: Hand) -> f32 {
+        if let Some(emulated) = self.emulated(hand) {
+            return emulated.squeeze;
+        }
+        match self.source(hand) {
+            Some(source) => self.f32_state("squeeze", subaction_path(source)),
+            None => 0.0,
+        }
+    }
+    pub fn trigger(&self, hand: Hand) -> f32 {
+        if let Some(emulated) = self.emulated(hand) {
+            return emulated.trigger;
+        }
+        match self.source(hand) {
+            Some(source) => self.f32_state("trigger", subaction_path(source)),
+            None => 0.0,
+        }
+    }
+    pub fn trigger_touched(&self, hand: Hand) -> bool {
+        if let Some(emulated) = self.emulated(hand) {
+            return emulated.trigger > 0.0;
+        }
+        match self.source(hand) {
+            Some(source) => self.bool_state("trigger_touched", subaction_path(source)),
+            None => false,
+        }
+    }
     pub fn x_button(&self) -> bool {
-        self.action_sets
-            .get_action_bool("oculus_input", "x_button")
-            .unwrap()
-            .state(&self.session, Path::NULL)
-            .unwrap()
-            .current_state
+        self.face_button(Hand::Left, SingleControllerButton::Primary, false)
     }
     pub fn x_button_touched(&self) -> bool {
-        self.action_sets
-            .get_action_bool("oculus_input", "x_button_touch")
-            .unwrap()
-            .state(&self.session, Path::NULL)
-            .unwrap()
-            .current_state
+        self.face_button(Hand::Left, SingleControllerButton::Primary, true)
     }
     pub fn y_button(&self) -> bool {
-        self.action_sets
-            .get_action_bool("oculus_input", "y_button")
-            .unwrap()
-            .state(&self.session, Path::NULL)
-            .unwrap()
-            .current_state
+        self.face_button(Hand::Left, SingleControllerButton::Secondary, false)
     }
     pub fn y_button_touched(&self) -> bool {
-        self.action_sets
-            .get_action_bool("oculus_input", "y_button_touch")
-            .unwrap()
-            .state(&self.session, Path::NULL)
-            .unwrap()
-            .current_state
+        self.face_button(Hand::Left, SingleControllerButton::Secondary, true)
     }
     pub fn menu_button(&self) -> bool {
+        if let Some(remap) = &self.oculus_controller.remap {
+            if remap.menu_pressed() {
+                return true;
+            }
+            //the menu button is on the left controller, which isn't held
+            if remap.controller == Hand::Right {
+                return false;
+            }
+        }
         self.action_sets
             .get_action_bool("oculus_input", "menu_button")
             .unwrap()
@@ -198,80 +239,65 @@ impl OculusControllerRef<'_> {
             .current_state
     }
     pub fn a_button(&self) -> bool {
-        self.action_sets
-            .get_action_bool("oculus_input", "a_button")
-            .unwrap()
-            .state(&self.session, Path::NULL)
-            .unwrap()
-            .current_state
+        self.face_button(Hand::Right, SingleControllerButton::Primary, false)
     }
     pub fn a_button_touched(&self) -> bool {
-        self.action_sets
-            .get_action_bool("oculus_input", "a_button_touch")
-            .unwrap()
-            .state(&self.session, Path::NULL)
-            .unwrap()
-            .current_state
+        self.face_button(Hand::Right, SingleControllerButton::Primary, true)
     }
     pub fn b_button(&self) -> bool {
-        self.action_sets
-            .get_action_bool("oculus_input", "b_button")
-            .unwrap()
-            .state(&self.session, Path::NULL)
-            .unwrap()
-            .current_state
+        self.face_button(Hand::Right, SingleControllerButton::Secondary, false)
     }
     pub fn b_button_touched(&self) -> bool {
-        self.action_sets
-            .get_action_bool("oculus_input", "b_button_touch")
-            .unwrap()
-            .state(&self.session, Path::NULL)
-            .unwrap()
-            .current_state
+        self.face_button(Hand::Right, SingleControllerButton::Secondary, true)
     }
     pub fn thumbstick_touch(&self, hand: Hand) -> bool {
-        self.action_sets
-            .get_action_bool("oculus_input", "thumbstick_touch")
-            .unwrap()
-            .state(&self.session, subaction_path(hand))
-            .unwrap()
-            .current_state
+        match self.source(hand) {
+            Some(source) => self.bool_state("thumbstick_touch", subaction_path(source)),
+            None => false,
+        }
     }
     pub fn thumbstick(&self, hand: Hand) -> Thumbstick {
+        let Some(source) = self.source(hand) else {
+            return Thumbstick {
+                x: 0.0,
+                y: 0.0,
+                click: false,
+            };
+        };
+        let path = subaction_path(source);
         Thumbstick {
-            x: self
-                .action_sets
-                .get_action_f32("oculus_input", "thumbstick_x")
-                .unwrap()
-                .state(&self.session, subaction_path(hand))
-                .unwrap()
-                .current_state,
-            y: self
-                .action_sets
-                .get_action_f32("oculus_input", "thumbstick_y")
-                .unwrap()
-                .state(&self.session, subaction_path(hand))
-                .unwrap()
-                .current_state,
-            click: self
-                .action_sets
-                .get_action_bool("oculus_input", "thumbstick_click")
-                .unwrap()
-                .state(&self.session, subaction_path(hand))
-                .unwrap()
-                .current_state,
+            x: self.f32_state("thumbstick_x", path),
+            y: self.f32_state("thumbstick_y", path),
+            click: !self.is_muted(SingleControllerButton::ThumbstickClick)
+                && self.bool_state("thumbstick_click", path),
         }
     }
     pub fn thumbrest_touch(&self, hand: Hand) -> bool {
-        self.action_sets
-            .get_action_bool("oculus_input", "thumbrest_touch")
-            .unwrap()
-            .state(&self.session, subaction_path(hand))
-            .unwrap()
-            .current_state
+        match self.source(hand) {
+            Some(_) if self.is_muted(SingleControllerButton::ThumbrestTouch) => false,
+            Some(source) => self.bool_state("thumbrest_touch", subaction_path(source)),
+            None => false,
+        }
     }
 }
 
+fn emulated_location(pose: Posef) -> (SpaceLocation, SpaceVelocity) {
+    (
+        SpaceLocation {
+            location_flags: SpaceLocationFlags::POSITION_VALID
+                | SpaceLocationFlags::ORIENTATION_VALID
+                | SpaceLocationFlags::POSITION_TRACKED
+                | SpaceLocationFlags::ORIENTATION_TRACKED,
+            pose,
+        },
+        SpaceVelocity {
+            velocity_flags: SpaceVelocityFlags::EMPTY,
+            linear_velocity: Default::default(),
+            angular_velocity: Default::default(),
+        },
+    )
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct Thumbstick {
     pub x: f32,
@@ -301,138 +327,141 @@ impl OculusController {
 pub struct OculusController {
     pub grip_space: Option<Handed<Space>>,
     pub aim_space: Option<Handed<Space>>,
+    /// inputs made up from tracked hands while no controller is held, see
+    /// [`GestureControllerEmulationPlugin`](crate::xr_input::hands::gesture_controller::GestureControllerEmulationPlugin)
+    pub emulated: Handed<Option<EmulatedControllerState>>,
+    /// set by [`SingleControllerPlugin`](crate::xr_input::single_controller::SingleControllerPlugin)
+    pub remap: Option<SingleControllerRemap>,
 }
 impl OculusController {
     pub fn new(mut action_sets: ResMut<SetupActionSets>) -> anyhow::Result<Self> {
-        let action_set =
-            action_sets.add_action_set("oculus_input", "Oculus Touch Controller Input".into(), 0);
-        action_set.new_action(
+        action_sets.add_manifest(oculus_touch_manifest());
+        Ok(OculusController {
+            grip_space: None,
+            aim_space: None,
+            emulated: Handed {
+                left: None,
+                right: None,
+            },
+            remap: None,
+        })
+    }
+}
+
+/// the action set of
+/// [`XrControllerType::OculusTouch`](super::controllers::XrControllerType::OculusTouch). the
+/// oculus touch bindings are the reference, the other profiles map the inputs their controllers
+/// have onto the same actions
+pub fn oculus_touch_manifest() -> XrActionSetManifest {
+    XrActionSetManifest::new("oculus_input", "Oculus Touch Controller Input", 0)
+        .with_action(
             "hand_pose",
-            "Hand Pose".into(),
+            "Hand Pose",
             ActionType::PoseF,
-            ActionHandednes::Double,
-        );
-        action_set.new_action(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_action(
             "pointer_pose",
-            "Pointer Pose".into(),
+            "Pointer Pose",
             ActionType::PoseF,
-            ActionHandednes::Double,
-        );
-        action_set.new_action(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_action(
             "squeeze",
-            "Grip Pull".into(),
+            "Grip Pull",
             ActionType::F32,
-            ActionHandednes::Double,
-        );
-        action_set.new_action(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_action(
             "trigger",
-            "Trigger Pull".into(),
+            "Trigger Pull",
             ActionType::F32,
-            ActionHandednes::Double,
-        );
-        action_set.new_action(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_action(
             "trigger_touched",
-            "Trigger Touch".into(),
+            "Trigger Touch",
             ActionType::Bool,
-            ActionHandednes::Double,
-        );
-        action_set.new_action(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_action(
             "haptic_feedback",
-            "Haptic Feedback".into(),
+            "Haptic Feedback",
             ActionType::Haptic,
-            ActionHandednes::Double,
-        );
-        action_set.new_action(
-            "x_button",
-            "X Button".into(),
-            ActionType::Bool,
-            ActionHandednes::Single,
-        );
-        action_set.new_action(
-            "x_button_touch",
-            "X Button Touch".into(),
-            ActionType::Bool,
-            ActionHandednes::Single,
-        );
-        action_set.new_action(
-            "y_button",
-            "Y Button".into(),
-            ActionType::Bool,
-            ActionHandednes::Single,
-        );
-        action_set.new_action(
-            "y_button_touch",
-            "Y Button Touch".into(),
-            ActionType::Bool,
-            ActionHandednes::Single,
-        );
-        action_set.new_action(
-            "a_button",
-            "A Button".into(),
-            ActionType::Bool,
-            ActionHandednes::Single,
-        );
-        action_set.new_action(
-            "a_button_touch",
-            "A Button Touch".into(),
-            ActionType::Bool,
-            ActionHandednes::Single,
-        );
-        action_set.new_action(
-            "b_button",
-            "B Button".into(),
-            ActionType::Bool,
-            ActionHandednes::Single,
-        );
-        action_set.new_action(
-            "b_button_touch",
-            "B Button Touch".into(),
-            ActionType::Bool,
-            ActionHandednes::Single,
-        );
-        action_set.new_action(
-            "menu_button",
-            "Menu Button".into(),
-            ActionType::Bool,
-            ActionHandednes::Single,
-        );
-        action_set.new_action(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_action("x_button", "X Button", ActionType::Bool, &[])
+        .with_action("x_button_touch", "X Button Touch", ActionType::Bool, &[])
+        .with_action("y_button", "Y Button", ActionType::Bool, &[])
+        .with_action("y_button_touch", "Y Button Touch", ActionType::Bool, &[])
+        .with_action("a_button", "A Button", ActionType::Bool, &[])
+        .with_action("a_button_touch", "A Button Touch", ActionType::Bool, &[])
+        .with_action("b_button", "B Button", ActionType::Bool, &[])
+        .with_action("b_button_touch", "B Button Touch", ActionType::Bool, &[])
+        .with_action("menu_button", "Menu Button", ActionType::Bool, &[])
+        .with_action(
             "thumbstick_x",
-            "Thumbstick X".into(),
+            "Thumbstick X",
             ActionType::F32,
-            ActionHandednes::Double,
-        );
-        action_set.new_action(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_action(
             "thumbstick_y",
-            "Thumbstick y".into(),
+            "Thumbstick y",
             ActionType::F32,
-            ActionHandednes::Double,
-        );
-        action_set.new_action(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_action(
             "thumbstick_touch",
-            "Thumbstick Touch".into(),
+            "Thumbstick Touch",
             ActionType::Bool,
-            ActionHandednes::Double,
-        );
-        action_set.new_action(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_action(
             "thumbstick_click",
-            "Thumbstick Click".into(),
+            "Thumbstick Click",
             ActionType::Bool,
-            ActionHandednes::Double,
-        );
-        action_set.new_action(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_action(
             "thumbrest_touch",
-            "Thumbrest Touch".into(),
+            "Thumbrest Touch",
             ActionType::Bool,
-            ActionHandednes::Double,
-        );
-
-        let this = OculusController {
-            grip_space: None,
-            aim_space: None,
-        };
-        action_set.suggest_binding(
+            &HAND_SUBACTION_PATHS,
+        )
+        .with_bindings(
             "/interaction_profiles/oculus/touch_controller",
+            &oculus_touch_bindings(),
+        )
+        //the same actions on other controllers, so one build works across headsets. inputs a
+        //controller doesn't have stay inactive
+        .with_bindings(
+            "/interaction_profiles/htc/vive_controller",
+            &[
+                XrBinding::new("hand_pose", "/user/hand/left/input/grip/pose"),
+                XrBinding::new("hand_pose", "/user/hand/right/input/grip/pose"),
+                XrBinding::new("pointer_pose", "/user/hand/left/input/aim/pose"),
+                XrBinding::new("pointer_pose", "/user/hand/right/input/aim/pose"),
+                XrBinding::new("squeeze", "/user/hand/left/input/squeeze/click"),
+                XrBinding::new("squeeze", "/user/hand/right/input/squeeze/click"),
+                XrBinding::new("trigger", "/user/hand/left/input/trigger/value"),
+                XrBinding::new("trigger", "/user/hand/right/input/trigger/value"),
+                XrBinding::new("haptic_feedback", "/user/hand/left/output/haptic"),
+                XrBinding::new("haptic_feedback", "/user/hand/right/output/haptic"),
+                XrBinding::new("menu_button", "/user/hand/left/input/menu/click"),
+                XrBinding::new("thumbstick_x", "/user/hand/left/input/trackpad/x"),
+                XrBinding::new("thumbstick_y", "/user/hand/left/input/trackpad/y"),
+                XrBinding::new("thumbstick_x", "/user/hand/right/input/trackpad/x"),
+                XrBinding::new("thumbstick_y", "/user/hand/right/input/trackpad/y"),
+                XrBinding::new("thumbstick_click", "/user/hand/left/input/trackpad/click"),
+                XrBinding::new("thumbstick_click", "/user/hand/right/input/trackpad/click"),
+                XrBinding::new("thumbstick_touch", "/user/hand/left/input/trackpad/touch"),
+                XrBinding::new("thumbstick_touch", "/user/hand/right/input/trackpad/touch"),
+            ],
+        )
+        //a and b of the left index controller stand in for x and y
+        .with_bindings(
+            "/interaction_profiles/valve/index_controller",
             &[
                 XrBinding::new("hand_pose", "/user/hand/left/input/grip/pose"),
                 XrBinding::new("hand_pose", "/user/hand/right/input/grip/pose"),
@@ -446,15 +475,14 @@ impl OculusController {
                 XrBinding::new("trigger_touched", "/user/hand/right/input/trigger/touch"),
                 XrBinding::new("haptic_feedback", "/user/hand/left/output/haptic"),
                 XrBinding::new("haptic_feedback", "/user/hand/right/output/haptic"),
-                XrBinding::new("x_button", "/user/hand/left/input/x/click"),
-                XrBinding::new("x_button_touch", "/user/hand/left/input/x/touch"),
-                XrBinding::new("y_button", "/user/hand/left/input/y/click"),
-                XrBinding::new("y_button_touch", "/user/hand/left/input/y/touch"),
+                XrBinding::new("x_button", "/user/hand/left/input/a/click"),
+                XrBinding::new("x_button_touch", "/user/hand/left/input/a/touch"),
+                XrBinding::new("y_button", "/user/hand/left/input/b/click"),
+                XrBinding::new("y_button_touch", "/user/hand/left/input/b/touch"),
                 XrBinding::new("a_button", "/user/hand/right/input/a/click"),
                 XrBinding::new("a_button_touch", "/user/hand/right/input/a/touch"),
                 XrBinding::new("b_button", "/user/hand/right/input/b/click"),
                 XrBinding::new("b_button_touch", "/user/hand/right/input/b/touch"),
-                XrBinding::new("menu_button", "/user/hand/left/input/menu/click"),
                 XrBinding::new("thumbstick_x", "/user/hand/left/input/thumbstick/x"),
                 XrBinding::new("thumbstick_y", "/user/hand/left/input/thumbstick/y"),
                 XrBinding::new("thumbstick_x", "/user/hand/right/input/thumbstick/x"),
@@ -469,10 +497,96 @@ impl OculusController {
                     "thumbstick_touch",
                     "/user/hand/right/input/thumbstick/touch",
                 ),
-                XrBinding::new("thumbrest_touch", "/user/hand/left/input/thumbrest/touch"),
-                XrBinding::new("thumbrest_touch", "/user/hand/right/input/thumbrest/touch"),
+                XrBinding::new("thumbrest_touch", "/user/hand/left/input/trackpad/touch"),
+                XrBinding::new("thumbrest_touch", "/user/hand/right/input/trackpad/touch"),
             ],
-        );
-        Ok(this)
-    }
+        )
+        .with_bindings(
+            "/interaction_profiles/microsoft/motion_controller",
+            &[
+                XrBinding::new("hand_pose", "/user/hand/left/input/grip/pose"),
+                XrBinding::new("hand_pose", "/user/hand/right/input/grip/pose"),
+                XrBinding::new("pointer_pose", "/user/hand/left/input/aim/pose"),
+                XrBinding::new("pointer_pose", "/user/hand/right/input/aim/pose"),
+                XrBinding::new("squeeze", "/user/hand/left/input/squeeze/click"),
+                XrBinding::new("squeeze", "/user/hand/right/input/squeeze/click"),
+                XrBinding::new("trigger", "/user/hand/left/input/trigger/value"),
+                XrBinding::new("trigger", "/user/hand/right/input/trigger/value"),
+                XrBinding::new("haptic_feedback", "/user/hand/left/output/haptic"),
+                XrBinding::new("haptic_feedback", "/user/hand/right/output/haptic"),
+                XrBinding::new("menu_button", "/user/hand/left/input/menu/click"),
+                XrBinding::new("thumbstick_x", "/user/hand/left/input/thumbstick/x"),
+                XrBinding::new("thumbstick_y", "/user/hand/left/input/thumbstick/y"),
+                XrBinding::new("thumbstick_x", "/user/hand/right/input/thumbstick/x"),
+                XrBinding::new("thumbstick_y", "/user/hand/right/input/thumbstick/y"),
+                XrBinding::new("thumbstick_click", "/user/hand/left/input/thumbstick/click"),
+                XrBinding::new(
+                    "thumbstick_click",
+                    "/user/hand/right/input/thumbstick/click",
+                ),
+                XrBinding::new("thumbstick_touch", "/user/hand/left/input/trackpad/touch"),
+                XrBinding::new("thumbstick_touch", "/user/hand/right/input/trackpad/touch"),
+            ],
+        )
+        //every runtime has to support the simple controller, so controllers without a profile
+        //of their own (go, gear vr, unknown ones) still get poses, select and menu
+        .with_bindings(
+            "/interaction_profiles/khr/simple_controller",
+            &[
+                XrBinding::new("hand_pose", "/user/hand/left/input/grip/pose"),
+                XrBinding::new("hand_pose", "/user/hand/right/input/grip/pose"),
+                XrBinding::new("pointer_pose", "/user/hand/left/input/aim/pose"),
+                XrBinding::new("pointer_pose", "/user/hand/right/input/aim/pose"),
+                XrBinding::new("trigger", "/user/hand/left/input/select/click"),
+                XrBinding::new("trigger", "/user/hand/right/input/select/click"),
+                XrBinding::new("haptic_feedback", "/user/hand/left/output/haptic"),
+                XrBinding::new("haptic_feedback", "/user/hand/right/output/haptic"),
+                XrBinding::new("menu_button", "/user/hand/left/input/menu/click"),
+                XrBinding::new("menu_button", "/user/hand/right/input/menu/click"),
+            ],
+        )
+}
+
+/// the bindings of the oculus touch action set on touch controllers, also used for the profiles
+/// of newer touch controllers that have the same inputs and more
+pub(crate) fn oculus_touch_bindings() -> Vec<XrBinding> {
+    vec![
+        XrBinding::new("hand_pose", "/user/hand/left/input/grip/pose"),
+        XrBinding::new("hand_pose", "/user/hand/right/input/grip/pose"),
+        XrBinding::new("pointer_pose", "/user/hand/left/input/aim/pose"),
+        XrBinding::new("pointer_pose", "/user/hand/right/input/aim/pose"),
+        XrBinding::new("squeeze", "/user/hand/left/input/squeeze/value"),
+        XrBinding::new("squeeze", "/user/hand/right/input/squeeze/value"),
+        XrBinding::new("trigger", "/user/hand/left/input/trigger/value"),
+        XrBinding::new("trigger", "/user/hand/right/input/trigger/value"),
+        XrBinding::new("trigger_touched", "/user/hand/left/input/trigger/touch"),
+        XrBinding::new("trigger_touched", "/user/hand/right/input/trigger/touch"),
+        XrBinding::new("haptic_feedback", "/user/hand/left/output/haptic"),
+        XrBinding::new("haptic_feedback", "/user/hand/right/output/haptic"),
+        XrBinding::new("x_button", "/user/hand/left/input/x/click"),
+        XrBinding::new("x_button_touch", "/user/hand/left/input/x/touch"),
+        XrBinding::new("y_button", "/user/hand/left/input/y/click"),
+        XrBinding::new("y_button_touch", "/user/hand/left/input/y/touch"),
+        XrBinding::new("a_button", "/user/hand/right/input/a/click"),
+        XrBinding::new("a_button_touch", "/user/hand/right/input/a/touch"),
+        XrBinding::new("b_button", "/user/hand/right/input/b/click"),
+        XrBinding::new("b_button_touch", "/user/hand/right/input/b/touch"),
+        XrBinding::new("menu_button", "/user/hand/left/input/menu/click"),
+        XrBinding::new("thumbstick_x", "/user/hand/left/input/thumbstick/x"),
+        XrBinding::new("thumbstick_y", "/user/hand/left/input/thumbstick/y"),
+        XrBinding::new("thumbstick_x", "/user/hand/right/input/thumbstick/x"),
+        XrBinding::new("thumbstick_y", "/user/hand/right/input/thumbstick/y"),
+        XrBinding::new("thumbstick_click", "/user/hand/left/input/thumbstick/click"),
+        XrBinding::new(
+            "thumbstick_click",
+            "/user/hand/right/input/thumbstick/click",
+        ),
+        XrBinding::new("thumbstick_touch", "/user/hand/left/input/thumbstick/touch"),
+        XrBinding::new(
+            "thumbstick_touch",
+            "/user/hand/right/input/thumbstick/touch",
+        ),
+        XrBinding::new("thumbrest_touch", "/user/hand/left/input/thumbrest/touch"),
+        XrBinding::new("thumbrest_touch", "/user/hand/right/input/thumbrest/touch"),
+    ]
 }
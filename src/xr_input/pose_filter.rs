@@ -0,0 +1,111 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+/// how a tracked pose is smoothed. smoothing removes jitter but makes the pose lag behind,
+/// the presets pick a side of that tradeoff
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PoseFilter {
+    /// the raw pose
+    #[default]
+    None,
+    /// moves a fixed part of the way to the raw pose every frame, lags the same at any speed
+    Exponential {
+        /// seconds until half of a change went through
+        half_life: f32,
+    },
+    /// the one euro filter, smooths a lot while the pose rests and little while it moves
+    OneEuro {
+        /// the cutoff frequency in hz at rest, lower removes more jitter
+        min_cutoff: f32,
+        /// how much the cutoff rises with the speed, in meters or radians per second, higher
+        /// lags less during fast movements
+        beta: f32,
+        /// the cutoff frequency the speed is smoothed with
+        derivative_cutoff: f32,
+    },
+}
+
+impl PoseFilter {
+    /// steady rays and cursors, a bit of lag during fast movements doesn't matter much
+    pub const UI_POINTING: Self = Self::OneEuro {
+        min_cutoff: 0.5,
+        beta: 10.0,
+        derivative_cutoff: 1.0,
+    };
+    /// little lag so thrown and pushed objects follow the hand, only the worst jitter is removed
+    pub const PHYSICS: Self = Self::OneEuro {
+        min_cutoff: 4.0,
+        beta: 40.0,
+        derivative_cutoff: 1.0,
+    };
+}
+
+/// the state of a [`PoseFilter`] for one pose
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PoseFilterState {
+    last: Option<(Vec3, Quat)>,
+    linear_speed: f32,
+    angular_speed: f32,
+}
+
+impl PoseFilterState {
+    /// forgets the last pose, call this when tracking was lost so the pose doesn't glide over
+    pub fn reset(&mut self) {
+        *self = default();
+    }
+
+    /// the smoothed pose, `dt` is the time since the last call in seconds
+    pub fn filter(
+        &mut self,
+        filter: &PoseFilter,
+        position: Vec3,
+        rotation: Quat,
+        dt: f32,
+    ) -> (Vec3, Quat) {
+        let Some((last_position, last_rotation)) = self.last else {
+            self.last = Some((position, rotation));
+            return (position, rotation);
+        };
+        if dt <= 0.0 {
+            return (last_position, last_rotation);
+        }
+        let (position_alpha, rotation_alpha) = match *filter {
+            PoseFilter::None => (1.0, 1.0),
+            PoseFilter::Exponential { half_life } if half_life > 0.0 => {
+                let alpha = 1.0 - 0.5f32.powf(dt / half_life);
+                (alpha, alpha)
+            }
+            PoseFilter::Exponential { .. } => (1.0, 1.0),
+            PoseFilter::OneEuro {
+                min_cutoff,
+                beta,
+                derivative_cutoff,
+            } => {
+                let alpha = smoothing_factor(derivative_cutoff, dt);
+                let linear = position.distance(last_position) / dt;
+                let angular = rotation.angle_between(last_rotation) / dt;
+                self.linear_speed += (linear - self.linear_speed) * alpha;
+                self.angular_speed += (angular - self.angular_speed) * alpha;
+                (
+                    smoothing_factor(min_cutoff + beta * self.linear_speed, dt),
+                    smoothing_factor(min_cutoff + beta * self.angular_speed, dt),
+                )
+            }
+        };
+        let filtered = (
+            last_position.lerp(position, position_alpha),
+            last_rotation.slerp(rotation, rotation_alpha).normalize(),
+        );
+        self.last = Some(filtered);
+        filtered
+    }
+}
+
+fn smoothing_factor(cutoff: f32, dt: f32) -> f32 {
+    if cutoff <= 0.0 {
+        return 0.0;
+    }
+    let time_constant = 1.0 / (TAU * cutoff);
+    1.0 / (1.0 + time_constant / dt)
+}
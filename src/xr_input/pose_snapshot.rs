@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::xr_init::xr_only;
+
+use super::hands::HandBone;
+use super::trackers::{OpenXRHMD, OpenXRLeftController, OpenXRRightController, XrTrackingRoot};
+use super::{Hand, QuatConv, Vec3Conv};
+
+/// compact pose that can be sent over the network, positions are in world units
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct PoseSnapshot {
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+}
+
+impl Default for PoseSnapshot {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl PoseSnapshot {
+    pub fn new(position: Vec3, rotation: Quat) -> Self {
+        Self {
+            position: position.to_array(),
+            rotation: rotation.to_array(),
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        Vec3::from_array(self.position)
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_array(self.rotation)
+    }
+
+    pub fn to_transform(&self) -> Transform {
+        Transform::from_translation(self.position()).with_rotation(self.rotation())
+    }
+
+    /// linear interpolation for the position and slerp for the rotation
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Self::new(
+            self.position().lerp(other.position(), t),
+            self.rotation().slerp(other.rotation(), t),
+        )
+    }
+}
+
+impl From<Transform> for PoseSnapshot {
+    fn from(transform: Transform) -> Self {
+        Self::new(transform.translation, transform.rotation)
+    }
+}
+
+impl From<&GlobalTransform> for PoseSnapshot {
+    fn from(transform: &GlobalTransform) -> Self {
+        let (_, rotation, translation) = transform.to_scale_rotation_translation();
+        Self::new(translation, rotation)
+    }
+}
+
+impl From<openxr::Posef> for PoseSnapshot {
+    fn from(pose: openxr::Posef) -> Self {
+        Self::new(pose.position.to_vec3(), pose.orientation.to_quat())
+    }
+}
+
+/// every joint of one hand, indexed with [`HandBone::get_index_from_bone`]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandSnapshot {
+    pub joints: [PoseSnapshot; 26],
+}
+
+impl HandSnapshot {
+    pub fn joint(&self, bone: HandBone) -> &PoseSnapshot {
+        &self.joints[bone.get_index_from_bone()]
+    }
+
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        let mut joints = self.joints;
+        for (joint, other) in joints.iter_mut().zip(other.joints.iter()) {
+            *joint = joint.interpolate(other, t);
+        }
+        Self { joints }
+    }
+}
+
+/// everything needed to show the avatar of a player on another machine
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvatarSnapshot {
+    /// time the snapshot was taken at, in seconds
+    pub time: f64,
+    /// the tracking root, which works as the body of the player
+    pub root: PoseSnapshot,
+    pub head: PoseSnapshot,
+    pub left_controller: Option<PoseSnapshot>,
+    pub right_controller: Option<PoseSnapshot>,
+    pub left_hand: Option<HandSnapshot>,
+    pub right_hand: Option<HandSnapshot>,
+}
+
+impl AvatarSnapshot {
+    /// interpolates between two snapshots, parts that are missing in one of them are taken from
+    /// the snapshot closest to `t`
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        fn optional<T: Copy>(
+            a: Option<T>,
+            b: Option<T>,
+            t: f32,
+            f: impl Fn(&T, &T) -> T,
+        ) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(f(&a, &b)),
+                (a, b) => match t < 0.5 {
+                    true => a,
+                    false => b,
+                },
+            }
+        }
+        Self {
+            time: self.time + (other.time - self.time) * t as f64,
+            root: self.root.interpolate(&other.root, t),
+            head: self.head.interpolate(&other.head, t),
+            left_controller: optional(self.left_controller, other.left_controller, t, |a, b| {
+                a.interpolate(b, t)
+            }),
+            right_controller: optional(self.right_controller, other.right_controller, t, |a, b| {
+                a.interpolate(b, t)
+            }),
+            left_hand: optional(self.left_hand, other.left_hand, t, |a, b| {
+                a.interpolate(b, t)
+            }),
+            right_hand: optional(self.right_hand, other.right_hand, t, |a, b| {
+                a.interpolate(b, t)
+            }),
+        }
+    }
+}
+
+/// buffers received snapshots and samples them at a time in the past, so remote avatars move
+/// smoothly even when snapshots arrive late or unevenly
+#[derive(Component, Clone, Debug)]
+pub struct SnapshotInterpolator {
+    snapshots: VecDeque<AvatarSnapshot>,
+    /// how many snapshots are kept at most
+    pub capacity: usize,
+    /// how far in the past the snapshots are sampled, in seconds
+    pub delay: f64,
+}
+
+impl Default for SnapshotInterpolator {
+    fn default() -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            capacity: 32,
+            delay: 0.1,
+        }
+    }
+}
+
+impl SnapshotInterpolator {
+    pub fn new(delay: f64) -> Self {
+        Self { delay, ..default() }
+    }
+
+    /// adds a snapshot, snapshots arriving out of order are dropped
+    pub fn push(&mut self, snapshot: AvatarSnapshot) {
+        if self
+            .snapshots
+            .back()
+            .is_some_and(|last| last.time >= snapshot.time)
+        {
+            return;
+        }
+        self.snapshots.push_back(snapshot);
+        while self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+    }
+
+    pub fn latest(&self) -> Option<&AvatarSnapshot> {
+        self.snapshots.back()
+    }
+
+    /// samples the snapshots at `time - delay`
+    pub fn sample(&self, time: f64) -> Option<AvatarSnapshot> {
+        let time = time - self.delay;
+        let first = self.snapshots.front()?;
+        if time <= first.time {
+            return Some(*first);
+        }
+        for (a, b) in self.snapshots.iter().zip(self.snapshots.iter().skip(1)) {
+            if time <= b.time {
+                let t = ((time - a.time) / (b.time - a.time)) as f32;
+                return Some(a.interpolate(b, t));
+            }
+        }
+        self.snapshots.back().copied()
+    }
+}
+
+/// the snapshot of the local player, updated every frame by [`PoseSnapshotPlugin`]
+#[derive(Resource, Clone, Copy, Debug, Default, Deref, DerefMut)]
+pub struct LocalAvatarSnapshot(pub AvatarSnapshot);
+
+/// keeps [`LocalAvatarSnapshot`] up to date, so it can be sent to other players
+pub struct PoseSnapshotPlugin;
+
+impl Plugin for PoseSnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LocalAvatarSnapshot>();
+        app.add_systems(
+            PostUpdate,
+            update_local_avatar_snapshot
+                .run_if(xr_only())
+                .after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_local_avatar_snapshot(
+    time: Res<Time>,
+    mut snapshot: ResMut<LocalAvatarSnapshot>,
+    root: Query<&GlobalTransform, With<XrTrackingRoot>>,
+    head: Query<&GlobalTransform, With<OpenXRHMD>>,
+    left_controller: Query<&GlobalTransform, With<OpenXRLeftController>>,
+    right_controller: Query<&GlobalTransform, With<OpenXRRightController>>,
+    bones: Query<(&GlobalTransform, &HandBone, &Hand)>,
+) {
+    let mut left_hand = HandSnapshot::default();
+    let mut right_hand = HandSnapshot::default();
+    let mut has_bones = false;
+    for (transform, bone, hand) in bones.iter() {
+        let hand = match hand {
+            Hand::Left => &mut left_hand,
+            Hand::Right => &mut right_hand,
+        };
+        hand.joints[bone.get_index_from_bone()] = transform.into();
+        has_bones = true;
+    }
+    snapshot.0 = AvatarSnapshot {
+        time: time.elapsed_seconds_f64(),
+        root: root.get_single().map(Into::into).unwrap_or_default(),
+        head: head.get_single().map(Into::into).unwrap_or_default(),
+        left_controller: left_controller.get_single().ok().map(Into::into),
+        right_controller: right_controller.get_single().ok().map(Into::into),
+        left_hand: has_bones.then_some(left_hand),
+        right_hand: has_bones.then_some(right_hand),
+    };
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use openxr::Path;
+
+use crate::resources::XrSession;
+use crate::xr_init::xr_only;
+
+use super::actions::{XrActionSets, XrSyncActions};
+use super::oculus_touch::subaction_path;
+use super::Hand;
+
+/// sends [`DoubleClicked`] and [`LongPressed`] for the boolean actions added to
+/// [`XrPressGestures`], so apps don't need their own timers for them
+pub struct XrPressGesturePlugin;
+
+impl Plugin for XrPressGesturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrPressGestures>();
+        app.add_event::<DoubleClicked>();
+        app.add_event::<LongPressed>();
+        app.add_systems(
+            PreUpdate,
+            detect_press_gestures.run_if(xr_only()).after(XrSyncActions),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct PressGestureConfig {
+    /// seconds between the two presses of a double click
+    pub double_click_window: f32,
+    /// seconds a press has to be held to count as a long press
+    pub long_press_duration: f32,
+}
+
+impl Default for PressGestureConfig {
+    fn default() -> Self {
+        Self {
+            double_click_window: 0.3,
+            long_press_duration: 0.5,
+        }
+    }
+}
+
+/// the actions watched for gestures
+#[derive(Resource, Default)]
+pub struct XrPressGestures {
+    /// used by actions added without their own config
+    pub config: PressGestureConfig,
+    actions: Vec<TrackedAction>,
+}
+
+struct TrackedAction {
+    action_set: &'static str,
+    action: &'static str,
+    hand: Option<Hand>,
+    config: Option<PressGestureConfig>,
+    pressed: bool,
+    pressed_at: f32,
+    long_press_sent: bool,
+    // the press that can become the first half of a double click
+    last_click: Option<f32>,
+}
+
+impl XrPressGestures {
+    /// watches a boolean action, `hand` picks the subaction path of actions bound to both hands
+    pub fn track(&mut self, action_set: &'static str, action: &'static str, hand: Option<Hand>) {
+        self.track_with(action_set, action, hand, None);
+    }
+
+    pub fn track_with(
+        &mut self,
+        action_set: &'static str,
+        action: &'static str,
+        hand: Option<Hand>,
+        config: Option<PressGestureConfig>,
+    ) {
+        self.untrack(action_set, action, hand);
+        self.actions.push(TrackedAction {
+            action_set,
+            action,
+            hand,
+            config,
+            pressed: false,
+            pressed_at: 0.0,
+            long_press_sent: false,
+            last_click: None,
+        });
+    }
+
+    pub fn untrack(&mut self, action_set: &'static str, action: &'static str, hand: Option<Hand>) {
+        self.actions.retain(|tracked| {
+            (tracked.action_set, tracked.action, tracked.hand) != (action_set, action, hand)
+        });
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DoubleClicked {
+    pub action_set: &'static str,
+    pub action: &'static str,
+    pub hand: Option<Hand>,
+}
+
+/// sent once per press, while the action is still held
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LongPressed {
+    pub action_set: &'static str,
+    pub action: &'static str,
+    pub hand: Option<Hand>,
+}
+
+pub fn detect_press_gestures(
+    mut gestures: ResMut<XrPressGestures>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Res<XrSession>,
+    time: Res<Time>,
+    mut double_clicked: EventWriter<DoubleClicked>,
+    mut long_pressed: EventWriter<LongPressed>,
+) {
+    let Some(action_sets) = action_sets else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let gestures = &mut *gestures;
+    let mut invalid = vec![];
+    for tracked in gestures.actions.iter_mut() {
+        let config = tracked.config.unwrap_or(gestures.config);
+        let path = tracked.hand.map(subaction_path).unwrap_or(Path::NULL);
+        let pressed = match action_sets.get_action_bool(tracked.action_set, tracked.action) {
+            Ok(action) => action
+                .state(&session, path)
+                .map(|state| state.is_active && state.current_state)
+                .unwrap_or_default(),
+            Err(err) => {
+                warn!(
+                    "stopped detecting gestures of {}/{}: {}",
+                    tracked.action_set, tracked.action, err
+                );
+                invalid.push((tracked.action_set, tracked.action, tracked.hand));
+                continue;
+            }
+        };
+        let (action_set, action, hand) = (tracked.action_set, tracked.action, tracked.hand);
+        if pressed && !tracked.pressed {
+            tracked.pressed_at = now;
+            tracked.long_press_sent = false;
+            match tracked.last_click {
+                Some(last) if now - last <= config.double_click_window => {
+                    double_clicked.send(DoubleClicked {
+                        action_set,
+                        action,
+                        hand,
+                    });
+                    //a third press starts a new double click
+                    tracked.last_click = None;
+                }
+                _ => tracked.last_click = Some(now),
+            }
+        }
+        if pressed
+            && !tracked.long_press_sent
+            && now - tracked.pressed_at >= config.long_press_duration
+        {
+            long_pressed.send(LongPressed {
+                action_set,
+                action,
+                hand,
+            });
+            tracked.long_press_sent = true;
+            //a long press isn't the start of a double click
+            tracked.last_click = None;
+        }
+        tracked.pressed = pressed;
+    }
+    for (action_set, action, hand) in invalid {
+        gestures.untrack(action_set, action, hand);
+    }
+}
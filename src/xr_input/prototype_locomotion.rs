@@ -11,8 +11,10 @@ use crate::{
 };
 
 use super::{
-    actions::XrActionSets, oculus_touch::OculusController, trackers::OpenXRTrackingRoot, Hand,
-    QuatConv, Vec3Conv,
+    actions::XrActionSets,
+    oculus_touch::OculusController,
+    trackers::{head_floor_position, rotate_root_around_head, XrTrackingRoot},
+    Hand, QuatConv, Vec3Conv,
 };
 
 pub enum LocomotionType {
@@ -59,13 +61,13 @@ impl Default for PrototypeLocomotionConfig {
 
 pub fn proto_locomotion(
     time: Res<Time>,
-    mut tracking_root_query: Query<(&mut Transform, With<OpenXRTrackingRoot>)>,
+    mut tracking_root_query: Query<(&mut Transform, With<XrTrackingRoot>)>,
     oculus_controller: Res<OculusController>,
     frame_state: Res<XrFrameState>,
     xr_input: Res<XrInput>,
     instance: Res<XrInstance>,
     session: Res<XrSession>,
-    views: ResMut<XrViews>,
+    views: Res<XrViews>,
     mut gizmos: Gizmos,
     config_option: Option<ResMut<PrototypeLocomotionConfig>>,
     action_sets: Res<XrActionSets>,
@@ -92,8 +94,7 @@ pub fn proto_locomotion(
             let reference_quat;
             match config.locomotion_type {
                 LocomotionType::Head => {
-                    let v = views.lock().unwrap();
-                    let views = v.get(0);
+                    let views = views.get(0);
                     match views {
                         Some(view) => {
                             reference_quat = view.pose.orientation.to_quat();
@@ -127,16 +128,13 @@ pub fn proto_locomotion(
                         rot_input * config.smooth_rotation_speed * time.delta_seconds(),
                     );
                     //apply rotation
-                    let v = views.lock().unwrap();
-                    let views = v.get(0);
+                    let views = views.get(0);
                     match views {
                         Some(view) => {
-                            let mut hmd_translation = view.pose.position.to_vec3();
-                            hmd_translation.y = 0.0;
-                            let local = position.0.translation;
-                            let global = position.0.rotation.mul_vec3(hmd_translation) + local;
+                            let hmd_translation = view.pose.position.to_vec3();
+                            let global = head_floor_position(&position.0, hmd_translation);
                             gizmos.circle(global, position.0.up(), 0.1, Color::GREEN);
-                            position.0.rotate_around(global, smoth_rot);
+                            rotate_root_around_head(&mut position.0, hmd_translation, smoth_rot);
                         }
                         None => return,
                     }
@@ -159,16 +157,17 @@ pub fn proto_locomotion(
                         let smoth_rot =
                             Quat::from_axis_angle(position.0.up(), config.snap_angle * dir);
                         //apply rotation
-                        let v = views.lock().unwrap();
-                        let views = v.get(0);
+                        let views = views.get(0);
                         match views {
                             Some(view) => {
-                                let mut hmd_translation = view.pose.position.to_vec3();
-                                hmd_translation.y = 0.0;
-                                let local = position.0.translation;
-                                let global = position.0.rotation.mul_vec3(hmd_translation) + local;
+                                let hmd_translation = view.pose.position.to_vec3();
+                                let global = head_floor_position(&position.0, hmd_translation);
                                 gizmos.circle(global, position.0.up(), 0.1, Color::GREEN);
-                                position.0.rotate_around(global, smoth_rot);
+                                rotate_root_around_head(
+                                    &mut position.0,
+                                    hmd_translation,
+                                    smoth_rot,
+                                );
                             }
                             None => return,
                         }
@@ -0,0 +1,394 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+
+use crate::input::XrInput;
+use crate::layers::XrQuadLayer;
+use crate::resources::{XrFrameState, XrSession};
+use crate::xr_init::xr_only;
+
+use super::actions::XrActionSets;
+use super::oculus_touch::OculusController;
+use super::trackers::{update_open_xr_controllers, OpenXRLeftController, OpenXRRightController};
+use super::Hand;
+
+/// trigger values above this select the hovered section
+const PRESS_THRESHOLD: f32 = 0.5;
+/// the gap between two sections, as a part of the menu's radius
+const SECTION_GAP: f32 = 0.02;
+/// how much of a section the icon covers
+const ICON_SCALE: f32 = 0.6;
+
+/// selection wheels on quad layers. an entity with an [`XrRadialMenu`] and a `SpatialBundle` gets
+/// an [`XrQuadLayer`] showing its sections, placed wherever the entity is, e.g. as a child of a
+/// controller. while it's open the thumbstick direction or the twist of the wrist hovers a
+/// section, the trigger (or letting go of the thumbstick) selects it, both are sent as
+/// [`XrRadialMenuEvent`]s
+pub struct XrRadialMenuPlugin;
+
+impl Plugin for XrRadialMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<XrRadialMenuEvent>();
+        app.add_systems(
+            Update,
+            update_radial_menus
+                .run_if(xr_only())
+                .after(update_open_xr_controllers),
+        );
+        app.add_systems(
+            PostUpdate,
+            (add_radial_menu_layers, apply_deferred, draw_radial_menus).chain(),
+        );
+    }
+}
+
+/// what hovers the sections
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XrRadialInput {
+    /// the section the thumbstick points at, sections go clockwise from the top
+    Thumbstick,
+    /// turning the wrist from `-range` to `range` radians, counted from the rotation the menu was
+    /// opened with, sweeps over the sections clockwise
+    WristRotation { range: f32 },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct XrRadialSection {
+    /// not drawn, for the app to tell the sections apart
+    pub label: String,
+    /// drawn in the middle of the section, read from its rgba8 data
+    pub icon: Option<Handle<Image>>,
+}
+
+impl XrRadialSection {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            icon: None,
+        }
+    }
+
+    pub fn with_icon(mut self, icon: Handle<Image>) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct XrRadialMenu {
+    pub sections: Vec<XrRadialSection>,
+    /// the controller that drives the menu
+    pub hand: Hand,
+    pub input: XrRadialInput,
+    /// the menu is only shown and read while this is on
+    pub open: bool,
+    /// closes the menu once a section was selected
+    pub close_on_select: bool,
+    /// selects the hovered section when the thumbstick is let go, besides the trigger
+    pub select_on_release: bool,
+    /// thumbstick deflections below this hover nothing
+    pub dead_zone: f32,
+    /// diameter in meters
+    pub size: f32,
+    /// width and height of the image in pixels
+    pub resolution: u32,
+    /// the empty middle, as a part of the radius
+    pub inner_radius: f32,
+    pub color: Color,
+    pub hovered_color: Color,
+    hovered: Option<usize>,
+    // the trigger was pressed last frame
+    pressed: bool,
+    // the controller rotation the wrist twist is counted from
+    opened_rotation: Option<Quat>,
+}
+
+impl Default for XrRadialMenu {
+    fn default() -> Self {
+        Self {
+            sections: vec![],
+            hand: Hand::Right,
+            input: XrRadialInput::Thumbstick,
+            open: false,
+            close_on_select: true,
+            select_on_release: true,
+            dead_zone: 0.5,
+            size: 0.25,
+            resolution: 256,
+            inner_radius: 0.35,
+            color: Color::rgba(0.1, 0.1, 0.1, 0.8),
+            hovered_color: Color::rgba(0.3, 0.5, 0.9, 0.9),
+            hovered: None,
+            pressed: false,
+            opened_rotation: None,
+        }
+    }
+}
+
+impl XrRadialMenu {
+    pub fn new(hand: Hand, sections: Vec<XrRadialSection>) -> Self {
+        Self {
+            hand,
+            sections,
+            ..default()
+        }
+    }
+
+    pub fn hovered(&self) -> Option<usize> {
+        self.hovered
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XrRadialMenuEvent {
+    /// `None` when nothing is hovered anymore
+    Hovered {
+        menu: Entity,
+        section: Option<usize>,
+    },
+    Selected {
+        menu: Entity,
+        section: usize,
+    },
+}
+
+// the section a direction points at, up is the middle of the first section
+fn section_at(direction: Vec2, sections: usize) -> usize {
+    let step = TAU / sections as f32;
+    let angle = direction.x.atan2(direction.y).rem_euclid(TAU);
+    ((angle / step + 0.5) as usize) % sections
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_radial_menus(
+    mut menus: Query<(Entity, &mut XrRadialMenu, &mut Visibility)>,
+    mut events: EventWriter<XrRadialMenuEvent>,
+    oculus_controller: Option<Res<OculusController>>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Res<XrSession>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    controllers: Query<
+        (&Transform, Has<OpenXRLeftController>),
+        Or<(With<OpenXRLeftController>, With<OpenXRRightController>)>,
+    >,
+) {
+    let (Some(oculus_controller), Some(action_sets)) = (oculus_controller, action_sets) else {
+        return;
+    };
+    let frame_state = *frame_state.lock().unwrap();
+    let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    for (entity, mut menu, mut visibility) in &mut menus {
+        let shown = match menu.open {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+        if *visibility != shown {
+            *visibility = shown;
+        }
+        let pressed = controller.trigger(menu.hand) > PRESS_THRESHOLD;
+        let was_pressed = std::mem::replace(&mut menu.bypass_change_detection().pressed, pressed);
+        if !menu.open {
+            menu.bypass_change_detection().opened_rotation = None;
+            if menu.hovered.is_some() {
+                menu.hovered = None;
+            }
+            continue;
+        }
+        let (hand, input, sections) = (menu.hand, menu.input, menu.sections.len());
+        let hovered = match input {
+            _ if sections == 0 => None,
+            XrRadialInput::Thumbstick => {
+                let stick = controller.thumbstick(hand);
+                let stick = Vec2::new(stick.x, stick.y);
+                (stick.length() >= menu.dead_zone).then(|| section_at(stick, sections))
+            }
+            XrRadialInput::WristRotation { range } => controllers
+                .iter()
+                .find(|(_, left)| *left == (hand == Hand::Left))
+                .map(|(transform, _)| {
+                    let rotation = transform.rotation;
+                    let opened = *menu
+                        .bypass_change_detection()
+                        .opened_rotation
+                        .get_or_insert(rotation);
+                    let twist = opened.inverse() * rotation;
+                    let twist = match twist.w < 0.0 {
+                        true => -twist,
+                        false => twist,
+                    };
+                    //the twist around the controller's forward axis, -z, clockwise for the user
+                    let clockwise = -2.0 * twist.z.atan2(twist.w);
+                    let sweep = (clockwise / range.max(f32::EPSILON)).clamp(-1.0, 1.0);
+                    (((sweep + 1.0) / 2.0 * sections as f32) as usize).min(sections - 1)
+                }),
+        };
+        let previous = menu.hovered;
+        if hovered != previous {
+            menu.hovered = hovered;
+            events.send(XrRadialMenuEvent::Hovered {
+                menu: entity,
+                section: hovered,
+            });
+        }
+        let released =
+            menu.select_on_release && input == XrRadialInput::Thumbstick && hovered.is_none();
+        let selected = match (pressed && !was_pressed, released) {
+            (true, _) => hovered,
+            (false, true) => previous,
+            (false, false) => None,
+        };
+        if let Some(section) = selected {
+            events.send(XrRadialMenuEvent::Selected {
+                menu: entity,
+                section,
+            });
+            if menu.close_on_select {
+                menu.open = false;
+            }
+        }
+    }
+}
+
+pub fn add_radial_menu_layers(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    menus: Query<(Entity, &XrRadialMenu), Without<XrQuadLayer>>,
+) {
+    for (entity, menu) in &menus {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: menu.resolution.max(1),
+                height: menu.resolution.max(1),
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        //quad layers copy from their image
+        image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+        commands.entity(entity).insert(XrQuadLayer {
+            image: images.add(image),
+            size: Vec2::splat(menu.size),
+        });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn draw_radial_menus(
+    mut images: ResMut<Assets<Image>>,
+    mut menus: Query<
+        (&XrRadialMenu, &mut XrQuadLayer),
+        Or<(Changed<XrRadialMenu>, Added<XrQuadLayer>)>,
+    >,
+) {
+    for (menu, mut layer) in &mut menus {
+        if layer.size != Vec2::splat(menu.size) {
+            layer.size = Vec2::splat(menu.size);
+        }
+        let resolution = menu.resolution.max(1);
+        let data = draw_radial_menu(menu, resolution, &images);
+        let Some(image) = images.get_mut(&layer.image) else {
+            continue;
+        };
+        let size = image.texture_descriptor.size;
+        match size.width == resolution && size.height == resolution {
+            true => image.data = data,
+            false => {
+                let mut new = Image::new(
+                    Extent3d {
+                        width: resolution,
+                        height: resolution,
+                        depth_or_array_layers: 1,
+                    },
+                    TextureDimension::D2,
+                    data,
+                    TextureFormat::Rgba8UnormSrgb,
+                );
+                new.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+                *image = new;
+            }
+        }
+    }
+}
+
+fn draw_radial_menu(menu: &XrRadialMenu, resolution: u32, images: &Assets<Image>) -> Vec<u8> {
+    let mut data = vec![0; (resolution * resolution * 4) as usize];
+    let sections = menu.sections.len();
+    if sections == 0 {
+        return data;
+    }
+    let step = TAU / sections as f32;
+    let color = menu.color.as_rgba_u8();
+    let hovered_color = menu.hovered_color.as_rgba_u8();
+    //the point in the menu at a pixel, from -1 to 1 with up as positive y
+    let point = |x: u32, y: u32| {
+        Vec2::new(
+            (x as f32 + 0.5) / resolution as f32 * 2.0 - 1.0,
+            1.0 - (y as f32 + 0.5) / resolution as f32 * 2.0,
+        )
+    };
+    for y in 0..resolution {
+        for x in 0..resolution {
+            let point = point(x, y);
+            let radius = point.length();
+            if radius > 1.0 || radius < menu.inner_radius {
+                continue;
+            }
+            let section = section_at(point, sections);
+            let angle = point.x.atan2(point.y) - section as f32 * step;
+            let from_middle = (angle + TAU / 2.0).rem_euclid(TAU) - TAU / 2.0;
+            if sections > 1 && (step / 2.0 - from_middle.abs()) * radius < SECTION_GAP / 2.0 {
+                continue;
+            }
+            let pixel = match menu.hovered == Some(section) {
+                true => hovered_color,
+                false => color,
+            };
+            let index = ((y * resolution + x) * 4) as usize;
+            data[index..index + 4].copy_from_slice(&pixel);
+        }
+    }
+    //the icons are centered between the inner and outer radius
+    let middle = (menu.inner_radius + 1.0) / 2.0;
+    let icon_size = ((1.0 - menu.inner_radius) * ICON_SCALE * resolution as f32 / 2.0) as u32;
+    for (index, section) in menu.sections.iter().enumerate() {
+        let Some(icon) = section.icon.as_ref().and_then(|icon| images.get(icon)) else {
+            continue;
+        };
+        let size = icon.texture_descriptor.size;
+        let size = UVec2::new(size.width, size.height);
+        if icon_size == 0 || icon.data.len() != (size.x * size.y * 4) as usize {
+            continue;
+        }
+        let angle = index as f32 * step;
+        let center = Vec2::new(angle.sin(), angle.cos()) * middle;
+        let left = ((center.x + 1.0) / 2.0 * resolution as f32) as i64 - icon_size as i64 / 2;
+        let top = ((1.0 - center.y) / 2.0 * resolution as f32) as i64 - icon_size as i64 / 2;
+        for y in 0..icon_size {
+            for x in 0..icon_size {
+                let (target_x, target_y) = (left + x as i64, top + y as i64);
+                if !(0..resolution as i64).contains(&target_x)
+                    || !(0..resolution as i64).contains(&target_y)
+                {
+                    continue;
+                }
+                let source_x = x * size.x / icon_size;
+                let source_y = y * size.y / icon_size;
+                let source = ((source_y * size.x + source_x) * 4) as usize;
+                let target = ((target_y as u32 * resolution + target_x as u32) * 4) as usize;
+                let alpha = icon.data[source + 3] as u32;
+                for channel in 0..3 {
+                    let over = icon.data[source + channel] as u32;
+                    let under = data[target + channel] as u32;
+                    data[target + channel] = ((over * alpha + under * (255 - alpha)) / 255) as u8;
+                }
+                let under = data[target + 3] as u32;
+                data[target + 3] = (alpha + under * (255 - alpha) / 255) as u8;
+            }
+        }
+    }
+    data
+}
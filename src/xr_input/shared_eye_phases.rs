@@ -0,0 +1,115 @@
+use bevy::core_pipeline::core_3d::{AlphaMask3d, Opaque3d, Transparent3d};
+use bevy::core_pipeline::prepass::{AlphaMask3dPrepass, Opaque3dPrepass};
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponentPlugin;
+use bevy::render::render_phase::{PhaseItem, RenderPhase};
+use bevy::render::view::VisibleEntities;
+use bevy::render::{Render, RenderApp, RenderSet};
+
+use super::xr_camera::{Eye, XrCameraType};
+
+/// queues, sorts and batches the meshes of a frame once for both eyes. the right eye gets no
+/// visible entities in the render world, so the material queue systems, the phase sort and the
+/// batching skip it, and once the left eye's phases are prepared the right eye draws the same
+/// items, sharing their mesh uniforms. transparent meshes are sorted by their distance to the
+/// left eye.
+///
+/// the queued pipelines are specialized for the left eye, so both eyes need the same camera
+/// settings (hdr, tonemapping, dither, prepasses)
+pub struct XrSharedEyePhasesPlugin;
+
+impl Plugin for XrSharedEyePhasesPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ExtractComponentPlugin<XrCameraType>>() {
+            app.add_plugins(ExtractComponentPlugin::<XrCameraType>::default());
+        }
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(
+            Render,
+            (
+                //the queue systems read the visible entities of each view
+                clear_right_eye_visible_entities.in_set(RenderSet::ManageViews),
+                //the phases are batched in `PrepareResources`
+                (
+                    copy_eye_phase::<Opaque3d>,
+                    copy_eye_phase::<AlphaMask3d>,
+                    copy_eye_phase::<Transparent3d>,
+                    copy_eye_phase::<Opaque3dPrepass>,
+                    copy_eye_phase::<AlphaMask3dPrepass>,
+                )
+                    .in_set(RenderSet::PrepareBindGroups),
+            ),
+        );
+    }
+}
+
+/// a phase item the right eye can draw a copy of
+pub trait SharedPhaseItem: PhaseItem {
+    fn copy(&self) -> Self;
+}
+
+macro_rules! impl_shared_phase_item {
+    ($item:ty) => {
+        impl SharedPhaseItem for $item {
+            fn copy(&self) -> Self {
+                Self {
+                    distance: self.distance,
+                    pipeline: self.pipeline,
+                    entity: self.entity,
+                    draw_function: self.draw_function,
+                    batch_range: self.batch_range.clone(),
+                    dynamic_offset: self.dynamic_offset,
+                }
+            }
+        }
+    };
+}
+
+impl_shared_phase_item!(Opaque3d);
+impl_shared_phase_item!(AlphaMask3d);
+impl_shared_phase_item!(Transparent3d);
+
+macro_rules! impl_shared_prepass_item {
+    ($item:ty) => {
+        impl SharedPhaseItem for $item {
+            fn copy(&self) -> Self {
+                Self {
+                    entity: self.entity,
+                    pipeline_id: self.pipeline_id,
+                    draw_function: self.draw_function,
+                    batch_range: self.batch_range.clone(),
+                    dynamic_offset: self.dynamic_offset,
+                }
+            }
+        }
+    };
+}
+
+impl_shared_prepass_item!(Opaque3dPrepass);
+impl_shared_prepass_item!(AlphaMask3dPrepass);
+
+pub fn clear_right_eye_visible_entities(mut views: Query<(&XrCameraType, &mut VisibleEntities)>) {
+    for (camera_type, mut visible_entities) in views.iter_mut() {
+        if *camera_type == XrCameraType::Xr(Eye::Right) {
+            visible_entities.entities.clear();
+        }
+    }
+}
+
+pub fn copy_eye_phase<I: SharedPhaseItem>(mut views: Query<(&XrCameraType, &mut RenderPhase<I>)>) {
+    let Some(items) = views
+        .iter()
+        .find(|(camera_type, _)| **camera_type == XrCameraType::Xr(Eye::Left))
+        .map(|(_, phase)| phase.items.iter().map(I::copy).collect::<Vec<_>>())
+    else {
+        return;
+    };
+    for (camera_type, mut phase) in views.iter_mut() {
+        if *camera_type == XrCameraType::Xr(Eye::Right) {
+            phase.items = items;
+            return;
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+use openxr::Path;
+
+use crate::resources::XrSession;
+use crate::xr_init::xr_only;
+
+use super::actions::XrActionSets;
+use super::oculus_touch::{subaction_path, OculusController};
+use super::{action_set_system, Hand};
+
+/// lets players that can only use one hand play with a single controller. the controller acts as
+/// its own hand, and as the other hand while the switch button is held (or after it was
+/// toggled). everything read through [`OculusControllerRef`](super::oculus_touch::OculusControllerRef)
+/// is remapped, the hand that isn't acted as reads as idle
+pub struct SingleControllerPlugin;
+
+impl Plugin for SingleControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SingleControllerConfig>();
+        app.add_systems(
+            PreUpdate,
+            update_single_controller_mode
+                .run_if(xr_only())
+                .after(action_set_system),
+        );
+    }
+}
+
+/// set from the `single_controller` entry of an action bindings file when it has one, see
+/// [`XrActionBindingsPlugin`](super::action_assets::XrActionBindingsPlugin)
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SingleControllerConfig {
+    pub enabled: bool,
+    /// the controller the player holds
+    pub controller: Hand,
+    pub switch: SingleControllerSwitch,
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub menu: SingleControllerMenu,
+}
+
+impl Default for SingleControllerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            controller: Hand::Right,
+            switch: SingleControllerSwitch::Toggle(SingleControllerButton::ThumbstickClick),
+            menu: SingleControllerMenu::default(),
+        }
+    }
+}
+
+/// how the menu button is reached, only the left controller has one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum SingleControllerMenu {
+    /// only the menu button of the left controller
+    Button,
+    /// both buttons pressed together on the held controller read as the menu button, they don't
+    /// send their own input while they are held together
+    Chord(SingleControllerButton, SingleControllerButton),
+}
+
+impl Default for SingleControllerMenu {
+    fn default() -> Self {
+        SingleControllerMenu::Chord(
+            SingleControllerButton::Primary,
+            SingleControllerButton::Secondary,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum SingleControllerSwitch {
+    /// acts as the other hand while the button is held, like a modifier key
+    Hold(SingleControllerButton),
+    /// each press switches between the hands
+    Toggle(SingleControllerButton),
+}
+
+impl SingleControllerSwitch {
+    pub fn button(&self) -> SingleControllerButton {
+        match self {
+            SingleControllerSwitch::Hold(button) | SingleControllerSwitch::Toggle(button) => {
+                *button
+            }
+        }
+    }
+}
+
+/// the button used for switching, it doesn't send its own input anymore
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum SingleControllerButton {
+    /// a on the right controller, x on the left one
+    Primary,
+    /// b on the right controller, y on the left one
+    Secondary,
+    ThumbstickClick,
+    ThumbrestTouch,
+}
+
+/// the current remapping, set by the plugin
+#[derive(Clone, Copy, Debug)]
+pub struct SingleControllerRemap {
+    /// the controller that is held
+    pub controller: Hand,
+    /// the hand the controller currently acts as
+    pub acting_as: Hand,
+    pub switch_button: SingleControllerButton,
+    /// the buttons of the menu chord, if they are held together right now
+    pub menu_chord: Option<(SingleControllerButton, SingleControllerButton)>,
+}
+
+impl SingleControllerRemap {
+    /// the physical hand to read for `hand`, none if the hand is idle right now
+    pub fn source(&self, hand: Hand) -> Option<Hand> {
+        (hand == self.acting_as).then_some(self.controller)
+    }
+
+    /// the switch button and a held menu chord don't send their own input
+    pub fn is_muted(&self, button: SingleControllerButton) -> bool {
+        self.switch_button == button
+            || self
+                .menu_chord
+                .is_some_and(|(first, second)| first == button || second == button)
+    }
+
+    /// whether the held controller reads as pressing the menu button, the physical one is only
+    /// on the left controller
+    pub fn menu_pressed(&self) -> bool {
+        self.menu_chord.is_some()
+    }
+}
+
+pub fn update_single_controller_mode(
+    config: Res<SingleControllerConfig>,
+    mut controller: ResMut<OculusController>,
+    action_sets: Res<XrActionSets>,
+    session: Res<XrSession>,
+    mut was_pressed: Local<bool>,
+    mut toggled: Local<bool>,
+) {
+    if !config.enabled {
+        controller.remap = None;
+        *toggled = false;
+        return;
+    }
+    let pressed = physical_button(
+        &action_sets,
+        &session,
+        config.controller,
+        config.switch.button(),
+    );
+    let other_hand = match config.switch {
+        SingleControllerSwitch::Hold(_) => pressed,
+        SingleControllerSwitch::Toggle(_) => {
+            if pressed && !*was_pressed {
+                *toggled = !*toggled;
+            }
+            *toggled
+        }
+    };
+    *was_pressed = pressed;
+    let menu_chord = match config.menu {
+        SingleControllerMenu::Button => None,
+        SingleControllerMenu::Chord(first, second) => {
+            let held = |button| physical_button(&action_sets, &session, config.controller, button);
+            (held(first) && held(second)).then_some((first, second))
+        }
+    };
+    controller.remap = Some(SingleControllerRemap {
+        controller: config.controller,
+        acting_as: match (other_hand, config.controller) {
+            (false, hand) => hand,
+            (true, Hand::Left) => Hand::Right,
+            (true, Hand::Right) => Hand::Left,
+        },
+        switch_button: config.switch.button(),
+        menu_chord,
+    });
+}
+
+fn physical_button(
+    action_sets: &XrActionSets,
+    session: &XrSession,
+    hand: Hand,
+    button: SingleControllerButton,
+) -> bool {
+    let (action, path) = match (button, hand) {
+        (SingleControllerButton::Primary, Hand::Left) => ("x_button", Path::NULL),
+        (SingleControllerButton::Primary, Hand::Right) => ("a_button", Path::NULL),
+        (SingleControllerButton::Secondary, Hand::Left) => ("y_button", Path::NULL),
+        (SingleControllerButton::Secondary, Hand::Right) => ("b_button", Path::NULL),
+        (SingleControllerButton::ThumbstickClick, hand) => {
+            ("thumbstick_click", subaction_path(hand))
+        }
+        (SingleControllerButton::ThumbrestTouch, hand) => ("thumbrest_touch", subaction_path(hand)),
+    };
+    action_sets
+        .get_action_bool("oculus_input", action)
+        .ok()
+        .and_then(|action| action.state(&**session, path).ok())
+        .is_some_and(|state| state.current_state)
+}
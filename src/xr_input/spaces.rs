@@ -0,0 +1,109 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use openxr as xr;
+
+use crate::call_trace::trace;
+use crate::convert::PosefConv;
+use crate::input::XrInput;
+use crate::resources::XrFrameState;
+
+use super::Vec3Conv;
+
+/// locates any openxr space relative to another one, e.g. a controller in view space:
+/// `spaces.locate(&grip_space, spaces.view())`. the results are in meters in the base space,
+/// the world scale and the tracking root aren't applied
+#[derive(SystemParam)]
+pub struct XrSpaces<'w> {
+    input: Res<'w, XrInput>,
+    frame_state: Res<'w, XrFrameState>,
+}
+
+/// a space located relative to a base space, with its velocities if the runtime knows them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrSpaceLocation {
+    pub transform: Transform,
+    /// false when the pose is only inferred or last known
+    pub tracked: bool,
+    pub linear_velocity: Option<Vec3>,
+    pub angular_velocity: Option<Vec3>,
+}
+
+impl<'w> XrSpaces<'w> {
+    pub fn stage(&self) -> &xr::Space {
+        &self.input.stage
+    }
+
+    pub fn local(&self) -> &xr::Space {
+        &self.input.local
+    }
+
+    pub fn view(&self) -> &xr::Space {
+        &self.input.head
+    }
+
+    /// the predicted display time of the current frame
+    pub fn display_time(&self) -> xr::Time {
+        self.frame_state.lock().unwrap().predicted_display_time
+    }
+
+    /// `space` in `base` at the predicted display time, `None` if it isn't located
+    pub fn locate(&self, space: &xr::Space, base: &xr::Space) -> Option<Transform> {
+        self.locate_at(space, base, self.display_time())
+    }
+
+    /// `space` in `base` at `time`, `None` if it isn't located
+    pub fn locate_at(
+        &self,
+        space: &xr::Space,
+        base: &xr::Space,
+        time: xr::Time,
+    ) -> Option<Transform> {
+        locate_space(space, base, time)
+            .ok()
+            .flatten()
+            .map(|location| location.transform)
+    }
+
+    /// like [`XrSpaces::locate`], with the tracking state and velocities
+    pub fn locate_with_velocity(
+        &self,
+        space: &xr::Space,
+        base: &xr::Space,
+    ) -> Option<XrSpaceLocation> {
+        locate_space(space, base, self.display_time())
+            .ok()
+            .flatten()
+    }
+}
+
+/// locates `space` in `base` at `time`, `Ok(None)` if the runtime has no pose for it
+pub fn locate_space(
+    space: &xr::Space,
+    base: &xr::Space,
+    time: xr::Time,
+) -> xr::Result<Option<XrSpaceLocation>> {
+    let (location, velocity) = trace(
+        "xrLocateSpace",
+        || format!("{:?}", time),
+        || space.relate(base, time),
+    )?;
+    let flags = location.location_flags;
+    if !flags.contains(
+        xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID,
+    ) {
+        return Ok(None);
+    }
+    let velocity_flags = velocity.velocity_flags;
+    Ok(Some(XrSpaceLocation {
+        transform: location.pose.to_transform(),
+        tracked: flags.contains(
+            xr::SpaceLocationFlags::POSITION_TRACKED | xr::SpaceLocationFlags::ORIENTATION_TRACKED,
+        ),
+        linear_velocity: velocity_flags
+            .contains(xr::SpaceVelocityFlags::LINEAR_VALID)
+            .then(|| velocity.linear_velocity.to_vec3()),
+        angular_velocity: velocity_flags
+            .contains(xr::SpaceVelocityFlags::ANGULAR_VALID)
+            .then(|| velocity.angular_velocity.to_vec3()),
+    }))
+}
@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+use openxr::Path;
+
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::{xr_only, XrSetup};
+
+use super::actions::{
+    ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding, XrSyncActions,
+};
+use super::oculus_touch::{oculus_touch_bindings, setup_oculus_controller};
+use super::Hand;
+
+/// the extension of the touch plus profile, the openxr crate has no bindings for it
+pub(crate) const TOUCH_CONTROLLER_PLUS_EXTENSION: &str = "XR_META_touch_controller_plus";
+
+const ACTION_SET: &str = "touch_pro_input";
+const TOUCH_PRO_PROFILE: &str = "/interaction_profiles/facebook/touch_controller_pro";
+const TOUCH_PLUS_PROFILE: &str = "/interaction_profiles/meta/touch_controller_plus";
+
+/// the inputs quest touch pro and touch plus controllers have on top of the touch controller:
+/// thumb rest and stylus pressure, trigger curl, slide and proximity. they live in the
+/// `touch_pro_input` action set next to the oculus touch one, which gets suggested for both
+/// profiles too, and can be read as [`XrTouchPro`]. the haptics of the touch pro's trigger and
+/// thumb rest are the `haptic_trigger` and `haptic_thumb` actions of the set. added by
+/// [`XrControllerType::TouchPro`](super::controllers::XrControllerType::TouchPro), a profile is
+/// only suggested when the runtime has its extension
+pub struct XrTouchProPlugin;
+
+impl Plugin for XrTouchProPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrTouchPro>();
+        app.add_systems(
+            XrSetup,
+            setup_touch_pro_actions.after(setup_oculus_controller),
+        );
+        app.add_systems(
+            PreUpdate,
+            update_xr_touch_pro.run_if(xr_only()).after(XrSyncActions),
+        );
+    }
+}
+
+const ACTIONS: [(&str, &str, ActionType); 9] = [
+    ("thumbrest_force", "Thumb Rest Force", ActionType::F32),
+    ("stylus_force", "Stylus Force", ActionType::F32),
+    ("trigger_curl", "Trigger Curl", ActionType::F32),
+    ("trigger_slide", "Trigger Slide", ActionType::F32),
+    ("trigger_force", "Trigger Force", ActionType::F32),
+    ("trigger_proximity", "Trigger Proximity", ActionType::Bool),
+    ("thumb_proximity", "Thumb Proximity", ActionType::Bool),
+    ("haptic_trigger", "Trigger Haptics", ActionType::Haptic),
+    ("haptic_thumb", "Thumb Rest Haptics", ActionType::Haptic),
+];
+
+// (action, left binding, right binding)
+const TOUCH_PRO_BINDINGS: [(&str, &str, &str); 8] = [
+    (
+        "thumbrest_force",
+        "/user/hand/left/input/thumbrest/force",
+        "/user/hand/right/input/thumbrest/force",
+    ),
+    (
+        "stylus_force",
+        "/user/hand/left/input/stylus_fb/force",
+        "/user/hand/right/input/stylus_fb/force",
+    ),
+    (
+        "trigger_curl",
+        "/user/hand/left/input/trigger/curl_fb",
+        "/user/hand/right/input/trigger/curl_fb",
+    ),
+    (
+        "trigger_slide",
+        "/user/hand/left/input/trigger/slide_fb",
+        "/user/hand/right/input/trigger/slide_fb",
+    ),
+    (
+        "trigger_proximity",
+        "/user/hand/left/input/trigger/proximity_fb",
+        "/user/hand/right/input/trigger/proximity_fb",
+    ),
+    (
+        "thumb_proximity",
+        "/user/hand/left/input/thumb_fb/proximity_fb",
+        "/user/hand/right/input/thumb_fb/proximity_fb",
+    ),
+    (
+        "haptic_trigger",
+        "/user/hand/left/output/haptic_trigger_fb",
+        "/user/hand/right/output/haptic_trigger_fb",
+    ),
+    (
+        "haptic_thumb",
+        "/user/hand/left/output/haptic_thumb_fb",
+        "/user/hand/right/output/haptic_thumb_fb",
+    ),
+];
+
+const TOUCH_PLUS_BINDINGS: [(&str, &str, &str); 5] = [
+    (
+        "trigger_curl",
+        "/user/hand/left/input/trigger/curl_meta",
+        "/user/hand/right/input/trigger/curl_meta",
+    ),
+    (
+        "trigger_slide",
+        "/user/hand/left/input/trigger/slide_meta",
+        "/user/hand/right/input/trigger/slide_meta",
+    ),
+    (
+        "trigger_force",
+        "/user/hand/left/input/trigger/force",
+        "/user/hand/right/input/trigger/force",
+    ),
+    (
+        "trigger_proximity",
+        "/user/hand/left/input/trigger/proximity_meta",
+        "/user/hand/right/input/trigger/proximity_meta",
+    ),
+    (
+        "thumb_proximity",
+        "/user/hand/left/input/thumb_meta/proximity_meta",
+        "/user/hand/right/input/thumb_meta/proximity_meta",
+    ),
+];
+
+pub fn setup_touch_pro_actions(
+    instance: Res<XrInstance>,
+    mut action_sets: ResMut<SetupActionSets>,
+) {
+    let plus = instance
+        .exts()
+        .other
+        .iter()
+        .any(|ext| ext == TOUCH_CONTROLLER_PLUS_EXTENSION);
+    //suggesting a profile whose extension isn't enabled fails for the whole profile
+    let profiles = [
+        (
+            TOUCH_PRO_PROFILE,
+            &TOUCH_PRO_BINDINGS[..],
+            instance.exts().fb_touch_controller_pro.is_some(),
+        ),
+        (TOUCH_PLUS_PROFILE, &TOUCH_PLUS_BINDINGS[..], plus),
+    ]
+    .into_iter()
+    .filter(|(_, _, enabled)| *enabled)
+    .map(|(profile, bindings, _)| (profile, bindings))
+    .collect::<Vec<_>>();
+    if let Some(oculus) = action_sets.get_action_set_mut("oculus_input") {
+        //the runtime picks one profile per hand for every action set, without these the touch
+        //actions would go unbound once it picks the pro or plus profile
+        for &(profile, _) in &profiles {
+            oculus.suggest_binding(profile, &oculus_touch_bindings());
+        }
+    }
+    let action_set = action_sets.add_action_set(ACTION_SET, "Touch Pro Controller Input".into(), 0);
+    for (name, pretty_name, action_type) in ACTIONS {
+        action_set.new_action(
+            name,
+            pretty_name.into(),
+            action_type,
+            ActionHandednes::Double,
+        );
+    }
+    for (profile, bindings) in profiles {
+        let bindings = bindings
+            .iter()
+            .flat_map(|&(name, left, right)| {
+                [XrBinding::new(name, left), XrBinding::new(name, right)]
+            })
+            .collect::<Vec<_>>();
+        action_set.suggest_binding(profile, &bindings);
+    }
+}
+
+/// the extra inputs of one controller this frame, `None` while the controller doesn't have
+/// the input or isn't active
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrTouchProHand {
+    /// how hard the thumb rest is pressed, from 0 to 1. touch pro only
+    pub thumbrest_force: Option<f32>,
+    /// from 0 to 1, touch pro only
+    pub stylus_force: Option<f32>,
+    /// how far the index finger curls while it isn't on the trigger, from 0 to 1
+    pub trigger_curl: Option<f32>,
+    /// how far the index finger slides from the trigger's tip towards the grip, from 0 to 1
+    pub trigger_slide: Option<f32>,
+    /// how hard the trigger is pressed once it is pulled all the way, touch plus only
+    pub trigger_force: Option<f32>,
+    /// the index finger is close to the trigger
+    pub trigger_proximity: Option<bool>,
+    /// the thumb is close to the buttons, the thumbstick or the thumb rest
+    pub thumb_proximity: Option<bool>,
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrTouchPro {
+    pub left: XrTouchProHand,
+    pub right: XrTouchProHand,
+}
+
+impl XrTouchPro {
+    pub fn get(&self, hand: Hand) -> &XrTouchProHand {
+        match hand {
+            Hand::Left => &self.left,
+            Hand::Right => &self.right,
+        }
+    }
+}
+
+pub fn update_xr_touch_pro(
+    action_sets: Option<Res<XrActionSets>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    mut touch_pro: ResMut<XrTouchPro>,
+    mut paths: Local<Option<[Path; 2]>>,
+) {
+    let Some(action_sets) = action_sets else {
+        return;
+    };
+    let [left, right] = *paths.get_or_insert_with(|| {
+        [
+            instance.string_to_path("/user/hand/left").unwrap(),
+            instance.string_to_path("/user/hand/right").unwrap(),
+        ]
+    });
+    let read = |path: Path| {
+        let axis = |name| {
+            action_sets
+                .get_action_f32(ACTION_SET, name)
+                .ok()
+                .and_then(|action| action.state(&session, path).ok())
+                .filter(|state| state.is_active)
+                .map(|state| state.current_state)
+        };
+        let button = |name| {
+            action_sets
+                .get_action_bool(ACTION_SET, name)
+                .ok()
+                .and_then(|action| action.state(&session, path).ok())
+                .filter(|state| state.is_active)
+                .map(|state| state.current_state)
+        };
+        XrTouchProHand {
+            thumbrest_force: axis("thumbrest_force"),
+            stylus_force: axis("stylus_force"),
+            trigger_curl: axis("trigger_curl"),
+            trigger_slide: axis("trigger_slide"),
+            trigger_force: axis("trigger_force"),
+            trigger_proximity: button("trigger_proximity"),
+            thumb_proximity: button("thumb_proximity"),
+        }
+    };
+    let new = XrTouchPro {
+        left: read(left),
+        right: read(right),
+    };
+    if *touch_pro != new {
+        *touch_pro = new;
+    }
+}
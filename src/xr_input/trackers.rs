@@ -1,17 +1,32 @@
 use bevy::log::{debug, info};
 use bevy::prelude::{
-    Added, BuildChildren, Commands, Component, Entity, Query, Res, Transform, Vec3, With, Without,
+    Added, BuildChildren, Commands, Component, Deref, DerefMut, Entity, EulerRot, Local, Or, Quat,
+    Query, Res, Resource, Time, Transform, Vec3, With, Without,
 };
+use openxr::{SpaceLocation, SpaceLocationFlags, SpaceVelocity, SpaceVelocityFlags};
 
 use crate::{
+    call_trace::trace,
+    convert::PosefConv,
     input::XrInput,
     resources::{XrFrameState, XrSession},
 };
 
-use super::{actions::XrActionSets, oculus_touch::OculusController, Hand, QuatConv, Vec3Conv};
+use super::{
+    actions::XrActionSets,
+    oculus_touch::OculusController,
+    pose_filter::{PoseFilter, PoseFilterState},
+    views::XrView,
+    Hand, Vec3Conv,
+};
 
+/// the origin of the tracking space, every tracker (head, eyes, controllers) is a child of it.
+/// moving, rotating or teleporting the player is just a change to the transform of this entity
 #[derive(Component)]
-pub struct OpenXRTrackingRoot;
+pub struct XrTrackingRoot;
+/// the old name of [`XrTrackingRoot`]. a re-export instead of a type alias, so it still works as
+/// a value in `commands.spawn(OpenXRTrackingRoot)`. re-exports can't be deprecated
+pub use XrTrackingRoot as OpenXRTrackingRoot;
 #[derive(Component)]
 pub struct OpenXRTracker;
 #[derive(Component)]
@@ -29,10 +44,187 @@ pub struct OpenXRController;
 #[derive(Component)]
 pub struct AimPose(pub Transform);
 
+/// whether the pose of a tracker was located this frame. trackers keep their last pose while it
+/// isn't valid instead of snapping to the origin, so apps can freeze or grey them out. every
+/// `OpenXRTracker` gets one, untracked until the first pose arrives
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct XrTrackingState {
+    pub position_valid: bool,
+    pub position_tracked: bool,
+    pub orientation_valid: bool,
+    pub orientation_tracked: bool,
+}
+
+impl XrTrackingState {
+    pub fn from_flags(flags: SpaceLocationFlags) -> Self {
+        Self {
+            position_valid: flags.contains(SpaceLocationFlags::POSITION_VALID),
+            position_tracked: flags.contains(SpaceLocationFlags::POSITION_TRACKED),
+            orientation_valid: flags.contains(SpaceLocationFlags::ORIENTATION_VALID),
+            orientation_tracked: flags.contains(SpaceLocationFlags::ORIENTATION_TRACKED),
+        }
+    }
+
+    /// the runtime knows a full pose, it may be inferred or the last known one
+    pub fn is_valid(&self) -> bool {
+        self.position_valid && self.orientation_valid
+    }
+
+    /// the pose is actively tracked
+    pub fn is_tracked(&self) -> bool {
+        self.is_valid() && self.position_tracked && self.orientation_tracked
+    }
+}
+
+/// smooths and extrapolates the grip and aim poses of the controllers before they are written
+/// to the controller entities. smoothing trades jitter for lag, extrapolating along the
+/// velocity the runtime reports wins some of that lag back but overshoots on sudden stops.
+/// without this resource the raw poses are used
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrControllerSmoothing {
+    pub filter: PoseFilter,
+    /// seconds the poses are moved ahead along their velocity, after the filter
+    pub extrapolation: f32,
+}
+
+impl XrControllerSmoothing {
+    /// the pose of a controller space after smoothing and extrapolation, `state` is the filter
+    /// state of the space and `dt` the time since the last frame in seconds
+    pub fn apply(
+        &self,
+        state: &mut PoseFilterState,
+        (location, velocity): (SpaceLocation, SpaceVelocity),
+        dt: f32,
+    ) -> Transform {
+        let mut transform = location.pose.to_transform();
+        let valid = SpaceLocationFlags::POSITION_VALID | SpaceLocationFlags::ORIENTATION_VALID;
+        if self.filter == PoseFilter::None || !location.location_flags.contains(valid) {
+            state.reset();
+        } else {
+            (transform.translation, transform.rotation) =
+                state.filter(&self.filter, transform.translation, transform.rotation, dt);
+        }
+        if self.extrapolation > 0.0 {
+            let flags = velocity.velocity_flags;
+            if flags.contains(SpaceVelocityFlags::LINEAR_VALID) {
+                transform.translation += velocity.linear_velocity.to_vec3() * self.extrapolation;
+            }
+            if flags.contains(SpaceVelocityFlags::ANGULAR_VALID) {
+                let turn = velocity.angular_velocity.to_vec3() * self.extrapolation;
+                let turn = Quat::from_scaled_axis(turn);
+                transform.rotation = (turn * transform.rotation).normalize();
+            }
+        }
+        transform
+    }
+}
+
+/// how many world units one meter in the tracking space is. this is applied as the scale of the
+/// tracking root, so the head, the eye separation and the controllers all scale together and the
+/// stereo stays correct. bigger than 1.0 makes the player a giant, smaller makes them tiny
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Deref, DerefMut)]
+pub struct XrWorldScale(pub f32);
+
+impl Default for XrWorldScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+pub fn update_xr_world_scale(
+    world_scale: Res<XrWorldScale>,
+    mut tracking_root_query: Query<&mut Transform, With<XrTrackingRoot>>,
+) {
+    let scale = Vec3::splat(world_scale.0);
+    for mut transform in tracking_root_query.iter_mut() {
+        if transform.scale != scale {
+            transform.scale = scale;
+        }
+    }
+}
+
+/// the point on the floor of the tracking space below the head, in world space.
+/// `head` is the position of the head relative to the tracking root
+pub fn head_floor_position(root: &Transform, head: Vec3) -> Vec3 {
+    root.transform_point(Vec3::new(head.x, 0.0, head.z))
+}
+
+/// rotates the tracking root around the head instead of around the tracking origin, so the
+/// player turns in place. `head` is the position of the head relative to the tracking root
+pub fn rotate_root_around_head(root: &mut Transform, head: Vec3, rotation: Quat) {
+    let pivot = head_floor_position(root, head);
+    root.rotate_around(pivot, rotation);
+}
+
+/// turns the tracking root around the head by `angle` radians around its up axis
+pub fn turn_root_around_head(root: &mut Transform, head: Vec3, angle: f32) {
+    let rotation = Quat::from_axis_angle(root.up(), angle);
+    rotate_root_around_head(root, head, rotation);
+}
+
+/// moves the tracking root so the floor below the head ends up at `target`
+pub fn teleport_root(root: &mut Transform, head: Vec3, target: Vec3) {
+    let offset = target - head_floor_position(root, head);
+    root.translation += offset;
+}
+
+/// sets the rotation of the tracking root so the head faces `yaw` radians around the world up
+/// axis, while keeping the head where it is
+pub fn set_root_head_yaw(root: &mut Transform, head: &Transform, yaw: f32) {
+    let (head_yaw, _, _) = (root.rotation * head.rotation).to_euler(EulerRot::YXZ);
+    rotate_root_around_head(
+        root,
+        head.translation,
+        Quat::from_rotation_y(yaw - head_yaw),
+    );
+}
+
+/// keeps the head tracker at the center between the eyes
+pub fn update_open_xr_hmd(
+    views: Query<(&XrView, &Transform), Without<OpenXRHMD>>,
+    mut hmd_query: Query<&mut Transform, With<OpenXRHMD>>,
+) {
+    let find = |index| views.iter().find(|(view, _)| view.index == index);
+    let (Some((_, left)), Some((_, right))) = (find(0), find(1)) else {
+        return;
+    };
+    let translation = (left.translation + right.translation) / 2.0;
+    for mut transform in hmd_query.iter_mut() {
+        transform.translation = translation;
+        transform.rotation = left.rotation.slerp(right.rotation, 0.5);
+    }
+}
+
+/// the tracking state of the head, also used for the eyes
+pub fn update_hmd_tracking_state(
+    xr_input: Res<XrInput>,
+    frame_state: Res<XrFrameState>,
+    mut states: Query<
+        &mut XrTrackingState,
+        Or<(With<OpenXRHMD>, With<OpenXRLeftEye>, With<OpenXRRightEye>)>,
+    >,
+) {
+    let time = frame_state.lock().unwrap().predicted_display_time;
+    let location = trace(
+        "xrLocateSpace",
+        || format!("head, {:?}", time),
+        || xr_input.head.locate(&xr_input.stage, time),
+    );
+    let state = match location {
+        Ok(location) => XrTrackingState::from_flags(location.location_flags),
+        Err(_) => XrTrackingState::default(),
+    };
+    for mut current in &mut states {
+        if *current != state {
+            *current = state;
+        }
+    }
+}
+
 pub fn adopt_open_xr_trackers(
     query: Query<Entity, Added<OpenXRTracker>>,
     mut commands: Commands,
-    tracking_root_query: Query<(Entity, With<OpenXRTrackingRoot>)>,
+    tracking_root_query: Query<(Entity, With<XrTrackingRoot>)>,
 ) {
     let root = tracking_root_query.get_single();
     match root {
@@ -41,12 +233,14 @@ pub fn adopt_open_xr_trackers(
             for tracker in query.iter() {
                 info!("we got a new tracker");
                 commands.entity(thing.0).add_child(tracker);
+                commands.entity(tracker).insert(XrTrackingState::default());
             }
         }
         Err(_) => info!("root isnt spawned yet?"),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_open_xr_controllers(
     oculus_controller: Res<OculusController>,
     mut left_controller_query: Query<(
@@ -54,87 +248,101 @@ pub fn update_open_xr_controllers(
         Option<&mut AimPose>,
         With<OpenXRLeftController>,
         Without<OpenXRRightController>,
+        Option<&mut XrTrackingState>,
     )>,
     mut right_controller_query: Query<(
         &mut Transform,
         Option<&mut AimPose>,
         With<OpenXRRightController>,
         Without<OpenXRLeftController>,
+        Option<&mut XrTrackingState>,
     )>,
     frame_state: Res<XrFrameState>,
     xr_input: Res<XrInput>,
     session: Res<XrSession>,
     action_sets: Res<XrActionSets>,
+    smoothing: Option<Res<XrControllerSmoothing>>,
+    time: Res<Time>,
+    //grip and aim of each hand
+    mut filter_states: Local<[[PoseFilterState; 2]; 2]>,
 ) {
     //lock dat frame?
     let frame_state = *frame_state.lock().unwrap();
     //get controller
     let controller = oculus_controller.get_ref(&session, &frame_state, &xr_input, &action_sets);
+    let smoothing = smoothing.as_deref().copied().unwrap_or_default();
+    let dt = time.delta_seconds();
+    let [[left_grip_state, left_aim_state], [right_grip_state, right_aim_state]] =
+        &mut *filter_states;
     //get left controller
-    let left_grip_space = controller.grip_space(Hand::Left);
-    let left_aim_space = controller.aim_space(Hand::Left);
-    let left_postion = left_grip_space.0.pose.position.to_vec3();
+    let left_grip_location = controller.grip_space(Hand::Left);
+    let left_state = XrTrackingState::from_flags(left_grip_location.0.location_flags);
+    let left_grip = smoothing.apply(left_grip_state, left_grip_location, dt);
+    let left_aim = smoothing.apply(left_aim_state, controller.aim_space(Hand::Left), dt);
     //TODO figure out how to not get the entity multiple times
     let left_aim_pose = left_controller_query.get_single_mut();
     //set aim pose
     match left_aim_pose {
         Ok(left_entity) => match left_entity.1 {
-            Some(mut pose) => {
-                *pose = AimPose(Transform {
-                    translation: left_aim_space.0.pose.position.to_vec3(),
-                    rotation: left_aim_space.0.pose.orientation.to_quat(),
-                    scale: Vec3::splat(1.0),
-                });
+            Some(mut pose) if left_state.is_valid() => {
+                *pose = AimPose(left_aim);
             }
-            None => (),
+            _ => (),
         },
         Err(_) => debug!("no left controlelr entity found"),
     }
+    //set tracking state, a lost controller keeps its last pose
+    if let Ok((_, _, _, _, Some(mut state))) = left_controller_query.get_single_mut() {
+        *state = left_state;
+    }
     //set translation
     let left_translation = left_controller_query.get_single_mut();
     match left_translation {
-        Ok(mut left_entity) => left_entity.0.translation = left_postion,
-        Err(_) => (),
+        Ok(mut left_entity) if left_state.is_valid() => {
+            left_entity.0.translation = left_grip.translation
+        }
+        _ => (),
     }
     //set rotation
     let left_rotataion = left_controller_query.get_single_mut();
     match left_rotataion {
-        Ok(mut left_entity) => {
-            left_entity.0.rotation = left_grip_space.0.pose.orientation.to_quat()
-        }
-        Err(_) => (),
+        Ok(mut left_entity) if left_state.is_valid() => left_entity.0.rotation = left_grip.rotation,
+        _ => (),
     }
     //get right controller
-    let right_grip_space = controller.grip_space(Hand::Right);
-    let right_aim_space = controller.aim_space(Hand::Right);
-    let right_postion = right_grip_space.0.pose.position.to_vec3();
+    let right_grip_location = controller.grip_space(Hand::Right);
+    let right_state = XrTrackingState::from_flags(right_grip_location.0.location_flags);
+    let right_grip = smoothing.apply(right_grip_state, right_grip_location, dt);
+    let right_aim = smoothing.apply(right_aim_state, controller.aim_space(Hand::Right), dt);
 
     let right_aim_pose = right_controller_query.get_single_mut();
     match right_aim_pose {
         Ok(right_entity) => match right_entity.1 {
-            Some(mut pose) => {
-                *pose = AimPose(Transform {
-                    translation: right_aim_space.0.pose.position.to_vec3(),
-                    rotation: right_aim_space.0.pose.orientation.to_quat(),
-                    scale: Vec3::splat(1.0),
-                });
+            Some(mut pose) if right_state.is_valid() => {
+                *pose = AimPose(right_aim);
             }
-            None => (),
+            _ => (),
         },
         Err(_) => debug!("no right controlelr entity found"),
     }
+    //set tracking state
+    if let Ok((_, _, _, _, Some(mut state))) = right_controller_query.get_single_mut() {
+        *state = right_state;
+    }
     //set translation
     let right_translation = right_controller_query.get_single_mut();
     match right_translation {
-        Ok(mut right_entity) => right_entity.0.translation = right_postion,
-        Err(_) => (),
+        Ok(mut right_entity) if right_state.is_valid() => {
+            right_entity.0.translation = right_grip.translation
+        }
+        _ => (),
     }
     //set rotation
     let right_rotataion = right_controller_query.get_single_mut();
     match right_rotataion {
-        Ok(mut right_entity) => {
-            right_entity.0.rotation = right_grip_space.0.pose.orientation.to_quat()
+        Ok(mut right_entity) if right_state.is_valid() => {
+            right_entity.0.rotation = right_grip.rotation
         }
-        Err(_) => (),
+        _ => (),
     }
 }
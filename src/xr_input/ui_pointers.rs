@@ -0,0 +1,257 @@
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy::utils::Uuid;
+
+use crate::input::XrInput;
+use crate::resources::{XrFrameState, XrSession};
+use crate::xr_init::xr_only;
+
+use super::actions::XrActionSets;
+use super::oculus_touch::OculusController;
+use super::trackers::{
+    AimPose, OpenXRHMD, OpenXRLeftController, OpenXRRightController, XrTrackingRoot,
+    XrTrackingState,
+};
+use super::Hand;
+
+/// trigger values above this press a ray
+const PRESS_THRESHOLD: f32 = 0.5;
+/// how far the emulated mouse ray can turn away from the head, in radians
+const MOUSE_RANGE: f32 = 1.0;
+
+/// world space pointer rays for ui and picking backends from every source the user might be
+/// using at once: the aim of both controllers, the head gaze and a mouse that turns a ray from
+/// the head. each source has its own [`XrPointerId`], [`XrPointerPolicy`] picks which ones are
+/// active. the pointers of the frame are in [`XrUiPointers`], updated after the transforms
+pub struct XrUiPointersPlugin;
+
+impl Plugin for XrUiPointersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrPointerPolicy>();
+        app.init_resource::<XrUiPointers>();
+        app.add_systems(
+            PostUpdate,
+            update_ui_pointers
+                .run_if(xr_only())
+                .after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum XrPointerId {
+    Ray(Hand),
+    Gaze,
+    Mouse,
+}
+
+impl XrPointerId {
+    /// a stable id for backends that key their pointers by uuid, like the custom pointers of
+    /// bevy_mod_picking
+    pub fn uuid(&self) -> Uuid {
+        Uuid::from_u128(match self {
+            XrPointerId::Ray(Hand::Left) => 0x6f2a_5c10_8d3e_4b7a_9c41_0e5d_2b6a_0001,
+            XrPointerId::Ray(Hand::Right) => 0x6f2a_5c10_8d3e_4b7a_9c41_0e5d_2b6a_0002,
+            XrPointerId::Gaze => 0x6f2a_5c10_8d3e_4b7a_9c41_0e5d_2b6a_0003,
+            XrPointerId::Mouse => 0x6f2a_5c10_8d3e_4b7a_9c41_0e5d_2b6a_0004,
+        })
+    }
+}
+
+/// which pointers are active
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrPointerPolicy {
+    pub rays: bool,
+    /// the gaze only hovers, it never presses
+    pub gaze: bool,
+    /// shows up once the mouse moves
+    pub mouse: bool,
+    /// only the pointer that pressed last stays active, for backends that handle one pointer
+    pub exclusive: bool,
+    /// radians the mouse ray turns per pixel of mouse motion
+    pub mouse_sensitivity: f32,
+}
+
+impl Default for XrPointerPolicy {
+    fn default() -> Self {
+        Self {
+            rays: true,
+            gaze: false,
+            mouse: false,
+            exclusive: false,
+            mouse_sensitivity: 0.002,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct XrUiPointer {
+    pub id: XrPointerId,
+    /// in world space
+    pub origin: Vec3,
+    pub direction: Vec3,
+    pub pressed: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+}
+
+/// the active pointers of this frame, a pointer that isn't in here left
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrUiPointers {
+    pointers: Vec<XrUiPointer>,
+    last_used: Option<XrPointerId>,
+    //every source that is held, the exclusive mode only publishes some of them
+    pressed: Vec<XrPointerId>,
+}
+
+impl XrUiPointers {
+    pub fn iter(&self) -> impl Iterator<Item = &XrUiPointer> {
+        self.pointers.iter()
+    }
+
+    pub fn get(&self, id: XrPointerId) -> Option<&XrUiPointer> {
+        self.pointers.iter().find(|pointer| pointer.id == id)
+    }
+
+    /// the pointer that pressed last
+    pub fn last_used(&self) -> Option<XrPointerId> {
+        self.last_used
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_ui_pointers(
+    policy: Res<XrPointerPolicy>,
+    mut pointers: ResMut<XrUiPointers>,
+    oculus_controller: Option<Res<OculusController>>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Res<XrSession>,
+    frame_state: Res<XrFrameState>,
+    xr_input: Res<XrInput>,
+    controllers: Query<
+        (
+            &AimPose,
+            Option<&XrTrackingState>,
+            Has<OpenXRLeftController>,
+        ),
+        Or<(With<OpenXRLeftController>, With<OpenXRRightController>)>,
+    >,
+    head: Query<&GlobalTransform, With<OpenXRHMD>>,
+    root: Query<&GlobalTransform, With<XrTrackingRoot>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut mouse_offset: Local<Option<Vec2>>,
+) {
+    let mut sources = Vec::new();
+    if let (true, Ok(root)) = (policy.rays, root.get_single()) {
+        let frame_state = *frame_state.lock().unwrap();
+        let controller = match (&oculus_controller, &action_sets) {
+            (Some(oculus_controller), Some(action_sets)) => {
+                Some(oculus_controller.get_ref(&session, &frame_state, &xr_input, action_sets))
+            }
+            _ => None,
+        };
+        for (aim, state, left) in &controllers {
+            if state.is_some_and(|state| !state.is_valid()) {
+                continue;
+            }
+            let hand = match left {
+                true => Hand::Left,
+                false => Hand::Right,
+            };
+            //the aim pose is relative to the tracking root
+            let aim = root.mul_transform(aim.0);
+            let pressed = controller
+                .as_ref()
+                .is_some_and(|controller| controller.trigger(hand) > PRESS_THRESHOLD);
+            sources.push((
+                XrPointerId::Ray(hand),
+                aim.translation(),
+                aim.forward(),
+                pressed,
+            ));
+        }
+    }
+    let head = head.get_single().ok();
+    if let (true, Some(head)) = (policy.gaze, head) {
+        sources.push((XrPointerId::Gaze, head.translation(), head.forward(), false));
+    }
+    let motion: Vec2 = mouse_motion.read().map(|motion| motion.delta).sum();
+    if !policy.mouse {
+        *mouse_offset = None;
+    } else if motion != Vec2::ZERO || mouse_offset.is_some() {
+        let offset = mouse_offset.get_or_insert(Vec2::ZERO);
+        *offset = (*offset - motion * policy.mouse_sensitivity)
+            .clamp(Vec2::splat(-MOUSE_RANGE), Vec2::splat(MOUSE_RANGE));
+        if let Some(head) = head {
+            let rotation = head.compute_transform().rotation
+                * Quat::from_euler(EulerRot::YXZ, offset.x, offset.y, 0.0);
+            sources.push((
+                XrPointerId::Mouse,
+                head.translation(),
+                rotation * Vec3::NEG_Z,
+                mouse_buttons.pressed(MouseButton::Left),
+            ));
+        }
+    }
+    let published = std::mem::take(&mut pointers.pointers);
+    let mut last_used = pointers.last_used;
+    let mut current = Vec::with_capacity(sources.len());
+    let mut pressed_sources = Vec::new();
+    for (id, origin, direction, pressed) in sources {
+        let was_pressed = pointers.pressed.contains(&id);
+        if pressed {
+            pressed_sources.push(id);
+        }
+        if pressed && !was_pressed {
+            last_used = Some(id);
+        }
+        current.push(XrUiPointer {
+            id,
+            origin,
+            direction,
+            pressed,
+            just_pressed: pressed && !was_pressed,
+            just_released: !pressed && was_pressed,
+        });
+    }
+    pointers.pressed = pressed_sources;
+    if policy.exclusive {
+        let active = last_used
+            .filter(|id| current.iter().any(|pointer| pointer.id == *id))
+            .or_else(|| current.first().map(|pointer| pointer.id));
+        let was_published_pressed = |id: XrPointerId| {
+            published
+                .iter()
+                .any(|pointer| pointer.id == id && pointer.pressed)
+        };
+        //a held pointer that stops being the active one is released once, so the backend
+        //doesn't keep it pressed
+        let dropped: Vec<XrUiPointer> = published
+            .iter()
+            .filter(|pointer| pointer.pressed && Some(pointer.id) != active)
+            .map(|pointer| XrUiPointer {
+                pressed: false,
+                just_pressed: false,
+                just_released: true,
+                ..current
+                    .iter()
+                    .find(|current| current.id == pointer.id)
+                    .copied()
+                    .unwrap_or(*pointer)
+            })
+            .collect();
+        current.retain(|pointer| Some(pointer.id) == active);
+        //the backend only saw what was published, the active pointer may have been held before
+        for pointer in &mut current {
+            let was_pressed = was_published_pressed(pointer.id);
+            pointer.just_pressed = pointer.pressed && !was_pressed;
+            pointer.just_released = !pointer.pressed && was_pressed;
+        }
+        current.extend(dropped);
+        last_used = active;
+    }
+    pointers.pointers = current;
+    pointers.last_used = last_used;
+}
@@ -0,0 +1,323 @@
+use bevy::prelude::*;
+use openxr::Path;
+
+use crate::resources::{XrInstance, XrSession};
+use crate::xr_init::{xr_only, XrSetup};
+
+use super::actions::{
+    ActionHandednes, ActionType, SetupActionSets, XrActionSets, XrBinding, XrSyncActions,
+};
+use super::Hand;
+
+const ACTION_SET: &str = "index_input";
+const PROFILE: &str = "/interaction_profiles/valve/index_controller";
+
+/// every input of valve index controllers through `/interaction_profiles/valve/index_controller`,
+/// including the ones the oculus touch action set has no action for: grip force, the trackpad,
+/// the a/b/system buttons of both hands and the capacitive touches. they live in the
+/// `index_input` action set and can be read through [`XrActionSets`] or as [`XrValveIndex`].
+/// added by [`XrControllerType::ValveIndex`](super::controllers::XrControllerType::ValveIndex),
+/// the poses still come from `OculusController`
+pub struct XrValveIndexPlugin;
+
+impl Plugin for XrValveIndexPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrValveIndex>();
+        app.add_systems(XrSetup, setup_valve_index_actions);
+        app.add_systems(
+            PreUpdate,
+            update_xr_valve_index.run_if(xr_only()).after(XrSyncActions),
+        );
+    }
+}
+
+// (action, pretty name, left binding, right binding)
+const BUTTONS: [(&str, &str, &str, &str); 11] = [
+    (
+        "a_click",
+        "A Button",
+        "/user/hand/left/input/a/click",
+        "/user/hand/right/input/a/click",
+    ),
+    (
+        "a_touch",
+        "A Button Touch",
+        "/user/hand/left/input/a/touch",
+        "/user/hand/right/input/a/touch",
+    ),
+    (
+        "b_click",
+        "B Button",
+        "/user/hand/left/input/b/click",
+        "/user/hand/right/input/b/click",
+    ),
+    (
+        "b_touch",
+        "B Button Touch",
+        "/user/hand/left/input/b/touch",
+        "/user/hand/right/input/b/touch",
+    ),
+    (
+        "system_click",
+        "System Button",
+        "/user/hand/left/input/system/click",
+        "/user/hand/right/input/system/click",
+    ),
+    (
+        "system_touch",
+        "System Button Touch",
+        "/user/hand/left/input/system/touch",
+        "/user/hand/right/input/system/touch",
+    ),
+    (
+        "trigger_click",
+        "Trigger Click",
+        "/user/hand/left/input/trigger/click",
+        "/user/hand/right/input/trigger/click",
+    ),
+    (
+        "trigger_touch",
+        "Trigger Touch",
+        "/user/hand/left/input/trigger/touch",
+        "/user/hand/right/input/trigger/touch",
+    ),
+    (
+        "thumbstick_click",
+        "Thumbstick Click",
+        "/user/hand/left/input/thumbstick/click",
+        "/user/hand/right/input/thumbstick/click",
+    ),
+    (
+        "thumbstick_touch",
+        "Thumbstick Touch",
+        "/user/hand/left/input/thumbstick/touch",
+        "/user/hand/right/input/thumbstick/touch",
+    ),
+    (
+        "trackpad_touch",
+        "Trackpad Touch",
+        "/user/hand/left/input/trackpad/touch",
+        "/user/hand/right/input/trackpad/touch",
+    ),
+];
+
+const AXES: [(&str, &str, &str, &str); 8] = [
+    (
+        "trigger_value",
+        "Trigger Pull",
+        "/user/hand/left/input/trigger/value",
+        "/user/hand/right/input/trigger/value",
+    ),
+    (
+        "squeeze_value",
+        "Grip Curl",
+        "/user/hand/left/input/squeeze/value",
+        "/user/hand/right/input/squeeze/value",
+    ),
+    (
+        "squeeze_force",
+        "Grip Force",
+        "/user/hand/left/input/squeeze/force",
+        "/user/hand/right/input/squeeze/force",
+    ),
+    (
+        "thumbstick_x",
+        "Thumbstick X",
+        "/user/hand/left/input/thumbstick/x",
+        "/user/hand/right/input/thumbstick/x",
+    ),
+    (
+        "thumbstick_y",
+        "Thumbstick Y",
+        "/user/hand/left/input/thumbstick/y",
+        "/user/hand/right/input/thumbstick/y",
+    ),
+    (
+        "trackpad_x",
+        "Trackpad X",
+        "/user/hand/left/input/trackpad/x",
+        "/user/hand/right/input/trackpad/x",
+    ),
+    (
+        "trackpad_y",
+        "Trackpad Y",
+        "/user/hand/left/input/trackpad/y",
+        "/user/hand/right/input/trackpad/y",
+    ),
+    (
+        "trackpad_force",
+        "Trackpad Force",
+        "/user/hand/left/input/trackpad/force",
+        "/user/hand/right/input/trackpad/force",
+    ),
+];
+
+pub fn setup_valve_index_actions(mut action_sets: ResMut<SetupActionSets>) {
+    let action_set =
+        action_sets.add_action_set(ACTION_SET, "Valve Index Controller Input".into(), 0);
+    let mut bindings = Vec::new();
+    for (actions, action_type) in [
+        (&BUTTONS[..], ActionType::Bool),
+        (&AXES[..], ActionType::F32),
+    ] {
+        for &(name, pretty_name, left, right) in actions {
+            action_set.new_action(
+                name,
+                pretty_name.into(),
+                action_type,
+                ActionHandednes::Double,
+            );
+            bindings.push(XrBinding::new(name, left));
+            bindings.push(XrBinding::new(name, right));
+        }
+    }
+    action_set.suggest_binding(PROFILE, &bindings);
+}
+
+/// how far the fingers are curled, from 0 to 1. openxr's index profile has no curl per finger
+/// (steamvr gives those as hand tracking), these come from the capacitive sensors
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrFingerCurls {
+    /// 1 while the thumb rests on a button, the thumbstick or the trackpad
+    pub thumb: f32,
+    /// half while the finger rests on the trigger, all the way when it's pulled
+    pub index: f32,
+    /// the middle, ring and pinky fingers around the grip
+    pub grip: f32,
+}
+
+/// the state of one index controller this frame, everything reads as released while it isn't
+/// active
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrValveIndexHand {
+    pub active: bool,
+    pub a: bool,
+    pub a_touch: bool,
+    pub b: bool,
+    pub b_touch: bool,
+    /// steamvr keeps the system button for its dashboard, it usually never reads as pressed
+    pub system: bool,
+    pub system_touch: bool,
+    /// 0 to 1
+    pub trigger: f32,
+    pub trigger_click: bool,
+    pub trigger_touch: bool,
+    /// how far the fingers close around the grip, from 0 to 1
+    pub squeeze: f32,
+    /// how hard the grip is squeezed once it is closed, from 0 to 1
+    pub squeeze_force: f32,
+    /// -1 to 1 on both axes, up is positive y
+    pub thumbstick: Vec2,
+    pub thumbstick_click: bool,
+    pub thumbstick_touch: bool,
+    /// -1 to 1 on both axes, up is positive y
+    pub trackpad: Vec2,
+    /// 0 to 1
+    pub trackpad_force: f32,
+    pub trackpad_touch: bool,
+}
+
+impl XrValveIndexHand {
+    pub fn finger_curls(&self) -> XrFingerCurls {
+        let thumb = self.a_touch
+            || self.b_touch
+            || self.system_touch
+            || self.thumbstick_touch
+            || self.trackpad_touch;
+        XrFingerCurls {
+            thumb: thumb as u8 as f32,
+            index: match self.trigger_touch {
+                true => 0.5 + self.trigger * 0.5,
+                false => self.trigger,
+            },
+            grip: self.squeeze,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrValveIndex {
+    pub left: XrValveIndexHand,
+    pub right: XrValveIndexHand,
+}
+
+impl XrValveIndex {
+    pub fn get(&self, hand: Hand) -> &XrValveIndexHand {
+        match hand {
+            Hand::Left => &self.left,
+            Hand::Right => &self.right,
+        }
+    }
+}
+
+pub fn update_xr_valve_index(
+    action_sets: Option<Res<XrActionSets>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    mut index: ResMut<XrValveIndex>,
+    mut paths: Local<Option<[Path; 2]>>,
+) {
+    let Some(action_sets) = action_sets else {
+        return;
+    };
+    let [left, right] = *paths.get_or_insert_with(|| {
+        [
+            instance.string_to_path("/user/hand/left").unwrap(),
+            instance.string_to_path("/user/hand/right").unwrap(),
+        ]
+    });
+    let read = |path: Path| {
+        let mut active = false;
+        let mut button = |name| {
+            let Ok(state) = action_sets
+                .get_action_bool(ACTION_SET, name)
+                .map(|action| action.state(&session, path))
+            else {
+                return false;
+            };
+            let state = state.ok().filter(|state| state.is_active);
+            active |= state.is_some();
+            state.is_some_and(|state| state.current_state)
+        };
+        let mut hand = XrValveIndexHand {
+            a: button("a_click"),
+            a_touch: button("a_touch"),
+            b: button("b_click"),
+            b_touch: button("b_touch"),
+            system: button("system_click"),
+            system_touch: button("system_touch"),
+            trigger_click: button("trigger_click"),
+            trigger_touch: button("trigger_touch"),
+            thumbstick_click: button("thumbstick_click"),
+            thumbstick_touch: button("thumbstick_touch"),
+            trackpad_touch: button("trackpad_touch"),
+            ..default()
+        };
+        let mut axis = |name| {
+            let Ok(state) = action_sets
+                .get_action_f32(ACTION_SET, name)
+                .map(|action| action.state(&session, path))
+            else {
+                return 0.0;
+            };
+            let state = state.ok().filter(|state| state.is_active);
+            active |= state.is_some();
+            state.map_or(0.0, |state| state.current_state)
+        };
+        hand.trigger = axis("trigger_value");
+        hand.squeeze = axis("squeeze_value");
+        hand.squeeze_force = axis("squeeze_force");
+        hand.thumbstick = Vec2::new(axis("thumbstick_x"), axis("thumbstick_y"));
+        hand.trackpad = Vec2::new(axis("trackpad_x"), axis("trackpad_y"));
+        hand.trackpad_force = axis("trackpad_force");
+        hand.active = active;
+        hand
+    };
+    let new = XrValveIndex {
+        left: read(left),
+        right: read(right),
+    };
+    if *index != new {
+        *index = new;
+    }
+}
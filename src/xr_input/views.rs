@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use bevy::render::extract_component::{ExtractComponent, ExtractComponentPlugin};
+use openxr::Fovf;
+
+use crate::convert::PosefConv;
+use crate::frame_loop::XrFrameSet;
+use crate::resources::XrViews;
+use crate::xr_init::xr_only;
+
+use super::trackers::XrTrackingRoot;
+
+/// keeps one entity per located view, so views can be queried like other ecs data. the view
+/// entities are children of the [`XrTrackingRoot`], their transform is the pose of the view in
+/// the tracking space. [`XrView`] is extracted to the render world too. [`XrViews`] stays the
+/// source for the frame submission, the entities follow it every frame
+pub struct XrViewEntitiesPlugin;
+
+impl Plugin for XrViewEntitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<XrView>::default());
+        app.add_systems(
+            PreUpdate,
+            sync_xr_view_entities
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+    }
+}
+
+/// a view the runtime renders, the eyes of a stereo headset are the views 0 (left) and 1 (right)
+#[derive(Component, ExtractComponent, Clone, Copy, Debug)]
+pub struct XrView {
+    pub index: u32,
+    pub fov: Fovf,
+}
+
+/// the view entities, indexed by view index
+#[derive(Resource, Clone, Debug, Default)]
+pub struct XrViewEntities(pub Vec<Entity>);
+
+impl XrViewEntities {
+    pub fn get(&self, index: u32) -> Option<Entity> {
+        self.0.get(index as usize).copied()
+    }
+}
+
+pub fn sync_xr_view_entities(
+    mut commands: Commands,
+    views: Res<XrViews>,
+    entities: Option<ResMut<XrViewEntities>>,
+    root: Query<Entity, With<XrTrackingRoot>>,
+    mut view_query: Query<(&mut XrView, &mut Transform)>,
+) {
+    let Some(mut entities) = entities else {
+        commands.init_resource::<XrViewEntities>();
+        return;
+    };
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    //views only get added, the view configuration doesn't change during a session
+    while entities.0.len() < views.len() {
+        let index = entities.0.len() as u32;
+        let entity = commands
+            .spawn((
+                SpatialBundle::default(),
+                XrView {
+                    index,
+                    fov: Fovf::default(),
+                },
+                Name::new(format!("XrView {}", index)),
+            ))
+            .id();
+        commands.entity(root).add_child(entity);
+        entities.0.push(entity);
+    }
+    for (index, view) in views.iter().enumerate() {
+        let Ok((mut xr_view, mut transform)) = view_query.get_mut(entities.0[index]) else {
+            continue;
+        };
+        xr_view.fov = view.fov;
+        let pose = view.pose.to_transform();
+        transform.translation = pose.translation;
+        transform.rotation = pose.rotation;
+    }
+}
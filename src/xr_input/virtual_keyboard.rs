@@ -0,0 +1,873 @@
+use std::ffi::{c_char, c_void, CString};
+use std::ptr;
+
+use bevy::animation::{AnimationClip, EntityPath, Keyframes, VariableCurve};
+use bevy::gltf::Gltf;
+use bevy::prelude::*;
+use openxr as xr;
+use xr::sys;
+
+use crate::call_trace::{check, trace};
+use crate::convert::TransformConv;
+use crate::error_log::{XrErrorLog, XrErrorSource};
+use crate::frame_loop::XrFrameSet;
+use crate::input::XrInput;
+use crate::raw_events::XrRawEvent;
+use crate::render_models::{XrRenderModel, XrRenderModelScene, RENDER_MODEL_SOURCE};
+use crate::resources::{XrFrameState, XrInstance, XrSession};
+use crate::xr_init::xr_only;
+
+use super::actions::XrActionSets;
+use super::oculus_touch::OculusController;
+use super::spaces::XrSpaces;
+use super::trackers::XrTrackingRoot;
+use super::Hand;
+
+pub(crate) const VIRTUAL_KEYBOARD_EXTENSION: &str = "XR_META_virtual_keyboard";
+
+/// the render model of the keyboard, see [`XrRenderModel`]
+pub const VIRTUAL_KEYBOARD_MODEL_PATH: &str = "/model_meta/keyboard/virtual";
+
+const TYPE_CREATE_INFO: i32 = 1000219002;
+const TYPE_SPACE_CREATE_INFO: i32 = 1000219003;
+const TYPE_LOCATION_INFO: i32 = 1000219004;
+const TYPE_MODEL_VISIBILITY_SET_INFO: i32 = 1000219005;
+const TYPE_ANIMATION_STATE: i32 = 1000219006;
+const TYPE_MODEL_ANIMATION_STATES: i32 = 1000219007;
+const TYPE_INPUT_INFO: i32 = 1000219010;
+const TYPE_TEXT_CONTEXT_CHANGE_INFO: i32 = 1000219011;
+const TYPE_EVENT_COMMIT_TEXT: i32 = 1000219014;
+const TYPE_EVENT_BACKSPACE: i32 = 1000219015;
+const TYPE_EVENT_ENTER: i32 = 1000219016;
+const TYPE_EVENT_SHOWN: i32 = 1000219017;
+const TYPE_EVENT_HIDDEN: i32 = 1000219018;
+
+// XrVirtualKeyboardLocationTypeMETA
+const LOCATION_CUSTOM: i32 = 0;
+const LOCATION_FAR: i32 = 1;
+const LOCATION_DIRECT: i32 = 2;
+
+// XrVirtualKeyboardInputStateFlagsMETA
+const INPUT_STATE_PRESSED: u64 = 0x1;
+
+const MAX_COMMIT_TEXT_SIZE: usize = 3992;
+
+//a controller trigger further down than this presses keys
+const PRESS_THRESHOLD: f32 = 0.5;
+
+type VirtualKeyboard = u64;
+
+// most fields are only read by the runtime
+#[repr(C)]
+#[allow(dead_code)]
+struct CreateInfo {
+    ty: sys::StructureType,
+    next: *const c_void,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct SpaceCreateInfo {
+    ty: sys::StructureType,
+    next: *const c_void,
+    location_type: i32,
+    space: sys::Space,
+    pose_in_space: sys::Posef,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct LocationInfo {
+    ty: sys::StructureType,
+    next: *const c_void,
+    location_type: i32,
+    space: sys::Space,
+    pose_in_space: sys::Posef,
+    scale: f32,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct ModelVisibilitySetInfo {
+    ty: sys::StructureType,
+    next: *const c_void,
+    visible: sys::Bool32,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct InputInfo {
+    ty: sys::StructureType,
+    next: *const c_void,
+    input_source: i32,
+    input_space: sys::Space,
+    input_pose_in_space: sys::Posef,
+    input_state: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct AnimationState {
+    ty: sys::StructureType,
+    next: *mut c_void,
+    animation_index: i32,
+    fraction: f32,
+}
+
+#[repr(C)]
+struct ModelAnimationStates {
+    ty: sys::StructureType,
+    next: *mut c_void,
+    state_capacity_input: u32,
+    state_count_output: u32,
+    states: *mut AnimationState,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct TextContextChangeInfo {
+    ty: sys::StructureType,
+    next: *const c_void,
+    text_context: *const c_char,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+struct CommitTextEvent {
+    ty: sys::StructureType,
+    next: *const c_void,
+    keyboard: VirtualKeyboard,
+    text: [c_char; MAX_COMMIT_TEXT_SIZE],
+}
+
+type CreateVirtualKeyboard = unsafe extern "system" fn(
+    session: sys::Session,
+    info: *const CreateInfo,
+    keyboard: *mut VirtualKeyboard,
+) -> sys::Result;
+type DestroyVirtualKeyboard = unsafe extern "system" fn(keyboard: VirtualKeyboard) -> sys::Result;
+type CreateVirtualKeyboardSpace = unsafe extern "system" fn(
+    session: sys::Session,
+    keyboard: VirtualKeyboard,
+    info: *const SpaceCreateInfo,
+    space: *mut sys::Space,
+) -> sys::Result;
+type SuggestVirtualKeyboardLocation =
+    unsafe extern "system" fn(keyboard: VirtualKeyboard, info: *const LocationInfo) -> sys::Result;
+type SetVirtualKeyboardModelVisibility = unsafe extern "system" fn(
+    keyboard: VirtualKeyboard,
+    info: *const ModelVisibilitySetInfo,
+) -> sys::Result;
+type ChangeVirtualKeyboardTextContext = unsafe extern "system" fn(
+    keyboard: VirtualKeyboard,
+    info: *const TextContextChangeInfo,
+) -> sys::Result;
+type SendVirtualKeyboardInput = unsafe extern "system" fn(
+    keyboard: VirtualKeyboard,
+    info: *const InputInfo,
+    interactor_root_pose: *mut sys::Posef,
+) -> sys::Result;
+type GetVirtualKeyboardScale =
+    unsafe extern "system" fn(keyboard: VirtualKeyboard, scale: *mut f32) -> sys::Result;
+type GetVirtualKeyboardModelAnimationStates = unsafe extern "system" fn(
+    keyboard: VirtualKeyboard,
+    states: *mut ModelAnimationStates,
+) -> sys::Result;
+
+#[derive(Clone, Copy)]
+struct Functions {
+    create: CreateVirtualKeyboard,
+    destroy: DestroyVirtualKeyboard,
+    create_space: CreateVirtualKeyboardSpace,
+    suggest_location: SuggestVirtualKeyboardLocation,
+    set_model_visibility: SetVirtualKeyboardModelVisibility,
+    change_text_context: ChangeVirtualKeyboardTextContext,
+    send_input: SendVirtualKeyboardInput,
+    get_scale: GetVirtualKeyboardScale,
+    get_animation_states: GetVirtualKeyboardModelAnimationStates,
+}
+
+/// text input through the system keyboard of XR_META_virtual_keyboard. set
+/// [`XrVirtualKeyboard::visible`] to summon it and read [`XrVirtualKeyboardEvent`]s for the typed
+/// text. the runtime only simulates the keyboard: the controllers' aim poses and triggers are
+/// sent to it as input, and its render model is spawned as an [`XrVirtualKeyboardModel`] at the
+/// keyboard's space, with the key animations the runtime reports applied to it. the model needs
+/// the `XrRenderModelPlugin`, the textures of the suggestion bar aren't updated. the openxr crate
+/// doesn't know the extension yet, so its functions are loaded by hand
+pub struct XrVirtualKeyboardPlugin;
+
+impl Plugin for XrVirtualKeyboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrVirtualKeyboard>();
+        app.add_event::<XrVirtualKeyboardEvent>();
+        app.add_systems(
+            PreUpdate,
+            (
+                update_virtual_keyboard,
+                send_virtual_keyboard_input,
+                update_virtual_keyboard_model,
+                animate_virtual_keyboard_model,
+                read_virtual_keyboard_events,
+            )
+                .chain()
+                .run_if(xr_only())
+                .in_set(XrFrameSet::AfterBeginFrame),
+        );
+    }
+}
+
+/// what the app wants the keyboard to do, applied whenever it changes
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct XrVirtualKeyboard {
+    pub visible: bool,
+    pub placement: KeyboardPlacement,
+    /// the text before the cursor, the runtime uses it for suggestions
+    pub text_context: Option<String>,
+    /// the app sends the input with [`XrVirtualKeyboardHandle::send_input`] itself, instead of
+    /// the controllers' aim poses and triggers
+    pub custom_input: bool,
+}
+
+impl XrVirtualKeyboard {
+    pub fn is_supported(instance: &XrInstance) -> bool {
+        instance
+            .exts()
+            .other
+            .iter()
+            .any(|ext| ext == VIRTUAL_KEYBOARD_EXTENSION)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum KeyboardPlacement {
+    /// out of reach, for typing with controller or hand rays
+    #[default]
+    Far,
+    /// within reach, for typing with fingers
+    Direct,
+    /// a world pose, a scale of 1 is the runtime's own size
+    Pose { transform: Transform, scale: f32 },
+    /// relative to a ui panel, `offset` is in the panel's local space and followed when the
+    /// panel moves
+    Panel {
+        entity: Entity,
+        offset: Transform,
+        scale: f32,
+    },
+}
+
+/// what types on the keyboard, rays for a far keyboard and direct touches for one within reach
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyboardInputSource {
+    ControllerRay(Hand),
+    HandRay(Hand),
+    ControllerDirect(Hand),
+    HandIndexTip(Hand),
+}
+
+impl KeyboardInputSource {
+    // XrVirtualKeyboardInputSourceMETA
+    fn into_raw(self) -> i32 {
+        match self {
+            KeyboardInputSource::ControllerRay(Hand::Left) => 1,
+            KeyboardInputSource::ControllerRay(Hand::Right) => 2,
+            KeyboardInputSource::HandRay(Hand::Left) => 3,
+            KeyboardInputSource::HandRay(Hand::Right) => 4,
+            KeyboardInputSource::ControllerDirect(Hand::Left) => 5,
+            KeyboardInputSource::ControllerDirect(Hand::Right) => 6,
+            KeyboardInputSource::HandIndexTip(Hand::Left) => 7,
+            KeyboardInputSource::HandIndexTip(Hand::Right) => 8,
+        }
+    }
+}
+
+/// the entity the keyboard's render model is spawned on, a child of the [`XrTrackingRoot`]
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct XrVirtualKeyboardModel;
+
+#[derive(Event, Clone, Debug, PartialEq, Eq)]
+pub enum XrVirtualKeyboardEvent {
+    /// text typed or picked from the suggestions
+    CommitText(String),
+    Backspace,
+    Enter,
+    Shown,
+    Hidden,
+}
+
+/// the keyboard the runtime created, inserted the first time it's shown
+#[derive(Resource)]
+pub struct XrVirtualKeyboardHandle {
+    functions: Functions,
+    keyboard: VirtualKeyboard,
+    space: Option<xr::Space>,
+}
+
+impl XrVirtualKeyboardHandle {
+    /// where the keyboard model goes, locate it with `XrSpaces`
+    pub fn space(&self) -> &xr::Space {
+        self.space.as_ref().unwrap()
+    }
+}
+
+impl Drop for XrVirtualKeyboardHandle {
+    fn drop(&mut self) {
+        //the space belongs to the keyboard, so it goes first
+        self.space.take();
+        unsafe { (self.functions.destroy)(self.keyboard) };
+    }
+}
+
+#[derive(Default)]
+pub struct AppliedKeyboard {
+    visible: Option<bool>,
+    location: Option<(i32, Transform, f32)>,
+    text_context: Option<Option<String>>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_virtual_keyboard(
+    mut commands: Commands,
+    setting: Res<XrVirtualKeyboard>,
+    keyboard: Option<Res<XrVirtualKeyboardHandle>>,
+    instance: Res<XrInstance>,
+    session: Res<XrSession>,
+    input: Res<XrInput>,
+    error_log: Res<XrErrorLog>,
+    root: Query<&GlobalTransform, With<XrTrackingRoot>>,
+    panels: Query<&GlobalTransform>,
+    mut applied: Local<AppliedKeyboard>,
+    mut failed: Local<bool>,
+) {
+    let Some(keyboard) = keyboard else {
+        if !setting.visible || *failed {
+            return;
+        }
+        //creation isn't retried, the runtime won't change its mind
+        *failed = true;
+        match create_virtual_keyboard(&instance, &session, &input) {
+            Ok(keyboard) => {
+                *failed = false;
+                commands.insert_resource(keyboard);
+            }
+            Err(err) => error_log.report_result(XrErrorSource::Other, err),
+        }
+        return;
+    };
+    let root = root.get_single().copied().unwrap_or_default();
+    let location = match setting.placement {
+        KeyboardPlacement::Far => Some((LOCATION_FAR, Transform::IDENTITY, 1.0)),
+        KeyboardPlacement::Direct => Some((LOCATION_DIRECT, Transform::IDENTITY, 1.0)),
+        KeyboardPlacement::Pose { transform, scale } => {
+            Some((LOCATION_CUSTOM, world_to_stage(&root, transform), scale))
+        }
+        KeyboardPlacement::Panel {
+            entity,
+            offset,
+            scale,
+        } => panels.get(entity).ok().map(|panel| {
+            let world = panel.mul_transform(offset).compute_transform();
+            (LOCATION_CUSTOM, world_to_stage(&root, world), scale)
+        }),
+    };
+    if let Some(location) = location.filter(|_| setting.visible) {
+        if applied.location != Some(location) {
+            applied.location = Some(location);
+            if let Err(err) = keyboard.suggest_location(&input, location) {
+                error_log.report_result(XrErrorSource::Other, err);
+            }
+        }
+    }
+    if applied.visible != Some(setting.visible) {
+        applied.visible = Some(setting.visible);
+        if let Err(err) = keyboard.set_model_visibility(setting.visible) {
+            error_log.report_result(XrErrorSource::Other, err);
+        }
+    }
+    if applied.text_context.as_ref() != Some(&setting.text_context) {
+        applied.text_context = Some(setting.text_context.clone());
+        if let Some(text_context) = &setting.text_context {
+            if let Err(err) = keyboard.change_text_context(text_context) {
+                error_log.report_result(XrErrorSource::Other, err);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn send_virtual_keyboard_input(
+    setting: Res<XrVirtualKeyboard>,
+    keyboard: Option<Res<XrVirtualKeyboardHandle>>,
+    controller: Option<Res<OculusController>>,
+    action_sets: Option<Res<XrActionSets>>,
+    session: Res<XrSession>,
+    frame_state: Res<XrFrameState>,
+    input: Res<XrInput>,
+    error_log: Res<XrErrorLog>,
+    mut failed: Local<bool>,
+) {
+    let (Some(keyboard), Some(controller), Some(action_sets)) = (keyboard, controller, action_sets)
+    else {
+        return;
+    };
+    if setting.custom_input || !setting.visible {
+        return;
+    }
+    let Some(aim_spaces) = controller.aim_space.as_ref() else {
+        return;
+    };
+    let frame_state = *frame_state.lock().unwrap();
+    let controller_ref = controller.get_ref(&session, &frame_state, &input, &action_sets);
+    for hand in [Hand::Left, Hand::Right] {
+        let source = match setting.placement {
+            KeyboardPlacement::Direct => KeyboardInputSource::ControllerDirect(hand),
+            _ => KeyboardInputSource::ControllerRay(hand),
+        };
+        let space = match hand {
+            Hand::Left => &aim_spaces.left,
+            Hand::Right => &aim_spaces.right,
+        };
+        let pressed = controller_ref.trigger(hand) > PRESS_THRESHOLD;
+        //sent every frame, so a failure is only reported once
+        match keyboard.send_input(source, space, Transform::IDENTITY, pressed) {
+            Ok(()) => *failed = false,
+            Err(err) if !*failed => {
+                *failed = true;
+                error_log.report_result(XrErrorSource::Other, err);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+pub fn update_virtual_keyboard_model(
+    mut commands: Commands,
+    setting: Res<XrVirtualKeyboard>,
+    keyboard: Option<Res<XrVirtualKeyboardHandle>>,
+    spaces: XrSpaces,
+    root: Query<Entity, With<XrTrackingRoot>>,
+    mut models: Query<(&mut Transform, &mut Visibility), With<XrVirtualKeyboardModel>>,
+) {
+    let Some(keyboard) = keyboard else {
+        for (_, mut visibility) in models.iter_mut() {
+            visibility.set_if_neq(Visibility::Hidden);
+        }
+        return;
+    };
+    if models.is_empty() {
+        let Ok(root) = root.get_single() else {
+            return;
+        };
+        let model = commands
+            .spawn((
+                SpatialBundle {
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                XrVirtualKeyboardModel,
+                XrRenderModel(VIRTUAL_KEYBOARD_MODEL_PATH.into()),
+                Name::new("XrVirtualKeyboard"),
+            ))
+            .id();
+        commands.entity(root).add_child(model);
+        return;
+    }
+    //the keyboard space is located in the stage, the local space of the root
+    let location = spaces.locate(keyboard.space(), spaces.stage());
+    let scale = keyboard.scale().unwrap_or(1.0);
+    for (mut transform, mut visibility) in models.iter_mut() {
+        visibility.set_if_neq(match setting.visible && location.is_some() {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        });
+        if let Some(location) = location {
+            *transform = location.with_scale(Vec3::splat(scale));
+        }
+    }
+}
+
+/// the named entities of a spawned render model and their animation paths
+#[derive(Default)]
+pub struct KeyboardModelNodes {
+    scene: Option<Entity>,
+    nodes: Vec<(EntityPath, Entity)>,
+}
+
+// the runtime animates the keys by reporting which animation of the model's glb is at which
+// fraction, bevy's animation player only plays one clip at a time, so the curves are sampled here
+#[allow(clippy::too_many_arguments)]
+pub fn animate_virtual_keyboard_model(
+    keyboard: Option<Res<XrVirtualKeyboardHandle>>,
+    error_log: Res<XrErrorLog>,
+    asset_server: Res<AssetServer>,
+    gltfs: Option<Res<Assets<Gltf>>>,
+    clips: Option<Res<Assets<AnimationClip>>>,
+    models: Query<&XrRenderModelScene, With<XrVirtualKeyboardModel>>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+    mut transforms: Query<&mut Transform, Without<XrVirtualKeyboardModel>>,
+    mut gltf_handle: Local<Option<Handle<Gltf>>>,
+    mut nodes: Local<KeyboardModelNodes>,
+    mut failed: Local<bool>,
+) {
+    let (Some(keyboard), Some(gltfs), Some(clips)) = (keyboard, gltfs, clips) else {
+        return;
+    };
+    let Some(scene) = models.iter().find_map(|scene| scene.0) else {
+        return;
+    };
+    //the animations are in the glb the render model scene was loaded from
+    let gltf_handle = gltf_handle.get_or_insert_with(|| {
+        asset_server.load(format!(
+            "{}://{}.glb",
+            RENDER_MODEL_SOURCE,
+            VIRTUAL_KEYBOARD_MODEL_PATH.trim_start_matches('/')
+        ))
+    });
+    let Some(gltf) = gltfs.get(&*gltf_handle) else {
+        return;
+    };
+    //the scene is spawned a few frames after the model is loaded
+    if nodes.scene != Some(scene) || nodes.nodes.is_empty() {
+        nodes.scene = Some(scene);
+        nodes.nodes.clear();
+        for child in children.get(scene).into_iter().flatten() {
+            collect_model_nodes(*child, &mut vec![], &children, &names, &mut nodes.nodes);
+        }
+    }
+    let states = match keyboard.animation_states() {
+        Ok(states) => states,
+        Err(err) => {
+            if !*failed {
+                *failed = true;
+                error_log.report_result(XrErrorSource::Other, err);
+            }
+            return;
+        }
+    };
+    for (index, fraction) in states {
+        let Some(clip) = usize::try_from(index)
+            .ok()
+            .and_then(|index| gltf.animations.get(index))
+            .and_then(|clip| clips.get(clip))
+        else {
+            continue;
+        };
+        let time = fraction.clamp(0.0, 1.0) * clip.duration();
+        for (path, entity) in &nodes.nodes {
+            let Some(curves) = clip.get_curves_by_path(path) else {
+                continue;
+            };
+            let Ok(mut transform) = transforms.get_mut(*entity) else {
+                continue;
+            };
+            for curve in curves {
+                sample_curve(curve, time, &mut transform);
+            }
+        }
+    }
+}
+
+// the animation paths are the names from the root nodes of the scene down
+fn collect_model_nodes(
+    entity: Entity,
+    path: &mut Vec<Name>,
+    children: &Query<&Children>,
+    names: &Query<&Name>,
+    nodes: &mut Vec<(EntityPath, Entity)>,
+) {
+    let Ok(name) = names.get(entity) else {
+        return;
+    };
+    path.push(name.clone());
+    nodes.push((
+        EntityPath {
+            parts: path.clone(),
+        },
+        entity,
+    ));
+    for child in children.get(entity).into_iter().flatten() {
+        collect_model_nodes(*child, path, children, names, nodes);
+    }
+    path.pop();
+}
+
+fn sample_curve(curve: &VariableCurve, time: f32, transform: &mut Transform) {
+    let times = &curve.keyframe_timestamps;
+    let Some(last) = times.len().checked_sub(1) else {
+        return;
+    };
+    let (from, to, t) = match times.partition_point(|t| *t <= time) {
+        0 => (0, 0, 0.0),
+        next if next > last => (last, last, 0.0),
+        next => (
+            next - 1,
+            next,
+            (time - times[next - 1]) / (times[next] - times[next - 1]),
+        ),
+    };
+    //cubic spline keyframes are (in tangent, value, out tangent), only the values are used
+    let index = |len: usize, keyframe: usize| match len == times.len() * 3 {
+        true => keyframe * 3 + 1,
+        false => keyframe,
+    };
+    match &curve.keyframes {
+        Keyframes::Translation(values) => {
+            let (from, to) = (index(values.len(), from), index(values.len(), to));
+            transform.translation = values[from].lerp(values[to], t);
+        }
+        Keyframes::Rotation(values) => {
+            let (from, to) = (index(values.len(), from), index(values.len(), to));
+            transform.rotation = values[from].slerp(values[to], t);
+        }
+        Keyframes::Scale(values) => {
+            let (from, to) = (index(values.len(), from), index(values.len(), to));
+            transform.scale = values[from].lerp(values[to], t);
+        }
+        _ => {}
+    }
+}
+
+pub fn read_virtual_keyboard_events(
+    mut raw_events: EventReader<XrRawEvent>,
+    mut events: EventWriter<XrVirtualKeyboardEvent>,
+) {
+    for raw in raw_events.read() {
+        let event = match raw.ty.into_raw() {
+            TYPE_EVENT_COMMIT_TEXT => {
+                let commit = unsafe { raw.read::<CommitTextEvent>() };
+                //the text is null terminated, but not if it fills the whole array
+                let bytes: Vec<u8> = commit
+                    .text
+                    .iter()
+                    .take_while(|c| **c != 0)
+                    .map(|c| *c as u8)
+                    .collect();
+                XrVirtualKeyboardEvent::CommitText(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            TYPE_EVENT_BACKSPACE => XrVirtualKeyboardEvent::Backspace,
+            TYPE_EVENT_ENTER => XrVirtualKeyboardEvent::Enter,
+            TYPE_EVENT_SHOWN => XrVirtualKeyboardEvent::Shown,
+            TYPE_EVENT_HIDDEN => XrVirtualKeyboardEvent::Hidden,
+            _ => continue,
+        };
+        events.send(event);
+    }
+}
+
+fn world_to_stage(root: &GlobalTransform, world: Transform) -> Transform {
+    Transform::from_matrix(root.compute_matrix().inverse() * world.compute_matrix())
+}
+
+/// needs XR_META_virtual_keyboard
+fn create_virtual_keyboard(
+    instance: &XrInstance,
+    session: &XrSession,
+    input: &XrInput,
+) -> xr::Result<XrVirtualKeyboardHandle> {
+    let Some(functions) = load_functions(instance) else {
+        return Err(sys::Result::ERROR_EXTENSION_NOT_PRESENT);
+    };
+    let info = CreateInfo {
+        ty: sys::StructureType::from_raw(TYPE_CREATE_INFO),
+        next: ptr::null(),
+    };
+    let mut keyboard = 0;
+    check(trace(
+        "xrCreateVirtualKeyboardMETA",
+        String::new,
+        || unsafe { (functions.create)(session.as_raw(), &info, &mut keyboard) },
+    ))?;
+    let space_info = SpaceCreateInfo {
+        ty: sys::StructureType::from_raw(TYPE_SPACE_CREATE_INFO),
+        next: ptr::null(),
+        location_type: LOCATION_FAR,
+        space: input.stage.as_raw(),
+        pose_in_space: Transform::IDENTITY.to_posef(),
+    };
+    let mut space = sys::Space::NULL;
+    let result = trace("xrCreateVirtualKeyboardSpaceMETA", String::new, || unsafe {
+        (functions.create_space)(session.as_raw(), keyboard, &space_info, &mut space)
+    });
+    if let Err(err) = check(result) {
+        unsafe { (functions.destroy)(keyboard) };
+        return Err(err);
+    }
+    Ok(XrVirtualKeyboardHandle {
+        functions,
+        keyboard,
+        space: Some(unsafe { xr::Space::reference_from_raw((**session).clone(), space) }),
+    })
+}
+
+impl XrVirtualKeyboardHandle {
+    fn suggest_location(
+        &self,
+        input: &XrInput,
+        (location_type, pose, scale): (i32, Transform, f32),
+    ) -> xr::Result<()> {
+        let info = LocationInfo {
+            ty: sys::StructureType::from_raw(TYPE_LOCATION_INFO),
+            next: ptr::null(),
+            location_type,
+            space: input.stage.as_raw(),
+            pose_in_space: pose.to_posef(),
+            scale,
+        };
+        check(trace(
+            "xrSuggestVirtualKeyboardLocationMETA",
+            || format!("{}, {:?}, {}", location_type, pose, scale),
+            || unsafe { (self.functions.suggest_location)(self.keyboard, &info) },
+        ))
+    }
+
+    fn set_model_visibility(&self, visible: bool) -> xr::Result<()> {
+        let info = ModelVisibilitySetInfo {
+            ty: sys::StructureType::from_raw(TYPE_MODEL_VISIBILITY_SET_INFO),
+            next: ptr::null(),
+            visible: visible.into(),
+        };
+        check(trace(
+            "xrSetVirtualKeyboardModelVisibilityMETA",
+            || visible.to_string(),
+            || unsafe { (self.functions.set_model_visibility)(self.keyboard, &info) },
+        ))
+    }
+
+    fn change_text_context(&self, text_context: &str) -> xr::Result<()> {
+        let Ok(text_context) = CString::new(text_context) else {
+            return Err(sys::Result::ERROR_VALIDATION_FAILURE);
+        };
+        let info = TextContextChangeInfo {
+            ty: sys::StructureType::from_raw(TYPE_TEXT_CONTEXT_CHANGE_INFO),
+            next: ptr::null(),
+            text_context: text_context.as_ptr(),
+        };
+        check(trace(
+            "xrChangeVirtualKeyboardTextContextMETA",
+            || format!("{:?}", text_context),
+            || unsafe { (self.functions.change_text_context)(self.keyboard, &info) },
+        ))
+    }
+
+    /// the pose of something typing on the keyboard in `space` and whether it presses, has to be
+    /// sent every frame while the keyboard is shown. done for the controllers unless
+    /// [`XrVirtualKeyboard::custom_input`] is set
+    pub fn send_input(
+        &self,
+        source: KeyboardInputSource,
+        space: &xr::Space,
+        pose: Transform,
+        pressed: bool,
+    ) -> xr::Result<()> {
+        let info = InputInfo {
+            ty: sys::StructureType::from_raw(TYPE_INPUT_INFO),
+            next: ptr::null(),
+            input_source: source.into_raw(),
+            input_space: space.as_raw(),
+            input_pose_in_space: pose.to_posef(),
+            input_state: match pressed {
+                true => INPUT_STATE_PRESSED,
+                false => 0,
+            },
+        };
+        //the runtime may move the root of the interactor, e.g. so a finger stops at a key. the
+        //pose it returns isn't used
+        let mut root_pose = pose.to_posef();
+        check(trace(
+            "xrSendVirtualKeyboardInputMETA",
+            || format!("{:?}, {:?}, {}", source, pose, pressed),
+            || unsafe { (self.functions.send_input)(self.keyboard, &info, &mut root_pose) },
+        ))
+    }
+
+    /// the scale the runtime shows the keyboard at
+    pub fn scale(&self) -> xr::Result<f32> {
+        let mut scale = 1.0;
+        check(trace(
+            "xrGetVirtualKeyboardScaleMETA",
+            String::new,
+            || unsafe { (self.functions.get_scale)(self.keyboard, &mut scale) },
+        ))?;
+        Ok(scale)
+    }
+
+    // (animation index, fraction) of the key animations to apply
+    fn animation_states(&self) -> xr::Result<Vec<(i32, f32)>> {
+        let mut states = ModelAnimationStates {
+            ty: sys::StructureType::from_raw(TYPE_MODEL_ANIMATION_STATES),
+            next: ptr::null_mut(),
+            state_capacity_input: 0,
+            state_count_output: 0,
+            states: ptr::null_mut(),
+        };
+        check(trace(
+            "xrGetVirtualKeyboardModelAnimationStatesMETA",
+            String::new,
+            || unsafe { (self.functions.get_animation_states)(self.keyboard, &mut states) },
+        ))?;
+        if states.state_count_output == 0 {
+            return Ok(vec![]);
+        }
+        let empty = AnimationState {
+            ty: sys::StructureType::from_raw(TYPE_ANIMATION_STATE),
+            next: ptr::null_mut(),
+            animation_index: 0,
+            fraction: 0.0,
+        };
+        let mut buffer = vec![empty; states.state_count_output as usize];
+        states.state_capacity_input = buffer.len() as u32;
+        states.states = buffer.as_mut_ptr();
+        check(trace(
+            "xrGetVirtualKeyboardModelAnimationStatesMETA",
+            || format!("{} states", buffer.len()),
+            || unsafe { (self.functions.get_animation_states)(self.keyboard, &mut states) },
+        ))?;
+        buffer.truncate(states.state_count_output as usize);
+        Ok(buffer
+            .iter()
+            .map(|state| (state.animation_index, state.fraction))
+            .collect())
+    }
+}
+
+fn load_functions(instance: &XrInstance) -> Option<Functions> {
+    if !XrVirtualKeyboard::is_supported(instance) {
+        return None;
+    }
+    //the types are the signatures of the extension's functions
+    unsafe {
+        Some(Functions {
+            create: load(instance, "xrCreateVirtualKeyboardMETA")?,
+            destroy: load(instance, "xrDestroyVirtualKeyboardMETA")?,
+            create_space: load(instance, "xrCreateVirtualKeyboardSpaceMETA")?,
+            suggest_location: load(instance, "xrSuggestVirtualKeyboardLocationMETA")?,
+            set_model_visibility: load(instance, "xrSetVirtualKeyboardModelVisibilityMETA")?,
+            change_text_context: load(instance, "xrChangeVirtualKeyboardTextContextMETA")?,
+            send_input: load(instance, "xrSendVirtualKeyboardInputMETA")?,
+            get_scale: load(instance, "xrGetVirtualKeyboardScaleMETA")?,
+            get_animation_states: load(instance, "xrGetVirtualKeyboardModelAnimationStatesMETA")?,
+        })
+    }
+}
+
+/// loads the instance function `name`
+///
+/// # Safety
+/// `F` has to be the function pointer type of `name`, calling it with another signature is
+/// undefined behavior
+unsafe fn load<F: Copy>(instance: &XrInstance, name: &str) -> Option<F> {
+    assert_eq!(
+        std::mem::size_of::<F>(),
+        std::mem::size_of::<sys::pfn::VoidFunction>()
+    );
+    let name = CString::new(name).unwrap();
+    let function = instance
+        .entry()
+        .get_instance_proc_addr(instance.as_raw(), name.as_ptr())
+        .ok()?;
+    Some(std::mem::transmute_copy::<sys::pfn::VoidFunction, F>(
+        &function,
+    ))
+}
@@ -0,0 +1,151 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::xr_init::xr_only;
+
+use super::hands::common::HandsResource;
+use super::trackers::{OpenXRHMD, OpenXRLeftController, OpenXRRightController, XrTrackingRoot};
+use super::Hand;
+
+/// keeps entities with a [`WristAnchor`] on the inside of the wrist and sends
+/// [`WristAnchorEvent`]s when the user looks at them, the usual way to show a hand menu.
+/// follows the wrist joint if the hand plugins are added, the controller otherwise
+pub struct WristAnchorPlugin;
+
+impl Plugin for WristAnchorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WristAnchorEvent>();
+        app.add_systems(
+            PostUpdate,
+            update_wrist_anchors
+                .run_if(xr_only())
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// attaches the entity to a wrist. the transform is set in world space, so the entity must not
+/// have a parent
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WristAnchor {
+    pub hand: Hand,
+    /// the pose relative to the wrist, the default sits on the inner wrist facing out of it
+    pub offset: Transform,
+    /// how far off the center of the view the anchor may be to count as looked at, in radians
+    pub look_angle: f32,
+    /// how far the front (+z) of the anchor may face away from the head to count as looked at,
+    /// in radians
+    pub facing_angle: f32,
+    /// hides the entity while it isn't looked at
+    pub hide_when_inactive: bool,
+}
+
+impl WristAnchor {
+    pub fn new(hand: Hand) -> Self {
+        Self {
+            hand,
+            offset: Transform::from_xyz(0.0, -0.03, 0.05)
+                .with_rotation(Quat::from_rotation_x(FRAC_PI_2)),
+            look_angle: 25f32.to_radians(),
+            facing_angle: 50f32.to_radians(),
+            hide_when_inactive: false,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: Transform) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn hide_when_inactive(mut self) -> Self {
+        self.hide_when_inactive = true;
+        self
+    }
+}
+
+/// whether the user is currently looking at the anchor, added by the plugin
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WristAnchorActive(pub bool);
+
+#[derive(Event, Clone, Copy, Debug)]
+pub enum WristAnchorEvent {
+    Activated { entity: Entity, hand: Hand },
+    Deactivated { entity: Entity, hand: Hand },
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_wrist_anchors(
+    mut commands: Commands,
+    hands_resource: Option<Res<HandsResource>>,
+    mut anchors: Query<(
+        Entity,
+        &WristAnchor,
+        &mut Transform,
+        Option<&mut WristAnchorActive>,
+        Option<&mut Visibility>,
+    )>,
+    transforms: Query<&Transform, Without<WristAnchor>>,
+    root: Query<&Transform, (With<XrTrackingRoot>, Without<WristAnchor>)>,
+    hmd: Query<&Transform, (With<OpenXRHMD>, Without<WristAnchor>)>,
+    left_controller: Query<&Transform, (With<OpenXRLeftController>, Without<WristAnchor>)>,
+    right_controller: Query<&Transform, (With<OpenXRRightController>, Without<WristAnchor>)>,
+    mut events: EventWriter<WristAnchorEvent>,
+) {
+    let root = root.get_single().copied().unwrap_or_default();
+    let head = hmd.get_single().map(|hmd| root.mul_transform(*hmd)).ok();
+    for (entity, anchor, mut transform, active, visibility) in anchors.iter_mut() {
+        //hand bones are already in world space, controllers are children of the root
+        let wrist = match (&hands_resource, anchor.hand) {
+            (Some(hands), Hand::Left) => transforms.get(hands.left.wrist).ok().copied(),
+            (Some(hands), Hand::Right) => transforms.get(hands.right.wrist).ok().copied(),
+            (None, Hand::Left) => left_controller
+                .get_single()
+                .ok()
+                .map(|c| root.mul_transform(*c)),
+            (None, Hand::Right) => right_controller
+                .get_single()
+                .ok()
+                .map(|c| root.mul_transform(*c)),
+        };
+        let Some(wrist) = wrist else {
+            continue;
+        };
+        *transform = wrist.mul_transform(anchor.offset);
+
+        let looked_at = head.is_some_and(|head| {
+            let to_anchor = (transform.translation - head.translation).normalize_or_zero();
+            let in_view = head.forward().angle_between(to_anchor) <= anchor.look_angle;
+            let facing = transform.back().angle_between(-to_anchor) <= anchor.facing_angle;
+            in_view && facing
+        });
+        let was_active = active.as_ref().is_some_and(|a| a.0);
+        match active {
+            Some(mut active) => {
+                if active.0 != looked_at {
+                    active.0 = looked_at;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(WristAnchorActive(looked_at));
+            }
+        }
+        if looked_at != was_active {
+            let hand = anchor.hand;
+            events.send(match looked_at {
+                true => WristAnchorEvent::Activated { entity, hand },
+                false => WristAnchorEvent::Deactivated { entity, hand },
+            });
+        }
+        if let (true, Some(mut visibility)) = (anchor.hide_when_inactive, visibility) {
+            let wanted = match looked_at {
+                true => Visibility::Inherited,
+                false => Visibility::Hidden,
+            };
+            if *visibility != wanted {
+                *visibility = wanted;
+            }
+        }
+    }
+}
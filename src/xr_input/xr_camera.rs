@@ -1,9 +1,11 @@
-use crate::xr_input::{QuatConv, Vec3Conv};
+use crate::convert::fov_to_projection;
+use crate::xr_input::views::XrView;
 use crate::{LEFT_XR_TEXTURE_HANDLE, RIGHT_XR_TEXTURE_HANDLE};
 use bevy::core_pipeline::tonemapping::{DebandDither, Tonemapping};
 use bevy::math::Vec3A;
 use bevy::prelude::*;
 use bevy::render::camera::{CameraProjection, CameraRenderGraph, RenderTarget};
+use bevy::render::extract_component::ExtractComponent;
 use bevy::render::primitives::Frustum;
 use bevy::render::view::{ColorGrading, VisibleEntities};
 use openxr::Fovf;
@@ -42,7 +44,7 @@ pub struct XrCameraBundle {
     pub color_grading: ColorGrading,
     pub xr_camera_type: XrCameraType,
 }
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Component)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Component, ExtractComponent)]
 pub enum XrCameraType {
     Xr(Eye),
     Flatscreen,
@@ -86,6 +88,9 @@ impl XrCameraBundle {
 pub struct XRProjection {
     pub near: f32,
     pub far: f32,
+    /// cuts off everything behind `far`, the projection is infinite otherwise and `far` only
+    /// limits culling
+    pub clip_far: bool,
     #[reflect(ignore)]
     pub fov: Fovf,
 }
@@ -95,6 +100,7 @@ impl Default for XRProjection {
         Self {
             near: 0.1,
             far: 1000.,
+            clip_far: false,
             fov: Default::default(),
         }
     }
@@ -102,114 +108,18 @@ impl Default for XRProjection {
 
 impl XRProjection {
     pub fn new(near: f32, far: f32, fov: Fovf) -> Self {
-        XRProjection { near, far, fov }
+        XRProjection {
+            near,
+            far,
+            clip_far: false,
+            fov,
+        }
     }
 }
 
 impl CameraProjection for XRProjection {
-    // =============================================================================
-    // math code adapted from
-    // https://github.com/KhronosGroup/OpenXR-SDK-Source/blob/master/src/common/xr_linear.h
-    // Copyright (c) 2017 The Khronos Group Inc.
-    // Copyright (c) 2016 Oculus VR, LLC.
-    // SPDX-License-Identifier: Apache-2.0
-    // =============================================================================
     fn get_projection_matrix(&self) -> Mat4 {
-        //  symmetric perspective for debugging
-        // let x_fov = (self.fov.angle_left.abs() + self.fov.angle_right.abs());
-        // let y_fov = (self.fov.angle_up.abs() + self.fov.angle_down.abs());
-        // return Mat4::perspective_infinite_reverse_rh(y_fov, x_fov / y_fov, self.near);
-
-        let fov = self.fov;
-        let is_vulkan_api = false; // FIXME wgpu probably abstracts this
-        let near_z = self.near;
-        let far_z = -1.; //   use infinite proj
-                         // let far_z = self.far;
-
-        let tan_angle_left = fov.angle_left.tan();
-        let tan_angle_right = fov.angle_right.tan();
-
-        let tan_angle_down = fov.angle_down.tan();
-        let tan_angle_up = fov.angle_up.tan();
-
-        let tan_angle_width = tan_angle_right - tan_angle_left;
-
-        // Set to tanAngleDown - tanAngleUp for a clip space with positive Y
-        // down (Vulkan). Set to tanAngleUp - tanAngleDown for a clip space with
-        // positive Y up (OpenGL / D3D / Metal).
-        // const float tanAngleHeight =
-        //     graphicsApi == GRAPHICS_VULKAN ? (tanAngleDown - tanAngleUp) : (tanAngleUp - tanAngleDown);
-        let tan_angle_height = if is_vulkan_api {
-            tan_angle_down - tan_angle_up
-        } else {
-            tan_angle_up - tan_angle_down
-        };
-
-        // Set to nearZ for a [-1,1] Z clip space (OpenGL / OpenGL ES).
-        // Set to zero for a [0,1] Z clip space (Vulkan / D3D / Metal).
-        // const float offsetZ =
-        //     (graphicsApi == GRAPHICS_OPENGL || graphicsApi == GRAPHICS_OPENGL_ES) ? nearZ : 0;
-        // FIXME handle enum of graphics apis
-        let offset_z = 0.;
-
-        let mut cols: [f32; 16] = [0.0; 16];
-
-        if far_z <= near_z {
-            // place the far plane at infinity
-            cols[0] = 2. / tan_angle_width;
-            cols[4] = 0.;
-            cols[8] = (tan_angle_right + tan_angle_left) / tan_angle_width;
-            cols[12] = 0.;
-
-            cols[1] = 0.;
-            cols[5] = 2. / tan_angle_height;
-            cols[9] = (tan_angle_up + tan_angle_down) / tan_angle_height;
-            cols[13] = 0.;
-
-            cols[2] = 0.;
-            cols[6] = 0.;
-            cols[10] = -1.;
-            cols[14] = -(near_z + offset_z);
-
-            cols[3] = 0.;
-            cols[7] = 0.;
-            cols[11] = -1.;
-            cols[15] = 0.;
-
-            //  bevy uses the _reverse_ infinite projection
-            //  https://dev.theomader.com/depth-precision/
-            let z_reversal = Mat4::from_cols_array_2d(&[
-                [1f32, 0., 0., 0.],
-                [0., 1., 0., 0.],
-                [0., 0., -1., 0.],
-                [0., 0., 1., 1.],
-            ]);
-
-            return z_reversal * Mat4::from_cols_array(&cols);
-        } else {
-            // normal projection
-            cols[0] = 2. / tan_angle_width;
-            cols[4] = 0.;
-            cols[8] = (tan_angle_right + tan_angle_left) / tan_angle_width;
-            cols[12] = 0.;
-
-            cols[1] = 0.;
-            cols[5] = 2. / tan_angle_height;
-            cols[9] = (tan_angle_up + tan_angle_down) / tan_angle_height;
-            cols[13] = 0.;
-
-            cols[2] = 0.;
-            cols[6] = 0.;
-            cols[10] = -(far_z + offset_z) / (far_z - near_z);
-            cols[14] = -(far_z * (near_z + offset_z)) / (far_z - near_z);
-
-            cols[3] = 0.;
-            cols[7] = 0.;
-            cols[11] = -1.;
-            cols[15] = 0.;
-        }
-
-        Mat4::from_cols_array(&cols)
+        fov_to_projection(self.fov, self.near, self.clip_far.then_some(self.far))
     }
 
     fn update(&mut self, _width: f32, _height: f32) {}
@@ -239,24 +149,102 @@ impl CameraProjection for XRProjection {
     }
 }
 
+// both eye cameras are driven from the view entities, which get the located views once a frame.
+//
+// what the eyes share in bevy 0.12: mesh/material extraction (it only depends on visibility,
+// which the eyes share through the combined frustum), and the shadow maps. with
+// `XrSharedEyePhasesPlugin` also phase queueing, sorting, batching and the mesh uniforms.
+// what stays per eye: light clustering and the view uniforms, those need multiview to be shared.
 pub fn xr_camera_head_sync(
-    views: ResMut<crate::resources::XrViews>,
-    mut query: Query<(&mut Transform, &XrCameraType, &mut XRProjection)>,
+    views: Query<(&XrView, &Transform), Without<XrCameraType>>,
+    mut query: Query<(&mut Transform, &XrCameraType, &mut XRProjection), Without<XrView>>,
 ) {
-    let mut f = || -> Option<()> {
-        //TODO calculate HMD position
-        for (mut transform, camera_type, mut xr_projection) in query.iter_mut() {
-            let view_idx = match camera_type {
-                XrCameraType::Xr(eye) => *eye as usize,
-                XrCameraType::Flatscreen => return None,
-            };
-            let v = views.lock().unwrap();
-            let view = v.get(view_idx)?;
-            xr_projection.fov = view.fov;
-            transform.rotation = view.pose.orientation.to_quat();
-            transform.translation = view.pose.position.to_vec3();
+    //TODO calculate HMD position
+    for (mut transform, camera_type, mut xr_projection) in query.iter_mut() {
+        let view_idx = match camera_type {
+            XrCameraType::Xr(eye) => *eye as u32,
+            XrCameraType::Flatscreen => continue,
+        };
+        let Some((view, view_transform)) = views.iter().find(|(view, _)| view.index == view_idx)
+        else {
+            continue;
+        };
+        xr_projection.fov = view.fov;
+        transform.rotation = view_transform.rotation;
+        transform.translation = view_transform.translation;
+    }
+}
+
+/// builds one frustum that covers both eyes and gives it to the left eye camera, so visibility
+/// only has to be checked once per frame. it uses the widest angle of the two eyes on every side,
+/// from a point behind the eyes where the outer planes of both eyes meet
+pub fn update_xr_stereo_frustum(
+    cameras: Query<(&XrCameraType, &GlobalTransform, &XRProjection)>,
+    mut frusta: Query<(&XrCameraType, &mut Frustum)>,
+) {
+    let mut eyes = [None, None];
+    for (camera_type, transform, projection) in cameras.iter() {
+        if let XrCameraType::Xr(eye) = camera_type {
+            eyes[*eye as usize] = Some((transform, projection));
+        }
+    }
+    let [Some((left_transform, left)), Some((right_transform, right))] = eyes else {
+        return;
+    };
+    let fov = Fovf {
+        angle_left: left.fov.angle_left.min(right.fov.angle_left),
+        angle_right: left.fov.angle_right.max(right.fov.angle_right),
+        angle_down: left.fov.angle_down.min(right.fov.angle_down),
+        angle_up: left.fov.angle_up.max(right.fov.angle_up),
+    };
+    //move back from the left eye until the widest left and right planes pass through both eyes
+    let (_, rotation, left_position) = left_transform.to_scale_rotation_translation();
+    let separation = (right_transform.translation() - left_position).dot(rotation * Vec3::X);
+    let slopes = (-fov.angle_left).tan() + fov.angle_right.tan();
+    let back = match separation > 0.0 && slopes > 0.0 {
+        true => separation / slopes,
+        false => 0.0,
+    };
+    let origin = left_position
+        + rotation * Vec3::X * ((-fov.angle_left).tan() * back)
+        + rotation * Vec3::Z * back;
+    let projection = XRProjection {
+        fov,
+        ..left.clone()
+    };
+    let view_projection = projection.get_projection_matrix()
+        * Mat4::from_rotation_translation(rotation, origin).inverse();
+    let mut combined = Frustum::from_view_projection_custom_far(
+        &view_projection,
+        &origin,
+        &(rotation * Vec3::Z),
+        projection.far() + back,
+    );
+    //the half spaces are left, right, bottom, top, near, far. the near plane of the eyes is
+    //used so nothing between them and the moved back origin counts as visible
+    let left_view_projection =
+        left.get_projection_matrix() * left_transform.compute_matrix().inverse();
+    combined.half_spaces[4] = Frustum::from_view_projection(&left_view_projection).half_spaces[4];
+    for (camera_type, mut frustum) in frusta.iter_mut() {
+        if *camera_type == XrCameraType::Xr(Eye::Left) {
+            *frustum = combined;
         }
-        Some(())
+    }
+}
+
+/// the right eye has no frustum of its own, so it gets the entities the left eye found visible
+/// with the combined frustum
+pub fn share_xr_visible_entities(mut cameras: Query<(&XrCameraType, &mut VisibleEntities)>) {
+    let Some(visible) = cameras
+        .iter()
+        .find(|(camera_type, _)| **camera_type == XrCameraType::Xr(Eye::Left))
+        .map(|(_, visible)| visible.entities.clone())
+    else {
+        return;
     };
-    let _ = f();
+    for (camera_type, mut visible_entities) in cameras.iter_mut() {
+        if *camera_type == XrCameraType::Xr(Eye::Right) {
+            visible_entities.entities.clone_from(&visible);
+        }
+    }
 }
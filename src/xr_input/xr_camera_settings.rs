@@ -0,0 +1,90 @@
+use bevy::core_pipeline::bloom::BloomSettings;
+use bevy::core_pipeline::tonemapping::{DebandDither, Tonemapping};
+use bevy::pbr::FogSettings;
+use bevy::prelude::*;
+use bevy::render::view::ColorGrading;
+
+use super::xr_camera::XrCameraType;
+
+/// copies the post processing of the entity with [`XrCameraSettings`] to both eye cameras, so
+/// the eyes never end up with different settings. edit the settings component instead of the
+/// cameras, changes made on the cameras directly get overwritten
+pub struct XrCameraSettingsPlugin;
+
+impl Plugin for XrCameraSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, sync_xr_camera_settings);
+    }
+}
+
+/// the post processing both eyes use. only one entity should have this, a good place is the
+/// [`XrTrackingRoot`](super::trackers::XrTrackingRoot)
+#[derive(Component, Clone)]
+pub struct XrCameraSettings {
+    /// renders to an hdr texture before tonemapping, bloom needs this
+    pub hdr: bool,
+    pub tonemapping: Tonemapping,
+    pub dither: DebandDither,
+    pub color_grading: ColorGrading,
+    pub bloom: Option<BloomSettings>,
+    pub fog: Option<FogSettings>,
+}
+
+impl Default for XrCameraSettings {
+    fn default() -> Self {
+        Self {
+            hdr: false,
+            tonemapping: Default::default(),
+            dither: DebandDither::Enabled,
+            color_grading: Default::default(),
+            bloom: None,
+            fog: None,
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn sync_xr_camera_settings(
+    mut commands: Commands,
+    settings: Query<Ref<XrCameraSettings>>,
+    mut cameras: Query<(
+        Entity,
+        Ref<XrCameraType>,
+        &mut Camera,
+        &mut Tonemapping,
+        &mut DebandDither,
+        &mut ColorGrading,
+    )>,
+    mut warned: Local<bool>,
+) {
+    let mut settings = settings.iter();
+    let Some(first) = settings.next() else {
+        return;
+    };
+    if settings.next().is_some() && !*warned {
+        warn!("more than one entity has XrCameraSettings, only one of them is used");
+        *warned = true;
+    }
+    for (entity, camera_type, mut camera, mut tonemapping, mut dither, mut color_grading) in
+        cameras.iter_mut()
+    {
+        if !matches!(*camera_type, XrCameraType::Xr(_))
+            || !(first.is_changed() || camera_type.is_added())
+        {
+            continue;
+        }
+        camera.hdr = first.hdr;
+        *tonemapping = first.tonemapping;
+        *dither = first.dither;
+        *color_grading = first.color_grading;
+        let mut entity = commands.entity(entity);
+        match first.bloom.clone() {
+            Some(bloom) => entity.insert(bloom),
+            None => entity.remove::<BloomSettings>(),
+        };
+        match first.fog.clone() {
+            Some(fog) => entity.insert(fog),
+            None => entity.remove::<FogSettings>(),
+        };
+    }
+}
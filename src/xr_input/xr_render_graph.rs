@@ -0,0 +1,159 @@
+use std::borrow::Cow;
+
+use bevy::core_pipeline::core_3d;
+use bevy::ecs::query::QueryItem;
+use bevy::prelude::*;
+use bevy::render::camera::CameraRenderGraph;
+use bevy::render::extract_component::ExtractComponentPlugin;
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraph, RenderGraphApp, RenderGraphContext, ViewNode, ViewNodeRunner,
+};
+use bevy::render::renderer::RenderContext;
+use bevy::render::RenderApp;
+
+use super::xr_camera::{Eye, XrCameraType};
+
+/// lets the eye cameras render with their own render graph, see [`XrEyeRenderGraphs`], and
+/// adds [`XrRenderGraphAppExt`] for post processing nodes that only run for the eyes
+pub struct XrRenderGraphPlugin;
+
+impl Plugin for XrRenderGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrEyeRenderGraphs>();
+        if !app.is_plugin_added::<ExtractComponentPlugin<XrCameraType>>() {
+            app.add_plugins(ExtractComponentPlugin::<XrCameraType>::default());
+        }
+        app.add_systems(PostUpdate, apply_xr_eye_render_graphs);
+    }
+}
+
+/// the render graph each eye camera uses, the default is the normal 3d graph. a custom graph has
+/// to be added to the render app with `add_render_sub_graph` before it can be used. can be
+/// changed at runtime
+#[derive(Resource, Clone, Debug, PartialEq, Eq)]
+pub struct XrEyeRenderGraphs {
+    pub left: Cow<'static, str>,
+    pub right: Cow<'static, str>,
+}
+
+impl Default for XrEyeRenderGraphs {
+    fn default() -> Self {
+        Self::both(core_3d::graph::NAME)
+    }
+}
+
+impl XrEyeRenderGraphs {
+    /// both eyes use `name`
+    pub fn both(name: impl Into<Cow<'static, str>>) -> Self {
+        let name = name.into();
+        Self {
+            left: name.clone(),
+            right: name,
+        }
+    }
+
+    pub fn get(&self, eye: Eye) -> &Cow<'static, str> {
+        match eye {
+            Eye::Left => &self.left,
+            Eye::Right => &self.right,
+        }
+    }
+}
+
+pub fn apply_xr_eye_render_graphs(
+    graphs: Res<XrEyeRenderGraphs>,
+    mut cameras: Query<(Ref<XrCameraType>, &mut CameraRenderGraph)>,
+) {
+    for (camera_type, mut render_graph) in cameras.iter_mut() {
+        let XrCameraType::Xr(eye) = *camera_type else {
+            continue;
+        };
+        if !graphs.is_changed() && !camera_type.is_added() {
+            continue;
+        }
+        let name = graphs.get(eye);
+        if **render_graph != *name {
+            render_graph.set(name.clone());
+        }
+    }
+}
+
+pub trait XrRenderGraphAppExt {
+    /// adds the view node `N` to the 3d graph, after tonemapping and before the built in post
+    /// processing (fxaa when its plugin was added before, upscaling). it only runs for the eye cameras, put `&XrCameraType` in the
+    /// view query of the node to do something different per eye
+    fn add_xr_eye_post_process_node<N: ViewNode + FromWorld + Send + Sync + 'static>(
+        &mut self,
+        name: &'static str,
+    ) -> &mut Self;
+}
+
+impl XrRenderGraphAppExt for App {
+    fn add_xr_eye_post_process_node<N: ViewNode + FromWorld + Send + Sync + 'static>(
+        &mut self,
+        name: &'static str,
+    ) -> &mut Self {
+        let Ok(render_app) = self.get_sub_app_mut(RenderApp) else {
+            return self;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<XrEyeNode<N>>>(core_3d::graph::NAME, name)
+            .add_render_graph_edges(
+                core_3d::graph::NAME,
+                &[
+                    core_3d::graph::node::TONEMAPPING,
+                    name,
+                    core_3d::graph::node::END_MAIN_PASS_POST_PROCESSING,
+                ],
+            );
+        //fxaa sits between tonemapping and the end of post processing too, it's only in the
+        //graph with the fxaa plugin
+        let has_fxaa = render_app
+            .world
+            .resource::<RenderGraph>()
+            .get_sub_graph(core_3d::graph::NAME)
+            .is_some_and(|graph| graph.get_node_state(core_3d::graph::node::FXAA).is_ok());
+        if has_fxaa {
+            render_app.add_render_graph_edge(
+                core_3d::graph::NAME,
+                name,
+                core_3d::graph::node::FXAA,
+            );
+        }
+        self
+    }
+}
+
+/// runs the wrapped node only for the eye cameras
+pub struct XrEyeNode<N> {
+    node: N,
+}
+
+impl<N: FromWorld> FromWorld for XrEyeNode<N> {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            node: N::from_world(world),
+        }
+    }
+}
+
+impl<N: ViewNode> ViewNode for XrEyeNode<N> {
+    type ViewQuery = (&'static XrCameraType, N::ViewQuery);
+
+    fn update(&mut self, world: &mut World) {
+        self.node.update(world);
+    }
+
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (camera_type, view_query): QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        match camera_type {
+            XrCameraType::Xr(_) => self.node.run(graph, render_context, view_query, world),
+            XrCameraType::Flatscreen => Ok(()),
+        }
+    }
+}
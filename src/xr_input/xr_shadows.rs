@@ -0,0 +1,249 @@
+use bevy::math::Vec3A;
+use bevy::pbr::{
+    Cascade, CascadeShadowConfig, Cascades, DirectionalLight, DirectionalLightShadowMap,
+    RenderLightSystems, SimulationLightSystems, ViewLightEntities, ViewLightsUniformOffset,
+    ViewShadowBindings,
+};
+use bevy::prelude::*;
+use bevy::reflect::Struct;
+use bevy::render::camera::CameraProjection;
+use bevy::render::extract_component::ExtractComponentPlugin;
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::utils::HashMap;
+
+use super::xr_camera::{Eye, XRProjection, XrCameraType};
+
+/// renders the shadow maps once per frame for both eyes. bevy prepares the shadow views of every
+/// light for each camera and records their passes in the shadow pass node of each camera's
+/// graph. the right eye gets the shadow views, shadow maps and light uniforms of the left eye
+/// instead, so its shadow pass node has nothing to record and its main pass samples the maps
+/// the left eye rendered. which lights cast shadows is left to the app
+pub struct XrSharedShadowsPlugin;
+
+impl Plugin for XrSharedShadowsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ExtractComponentPlugin<XrCameraType>>() {
+            app.add_plugins(ExtractComponentPlugin::<XrCameraType>::default());
+        }
+        app.add_systems(
+            PostUpdate,
+            update_xr_directional_light_cascades
+                .after(SimulationLightSystems::UpdateDirectionalLightCascades)
+                .before(SimulationLightSystems::UpdateLightFrusta),
+        );
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        //the shadow views are spawned in `ManageViews` and only exist after its flush, the
+        //shadow phases are queued from them
+        render_app.add_systems(
+            Render,
+            share_xr_shadow_views
+                .in_set(RenderSet::Queue)
+                .before(RenderLightSystems::QueueShadows),
+        );
+    }
+}
+
+// the cameras render sorted by order and then by target, both eyes have the same order and the
+// left eye texture handle sorts first, so its shadow maps are rendered before the right eye's
+// main pass reads them.
+//
+// the point and spot light shadow views don't depend on the camera. the directional light
+// cascades of both eyes are the same, `update_xr_directional_light_cascades` fits one set to
+// both, so the right eye can sample the cascades the left eye rendered.
+pub fn share_xr_shadow_views(
+    mut views: Query<(
+        &XrCameraType,
+        &mut ViewLightEntities,
+        &mut ViewShadowBindings,
+        &mut ViewLightsUniformOffset,
+    )>,
+) {
+    let Some((bindings, offset)) = views
+        .iter()
+        .find(|(camera_type, ..)| **camera_type == XrCameraType::Xr(Eye::Left))
+        .map(|(_, _, bindings, offset)| (clone_shadow_bindings(bindings), offset.offset))
+    else {
+        return;
+    };
+    for (camera_type, mut view_lights, mut view_bindings, mut view_offset) in views.iter_mut() {
+        if *camera_type != XrCameraType::Xr(Eye::Right) {
+            continue;
+        }
+        //the light views are rendered by the left eye's shadow pass, the right eye's would
+        //render them a second time
+        view_lights.lights.clear();
+        *view_bindings = clone_shadow_bindings(&bindings);
+        view_offset.offset = offset;
+    }
+}
+
+fn clone_shadow_bindings(bindings: &ViewShadowBindings) -> ViewShadowBindings {
+    ViewShadowBindings {
+        point_light_depth_texture: bindings.point_light_depth_texture.clone(),
+        point_light_depth_texture_view: bindings.point_light_depth_texture_view.clone(),
+        directional_light_depth_texture: bindings.directional_light_depth_texture.clone(),
+        directional_light_depth_texture_view: bindings.directional_light_depth_texture_view.clone(),
+    }
+}
+
+// bevy only builds directional light cascades for cameras with a `Projection`, and its builder
+// clears the cascades of every other view, so the eye cameras get theirs here. one set covers
+// both eyes and goes to both eye cameras, they share the shadow maps in the render world.
+pub fn update_xr_directional_light_cascades(
+    directional_light_shadow_map: Res<DirectionalLightShadowMap>,
+    views: Query<(
+        Entity,
+        &GlobalTransform,
+        &XRProjection,
+        &XrCameraType,
+        &Camera,
+    )>,
+    mut lights: Query<(
+        &GlobalTransform,
+        &DirectionalLight,
+        &CascadeShadowConfig,
+        &mut Cascades,
+    )>,
+) {
+    let eyes: Vec<_> = views
+        .iter()
+        .filter(|(_, _, _, camera_type, camera)| {
+            camera.is_active && matches!(camera_type, XrCameraType::Xr(_))
+        })
+        .map(|(entity, transform, projection, _, _)| {
+            (entity, transform.compute_matrix(), projection.clone())
+        })
+        .collect();
+    if eyes.is_empty() {
+        return;
+    }
+    for (transform, directional_light, cascades_config, mut cascades) in &mut lights {
+        if !directional_light.shadows_enabled {
+            continue;
+        }
+        // It is very important to the numerical and thus visual stability of shadows that
+        // light_to_world has orthogonal upper-left 3x3 and zero translation.
+        let light_to_world = Mat4::from_quat(transform.compute_transform().rotation);
+        let light_to_world_inverse = light_to_world.inverse();
+
+        let stereo_cascades: Vec<Cascade> = cascades_config
+            .bounds
+            .iter()
+            .enumerate()
+            .map(|(idx, far_bound)| {
+                // Negate bounds as -z is camera forward direction.
+                let z_near = if idx > 0 {
+                    (1.0 - cascades_config.overlap_proportion) * -cascades_config.bounds[idx - 1]
+                } else {
+                    -cascades_config.minimum_distance
+                };
+                let z_far = -far_bound;
+                calculate_stereo_cascade(
+                    &eyes,
+                    z_near,
+                    z_far,
+                    directional_light_shadow_map.size as f32,
+                    light_to_world,
+                    light_to_world_inverse,
+                )
+            })
+            .collect();
+
+        let Some(view_cascades) = cascades
+            .field_mut("cascades")
+            .and_then(|c| c.downcast_mut::<HashMap<Entity, Vec<Cascade>>>())
+        else {
+            warn!("unable to access directional light cascades");
+            continue;
+        };
+        for (entity, _, _) in eyes.iter() {
+            view_cascades.insert(*entity, stereo_cascades.clone());
+        }
+    }
+}
+
+// same as bevy's cascade calculation, but bounding the frustum slices of every eye at once
+fn calculate_stereo_cascade(
+    eyes: &[(Entity, Mat4, XRProjection)],
+    z_near: f32,
+    z_far: f32,
+    cascade_texture_size: f32,
+    light_to_world: Mat4,
+    light_to_world_inverse: Mat4,
+) -> Cascade {
+    let mut min = Vec3A::splat(f32::MAX);
+    let mut max = Vec3A::splat(f32::MIN);
+    let mut cascade_diameter: f32 = 0.0;
+    let mut eye_positions = Vec::with_capacity(eyes.len());
+    for (_, view_to_world, projection) in eyes.iter() {
+        let corners = projection.get_frustum_corners(z_near, z_far);
+        let camera_to_light = light_to_world_inverse * *view_to_world;
+        for corner in corners {
+            let corner_light_view = camera_to_light.transform_point3a(corner);
+            min = min.min(corner_light_view);
+            max = max.max(corner_light_view);
+        }
+        cascade_diameter = cascade_diameter
+            .max((corners[0] - corners[6]).length())
+            .max((corners[4] - corners[6]).length());
+        eye_positions.push(view_to_world.w_axis.truncate());
+    }
+    // the eyes are offset from each other, so the slices of both eyes together are at most the
+    // distance between the eyes bigger than the biggest slice of a single eye
+    let eye_separation = eye_positions
+        .iter()
+        .flat_map(|a| eye_positions.iter().map(move |b| a.distance(*b)))
+        .fold(0.0, f32::max);
+    // round the separation so small ipd changes don't make the shadows swim
+    let cascade_diameter = (cascade_diameter + (eye_separation * 100.0).ceil() / 100.0).ceil();
+
+    let cascade_texel_size = cascade_diameter / cascade_texture_size;
+    // NOTE: For shadow stability it is very important that the near_plane_center is at integer
+    //       multiples of the texel size to be exactly representable in a floating point value.
+    let near_plane_center = Vec3A::new(
+        (0.5 * (min.x + max.x) / cascade_texel_size).floor() * cascade_texel_size,
+        (0.5 * (min.y + max.y) / cascade_texel_size).floor() * cascade_texel_size,
+        // NOTE: max.z is the near plane for right-handed y-up
+        max.z,
+    );
+
+    let light_to_world_transpose = light_to_world.transpose();
+    let world_to_cascade = Mat4::from_cols(
+        light_to_world_transpose.x_axis,
+        light_to_world_transpose.y_axis,
+        light_to_world_transpose.z_axis,
+        (-near_plane_center).extend(1.0),
+    );
+
+    // Right-handed orthographic projection, centered at `near_plane_center`.
+    // NOTE: This is different from the reference material, as we use reverse Z.
+    let r = (max.z - min.z).recip();
+    let cascade_projection = Mat4::from_cols(
+        Vec4::new(2.0 / cascade_diameter, 0.0, 0.0, 0.0),
+        Vec4::new(0.0, 2.0 / cascade_diameter, 0.0, 0.0),
+        Vec4::new(0.0, 0.0, r, 0.0),
+        Vec4::new(0.0, 0.0, 1.0, 1.0),
+    );
+
+    let cascade_view_projection = cascade_projection * world_to_cascade;
+
+    // the fields of `Cascade` aren't public, so they get filled in through reflection
+    let mut cascade = Cascade::default();
+    set_cascade_field(&mut cascade, "view_transform", world_to_cascade.inverse());
+    set_cascade_field(&mut cascade, "projection", cascade_projection);
+    set_cascade_field(&mut cascade, "view_projection", cascade_view_projection);
+    set_cascade_field(&mut cascade, "texel_size", cascade_texel_size);
+    cascade
+}
+
+fn set_cascade_field<T: Reflect>(cascade: &mut Cascade, name: &str, value: T) {
+    match cascade
+        .field_mut(name)
+        .and_then(|field| field.downcast_mut::<T>())
+    {
+        Some(field) => *field = value,
+        None => warn!("unable to set cascade field {}", name),
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::core_pipeline::experimental::taa::{
+    TemporalAntiAliasBundle, TemporalAntiAliasPlugin, TemporalAntiAliasSettings,
+};
+use bevy::pbr::PreviousViewProjection;
+use bevy::prelude::*;
+use bevy::render::camera::{CameraProjection, CameraUpdateSystem};
+use bevy::render::{Extract, ExtractSchedule, RenderApp};
+use bevy::transform::TransformSystem;
+
+use super::trackers::XrTrackingRoot;
+use super::xr_camera::{XRProjection, XrCameraType};
+
+/// temporal anti aliasing for the eye cameras. bevy's taa expects the view to come from its own
+/// projections, so this keeps the previous view projection of each eye in sync with the xr
+/// projection it was rendered with, and resets the history when the tracking root jumps
+/// (teleports, snap turns) so the old frames don't smear into the new view. bevy only extracts
+/// the taa settings of cameras with a perspective [`Projection`], the eyes are extracted here, so
+/// the render world jitters the eye projections like any other taa camera. taa needs `Msaa::Off`
+pub struct XrTemporalAntiAliasPlugin;
+
+impl Plugin for XrTemporalAntiAliasPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<TemporalAntiAliasPlugin>() {
+            app.add_plugins(TemporalAntiAliasPlugin);
+        }
+        app.init_resource::<XrTemporalAntiAlias>();
+        app.add_systems(
+            PostUpdate,
+            (
+                add_xr_taa,
+                update_xr_previous_view_projections
+                    .after(TransformSystem::TransformPropagate)
+                    .after(CameraUpdateSystem),
+                reset_xr_taa_on_jumps.after(TransformSystem::TransformPropagate),
+            ),
+        );
+        //a reset only applies to the frame it was extracted in
+        app.add_systems(First, clear_xr_taa_resets);
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(ExtractSchedule, extract_xr_taa_settings);
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct XrTemporalAntiAlias {
+    pub enabled: bool,
+    /// the history is dropped when the tracking root moves further than this in one frame
+    pub reset_distance: f32,
+    /// or turns more than this in one frame, in radians
+    pub reset_angle: f32,
+}
+
+impl Default for XrTemporalAntiAlias {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            reset_distance: 0.5,
+            reset_angle: 15f32.to_radians(),
+        }
+    }
+}
+
+/// the view projection an eye was rendered with last frame, without jitter
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct XrTaaHistory {
+    view_projection: Option<Mat4>,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn add_xr_taa(
+    mut commands: Commands,
+    settings: Res<XrTemporalAntiAlias>,
+    msaa: Res<Msaa>,
+    cameras: Query<(Entity, &XrCameraType, Option<&TemporalAntiAliasSettings>)>,
+    mut warned: Local<bool>,
+) {
+    if settings.is_changed() && settings.enabled && *msaa != Msaa::Off && !*warned {
+        warn!("xr taa needs Msaa::Off, the eyes will render without it");
+        *warned = true;
+    }
+    for (entity, camera_type, taa) in cameras.iter() {
+        if !matches!(camera_type, XrCameraType::Xr(_)) {
+            continue;
+        }
+        match (settings.enabled, taa.is_some()) {
+            (true, false) => {
+                commands
+                    .entity(entity)
+                    .insert((TemporalAntiAliasBundle::default(), XrTaaHistory::default()));
+            }
+            (false, true) => {
+                commands
+                    .entity(entity)
+                    .remove::<(TemporalAntiAliasBundle, XrTaaHistory)>();
+            }
+            _ => {}
+        }
+    }
+}
+
+// runs after the camera transforms and projections are final for this frame, so the previous
+// view projection the prepass uses for motion vectors is the one the eye really rendered with
+pub fn update_xr_previous_view_projections(
+    mut commands: Commands,
+    mut cameras: Query<(
+        Entity,
+        &XrCameraType,
+        &GlobalTransform,
+        &XRProjection,
+        &mut XrTaaHistory,
+    )>,
+) {
+    for (entity, camera_type, transform, projection, mut history) in cameras.iter_mut() {
+        if !matches!(camera_type, XrCameraType::Xr(_)) {
+            continue;
+        }
+        let view_projection =
+            projection.get_projection_matrix() * transform.compute_matrix().inverse();
+        commands.entity(entity).insert(PreviousViewProjection {
+            view_proj: history.view_projection.unwrap_or(view_projection),
+        });
+        history.view_projection = Some(view_projection);
+    }
+}
+
+pub fn reset_xr_taa_on_jumps(
+    settings: Res<XrTemporalAntiAlias>,
+    root: Query<&GlobalTransform, With<XrTrackingRoot>>,
+    mut cameras: Query<(&XrCameraType, &mut TemporalAntiAliasSettings)>,
+    mut last_root: Local<Option<GlobalTransform>>,
+) {
+    let Ok(root) = root.get_single() else {
+        return;
+    };
+    let jumped = last_root.is_some_and(|last| {
+        let (_, last_rotation, last_translation) = last.to_scale_rotation_translation();
+        let (_, rotation, translation) = root.to_scale_rotation_translation();
+        last_translation.distance(translation) > settings.reset_distance
+            || last_rotation.angle_between(rotation) > settings.reset_angle
+    });
+    *last_root = Some(*root);
+    if !jumped {
+        return;
+    }
+    for (camera_type, mut taa) in cameras.iter_mut() {
+        if matches!(camera_type, XrCameraType::Xr(_)) {
+            taa.reset = true;
+        }
+    }
+}
+
+pub fn clear_xr_taa_resets(mut cameras: Query<(&XrCameraType, &mut TemporalAntiAliasSettings)>) {
+    for (camera_type, mut taa) in cameras.iter_mut() {
+        if matches!(camera_type, XrCameraType::Xr(_)) && taa.reset {
+            taa.reset = false;
+        }
+    }
+}
+
+// the taa jitter and node only run for views with the settings
+pub fn extract_xr_taa_settings(
+    mut commands: Commands,
+    cameras: Extract<Query<(Entity, &Camera, &XrCameraType, &TemporalAntiAliasSettings)>>,
+) {
+    for (entity, camera, camera_type, taa) in cameras.iter() {
+        if camera.is_active && matches!(camera_type, XrCameraType::Xr(_)) {
+            commands.get_or_spawn(entity).insert(taa.clone());
+        }
+    }
+}
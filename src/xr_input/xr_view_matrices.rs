@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy::render::camera::{CameraProjection, CameraUpdateSystem};
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_resource::ShaderType;
+use bevy::transform::TransformSystem;
+
+use super::xr_camera::{XRProjection, XrCameraType};
+
+/// the view and projection matrices of both eyes, of this frame and the last one, for custom
+/// shaders that need them outside of bevy's view uniforms, e.g. stereo effects or reprojecting
+/// the last frame. [`XrViewMatrices`] is a `ShaderType`, so it can be written into a uniform
+/// buffer as is, and it's extracted to the render world
+pub struct XrViewMatricesPlugin;
+
+impl Plugin for XrViewMatricesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractResourcePlugin::<XrViewMatrices>::default());
+        app.init_resource::<XrViewMatrices>();
+        app.add_systems(
+            PostUpdate,
+            update_xr_view_matrices
+                .after(TransformSystem::TransformPropagate)
+                .after(CameraUpdateSystem),
+        );
+    }
+}
+
+/// the matrices of one eye, the view matrices go from world to view space
+#[derive(ShaderType, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrEyeMatrices {
+    pub view: Mat4,
+    pub inverse_view: Mat4,
+    pub projection: Mat4,
+    pub view_projection: Mat4,
+    /// the same as this frame's on the first frame
+    pub previous_view: Mat4,
+    pub previous_projection: Mat4,
+    pub previous_view_projection: Mat4,
+    /// in world space
+    pub position: Vec3,
+}
+
+/// indexed by `Eye`, left first
+#[derive(Resource, ExtractResource, ShaderType, Clone, Copy, Debug, Default, PartialEq)]
+pub struct XrViewMatrices {
+    pub eyes: [XrEyeMatrices; 2],
+}
+
+pub fn update_xr_view_matrices(
+    mut matrices: ResMut<XrViewMatrices>,
+    cameras: Query<(&XrCameraType, &GlobalTransform, &XRProjection)>,
+    mut initialized: Local<[bool; 2]>,
+) {
+    for (camera_type, transform, projection) in &cameras {
+        let XrCameraType::Xr(eye) = camera_type else {
+            continue;
+        };
+        let index = *eye as usize;
+        let inverse_view = transform.compute_matrix();
+        let view = inverse_view.inverse();
+        let projection = projection.get_projection_matrix();
+        let eye = &mut matrices.eyes[index];
+        let previous = match initialized[index] {
+            true => *eye,
+            false => XrEyeMatrices {
+                view,
+                projection,
+                view_projection: projection * view,
+                ..default()
+            },
+        };
+        initialized[index] = true;
+        *eye = XrEyeMatrices {
+            view,
+            inverse_view,
+            projection,
+            view_projection: projection * view,
+            previous_view: previous.view,
+            previous_projection: previous.projection,
+            previous_view_projection: previous.view_projection,
+            position: transform.translation(),
+        };
+    }
+}
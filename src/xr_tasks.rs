@@ -0,0 +1,173 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use bevy::utils::HashMap;
+use futures_lite::future;
+use openxr as xr;
+
+/// runs a slow or asynchronous openxr operation (anchor persistence, scene queries, render model
+/// loading...) on the async compute pool. when the future is done the component is replaced with
+/// an [`XrTaskOutput`] on the same entity and an [`XrTaskFinished`] event is sent. the output type
+/// has to be registered with [`XrTaskAppExt::add_xr_task`]
+#[derive(Component)]
+pub struct XrTask<T: Send + Sync + 'static> {
+    task: Task<xr::Result<T>>,
+}
+
+impl<T: Send + Sync + 'static> XrTask<T> {
+    pub fn spawn(future: impl Future<Output = xr::Result<T>> + Send + 'static) -> Self {
+        Self {
+            task: AsyncComputeTaskPool::get().spawn(future),
+        }
+    }
+}
+
+/// the result of a finished [`XrTask`]
+#[derive(Component, Debug)]
+pub struct XrTaskOutput<T: Send + Sync + 'static>(pub xr::Result<T>);
+
+/// sent when the [`XrTask`] on `entity` finished, the result is on the entity
+#[derive(Event)]
+pub struct XrTaskFinished<T: Send + Sync + 'static> {
+    pub entity: Entity,
+    pub succeeded: bool,
+    marker: PhantomData<fn() -> T>,
+}
+
+pub trait XrTaskAppExt {
+    /// polls the [`XrTask<T>`]s every frame
+    fn add_xr_task<T: Send + Sync + 'static>(&mut self) -> &mut Self;
+}
+
+impl XrTaskAppExt for App {
+    fn add_xr_task<T: Send + Sync + 'static>(&mut self) -> &mut Self {
+        self.add_event::<XrTaskFinished<T>>();
+        self.add_systems(PreUpdate, poll_xr_tasks::<T>)
+    }
+}
+
+pub fn poll_xr_tasks<T: Send + Sync + 'static>(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut XrTask<T>)>,
+    mut events: EventWriter<XrTaskFinished<T>>,
+) {
+    for (entity, mut task) in tasks.iter_mut() {
+        let Some(result) = future::block_on(future::poll_once(&mut task.task)) else {
+            continue;
+        };
+        events.send(XrTaskFinished {
+            entity,
+            succeeded: result.is_ok(),
+            marker: PhantomData,
+        });
+        commands
+            .entity(entity)
+            .remove::<XrTask<T>>()
+            .insert(XrTaskOutput(result));
+    }
+}
+
+#[derive(Default)]
+struct RequestState {
+    result: Option<xr::sys::Result>,
+    //the space of events that create one, like the spatial anchor creation
+    space: Option<xr::sys::Space>,
+    waker: Option<Waker>,
+}
+
+/// the pending requests of the extensions that report completion through an event with an
+/// `XrAsyncRequestIdFB` (spatial anchors, scene queries). the completion events are polled in
+/// `xr_begin_frame`
+#[derive(Resource, Clone, Default)]
+pub struct XrAsyncRequests(Arc<Mutex<HashMap<u64, Arc<Mutex<RequestState>>>>>);
+
+impl XrAsyncRequests {
+    /// a future that resolves with the result of the completion event of `request_id`
+    pub fn wait(&self, request_id: xr::sys::AsyncRequestIdFB) -> XrAsyncRequest {
+        let state = self
+            .0
+            .lock()
+            .unwrap()
+            .entry(request_id.into_raw())
+            .or_default()
+            .clone();
+        XrAsyncRequest {
+            requests: self.clone(),
+            request_id: request_id.into_raw(),
+            state,
+        }
+    }
+
+    /// like [`XrAsyncRequests::wait`], resolves with the space the completion event carries
+    pub fn wait_for_space(
+        &self,
+        request_id: xr::sys::AsyncRequestIdFB,
+    ) -> impl Future<Output = xr::Result<xr::sys::Space>> + Send + 'static {
+        let request = self.wait(request_id);
+        let state = request.state.clone();
+        async move {
+            request.await?;
+            let space = state.lock().unwrap().space;
+            space.ok_or(xr::sys::Result::ERROR_RUNTIME_FAILURE)
+        }
+    }
+
+    pub(crate) fn complete(&self, request_id: xr::sys::AsyncRequestIdFB, result: xr::sys::Result) {
+        self.complete_with_space(request_id, result, None);
+    }
+
+    pub(crate) fn complete_with_space(
+        &self,
+        request_id: xr::sys::AsyncRequestIdFB,
+        result: xr::sys::Result,
+        space: Option<xr::sys::Space>,
+    ) {
+        //the event can arrive before anyone waits for it
+        let state = self
+            .0
+            .lock()
+            .unwrap()
+            .entry(request_id.into_raw())
+            .or_default()
+            .clone();
+        let mut state = state.lock().unwrap();
+        state.result = Some(result);
+        state.space = space;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// resolves to `Ok` with the success code or `Err` with the error of the completion event
+pub struct XrAsyncRequest {
+    requests: XrAsyncRequests,
+    request_id: u64,
+    state: Arc<Mutex<RequestState>>,
+}
+
+impl Future for XrAsyncRequest {
+    type Output = xr::Result<xr::sys::Result>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.result {
+            Some(result) => {
+                self.requests.0.lock().unwrap().remove(&self.request_id);
+                Poll::Ready(match result.into_raw() >= 0 {
+                    true => Ok(result),
+                    false => Err(result),
+                })
+            }
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}